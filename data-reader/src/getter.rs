@@ -0,0 +1,276 @@
+use std::any::Any;
+use std::future::Future;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex,
+};
+use std::time::Duration;
+
+use anyhow::Result;
+use moka::sync::Cache;
+use tokio::time::Instant;
+use ton_block::MsgAddressInt;
+use transaction_consumer::JrpcClient;
+
+use crate::RpcLimiter;
+
+/// The least recently used results are evicted past this
+const MAX_CACHED_RESULTS: u64 = 100_000;
+
+type CacheKey = (String, &'static str);
+
+/// Spaces calls at least `interval` apart
+struct RateLimit {
+    interval: Duration,
+    next: Mutex<Instant>,
+}
+
+impl RateLimit {
+    fn new(rate_per_sec: u32) -> Self {
+        Self {
+            interval: Duration::from_secs(1) / rate_per_sec,
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    async fn wait(&self) {
+        let at = {
+            let mut next = self.next.lock().unwrap();
+            let at = (*next).max(Instant::now());
+            *next = at + self.interval;
+            at
+        };
+        tokio::time::sleep_until(at).await;
+    }
+}
+
+/// Results of getter calls by address and getter, kept for `ttl`
+struct GetterCache {
+    results: Cache<CacheKey, Arc<dyn Any + Send + Sync>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl GetterCache {
+    fn new(ttl: Duration) -> Self {
+        Self {
+            results: Cache::builder()
+                .max_capacity(MAX_CACHED_RESULTS)
+                .time_to_live(ttl)
+                .build(),
+            hits: Default::default(),
+            misses: Default::default(),
+        }
+    }
+
+    /// Cached result of `method` on `address`, otherwise the result of `read`. Failed
+    /// reads are not cached
+    async fn get_or_read<T, Fut>(
+        &self,
+        address: String,
+        method: &'static str,
+        read: impl FnOnce() -> Fut,
+    ) -> Result<T>
+    where
+        T: Clone + Send + Sync + 'static,
+        Fut: Future<Output = Result<T>>,
+    {
+        let key = (address, method);
+        if let Some(value) = self.cached(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(value);
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        let value = read().await?;
+        self.results.insert(key, Arc::new(value.clone()));
+
+        Ok(value)
+    }
+
+    fn invalidate(&self, address: &str) {
+        let keys = self
+            .results
+            .iter()
+            .filter(|(key, _)| key.0 == address)
+            .map(|(key, _)| key)
+            .collect::<Vec<_>>();
+        for key in keys {
+            self.results.invalidate(&*key);
+        }
+    }
+
+    fn cached<T: Clone + 'static>(&self, key: &CacheKey) -> Option<T> {
+        self.results.get(key)?.downcast_ref::<T>().cloned()
+    }
+}
+
+/// Contract getter calls of the metadata, royalty and TIP-4.3 readers and the API.
+///
+/// Calls wait for their slot of the per-second rate limit and for a permit of the
+/// `RpcLimiter`. Results are cached by address and getter for `cache_ttl`, so a
+/// backfill reading the same collection for each of its nfts hits the node once.
+/// Clones share the limits and the cache.
+#[derive(Clone)]
+pub struct GetterClient {
+    jrpc_client: JrpcClient,
+    rpc_limiter: RpcLimiter,
+    rate_limit: Option<Arc<RateLimit>>,
+    cache: Arc<GetterCache>,
+}
+
+impl GetterClient {
+    /// `rate_limit_per_sec` of `None` or 0 doesn't limit the rate
+    pub fn new(
+        jrpc_client: JrpcClient,
+        rpc_limiter: RpcLimiter,
+        rate_limit_per_sec: Option<u32>,
+        cache_ttl: Duration,
+    ) -> Self {
+        Self {
+            jrpc_client,
+            rpc_limiter,
+            rate_limit: rate_limit_per_sec
+                .filter(|rate| *rate > 0)
+                .map(|rate| Arc::new(RateLimit::new(rate))),
+            cache: Arc::new(GetterCache::new(cache_ttl)),
+        }
+    }
+
+    pub fn rpc_limiter(&self) -> &RpcLimiter {
+        &self.rpc_limiter
+    }
+
+    /// Result of `method` on `address`, cached or read by `getter`. Failed calls are
+    /// not cached
+    pub async fn call<T, F, Fut>(
+        &self,
+        address: &MsgAddressInt,
+        method: &'static str,
+        getter: F,
+    ) -> Result<T>
+    where
+        T: Clone + Send + Sync + 'static,
+        F: FnOnce(JrpcClient) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        self.cache
+            .get_or_read(address.to_string(), method, || async {
+                if let Some(rate_limit) = &self.rate_limit {
+                    rate_limit.wait().await;
+                }
+                let _permit = self.rpc_limiter.acquire().await;
+                getter(self.jrpc_client.clone()).await
+            })
+            .await
+    }
+
+    /// Forgets the cached results of every getter of `address`
+    pub fn invalidate(&self, address: &str) {
+        self.cache.invalidate(address);
+    }
+
+    pub fn cache_hits(&self) -> u64 {
+        self.cache.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn cache_misses(&self) -> u64 {
+        self.cache.misses.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use anyhow::anyhow;
+    use tokio::time::Instant;
+
+    use super::{GetterCache, RateLimit};
+
+    const TTL: Duration = Duration::from_secs(60);
+
+    /// Reads `value` and counts the reads
+    async fn read(cache: &GetterCache, address: &str, reads: &AtomicUsize, value: u32) -> u32 {
+        cache
+            .get_or_read(address.to_string(), "getInfo", || async {
+                reads.fetch_add(1, Ordering::Relaxed);
+                Ok(value)
+            })
+            .await
+            .unwrap()
+    }
+
+    // the cache keeps its own clock, this one waits out a short ttl for real
+    #[tokio::test]
+    async fn test_results_are_cached_until_the_ttl() {
+        let ttl = Duration::from_millis(100);
+        let (cache, reads) = (GetterCache::new(ttl), AtomicUsize::new(0));
+
+        assert_eq!(read(&cache, "0:a", &reads, 1).await, 1);
+        assert_eq!(read(&cache, "0:a", &reads, 2).await, 1);
+        assert_eq!(reads.load(Ordering::Relaxed), 1);
+        assert_eq!(cache.hits.load(Ordering::Relaxed), 1);
+        assert_eq!(cache.misses.load(Ordering::Relaxed), 1);
+
+        // another address or getter is another result
+        assert_eq!(read(&cache, "0:b", &reads, 3).await, 3);
+        let other_getter = cache
+            .get_or_read("0:a".to_string(), "royaltyInfo", || async { Ok(4u32) })
+            .await
+            .unwrap();
+        assert_eq!(other_getter, 4);
+
+        tokio::time::sleep(ttl).await;
+        assert_eq!(read(&cache, "0:a", &reads, 5).await, 5);
+        assert_eq!(reads.load(Ordering::Relaxed), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_invalidate_drops_every_getter_of_the_address() {
+        let (cache, reads) = (GetterCache::new(TTL), AtomicUsize::new(0));
+        read(&cache, "0:a", &reads, 1).await;
+        read(&cache, "0:b", &reads, 1).await;
+
+        cache.invalidate("0:a");
+
+        assert_eq!(read(&cache, "0:a", &reads, 2).await, 2);
+        assert_eq!(read(&cache, "0:b", &reads, 2).await, 1);
+        assert_eq!(reads.load(Ordering::Relaxed), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_failed_reads_are_not_cached() {
+        let (cache, reads) = (GetterCache::new(TTL), AtomicUsize::new(0));
+
+        let failed = cache
+            .get_or_read("0:a".to_string(), "getInfo", || async {
+                Err::<u32, _>(anyhow!("node unavailable"))
+            })
+            .await;
+        assert!(failed.is_err());
+
+        assert_eq!(read(&cache, "0:a", &reads, 1).await, 1);
+        assert_eq!(reads.load(Ordering::Relaxed), 1);
+        assert_eq!(cache.misses.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rate_limit_spaces_calls() {
+        let rate_limit = RateLimit::new(4);
+        let started = Instant::now();
+
+        for _ in 0..3 {
+            rate_limit.wait().await;
+        }
+        assert_eq!(started.elapsed(), Duration::from_millis(500));
+
+        // an idle limiter doesn't save up slots
+        tokio::time::advance(Duration::from_secs(10)).await;
+        let idle = Instant::now();
+        rate_limit.wait().await;
+        rate_limit.wait().await;
+        assert_eq!(idle.elapsed(), Duration::from_millis(250));
+    }
+}
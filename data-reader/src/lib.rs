@@ -1,7 +1,13 @@
+mod getter;
+mod limiter;
 mod meta;
 mod price;
+mod rarity_queue;
 mod service;
 
+pub use getter::*;
+pub use limiter::*;
 pub use meta::*;
 pub use price::*;
+pub use rarity_queue::*;
 pub use service::*;
@@ -0,0 +1,55 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+/// Caps the number of node RPC calls running at once.
+///
+/// Clones share the same semaphore, so every resolver job holding a clone
+/// competes for the same limit.
+#[derive(Clone)]
+pub struct RpcLimiter {
+    semaphore: Arc<Semaphore>,
+    in_flight: Arc<AtomicUsize>,
+}
+
+pub struct RpcPermit<'a> {
+    _permit: SemaphorePermit<'a>,
+    in_flight: &'a AtomicUsize,
+}
+
+impl RpcLimiter {
+    pub fn new(max_concurrency: usize) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(max_concurrency.max(1))),
+            in_flight: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    pub async fn acquire(&self) -> RpcPermit<'_> {
+        let permit = self
+            .semaphore
+            .acquire()
+            .await
+            .expect("RPC limiter semaphore is never closed");
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+
+        RpcPermit {
+            _permit: permit,
+            in_flight: &self.in_flight,
+        }
+    }
+
+    /// Number of RPC calls currently holding a permit
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for RpcPermit<'_> {
+    fn drop(&mut self) {
+        self.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
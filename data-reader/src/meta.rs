@@ -1,30 +1,33 @@
 use std::{str::FromStr, time::Duration};
 
-use crate::service::MetadataJrpcService;
+use crate::{service::MetadataJrpcService, GetterClient, RarityQueue};
 use anyhow::{bail, Result};
 use indexer_repo::{
     meta::{MetadataModelService, NftAddressData, NftMeta, NftMetaAttribute},
-    types::NftCollectionMeta,
+    token_registry::{dequeue_token, get_queued_tokens, save_token, Token},
+    types::{CollectionRoyalty, NftCollectionMeta},
 };
 use serde_json::Value;
 use sqlx::{types::chrono, PgPool};
 use ton_block::MsgAddressInt;
-use transaction_consumer::JrpcClient;
 
 const NFT_PER_ITERATION: i64 = 1_000;
 const COLLECTION_PER_ITERATION: i64 = 100;
+const TOKEN_PER_ITERATION: i64 = 100;
 
 #[derive(Clone)]
 pub struct MetaReaderContext {
-    pub jrpc_client: JrpcClient,
+    pub getter_client: GetterClient,
     pub pool: PgPool,
+    /// Marked with the collections whose nft attributes were saved
+    pub rarity_queue: RarityQueue,
     pub jrpc_req_latency_millis: u64,
     pub idle_after_loop: u64,
 }
 
 pub async fn run_meta_reader(context: MetaReaderContext) -> Result<()> {
     log::info!("Run metadata reader");
-    let meta_jrpc_service = MetadataJrpcService::new(context.jrpc_client.clone());
+    let meta_jrpc_service = MetadataJrpcService::new(context.getter_client.clone());
     let meta_model_service = MetadataModelService::new(context.pool.clone());
 
     loop {
@@ -33,8 +36,13 @@ pub async fn run_meta_reader(context: MetaReaderContext) -> Result<()> {
             .await?;
 
         for address_data in nft_addresses.iter() {
-            if let Err(e) =
-                update_nft_meta(address_data, &meta_model_service, &meta_jrpc_service).await
+            if let Err(e) = update_nft_meta(
+                address_data,
+                &meta_model_service,
+                &meta_jrpc_service,
+                &context.rarity_queue,
+            )
+            .await
             {
                 log::error!("{:#?}", e);
 
@@ -82,8 +90,37 @@ pub async fn run_meta_reader(context: MetaReaderContext) -> Result<()> {
             tokio::time::sleep(Duration::from_millis(context.jrpc_req_latency_millis)).await;
         }
 
-        if nft_addresses.is_empty() && collection_addresses.is_empty() {
-            log::info!("Finished updating metadata work. Idling");
+        let token_addresses = get_queued_tokens(&context.pool, TOKEN_PER_ITERATION).await?;
+
+        for address in token_addresses.iter() {
+            if let Err(e) = update_token(address, &context.pool, &meta_jrpc_service).await {
+                log::error!(
+                    "Token address: {}, error while reading token meta: {:#?}",
+                    address,
+                    e
+                );
+
+                // The token is queued again by its next listing or sale
+                if let Err(e) = dequeue_token(&context.pool, address).await {
+                    log::error!(
+                        "Token address: {}, error while removing from token_registry_queue table: {:#?}",
+                        address,
+                        e
+                    );
+                }
+            }
+
+            tokio::time::sleep(Duration::from_millis(context.jrpc_req_latency_millis)).await;
+        }
+
+        if nft_addresses.is_empty() && collection_addresses.is_empty() && token_addresses.is_empty()
+        {
+            log::info!(
+                "Finished updating metadata work. Idling (rpc calls in flight: {}, getter cache hits: {}, misses: {})",
+                context.getter_client.rpc_limiter().in_flight(),
+                context.getter_client.cache_hits(),
+                context.getter_client.cache_misses()
+            );
             tokio::time::sleep(Duration::from_secs(context.idle_after_loop)).await;
 
             continue;
@@ -91,6 +128,31 @@ pub async fn run_meta_reader(context: MetaReaderContext) -> Result<()> {
     }
 }
 
+pub async fn update_token(
+    address: &str,
+    pool: &PgPool,
+    meta_jrpc_service: &MetadataJrpcService,
+) -> Result<()> {
+    let Ok(root) = MsgAddressInt::from_str(address) else {
+        bail!(
+            "Error while converting token address {} to MsgAddressInt",
+            address
+        );
+    };
+
+    let (symbol, decimals) = meta_jrpc_service.get_token_meta(&root).await?;
+
+    save_token(
+        pool,
+        &Token {
+            address: address.to_string(),
+            symbol,
+            decimals: decimals as i32,
+        },
+    )
+    .await
+}
+
 pub async fn update_collections_meta(
     address: &str,
     meta_model_service: &MetadataModelService,
@@ -102,6 +164,18 @@ pub async fn update_collections_meta(
 
     let mut failed = None;
 
+    let royalty = match meta_jrpc_service
+        .get_collection_royalty(&collection_address)
+        .await
+    {
+        Ok(royalty) => Some(royalty),
+        Err(e) => {
+            log::error!("Error while reading {address} collection royalty: {:#?}", e);
+            failed = Some(true);
+            None
+        }
+    };
+
     let (owner, meta) = match meta_jrpc_service
         .get_collection_meta(collection_address)
         .await
@@ -170,6 +244,32 @@ pub async fn update_collections_meta(
         );
     };
 
+    if let Some(royalty) = royalty {
+        let (numerator, denominator, recipient) = match royalty {
+            Some((numerator, denominator, recipient)) => (
+                Some(numerator as i32),
+                Some(denominator as i32),
+                Some(recipient),
+            ),
+            None => (None, None, None),
+        };
+        let royalty = CollectionRoyalty {
+            collection: address.into(),
+            numerator,
+            denominator,
+            recipient,
+            updated: now,
+        };
+
+        if let Err(e) = tx.update_collection_royalty(&royalty).await {
+            bail!(
+                "Collection address: {}, error while updating collection royalty: {:#?}",
+                address,
+                e
+            );
+        };
+    }
+
     if let Err(e) = tx.add_to_proceeded(address, failed).await {
         bail!(
             "Collection address: {}, error while adding to meta_handled_addresses table: {:#?}",
@@ -193,6 +293,7 @@ pub async fn update_nft_meta(
     address_data: &NftAddressData,
     meta_model_service: &MetadataModelService,
     meta_jrpc_service: &MetadataJrpcService,
+    rarity_queue: &RarityQueue,
 ) -> Result<()> {
     let Ok(nft_address) = MsgAddressInt::from_str(&address_data.nft) else {
                 bail!("Error while converting nft address {} to MsgAddressInt", address_data.nft);
@@ -225,13 +326,14 @@ pub async fn update_nft_meta(
     let attr = meta
         .get("attributes")
         .and_then(|d| d.as_array())
-        .and_then(|d| d.is_empty().then_some(d))
+        .and_then(|d| (!d.is_empty()).then_some(d))
         .map(|d| {
             d.iter()
                 .map(|e| NftMetaAttribute::new(e, address_data))
                 .collect::<Vec<_>>()
         });
 
+    let attributes_updated = attr.is_some();
     if let Some(attr) = attr {
         if let Err(e) = tx.update_nft_attributes(&attr).await {
             bail!(
@@ -256,6 +358,48 @@ pub async fn update_nft_meta(
         );
     };
 
+    // Nfts without a TIP-4.3 index keep the collection from their events
+    let mut collection = address_data.collection.clone();
+    match meta_jrpc_service
+        .get_verified_collection(&nft_address)
+        .await
+    {
+        Ok(Some((verified, index))) => {
+            collection = verified.to_string();
+            if collection != address_data.collection {
+                log::warn!(
+                    "Nft {} claims collection {}, its index {} proves {}",
+                    address_data.nft,
+                    address_data.collection,
+                    index,
+                    collection
+                );
+            }
+
+            if let Err(e) = tx
+                .update_nft_collection_verified(
+                    &address_data.nft,
+                    &collection,
+                    &index.to_string(),
+                    chrono::Utc::now().naive_utc(),
+                )
+                .await
+            {
+                bail!(
+                    "Nft address: {}, error while saving verified collection: {:#?}",
+                    &address_data.nft,
+                    e
+                );
+            }
+        }
+        Ok(None) => {}
+        Err(e) => log::error!(
+            "Error while verifying {} collection: {:#?}",
+            address_data.nft,
+            e
+        ),
+    }
+
     if let Err(e) = tx.add_to_proceeded(&address_data.nft, None).await {
         bail!(
             "Nft address: {}, error while adding to meta_handled_addresses table: {:#?}",
@@ -272,6 +416,11 @@ pub async fn update_nft_meta(
         );
     };
 
+    // Scores depend on every nft's attributes, the collection is ranked again
+    if attributes_updated {
+        rarity_queue.mark(collection);
+    }
+
     Ok(())
 }
 
@@ -0,0 +1,72 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+use indexer_repo::types::decoded::EventRecord;
+use indexer_repo::types::EventType;
+
+/// Collections whose supply or nft attributes changed since the last rarity run. Mints
+/// come in bursts, so they are collected and each collection is recomputed once per run
+#[derive(Clone, Default)]
+pub struct RarityQueue {
+    collections: Arc<Mutex<HashSet<String>>>,
+}
+
+impl RarityQueue {
+    /// Marks the collections of committed `NftCreated`/`NftBurned` events
+    pub fn mark_supply_changes(&self, events: &[EventRecord]) {
+        let changed = events
+            .iter()
+            .filter(|e| matches!(e.event_type, EventType::NftCreated | EventType::NftBurned))
+            .filter_map(|e| e.collection.clone());
+
+        self.collections.lock().unwrap().extend(changed);
+    }
+
+    pub fn take(&self) -> HashSet<String> {
+        std::mem::take(&mut *self.collections.lock().unwrap())
+    }
+
+    pub fn mark(&self, collection: String) {
+        self.collections.lock().unwrap().insert(collection);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use indexer_repo::types::decoded::EventRecord;
+    use indexer_repo::types::{EventCategory, EventType};
+
+    use super::RarityQueue;
+
+    fn event(event_type: EventType, collection: &str) -> EventRecord {
+        EventRecord {
+            event_category: EventCategory::Collection,
+            event_type,
+            address: collection.to_string(),
+            created_lt: 1,
+            created_at: 0,
+            message_hash: "hash".to_string(),
+            nft: Some("0:nft".to_string()),
+            collection: Some(collection.to_string()),
+            raw_data: serde_json::Value::Null,
+        }
+    }
+
+    #[test]
+    fn test_collections_are_recomputed_once_per_run_on_supply_change() {
+        let queue = RarityQueue::default();
+
+        queue.mark_supply_changes(&[
+            event(EventType::NftCreated, "0:minting"),
+            event(EventType::NftCreated, "0:minting"),
+            event(EventType::NftBurned, "0:burning"),
+            event(EventType::NftOwnerChanged, "0:trading"),
+        ]);
+        queue.mark("0:burning".to_string());
+
+        let mut marked = queue.take().into_iter().collect::<Vec<_>>();
+        marked.sort();
+        assert_eq!(marked, ["0:burning", "0:minting"]);
+        assert!(queue.take().is_empty());
+    }
+}
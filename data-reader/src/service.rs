@@ -1,22 +1,89 @@
-use anyhow::{anyhow, Result};
-use nekoton_abi::{FunctionBuilder, FunctionExt, UnpackFirst};
+use anyhow::{anyhow, bail, Result};
+use nekoton_abi::{BuildTokenValue, FunctionBuilder, FunctionExt, UnpackAbi, UnpackFirst};
 use nekoton_utils::SimpleClock;
-use ton_block::{MsgAddrStd, MsgAddressInt};
+use ton_abi::TokenValue;
+use ton_block::{AccountState, MsgAddrStd, MsgAddressInt};
+use ton_types::UInt256;
 use transaction_consumer::JrpcClient;
 
+use crate::GetterClient;
+
+/// Getter calls go through the shared `GetterClient`, the `read_*` functions run on a
+/// cache miss
 #[derive(Clone)]
 pub struct MetadataJrpcService {
-    jrpc_client: JrpcClient,
+    getter_client: GetterClient,
 }
 
 impl MetadataJrpcService {
-    pub fn new(jrpc_client: JrpcClient) -> Self {
-        Self { jrpc_client }
+    pub fn new(getter_client: GetterClient) -> Self {
+        Self { getter_client }
+    }
+
+    pub fn getter_client(&self) -> &GetterClient {
+        &self.getter_client
     }
 
     pub async fn get_nft_meta(&self, address: &MsgAddressInt) -> Result<serde_json::Value> {
-        let contract = self
-            .jrpc_client
+        self.getter_client
+            .call(address, "getJson", |jrpc_client| {
+                Self::read_nft_meta(jrpc_client, address)
+            })
+            .await
+    }
+
+    /// Symbol and decimals of a TIP-3 token root
+    pub async fn get_token_meta(&self, root: &MsgAddressInt) -> Result<(String, u8)> {
+        self.getter_client
+            .call(root, "symbol", |jrpc_client| {
+                Self::read_token_meta(jrpc_client, root)
+            })
+            .await
+    }
+
+    /// `(numerator, denominator, receiver)`, `None` if the collection has no `royaltyInfo` getter
+    pub async fn get_collection_royalty(
+        &self,
+        collection: &MsgAddressInt,
+    ) -> Result<Option<(u32, u32, String)>> {
+        self.getter_client
+            .call(collection, "royaltyInfo", |jrpc_client| {
+                Self::read_collection_royalty(jrpc_client, collection)
+            })
+            .await
+    }
+
+    /// Collection of a TIP-4.3 nft and the address of its index, `None` for nfts
+    /// without an index. The nft resolves the index itself, so the deployed index must
+    /// run the index code of the claimed collection salted with collection and owner,
+    /// which a forged nft can't make its own contract run
+    pub async fn get_verified_collection(
+        &self,
+        nft: &MsgAddressInt,
+    ) -> Result<Option<(MsgAddressInt, MsgAddressInt)>> {
+        self.getter_client
+            .call(nft, "resolveIndex", |jrpc_client| {
+                Self::read_verified_collection(jrpc_client, nft)
+            })
+            .await
+    }
+
+    pub async fn get_collection_meta(
+        &self,
+        collection: MsgAddressInt,
+    ) -> Result<(Option<String>, serde_json::Value)> {
+        self.getter_client
+            .call(&collection, "getJson+owner", |jrpc_client| {
+                Self::read_collection_meta(jrpc_client, collection.clone())
+            })
+            .await
+    }
+
+    async fn read_nft_meta(
+        jrpc_client: JrpcClient,
+        address: &MsgAddressInt,
+    ) -> Result<serde_json::Value> {
+        let contract = jrpc_client
             .get_contract_state(address)
             .await?
             .ok_or_else(|| anyhow!("Contract state is none!"))?;
@@ -29,6 +96,20 @@ impl MetadataJrpcService {
         )?)
     }
 
+    async fn read_token_meta(
+        jrpc_client: JrpcClient,
+        root: &MsgAddressInt,
+    ) -> Result<(String, u8)> {
+        let contract = jrpc_client
+            .get_contract_state(root)
+            .await?
+            .ok_or_else(|| anyhow!("Contract state is none!"))?;
+
+        let root = nekoton_contracts::tip3::RootTokenContract(contract.as_context(&SimpleClock));
+
+        Ok((root.symbol()?, root.decimals()?))
+    }
+
     fn owner() -> ton_abi::Function {
         FunctionBuilder::new("owner")
             .abi_version(ton_abi::contract::ABI_VERSION_2_2)
@@ -45,12 +126,162 @@ impl MetadataJrpcService {
             .build()
     }
 
-    pub async fn get_collection_meta(
-        &self,
+    fn royalty_info() -> ton_abi::Function {
+        FunctionBuilder::new("royaltyInfo")
+            .abi_version(ton_abi::contract::ABI_VERSION_2_2)
+            .default_headers()
+            .output("numerator", ton_abi::ParamType::Uint(32))
+            .output("denominator", ton_abi::ParamType::Uint(32))
+            .output("receiver", ton_abi::ParamType::Address)
+            .build()
+    }
+
+    async fn read_collection_royalty(
+        jrpc_client: JrpcClient,
+        collection: &MsgAddressInt,
+    ) -> Result<Option<(u32, u32, String)>> {
+        let contract = jrpc_client
+            .get_contract_state(collection)
+            .await?
+            .ok_or_else(|| anyhow!("Contract state is none!"))?;
+
+        // The state was read, so a failing getter means the contract doesn't implement it
+        let output = match MetadataJrpcService::royalty_info().run_local(
+            &SimpleClock,
+            contract.account,
+            &[],
+        ) {
+            Ok(output) if output.result_code == 0 => output,
+            Ok(output) => {
+                log::debug!(
+                    "Collection {} has no royalty getter (exit code {})",
+                    collection.to_string(),
+                    output.result_code
+                );
+                return Ok(None);
+            }
+            Err(e) => {
+                log::debug!(
+                    "Collection {} has no royalty getter: {:#?}",
+                    collection.to_string(),
+                    e
+                );
+                return Ok(None);
+            }
+        };
+
+        let Some(tokens) = output.tokens else {
+            return Ok(None);
+        };
+        let mut tokens = tokens.into_iter();
+        let mut next = || {
+            tokens
+                .next()
+                .ok_or_else(|| anyhow!("Royalty getter returned too few values"))
+        };
+
+        let numerator: u32 = next()?.unpack()?;
+        let denominator: u32 = next()?.unpack()?;
+        let receiver: MsgAddressInt = next()?.unpack()?;
+
+        Ok(Some((numerator, denominator, receiver.to_string())))
+    }
+
+    async fn read_verified_collection(
+        jrpc_client: JrpcClient,
+        nft: &MsgAddressInt,
+    ) -> Result<Option<(MsgAddressInt, MsgAddressInt)>> {
+        let nft_contract = jrpc_client
+            .get_contract_state(nft)
+            .await?
+            .ok_or_else(|| anyhow!("Contract state is none!"))?;
+
+        let info = nekoton_contracts::tip4_1::NftContract(nft_contract.as_context(&SimpleClock))
+            .get_info()?;
+
+        // The state was read, so a failing getter means the nft doesn't implement TIP-4.3
+        let index =
+            match nekoton_contracts::tip4_3::NftContract(nft_contract.as_context(&SimpleClock))
+                .resolve_index(&info.collection, &info.owner)
+            {
+                Ok(index) => index,
+                Err(e) => {
+                    log::debug!("Nft {} has no index: {:#?}", nft.to_string(), e);
+                    return Ok(None);
+                }
+            };
+
+        let Some(index_contract) = jrpc_client.get_contract_state(&index).await? else {
+            log::debug!("Index {} of nft {} is not deployed", index, nft);
+            return Ok(None);
+        };
+
+        let index_info =
+            nekoton_contracts::tip4_3::IndexContract(index_contract.as_context(&SimpleClock))
+                .get_info()?;
+
+        if &index_info.nft != nft || index_info.collection != info.collection {
+            bail!(
+                "Index {} doesn't belong to nft {} of collection {}",
+                index,
+                nft,
+                info.collection
+            );
+        }
+
+        let expected =
+            Self::read_index_code_hash(&jrpc_client, &info.collection, &info.owner).await?;
+        let deployed = match &index_contract.account.storage.state {
+            AccountState::AccountActive { state_init } => {
+                state_init.code.as_ref().map(|code| code.repr_hash())
+            }
+            _ => None,
+        };
+        if deployed != Some(expected) {
+            bail!(
+                "Index {} of nft {} doesn't run the index code of collection {}",
+                index,
+                nft,
+                info.collection
+            );
+        }
+
+        Ok(Some((index_info.collection, index)))
+    }
+
+    /// Hash of the code `collection` deploys the indexes of `owner`'s nfts with, its
+    /// index code salted as TIP-4.3 `_buildIndexCode` does
+    async fn read_index_code_hash(
+        jrpc_client: &JrpcClient,
+        collection: &MsgAddressInt,
+        owner: &MsgAddressInt,
+    ) -> Result<UInt256> {
+        let contract = jrpc_client
+            .get_contract_state(collection)
+            .await?
+            .ok_or_else(|| anyhow!("Contract state is none!"))?;
+
+        let code = nekoton_contracts::tip4_3::CollectionContract(contract.as_context(&SimpleClock))
+            .index_code()?;
+        let salt = TokenValue::pack_values_into_chain(
+            &[
+                "nft".to_string().token_value(),
+                collection.clone().token_value(),
+                owner.clone().token_value(),
+            ],
+            Vec::new(),
+            &ton_abi::contract::ABI_VERSION_2_2,
+        )?
+        .into_cell()?;
+
+        Ok(nekoton_abi::set_code_salt(code, salt)?.repr_hash())
+    }
+
+    async fn read_collection_meta(
+        jrpc_client: JrpcClient,
         collection: MsgAddressInt,
     ) -> Result<(Option<String>, serde_json::Value)> {
-        let contract = self
-            .jrpc_client
+        let contract = jrpc_client
             .get_contract_state(&collection)
             .await?
             .ok_or_else(|| anyhow!("Contract state is none!"))?;
@@ -0,0 +1,44 @@
+use actix_web::{get, post, web, HttpResponse};
+use data_reader::MetadataJrpcService;
+use serde_json::json;
+
+use crate::ParserControl;
+
+#[get("/stats")]
+pub async fn get_stats(
+    parser_control: web::Data<ParserControl>,
+    meta_jrpc_service: web::Data<MetadataJrpcService>,
+) -> HttpResponse {
+    HttpResponse::Ok().json(json!({
+        "paused_parsers": parser_control.paused(),
+        "parsers_with_parked_events": parser_control.parked(),
+        "rpc_in_flight": meta_jrpc_service.getter_client().rpc_limiter().in_flight(),
+        "getter_cache_hits": meta_jrpc_service.getter_client().cache_hits(),
+        "getter_cache_misses": meta_jrpc_service.getter_client().cache_misses(),
+    }))
+}
+
+#[post("/parsers/{parser}/pause")]
+pub async fn pause_parser(
+    parser: web::Path<String>,
+    parser_control: web::Data<ParserControl>,
+) -> HttpResponse {
+    set_paused(&parser, &parser_control, true)
+}
+
+#[post("/parsers/{parser}/resume")]
+pub async fn resume_parser(
+    parser: web::Path<String>,
+    parser_control: web::Data<ParserControl>,
+) -> HttpResponse {
+    set_paused(&parser, &parser_control, false)
+}
+
+fn set_paused(parser: &str, parser_control: &ParserControl, paused: bool) -> HttpResponse {
+    if parser_control.set_paused(parser, paused) {
+        log::warn!("Parser {} paused: {}", parser, paused);
+        HttpResponse::Ok().json(json!({ "paused_parsers": parser_control.paused() }))
+    } else {
+        HttpResponse::NotFound().body(format!("Unknown parser {parser}"))
+    }
+}
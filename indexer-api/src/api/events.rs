@@ -0,0 +1,83 @@
+use actix_web::{get, web, HttpResponse};
+use indexer_repo::events::{EventCursor, EventFilter};
+use indexer_repo::types::decoded::EventRecord;
+use indexer_repo::types::EventType;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use crate::api::nft::Pagination;
+
+#[derive(Deserialize)]
+pub struct EventsQuery {
+    address: Option<String>,
+    #[serde(rename = "type")]
+    event_type: Option<EventType>,
+    /// `next` of the previous page
+    after: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct EventsPage {
+    events: Vec<EventRecord>,
+    next: Option<String>,
+}
+
+#[get("/events")]
+pub async fn get_events(
+    query: web::Query<EventsQuery>,
+    page: web::Query<Pagination>,
+    pool: web::Data<PgPool>,
+) -> HttpResponse {
+    let after = match query.after.as_deref().map(str::parse::<EventCursor>) {
+        Some(Ok(cursor)) => Some(cursor),
+        Some(Err(err)) => return HttpResponse::BadRequest().body(err.to_string()),
+        None => None,
+    };
+
+    let filter = EventFilter {
+        address: query.address.clone(),
+        event_type: query.event_type,
+        from_lt: None,
+    };
+
+    match indexer_repo::events::list_events(&pool, &filter, after.as_ref(), page.limit()).await {
+        Ok((events, next)) => HttpResponse::Ok().json(EventsPage {
+            events,
+            next: next.map(|cursor| cursor.to_string()),
+        }),
+        Err(err) => {
+            log::error!("list events error {err}");
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// What was indexed from the message, with the decoded event args
+#[get("/events/{message_hash}")]
+pub async fn get_event(message_hash: web::Path<String>, pool: web::Data<PgPool>) -> HttpResponse {
+    match indexer_repo::events::get_event_by_message_hash(&pool, &message_hash).await {
+        Ok(Some(event)) => HttpResponse::Ok().json(event),
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Err(err) => {
+            log::error!("get event error {err}");
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// Newest events the address sold, bought, bid or owned in, see
+/// `indexer_repo::events::get_address_activity` for the searched fields
+#[get("/address/{address}/activity")]
+pub async fn get_address_activity(
+    address: web::Path<String>,
+    page: web::Query<Pagination>,
+    pool: web::Data<PgPool>,
+) -> HttpResponse {
+    match indexer_repo::events::get_address_activity(&pool, &address, page.limit()).await {
+        Ok(events) => HttpResponse::Ok().json(events),
+        Err(err) => {
+            log::error!("get address activity error {err}");
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
@@ -0,0 +1,479 @@
+use std::collections::HashMap;
+
+use actix_web::{post, web};
+use async_graphql::dataloader::{DataLoader, Loader};
+use async_graphql::{Context, EmptyMutation, EmptySubscription, Error, Object, Result, Schema};
+use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse};
+use chrono::NaiveDateTime;
+use indexer_repo::auction::{get_active_auctions_by_nfts, get_auction, AuctionRecord};
+use indexer_repo::collection::{get_collections_by_addresses, CollectionRecord};
+use indexer_repo::direct_sell::{
+    get_active_direct_sells_by_nfts, get_direct_sells, DirectSellRecord,
+};
+use indexer_repo::nft::{get_collection_nfts, get_nft, NftRecord};
+use indexer_repo::price::NftPriceModel;
+use indexer_repo::types::decoded::NftPriceHistory;
+use sqlx::PgPool;
+
+use crate::api::nft::Pagination;
+
+/// Nested enough for an nft with its collection and listings, deeper queries are
+/// rejected before they reach the database
+const MAX_QUERY_DEPTH: usize = 8;
+
+pub type IndexerSchema = Schema<Query, EmptyMutation, EmptySubscription>;
+
+/// Read-only schema over the indexed tables. Collections, current listings and price
+/// histories are batched per request, so a page of nfts costs one query per relation
+pub fn schema(pool: PgPool) -> IndexerSchema {
+    Schema::build(Query, EmptyMutation, EmptySubscription)
+        .data(DataLoader::new(
+            PriceHistoryLoader(NftPriceModel::new(pool.clone())),
+            actix_web::rt::spawn,
+        ))
+        .data(DataLoader::new(
+            CollectionLoader(pool.clone()),
+            actix_web::rt::spawn,
+        ))
+        .data(DataLoader::new(
+            DirectSellLoader(pool.clone()),
+            actix_web::rt::spawn,
+        ))
+        .data(DataLoader::new(
+            AuctionLoader(pool.clone()),
+            actix_web::rt::spawn,
+        ))
+        .data(pool)
+        .limit_depth(MAX_QUERY_DEPTH)
+        .finish()
+}
+
+#[post("/graphql")]
+pub async fn graphql(schema: web::Data<IndexerSchema>, request: GraphQLRequest) -> GraphQLResponse {
+    schema.execute(request.into_inner()).await.into()
+}
+
+fn db_error(e: anyhow::Error) -> Error {
+    log::error!("graphql query error {e:#}");
+    Error::new("database error")
+}
+
+pub struct Query;
+
+#[Object]
+impl Query {
+    async fn nft(&self, ctx: &Context<'_>, address: String) -> Result<Option<Nft>> {
+        let pool = ctx.data::<PgPool>()?;
+        Ok(get_nft(pool, &address).await.map_err(db_error)?.map(Nft))
+    }
+
+    async fn collection(&self, ctx: &Context<'_>, address: String) -> Result<Option<Collection>> {
+        let loader = ctx.data::<DataLoader<CollectionLoader>>()?;
+        Ok(loader.load_one(address).await?.map(Collection))
+    }
+
+    async fn direct_sell(&self, ctx: &Context<'_>, address: String) -> Result<Option<DirectSell>> {
+        let pool = ctx.data::<PgPool>()?;
+        Ok(get_direct_sells(pool, &[&address])
+            .await
+            .map_err(db_error)?
+            .into_iter()
+            .next()
+            .map(DirectSell))
+    }
+
+    async fn auction(&self, ctx: &Context<'_>, address: String) -> Result<Option<Auction>> {
+        let pool = ctx.data::<PgPool>()?;
+        Ok(get_auction(pool, &address)
+            .await
+            .map_err(db_error)?
+            .map(Auction))
+    }
+}
+
+pub struct Nft(NftRecord);
+
+#[Object]
+impl Nft {
+    async fn address(&self) -> &str {
+        &self.0.address
+    }
+
+    async fn id(&self) -> String {
+        self.0.id.to_string()
+    }
+
+    async fn name(&self) -> Option<&str> {
+        self.0.name.as_deref()
+    }
+
+    async fn description(&self) -> Option<&str> {
+        self.0.description.as_deref()
+    }
+
+    async fn owner(&self) -> &str {
+        &self.0.owner
+    }
+
+    async fn manager(&self) -> &str {
+        &self.0.manager
+    }
+
+    async fn burned(&self) -> bool {
+        self.0.burned
+    }
+
+    /// Null until the mint is indexed
+    async fn minter(&self) -> Option<&str> {
+        self.0.minter.as_deref()
+    }
+
+    async fn created_lt(&self) -> Option<String> {
+        self.0.created_lt.map(|lt| lt.to_string())
+    }
+
+    async fn created_at(&self) -> Option<NaiveDateTime> {
+        self.0.created_at
+    }
+
+    async fn collection(&self, ctx: &Context<'_>) -> Result<Option<Collection>> {
+        let loader = ctx.data::<DataLoader<CollectionLoader>>()?;
+        Ok(loader
+            .load_one(self.0.collection.clone())
+            .await?
+            .map(Collection))
+    }
+
+    /// Active, unexpired listing of the nft
+    async fn direct_sell(&self, ctx: &Context<'_>) -> Result<Option<DirectSell>> {
+        let loader = ctx.data::<DataLoader<DirectSellLoader>>()?;
+        Ok(loader
+            .load_one(self.0.address.clone())
+            .await?
+            .map(DirectSell))
+    }
+
+    /// Active auction of the nft
+    async fn auction(&self, ctx: &Context<'_>) -> Result<Option<Auction>> {
+        let loader = ctx.data::<DataLoader<AuctionLoader>>()?;
+        Ok(loader.load_one(self.0.address.clone()).await?.map(Auction))
+    }
+
+    /// Sales and bids of the nft, newest first
+    async fn price_history(
+        &self,
+        ctx: &Context<'_>,
+        limit: Option<i64>,
+        offset: Option<i64>,
+    ) -> Result<Vec<PriceHistoryPoint>> {
+        let page = Pagination::new(limit, offset);
+        let loader = ctx.data::<DataLoader<PriceHistoryLoader>>()?;
+        Ok(loader
+            .load_one(PriceHistoryPage {
+                nft: self.0.address.clone(),
+                limit: page.limit(),
+                offset: page.offset(),
+            })
+            .await?
+            .unwrap_or_default()
+            .into_iter()
+            .map(PriceHistoryPoint)
+            .collect())
+    }
+}
+
+pub struct Collection(CollectionRecord);
+
+#[Object]
+impl Collection {
+    async fn address(&self) -> &str {
+        &self.0.address
+    }
+
+    async fn owner(&self) -> Option<&str> {
+        self.0.owner.as_deref()
+    }
+
+    async fn name(&self) -> Option<&str> {
+        self.0.name.as_deref()
+    }
+
+    async fn description(&self) -> Option<&str> {
+        self.0.description.as_deref()
+    }
+
+    async fn logo(&self) -> Option<&str> {
+        self.0.logo.as_deref()
+    }
+
+    async fn wallpaper(&self) -> Option<&str> {
+        self.0.wallpaper.as_deref()
+    }
+
+    async fn verified(&self) -> bool {
+        self.0.verified
+    }
+
+    /// Page of the collection's nfts in mint order
+    async fn nfts(
+        &self,
+        ctx: &Context<'_>,
+        limit: Option<i64>,
+        offset: Option<i64>,
+    ) -> Result<Vec<Nft>> {
+        let page = Pagination::new(limit, offset);
+        let pool = ctx.data::<PgPool>()?;
+        Ok(
+            get_collection_nfts(pool, &self.0.address, page.limit(), page.offset())
+                .await
+                .map_err(db_error)?
+                .into_iter()
+                .map(Nft)
+                .collect(),
+        )
+    }
+}
+
+pub struct DirectSell(DirectSellRecord);
+
+#[Object]
+impl DirectSell {
+    async fn address(&self) -> &str {
+        &self.0.address
+    }
+
+    async fn nft(&self) -> &str {
+        &self.0.nft
+    }
+
+    async fn collection(&self) -> Option<&str> {
+        self.0.collection.as_deref()
+    }
+
+    async fn price_token(&self) -> &str {
+        &self.0.price_token
+    }
+
+    /// Decimal string in the token's smallest units
+    async fn price(&self) -> String {
+        self.0.price.to_string()
+    }
+
+    async fn seller(&self) -> Option<&str> {
+        self.0.seller.as_deref()
+    }
+
+    async fn state(&self) -> String {
+        format!("{:?}", self.0.state)
+    }
+
+    async fn created(&self) -> Option<NaiveDateTime> {
+        self.0.created
+    }
+
+    async fn expired_at(&self) -> Option<NaiveDateTime> {
+        self.0.expired_at
+    }
+
+    async fn finished_at(&self) -> Option<NaiveDateTime> {
+        self.0.finished_at
+    }
+}
+
+pub struct Auction(AuctionRecord);
+
+#[Object]
+impl Auction {
+    async fn address(&self) -> &str {
+        &self.0.address
+    }
+
+    async fn nft(&self) -> &str {
+        &self.0.nft
+    }
+
+    async fn collection(&self) -> &str {
+        &self.0.collection
+    }
+
+    async fn nft_owner(&self) -> &str {
+        &self.0.nft_owner
+    }
+
+    async fn price_token(&self) -> Option<&str> {
+        self.0.price_token.as_deref()
+    }
+
+    async fn start_price(&self) -> Option<String> {
+        self.0.start_price.as_ref().map(ToString::to_string)
+    }
+
+    async fn min_bid(&self) -> Option<String> {
+        self.0.min_bid.as_ref().map(ToString::to_string)
+    }
+
+    async fn max_bid(&self) -> Option<String> {
+        self.0.max_bid.as_ref().map(ToString::to_string)
+    }
+
+    async fn status(&self) -> String {
+        format!("{:?}", self.0.status)
+    }
+
+    async fn created_at(&self) -> Option<NaiveDateTime> {
+        self.0.created_at
+    }
+
+    async fn finished_at(&self) -> Option<NaiveDateTime> {
+        self.0.finished_at
+    }
+
+    async fn winner(&self) -> Option<&str> {
+        self.0.winner.as_deref()
+    }
+}
+
+pub struct PriceHistoryPoint(NftPriceHistory);
+
+#[Object]
+impl PriceHistoryPoint {
+    async fn source(&self) -> &str {
+        &self.0.source
+    }
+
+    async fn source_type(&self) -> String {
+        format!("{:?}", self.0.source_type)
+    }
+
+    async fn created_at(&self) -> NaiveDateTime {
+        self.0.created_at
+    }
+
+    async fn price(&self) -> String {
+        self.0.price.to_string()
+    }
+
+    async fn price_token(&self) -> &str {
+        &self.0.price_token
+    }
+
+    /// The token address while the token isn't in the registry
+    async fn price_token_symbol(&self) -> Option<&str> {
+        self.0.price_token_symbol.as_deref()
+    }
+
+    async fn usd_price(&self) -> Option<String> {
+        self.0.usd_price.as_ref().map(ToString::to_string)
+    }
+
+    async fn buyer(&self) -> Option<&str> {
+        self.0.buyer.as_deref()
+    }
+
+    async fn seller(&self) -> Option<&str> {
+        self.0.seller.as_deref()
+    }
+}
+
+/// Collections by address
+pub struct CollectionLoader(PgPool);
+
+#[async_trait::async_trait]
+impl Loader<String> for CollectionLoader {
+    type Value = CollectionRecord;
+    type Error = Error;
+
+    async fn load(&self, keys: &[String]) -> Result<HashMap<String, Self::Value>, Self::Error> {
+        Ok(get_collections_by_addresses(&self.0, keys)
+            .await
+            .map_err(db_error)?
+            .into_iter()
+            .map(|c| (c.address.clone(), c))
+            .collect())
+    }
+}
+
+/// Active listings by nft address
+pub struct DirectSellLoader(PgPool);
+
+#[async_trait::async_trait]
+impl Loader<String> for DirectSellLoader {
+    type Value = DirectSellRecord;
+    type Error = Error;
+
+    async fn load(&self, keys: &[String]) -> Result<HashMap<String, Self::Value>, Self::Error> {
+        Ok(get_active_direct_sells_by_nfts(&self.0, keys)
+            .await
+            .map_err(db_error)?
+            .into_iter()
+            .map(|ds| (ds.nft.clone(), ds))
+            .collect())
+    }
+}
+
+/// Active auctions by nft address
+pub struct AuctionLoader(PgPool);
+
+#[async_trait::async_trait]
+impl Loader<String> for AuctionLoader {
+    type Value = AuctionRecord;
+    type Error = Error;
+
+    async fn load(&self, keys: &[String]) -> Result<HashMap<String, Self::Value>, Self::Error> {
+        Ok(get_active_auctions_by_nfts(&self.0, keys)
+            .await
+            .map_err(db_error)?
+            .into_iter()
+            .map(|a| (a.nft.clone(), a))
+            .collect())
+    }
+}
+
+/// Page of the price history of an nft
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct PriceHistoryPage {
+    nft: String,
+    limit: i64,
+    offset: i64,
+}
+
+/// Price histories by nft, one query per distinct page asked for
+pub struct PriceHistoryLoader(NftPriceModel);
+
+#[async_trait::async_trait]
+impl Loader<PriceHistoryPage> for PriceHistoryLoader {
+    type Value = Vec<NftPriceHistory>;
+    type Error = Error;
+
+    async fn load(
+        &self,
+        keys: &[PriceHistoryPage],
+    ) -> Result<HashMap<PriceHistoryPage, Self::Value>, Self::Error> {
+        let mut nfts_by_page: HashMap<(i64, i64), Vec<String>> = HashMap::new();
+        for key in keys {
+            nfts_by_page
+                .entry((key.limit, key.offset))
+                .or_default()
+                .push(key.nft.clone());
+        }
+
+        let mut pages: HashMap<PriceHistoryPage, Self::Value> = HashMap::with_capacity(keys.len());
+        for ((limit, offset), nfts) in nfts_by_page {
+            let prices = self
+                .0
+                .get_nfts_price_history(&nfts, limit, offset)
+                .await
+                .map_err(db_error)?;
+            for price in prices {
+                let page = PriceHistoryPage {
+                    nft: price.nft.clone(),
+                    limit,
+                    offset,
+                };
+                pages.entry(page).or_default().push(price);
+            }
+        }
+
+        Ok(pages)
+    }
+}
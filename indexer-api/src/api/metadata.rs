@@ -1,6 +1,6 @@
 use actix_web::web::Json;
 use actix_web::{post, web, HttpResponse};
-use data_reader::MetadataJrpcService;
+use data_reader::{MetadataJrpcService, RarityQueue};
 use indexer_repo::meta::{MetadataModelService, NftAddressData};
 use opg::OpgModel;
 use serde::Deserialize;
@@ -18,7 +18,12 @@ pub async fn refresh_metadata_by_nft(
     path: Json<RefreshMetadataParams>,
     meta_jrpc_service: web::Data<MetadataJrpcService>,
     meta_model_service: web::Data<MetadataModelService>,
+    rarity_queue: web::Data<RarityQueue>,
 ) -> HttpResponse {
+    // A refresh reads the contracts again rather than the cached getter results
+    let getter_client = meta_jrpc_service.getter_client();
+    getter_client.invalidate(&path.0.collection);
+
     let result = match path.0.nft {
         None => {
             if let Err(e) = data_reader::update_collections_meta(
@@ -38,6 +43,7 @@ pub async fn refresh_metadata_by_nft(
                         let mut result = Ok(());
 
                         for nft in nfts {
+                            getter_client.invalidate(&nft);
                             if let Err(e) = data_reader::update_nft_meta(
                                 &NftAddressData {
                                     nft,
@@ -45,6 +51,7 @@ pub async fn refresh_metadata_by_nft(
                                 },
                                 &meta_model_service,
                                 &meta_jrpc_service,
+                                &rarity_queue,
                             )
                             .await
                             {
@@ -60,6 +67,7 @@ pub async fn refresh_metadata_by_nft(
             }
         }
         Some(nft) => {
+            getter_client.invalidate(&nft);
             data_reader::update_nft_meta(
                 &NftAddressData {
                     nft,
@@ -67,6 +75,7 @@ pub async fn refresh_metadata_by_nft(
                 },
                 &meta_model_service,
                 &meta_jrpc_service,
+                &rarity_queue,
             )
             .await
         }
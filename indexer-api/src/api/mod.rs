@@ -1,2 +1,8 @@
+pub mod admin;
 pub mod docs;
+pub mod events;
+pub mod graphql;
 pub mod metadata;
+pub mod nft;
+pub mod sales;
+pub mod schema;
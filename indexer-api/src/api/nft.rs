@@ -0,0 +1,156 @@
+use actix_web::{get, post, web, HttpResponse};
+use indexer_repo::price::NftPriceModel;
+use serde::Deserialize;
+use sqlx::PgPool;
+
+const DEFAULT_PAGE_LIMIT: i64 = 50;
+const MAX_PAGE_LIMIT: i64 = 500;
+
+#[derive(Deserialize)]
+pub struct Pagination {
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+impl Pagination {
+    pub fn new(limit: Option<i64>, offset: Option<i64>) -> Self {
+        Self { limit, offset }
+    }
+
+    pub fn limit(&self) -> i64 {
+        self.limit
+            .unwrap_or(DEFAULT_PAGE_LIMIT)
+            .clamp(1, MAX_PAGE_LIMIT)
+    }
+
+    pub fn offset(&self) -> i64 {
+        self.offset.unwrap_or_default().max(0)
+    }
+}
+
+#[get("/nft/{address}")]
+pub async fn get_nft(address: web::Path<String>, pool: web::Data<PgPool>) -> HttpResponse {
+    match indexer_repo::nft::get_nft(&pool, &address).await {
+        Ok(Some(nft)) => HttpResponse::Ok().json(nft),
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Err(err) => {
+            log::error!("get nft error {err}");
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+#[get("/collection/{address}/nfts")]
+pub async fn get_collection_nfts(
+    address: web::Path<String>,
+    page: web::Query<Pagination>,
+    pool: web::Data<PgPool>,
+) -> HttpResponse {
+    match indexer_repo::nft::get_collection_nfts(&pool, &address, page.limit(), page.offset()).await
+    {
+        Ok(nfts) => HttpResponse::Ok().json(nfts),
+        Err(err) => {
+            log::error!("get collection nfts error {err}");
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct AttributeFilter {
+    trait_type: String,
+    value: String,
+}
+
+/// Nfts of the collection having all of the posted attributes
+#[post("/collection/{address}/nfts/search")]
+pub async fn search_collection_nfts(
+    address: web::Path<String>,
+    filters: web::Json<Vec<AttributeFilter>>,
+    page: web::Query<Pagination>,
+    pool: web::Data<PgPool>,
+) -> HttpResponse {
+    let filters = filters
+        .into_inner()
+        .into_iter()
+        .map(|f| (f.trait_type, f.value))
+        .collect::<Vec<_>>();
+
+    match indexer_repo::nft::search_nfts_by_attributes(
+        &pool,
+        &address,
+        &filters,
+        page.limit(),
+        page.offset(),
+    )
+    .await
+    {
+        Ok(nfts) => HttpResponse::Ok().json(nfts),
+        Err(err) => {
+            log::error!("search collection nfts error {err}");
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// Rarest nfts of the collection, ranks may still change while `provisional`
+#[get("/collection/{address}/rarity")]
+pub async fn get_collection_rarity(
+    address: web::Path<String>,
+    page: web::Query<Pagination>,
+    pool: web::Data<PgPool>,
+) -> HttpResponse {
+    match indexer_repo::rarity::get_collection_rarity_ranking(&pool, &address, page.limit()).await {
+        Ok(ranking) => HttpResponse::Ok().json(ranking),
+        Err(err) => {
+            log::error!("get collection rarity error {err}");
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// Active listings and unburned nfts of the collection
+#[get("/collection/{address}/stats")]
+pub async fn get_collection_stats(
+    address: web::Path<String>,
+    pool: web::Data<PgPool>,
+) -> HttpResponse {
+    match indexer_repo::collection::get_collection_stats(&pool, &address).await {
+        Ok(Some(stats)) => HttpResponse::Ok().json(stats),
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Err(err) => {
+            log::error!("get collection stats error {err}");
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// Active, unexpired buy offers for the nft, highest price first
+#[get("/nft/{address}/offers")]
+pub async fn get_nft_offers(address: web::Path<String>, pool: web::Data<PgPool>) -> HttpResponse {
+    match indexer_repo::direct_buy::get_nft_offers(&pool, &address).await {
+        Ok(offers) => HttpResponse::Ok().json(offers),
+        Err(err) => {
+            log::error!("get nft offers error {err}");
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+#[get("/nft/{address}/price-history")]
+pub async fn get_nft_price_history(
+    address: web::Path<String>,
+    page: web::Query<Pagination>,
+    price_model: web::Data<NftPriceModel>,
+) -> HttpResponse {
+    match price_model
+        .get_nft_price_history(&address, page.limit(), page.offset())
+        .await
+    {
+        Ok(history) => HttpResponse::Ok().json(history),
+        Err(err) => {
+            log::error!("get nft price history error {err}");
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
@@ -0,0 +1,16 @@
+use actix_web::{get, web, HttpResponse};
+use indexer_repo::price::NftPriceModel;
+
+#[get("/address/{address}/counterparties")]
+pub async fn get_counterparties(
+    address: web::Path<String>,
+    price_model: web::Data<NftPriceModel>,
+) -> HttpResponse {
+    match price_model.get_counterparty_stats(&address).await {
+        Ok(counterparties) => HttpResponse::Ok().json(counterparties),
+        Err(err) => {
+            log::error!("counterparty stats error {err}");
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
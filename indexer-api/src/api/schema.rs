@@ -0,0 +1,30 @@
+use actix_web::{get, HttpResponse};
+use indexer_repo::types::decoded::{
+    AuctionActive, AuctionBid, DirectBuy, DirectSell, EventRecord, NftPriceHistory,
+};
+use indexer_repo::types::{
+    AuctionStatus, DirectBuyState, DirectSellState, EventCategory, EventType, NftPriceSource,
+};
+use schemars::schema_for;
+
+#[get("/schema")]
+pub async fn get_schema() -> HttpResponse {
+    HttpResponse::Ok().json(serde_json::json!({
+        "types": {
+            "event": schema_for!(EventRecord),
+            "direct_sell": schema_for!(DirectSell),
+            "direct_buy": schema_for!(DirectBuy),
+            "auction": schema_for!(AuctionActive),
+            "auction_bid": schema_for!(AuctionBid),
+            "price_history": schema_for!(NftPriceHistory),
+        },
+        "enums": {
+            "event_type": schema_for!(EventType),
+            "event_category": schema_for!(EventCategory),
+            "nft_price_source": schema_for!(NftPriceSource),
+            "auction_status": schema_for!(AuctionStatus),
+            "direct_sell_state": schema_for!(DirectSellState),
+            "direct_buy_state": schema_for!(DirectBuyState),
+        }
+    }))
+}
@@ -0,0 +1,74 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use indexer_api::run_read_api;
+use indexer_repo::db::{connect, DbConfig};
+use tracing_subscriber::EnvFilter;
+
+const DEFAULT_READ_API_URL: &str = "0.0.0.0:3002";
+const DEFAULT_DATABASE_MAX_CONNECTIONS: u32 = 5;
+const DEFAULT_DATABASE_ACQUIRE_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_DATABASE_IDLE_TIMEOUT_SECS: u64 = 600;
+/// Shorter than the indexer's, a client waits on every read
+const DEFAULT_DATABASE_STATEMENT_TIMEOUT_MS: u64 = 30_000;
+
+/// Read-only HTTP API over the indexed data, see `run_read_api`. Reads the `DATABASE_*`
+/// settings of the indexer and listens on `READ_API_URL`
+#[actix_web::main]
+async fn main() -> Result<()> {
+    dotenv::dotenv().ok();
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+        )
+        .init();
+
+    let address = env("READ_API_URL")?
+        .unwrap_or_else(|| DEFAULT_READ_API_URL.to_string())
+        .parse::<SocketAddr>()
+        .context("READ_API_URL must be a socket address")?;
+    let idle_timeout =
+        env_number("DATABASE_IDLE_TIMEOUT_SECS")?.unwrap_or(DEFAULT_DATABASE_IDLE_TIMEOUT_SECS);
+    let statement_timeout = env_number("DATABASE_STATEMENT_TIMEOUT_MS")?
+        .unwrap_or(DEFAULT_DATABASE_STATEMENT_TIMEOUT_MS);
+    let pool = connect(&DbConfig {
+        url: env("DATABASE_URL")?.context("DATABASE_URL is not set")?,
+        max_connections: env_number("DATABASE_MAX_CONNECTIONS")?
+            .map(|max| max as u32)
+            .unwrap_or(DEFAULT_DATABASE_MAX_CONNECTIONS),
+        acquire_timeout: Duration::from_secs(
+            env_number("DATABASE_ACQUIRE_TIMEOUT_SECS")?
+                .unwrap_or(DEFAULT_DATABASE_ACQUIRE_TIMEOUT_SECS),
+        ),
+        idle_timeout: (idle_timeout > 0).then(|| Duration::from_secs(idle_timeout)),
+        statement_timeout: (statement_timeout > 0)
+            .then(|| Duration::from_millis(statement_timeout)),
+        // the indexer owns the database, its sessions are not ours to kill
+        terminate_open_connections: false,
+    })
+    .await?;
+
+    log::info!("Serving the read API on {address}");
+    run_read_api(&address, pool).await?;
+
+    Ok(())
+}
+
+fn env(name: &str) -> Result<Option<String>> {
+    match std::env::var(name) {
+        Ok(value) => Ok(Some(value)),
+        Err(std::env::VarError::NotPresent) => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("{name} is invalid")),
+    }
+}
+
+fn env_number(name: &str) -> Result<Option<u64>> {
+    env(name)?
+        .map(|value| {
+            value
+                .parse()
+                .with_context(|| format!("{name} must be a number"))
+        })
+        .transpose()
+}
@@ -1,4 +1,6 @@
 mod api;
+mod parsers;
 mod server;
 
+pub use parsers::*;
 pub use server::*;
@@ -0,0 +1,95 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Parser groups that can be paused independently, named after event categories
+pub const PARSERS: [&str; 6] = [
+    "auction",
+    "direct_buy",
+    "direct_sell",
+    "nft",
+    "collection",
+    "common",
+];
+
+fn flags() -> Arc<BTreeMap<&'static str, AtomicBool>> {
+    Arc::new(
+        PARSERS
+            .into_iter()
+            .map(|p| (p, AtomicBool::new(false)))
+            .collect(),
+    )
+}
+
+/// Runtime pause switches shared by the indexer loop and the admin API. Events of a
+/// paused parser are parked, and a parser with parked events keeps parking new ones
+/// until the writer applied the parked ones, so its events stay in order
+#[derive(Clone)]
+pub struct ParserControl {
+    paused: Arc<BTreeMap<&'static str, AtomicBool>>,
+    parked: Arc<BTreeMap<&'static str, AtomicBool>>,
+}
+
+impl Default for ParserControl {
+    fn default() -> Self {
+        Self {
+            paused: flags(),
+            parked: flags(),
+        }
+    }
+}
+
+impl ParserControl {
+    /// Returns false for an unknown parser name
+    pub fn set_paused(&self, parser: &str, paused: bool) -> bool {
+        match self.paused.get(parser) {
+            Some(flag) => {
+                flag.store(paused, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_paused(&self, parser: &str) -> bool {
+        self.paused
+            .get(parser)
+            .map_or(false, |flag| flag.load(Ordering::Relaxed))
+    }
+
+    pub fn paused(&self) -> Vec<&'static str> {
+        raised(&self.paused)
+    }
+
+    /// Set by the writer once parked events of `parser` are saved, cleared once they
+    /// are all applied
+    pub fn set_parked(&self, parser: &str, parked: bool) {
+        if let Some(flag) = self.parked.get(parser) {
+            flag.store(parked, Ordering::Relaxed);
+        }
+    }
+
+    pub fn has_parked(&self, parser: &str) -> bool {
+        self.parked
+            .get(parser)
+            .map_or(false, |flag| flag.load(Ordering::Relaxed))
+    }
+
+    /// Parsers with parked events
+    pub fn parked(&self) -> Vec<&'static str> {
+        raised(&self.parked)
+    }
+
+    /// Events of the parser are applied, not parked
+    pub fn is_active(&self, parser: &str) -> bool {
+        !self.is_paused(parser) && !self.has_parked(parser)
+    }
+}
+
+fn raised(flags: &BTreeMap<&'static str, AtomicBool>) -> Vec<&'static str> {
+    flags
+        .iter()
+        .filter(|(_, flag)| flag.load(Ordering::Relaxed))
+        .map(|(parser, _)| *parser)
+        .collect()
+}
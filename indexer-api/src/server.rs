@@ -1,30 +1,65 @@
 use actix_cors::Cors;
 use actix_web::middleware::Logger;
-use actix_web::web::Data;
+use actix_web::web::{Data, ServiceConfig};
 use actix_web::{get, App, HttpResponse, HttpServer};
 use data_reader::{MetaReaderContext, MetadataJrpcService};
 use indexer_repo::meta::MetadataModelService;
+use indexer_repo::price::NftPriceModel;
+use sqlx::PgPool;
 use std::net::SocketAddr;
 
 use crate::api;
 use crate::api::docs::v1::{swagger_json, swagger_yaml};
+use crate::ParserControl;
 
-pub async fn run_api(address: &SocketAddr, context: MetaReaderContext) -> std::io::Result<()> {
-    let meta_jrpc_service = MetadataJrpcService::new(context.jrpc_client);
-    let meta_model_service = MetadataModelService::new(context.pool);
+#[derive(Clone, Default)]
+pub struct ApiConfig {
+    /// Exposes buyer/seller pairs per address, which is sensitive for wallets
+    pub counterparty_api_enabled: bool,
+    /// Exposes parser pause/resume, there is no auth on these routes
+    pub admin_api_enabled: bool,
+}
+
+pub async fn run_api(
+    address: &SocketAddr,
+    context: MetaReaderContext,
+    config: ApiConfig,
+    parser_control: ParserControl,
+) -> std::io::Result<()> {
+    let meta_jrpc_service = MetadataJrpcService::new(context.getter_client);
+    let meta_model_service = MetadataModelService::new(context.pool.clone());
+    let price_model = NftPriceModel::new(context.pool.clone());
+    let rarity_queue = context.rarity_queue;
+    let pool = context.pool;
     let address_str = address.to_string();
 
     HttpServer::new(move || {
         let cors = Cors::permissive();
+        let config = config.clone();
         App::new()
             .wrap(Logger::default())
             .wrap(cors)
             .service(api::metadata::refresh_metadata_by_nft)
+            .service(api::schema::get_schema)
             .service(swagger_yaml)
             .service(swagger_json)
             .service(health)
+            .service(api::admin::get_stats)
+            .configure(move |cfg: &mut ServiceConfig| {
+                if config.counterparty_api_enabled {
+                    cfg.service(api::sales::get_counterparties);
+                }
+                if config.admin_api_enabled {
+                    cfg.service(api::admin::pause_parser)
+                        .service(api::admin::resume_parser);
+                }
+            })
             .app_data(Data::new(meta_jrpc_service.clone()))
             .app_data(Data::new(meta_model_service.clone()))
+            .app_data(Data::new(price_model.clone()))
+            .app_data(Data::new(rarity_queue.clone()))
+            .app_data(Data::new(pool.clone()))
+            .app_data(Data::new(parser_control.clone()))
             .app_data(Data::new(address_str.clone()))
     })
     .bind(address)?
@@ -32,6 +67,38 @@ pub async fn run_api(address: &SocketAddr, context: MetaReaderContext) -> std::i
     .await
 }
 
+/// Read-only nft, collection, price history and event routes and `/graphql`, served by
+/// the `api` binary. They only need the pool, so the API runs and scales apart from
+/// the indexer and an HTTP load spike never stalls the indexer loop
+pub async fn run_read_api(address: &SocketAddr, pool: PgPool) -> std::io::Result<()> {
+    let price_model = NftPriceModel::new(pool.clone());
+    let graphql_schema = api::graphql::schema(pool.clone());
+
+    HttpServer::new(move || {
+        App::new()
+            .wrap(Logger::default())
+            .wrap(Cors::permissive())
+            .service(health)
+            .service(api::nft::get_nft_price_history)
+            .service(api::nft::get_nft)
+            .service(api::nft::get_nft_offers)
+            .service(api::nft::get_collection_nfts)
+            .service(api::nft::search_collection_nfts)
+            .service(api::nft::get_collection_rarity)
+            .service(api::nft::get_collection_stats)
+            .service(api::events::get_events)
+            .service(api::events::get_event)
+            .service(api::events::get_address_activity)
+            .service(api::graphql::graphql)
+            .app_data(Data::new(price_model.clone()))
+            .app_data(Data::new(pool.clone()))
+            .app_data(Data::new(graphql_schema.clone()))
+    })
+    .bind(address)?
+    .run()
+    .await
+}
+
 #[get("/healthz")]
 async fn health() -> HttpResponse {
     HttpResponse::Ok().finish()
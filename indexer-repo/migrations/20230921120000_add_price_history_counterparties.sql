@@ -0,0 +1,7 @@
+alter table nft_price_history
+    add column buyer t_address;
+alter table nft_price_history
+    add column seller t_address;
+
+create index idx_nft_price_history_buyer on nft_price_history using btree (buyer);
+create index idx_nft_price_history_seller on nft_price_history using btree (seller);
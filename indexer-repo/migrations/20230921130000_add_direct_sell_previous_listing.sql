@@ -0,0 +1,4 @@
+alter table nft_direct_sell
+    add column previous_listing_id t_address;
+
+create index idx_nft_direct_sell_nft_seller on nft_direct_sell using btree (nft, seller);
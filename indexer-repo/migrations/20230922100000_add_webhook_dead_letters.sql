@@ -0,0 +1,9 @@
+create table webhook_dead_letters(
+    id bigserial primary key,
+    url text not null,
+    payload jsonb not null,
+    error text not null,
+    created_at timestamp not null default now()
+);
+
+create index idx_webhook_dead_letters_created_at on webhook_dead_letters using btree (created_at);
@@ -0,0 +1,11 @@
+create table marketplace_fee_history (
+    address     t_address not null,
+    numerator   int       not null,
+    denominator int       not null,
+    changed_at  timestamp not null,
+    changed_lt  bigint    not null,
+    primary key (address, changed_lt)
+);
+
+alter table nft_price_history
+    add column marketplace_fee numeric;
@@ -0,0 +1,11 @@
+create table token_registry (
+    address  t_address primary key,
+    symbol   text      not null,
+    decimals int       not null
+);
+
+alter table nft_direct_sell
+    add column price_normalized numeric;
+
+alter table nft_auction_bid
+    add column price_normalized numeric;
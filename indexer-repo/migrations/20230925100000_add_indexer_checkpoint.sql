@@ -0,0 +1,7 @@
+create table indexer_checkpoint (
+    id           smallint  primary key default 1 check (id = 1),
+    tx_timestamp bigint    not null,
+    tx_lt        bigint    not null,
+    tx_hash      text      not null,
+    updated      timestamp not null default now()
+);
@@ -0,0 +1,12 @@
+create table failed_events (
+    id           bigserial primary key,
+    address      t_address not null,
+    event_name   text      not null,
+    message_hash text      not null,
+    tx_lt        bigint    not null,
+    tokens       text      not null,
+    error        text      not null,
+    created      timestamp not null default now()
+);
+
+create index failed_events_event_name_idx on failed_events using btree (event_name);
@@ -0,0 +1,16 @@
+create materialized view collection_floor as
+select n.collection,
+       ds.price_token,
+       min(ds.price) as floor_price
+from nft_direct_sell ds
+         join offers_whitelist ow on ow.address = ds.address
+         join nft n on n.address = ds.nft and not n.burned
+where ds.state = 'active'::direct_sell_state
+  and (ds.expired_at = to_timestamp(0) or ds.expired_at > now()::timestamp)
+group by n.collection, ds.price_token;
+
+create unique index on collection_floor (collection, price_token);
+
+-- Also refreshed by the indexer after direct sell updates; this catches listings expiring
+select cron.schedule('refresh collection_floor', '*/5 * * * *',
+                     'refresh materialized view concurrently collection_floor;');
@@ -0,0 +1,18 @@
+create table collection_volume_daily (
+    collection t_address not null,
+    day        date      not null,
+    volume_usd numeric   not null default 0,
+    sales      bigint    not null default 0,
+
+    constraint collection_volume_daily_pk primary key (collection, day)
+);
+
+insert into collection_volume_daily (collection, day, volume_usd, sales)
+select nph.collection,
+       nph.ts::date,
+       coalesce(sum(nph.usd_price), 0),
+       count(1)
+from nft_price_history nph
+         join offers_whitelist ow on ow.address = nph.source
+where nph.ts is not null
+group by nph.collection, nph.ts::date;
@@ -0,0 +1,9 @@
+alter table nft_auction
+    add column winner t_address;
+
+update nft_auction a
+set winner = ph.buyer
+from nft_price_history ph
+where ph.source = a.address
+  and ph.source_type = 'auctionBid'::nft_price_source
+  and a.status = 'completed'::auction_status;
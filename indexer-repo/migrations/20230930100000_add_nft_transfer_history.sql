@@ -0,0 +1,28 @@
+create type nft_transfer_kind as enum (
+    'owner',
+    'manager'
+);
+
+create table nft_transfer_history (
+    nft         t_address         not null,
+    kind        nft_transfer_kind not null,
+    old_address t_address         not null,
+    new_address t_address         not null,
+    created_lt  bigint            not null,
+    created_at  timestamp         not null,
+
+    constraint nft_transfer_history_pk primary key (nft, kind, created_lt)
+);
+
+insert into nft_transfer_history (nft, kind, old_address, new_address, created_lt, created_at)
+select ne.address,
+       case when ne.event_type = 'nft_owner_changed' then 'owner'::nft_transfer_kind
+            else 'manager'::nft_transfer_kind end,
+       coalesce(ne.args ->> 'old_owner', ne.args ->> 'old_manager'),
+       coalesce(ne.args ->> 'new_owner', ne.args ->> 'new_manager'),
+       ne.created_lt,
+       to_timestamp(ne.created_at) at time zone 'utc'
+from nft_events ne
+where ne.event_type in ('nft_owner_changed', 'nft_manager_changed')
+  and ne.args is not null
+on conflict do nothing;
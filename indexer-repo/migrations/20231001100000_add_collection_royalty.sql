@@ -0,0 +1,9 @@
+-- A row with null numerator/denominator/recipient marks a collection without the
+-- royalty getter, so it isn't queried again
+create table collection_royalty (
+    collection  t_address primary key,
+    numerator   int,
+    denominator int,
+    recipient   t_address,
+    updated     timestamp not null
+);
@@ -0,0 +1,22 @@
+delete from nft_price_history a
+using nft_price_history b
+where a.ctid > b.ctid
+  and a.source = b.source
+  and a.source_type = b.source_type
+  and a.ts = b.ts;
+
+create unique index nft_price_history_source_uindex
+    on nft_price_history using btree (source, source_type, ts);
+
+-- Replayed duplicates were counted into the daily volume as well
+delete from collection_volume_daily;
+
+insert into collection_volume_daily (collection, day, volume_usd, sales)
+select nph.collection,
+       nph.ts::date,
+       coalesce(sum(nph.usd_price), 0),
+       count(1)
+from nft_price_history nph
+         join offers_whitelist ow on ow.address = nph.source
+where nph.ts is not null
+group by nph.collection, nph.ts::date;
@@ -0,0 +1,3 @@
+-- Serialized transaction (BOC) an event was emitted in, only stored when enabled
+alter table nft_events
+    add column raw_tx bytea;
@@ -0,0 +1,2 @@
+create index if not exists ix_nft_attributes_collection_trait_value
+    on nft_attributes using btree (collection, trait_type, (value #>> '{}'));
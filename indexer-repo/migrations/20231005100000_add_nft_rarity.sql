@@ -0,0 +1,12 @@
+-- Rank 1 is the rarest nft of the collection. Provisional ranks belong to collections
+-- still minting or with nfts whose attributes aren't loaded yet
+create table nft_rarity (
+    nft         t_address primary key,
+    collection  t_address not null,
+    score       double precision not null,
+    rank        bigint not null,
+    provisional boolean not null,
+    updated     timestamp not null
+);
+
+create index ix_nft_rarity_collection_rank on nft_rarity using btree (collection, rank);
@@ -0,0 +1,2 @@
+create index if not exists ix_nft_events_collection_created_at
+    on nft_events using btree (collection, created_at);
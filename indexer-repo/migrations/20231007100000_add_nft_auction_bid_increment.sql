@@ -0,0 +1 @@
+alter table nft_auction add column if not exists bid_increment numeric;
@@ -0,0 +1,9 @@
+create table if not exists nft_collection_verified (
+    nft t_address primary key,
+    collection t_address not null,
+    index_address t_address not null,
+    verified_at timestamp not null
+);
+
+create index if not exists ix_nft_collection_verified_collection
+    on nft_collection_verified using btree (collection);
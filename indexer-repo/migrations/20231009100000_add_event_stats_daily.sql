@@ -0,0 +1,14 @@
+create table event_stats_daily (
+    date       date       not null,
+    event_type event_type not null,
+    count      bigint     not null default 0,
+
+    constraint event_stats_daily_pk primary key (date, event_type)
+);
+
+insert into event_stats_daily (date, event_type, count)
+select (to_timestamp(created_at) at time zone 'utc')::date,
+       event_type,
+       count(1)
+from nft_events
+group by 1, 2;
@@ -0,0 +1,7 @@
+create table collection_royalty_earned (
+    collection t_address not null,
+    token      t_address not null,
+    amount     numeric   not null default 0,
+
+    constraint collection_royalty_earned_pk primary key (collection, token)
+);
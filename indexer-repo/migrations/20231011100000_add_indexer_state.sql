@@ -0,0 +1,6 @@
+create table indexer_state (
+    address    t_address primary key,
+    last_lt    bigint    not null,
+    last_at    timestamp not null,
+    updated_at timestamp not null default now()
+);
@@ -0,0 +1,11 @@
+alter table nft_direct_sell
+    add column price_token_symbol text;
+
+alter table nft_price_history
+    add column price_token_symbol text;
+
+-- Price tokens missing from token_registry, read from the chain by the meta reader
+create table token_registry_queue (
+    address     t_address primary key,
+    enqueued_at timestamp not null default now()
+);
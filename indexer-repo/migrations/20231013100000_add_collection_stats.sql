@@ -0,0 +1,40 @@
+-- total_nfts is set by the periodic reconciliation, active_listings is kept up to date
+-- by the trigger below and corrected by the reconciliation on drift
+create table collection_stats (
+    collection      t_address primary key,
+    active_listings bigint    not null default 0,
+    total_nfts      bigint    not null default 0,
+    reconciled_at   timestamp
+);
+
+insert into collection_stats (collection, active_listings, total_nfts, reconciled_at)
+select c.address,
+       (select count(1) from nft_direct_sell ds where ds.collection = c.address and ds.state = 'active'),
+       (select count(1) from nft n where n.collection = c.address and not n.burned),
+       now()
+from nft_collection c;
+
+create or replace function count_active_listings() returns trigger as $$
+begin
+    if tg_op <> 'INSERT' and old.state = 'active' and old.collection is not null then
+        insert into collection_stats (collection, active_listings)
+        values (old.collection, -1)
+        on conflict (collection) do update
+            set active_listings = collection_stats.active_listings - 1;
+    end if;
+
+    if tg_op <> 'DELETE' and new.state = 'active' and new.collection is not null then
+        insert into collection_stats (collection, active_listings)
+        values (new.collection, 1)
+        on conflict (collection) do update
+            set active_listings = collection_stats.active_listings + 1;
+    end if;
+
+    return null;
+end;
+$$ language plpgsql;
+
+create trigger count_active_listings_trigger
+after insert or delete or update of state, collection on nft_direct_sell
+for each row
+execute function count_active_listings();
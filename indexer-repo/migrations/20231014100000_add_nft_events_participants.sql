@@ -0,0 +1,17 @@
+-- Sellers, buyers, bidders and owners named by the event args, see get_address_activity
+alter table nft_events
+    add column participants text[] generated always as (
+        array_remove(array [
+            args -> 'value0' ->> 'subject_owner',
+            args -> 'value2' ->> 'subject_owner',
+            args -> 'value3' ->> 'subject_owner',
+            args -> 'value2' ->> 'creator',
+            args ->> 'sender',
+            args ->> 'buyer',
+            args ->> 'owner',
+            args ->> 'old_owner',
+            args ->> 'new_owner'
+            ], null)
+        ) stored;
+
+create index ix_nft_events_participants on nft_events using gin (participants);
@@ -0,0 +1,5 @@
+-- Listings stored before activation, or whose contract reported no start, were
+-- created at the epoch
+alter table nft_direct_sell alter column created drop not null;
+
+update nft_direct_sell set created = null where created = to_timestamp(0)::timestamp;
@@ -0,0 +1,15 @@
+-- Who minted the nft and when, set once by NftCreated
+alter table nft
+    add column minter     t_address,
+    add column created_lt bigint,
+    add column created_at timestamp;
+
+update nft n
+set minter     = e.args ->> 'creator',
+    created_lt = e.created_lt,
+    created_at = to_timestamp(e.created_at)::timestamp
+from (select distinct on (nft) nft, args, created_lt, created_at
+      from nft_events
+      where event_type = 'nft_created'
+      order by nft, created_lt) e
+where e.nft = n.address;
@@ -0,0 +1,16 @@
+-- Events of a paused parser, applied in id order once it is resumed
+create table parked_events(
+    id           bigint not null generated always as identity primary key,
+    parser       text not null,
+    event_cat    event_category not null,
+    event_type   event_type not null,
+    address      t_address not null,
+    nft          t_address,
+    collection   t_address,
+    created_lt   bigint not null,
+    created_at   bigint not null,
+    message_hash text not null,
+    args         jsonb not null
+);
+
+create index ix_parked_events_parser on parked_events (parser, id);
@@ -0,0 +1,2 @@
+create index if not exists ix_nft_events_feed
+    on nft_events using btree (created_at, created_lt, message_hash);
@@ -0,0 +1,2 @@
+alter table nft_price_history
+    add column royalty numeric;
@@ -0,0 +1,8 @@
+delete from nft_auction_bid a
+using nft_auction_bid b
+where a.ctid > b.ctid
+  and a.auction = b.auction
+  and a.tx_lt = b.tx_lt;
+
+create unique index nft_auction_bid_auction_tx_lt_uindex
+    on nft_auction_bid using btree (auction, tx_lt);
@@ -0,0 +1,151 @@
+use anyhow::{anyhow, Result};
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::{types::BigDecimal, PgPool};
+
+use crate::types::AuctionStatus;
+
+/// Auction as seeded by `AuctionDeployed`, opened by `AuctionActive` and closed by
+/// `AuctionComplete` (winner and final `max_bid`) or `AuctionCancelled`
+#[derive(Clone, Debug, Serialize)]
+pub struct AuctionRecord {
+    pub address: String,
+    pub root: String,
+    pub nft: String,
+    pub collection: String,
+    pub nft_owner: String,
+    pub wallet_for_bids: Option<String>,
+    pub price_token: Option<String>,
+    pub start_price: Option<BigDecimal>,
+    pub min_bid: Option<BigDecimal>,
+    pub max_bid: Option<BigDecimal>,
+    /// Step between the highest bid and `min_bid`, known after the first bid
+    pub bid_increment: Option<BigDecimal>,
+    pub status: AuctionStatus,
+    pub created_at: Option<NaiveDateTime>,
+    pub finished_at: Option<NaiveDateTime>,
+    pub winner: Option<String>,
+    pub tx_lt: i64,
+}
+
+/// Placed or declined bid of an auction
+#[derive(Clone, Debug, Serialize)]
+pub struct AuctionBidRecord {
+    pub buyer: String,
+    pub price: BigDecimal,
+    pub price_normalized: Option<BigDecimal>,
+    pub next_bid_value: Option<BigDecimal>,
+    pub created_at: NaiveDateTime,
+    pub tx_lt: i64,
+    pub declined: bool,
+}
+
+pub async fn get_auction(pg_pool: &PgPool, address: &str) -> Result<Option<AuctionRecord>> {
+    sqlx::query_as!(
+        AuctionRecord,
+        r#"
+        select address,
+               root,
+               nft,
+               collection,
+               nft_owner,
+               wallet_for_bids,
+               price_token,
+               start_price,
+               min_bid,
+               max_bid,
+               bid_increment,
+               status as "status: AuctionStatus",
+               created_at,
+               finished_at,
+               winner,
+               tx_lt
+        from nft_auction
+        where address = $1
+        "#,
+        address as _
+    )
+    .fetch_optional(pg_pool)
+    .await
+    .map_err(|e| anyhow!(e))
+}
+
+/// Lowest bid the auction accepts next: the start price until the first bid, then
+/// the next bid value of the highest bid
+pub async fn get_auction_min_next_bid(
+    pg_pool: &PgPool,
+    address: &str,
+) -> Result<Option<BigDecimal>> {
+    sqlx::query!(
+        r#"
+        select case when max_bid is null then start_price else min_bid end as min_next_bid
+        from nft_auction
+        where address = $1
+        "#,
+        address as _
+    )
+    .fetch_optional(pg_pool)
+    .await
+    .map(|row| row.and_then(|r| r.min_next_bid))
+    .map_err(|e| anyhow!(e))
+}
+
+/// Latest active auction of each of the nfts that has one
+pub async fn get_active_auctions_by_nfts(
+    pg_pool: &PgPool,
+    nfts: &[String],
+) -> Result<Vec<AuctionRecord>> {
+    sqlx::query_as!(
+        AuctionRecord,
+        r#"
+        select distinct on (nft)
+               address,
+               root,
+               nft,
+               collection,
+               nft_owner,
+               wallet_for_bids,
+               price_token,
+               start_price,
+               min_bid,
+               max_bid,
+               bid_increment,
+               status as "status: AuctionStatus",
+               created_at,
+               finished_at,
+               winner,
+               tx_lt
+        from nft_auction
+        where nft = any($1::varchar[])
+          and status = 'active'::auction_status
+        order by nft, created_at desc
+        "#,
+        nfts as _
+    )
+    .fetch_all(pg_pool)
+    .await
+    .map_err(|e| anyhow!(e))
+}
+
+/// Bidding timeline of an auction, oldest bid first
+pub async fn get_auction_bids(pg_pool: &PgPool, address: &str) -> Result<Vec<AuctionBidRecord>> {
+    sqlx::query_as!(
+        AuctionBidRecord,
+        r#"
+        select buyer,
+               price,
+               price_normalized,
+               next_bid_value,
+               created_at,
+               tx_lt,
+               coalesce(declined, false) as "declined!"
+        from nft_auction_bid
+        where auction = $1
+        order by tx_lt, created_at
+        "#,
+        address as _
+    )
+    .fetch_all(pg_pool)
+    .await
+    .map_err(|e| anyhow!(e))
+}
@@ -1,6 +1,8 @@
-use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+
 use sqlx::{Postgres, Transaction};
 
+use crate::error::{IndexerError, Result};
 use crate::types::{decoded::AuctionActive, AuctionStatus};
 
 pub async fn save_auc_active(
@@ -29,7 +31,7 @@ pub async fn save_auc_active(
         r#"
         update nft_auction set
             wallet_for_bids = data.wallet,
-            price_token = data.price_token,
+            price_token = coalesce(nft_auction.price_token, data.price_token),
             start_price = data.start_price,
             min_bid = data.min_bid,
             created_at = data.created,
@@ -49,6 +51,7 @@ pub async fn save_auc_active(
                 $9::auction_status as status
         ) as data
         where nft_auction.address = data.address
+          and nft_auction.tx_lt <= data.tx_lt
         "#,
         addresses as _,
         wallets as _,
@@ -62,6 +65,29 @@ pub async fn save_auc_active(
     )
     .execute(tx)
     .await
-    .map_err(|e| anyhow!(e))
+    .map_err(IndexerError::db)
     .map(|_| ())
 }
+
+/// Payment tokens fixed for the given auctions at activation
+pub async fn get_auction_price_tokens(
+    tx: &mut Transaction<'_, Postgres>,
+    addresses: &[&str],
+) -> Result<HashMap<String, String>> {
+    let rows = sqlx::query!(
+        r#"
+        select address as "address!", price_token as "price_token!"
+        from nft_auction
+        where address = any($1::varchar[]) and price_token is not null
+        "#,
+        addresses as _,
+    )
+    .fetch_all(tx)
+    .await
+    .map_err(IndexerError::db)?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| (r.address, r.price_token))
+        .collect())
+}
@@ -1,12 +1,17 @@
-use anyhow::{anyhow, Result};
 use sqlx::{Postgres, Transaction};
 
+use crate::error::{IndexerError, Result};
 use crate::types::decoded::AuctionBid;
 
+/// A bid is saved once per auction transaction, a redelivered or replayed one is skipped
 pub async fn save_auc_bid(tx: &mut Transaction<'_, Postgres>, data: &[AuctionBid]) -> Result<()> {
     let auctions = data.iter().map(|e| e.address.as_str()).collect::<Vec<_>>();
     let buyers = data.iter().map(|e| e.buyer.as_str()).collect::<Vec<_>>();
     let bid_vals = data.iter().map(|e| e.bid_value.clone()).collect::<Vec<_>>();
+    let bid_vals_normalized = data
+        .iter()
+        .map(|e| e.bid_value_normalized.clone())
+        .collect::<Vec<_>>();
     let next_vals = data
         .iter()
         .map(|e| e.next_value.clone())
@@ -41,7 +46,8 @@ pub async fn save_auc_bid(tx: &mut Transaction<'_, Postgres>, data: &[AuctionBid
                 nft,
                 nft_owner,
                 collection,
-                price_token
+                price_token,
+                price_normalized
             )
             select
                 unnest($1::varchar[]),
@@ -54,7 +60,9 @@ pub async fn save_auc_bid(tx: &mut Transaction<'_, Postgres>, data: &[AuctionBid
                 unnest($8::varchar[]),
                 unnest($9::varchar[]),
                 unnest($10::varchar[]),
-                unnest($11::varchar[])
+                unnest($11::varchar[]),
+                unnest($12::numeric[])
+            on conflict (auction, tx_lt) do nothing
         "#,
         auctions as _,
         buyers as _,
@@ -67,9 +75,10 @@ pub async fn save_auc_bid(tx: &mut Transaction<'_, Postgres>, data: &[AuctionBid
         nfts_owners as _,
         collections as _,
         price_tokens as _,
+        bid_vals_normalized as _,
     )
     .execute(tx)
     .await
-    .map_err(|e| anyhow!(e))
+    .map_err(IndexerError::db)
     .map(|_| ())
 }
@@ -1,6 +1,6 @@
-use anyhow::{anyhow, Result};
 use sqlx::{Postgres, Transaction};
 
+use crate::error::{IndexerError, Result};
 use crate::types::{
     decoded::{AuctionCancelled, AuctionComplete},
     AuctionStatus,
@@ -12,28 +12,37 @@ pub async fn save_auc_complete(
 ) -> Result<()> {
     let addresses = data.iter().map(|e| e.address.as_str()).collect::<Vec<_>>();
     let max_bids = data.iter().map(|e| e.max_bid.clone()).collect::<Vec<_>>();
+    let winners = data.iter().map(|e| e.winner.as_str()).collect::<Vec<_>>();
+    let tx_lts = data.iter().map(|e| e.tx_lt).collect::<Vec<_>>();
 
     sqlx::query!(
         r#"
         update nft_auction set
             max_bid = data.max_bid,
+            winner = data.winner,
+            tx_lt = data.tx_lt,
             status = data.status
         from
         (
             select 
                 unnest($1::varchar[]) as address,
                 unnest($2::numeric[]) as max_bid,
-                $3::auction_status as status
+                unnest($3::varchar[]) as winner,
+                unnest($4::bigint[]) as tx_lt,
+                $5::auction_status as status
         ) as data
         where nft_auction.address = data.address
+          and nft_auction.tx_lt <= data.tx_lt
     "#,
         addresses as _,
         max_bids as _,
+        winners as _,
+        tx_lts as _,
         AuctionStatus::Completed as _,
     )
     .execute(tx)
     .await
-    .map_err(|e| anyhow!(e))
+    .map_err(IndexerError::db)
     .map(|_| ())
 }
 
@@ -42,24 +51,33 @@ pub async fn save_auc_cancelled(
     data: &[AuctionCancelled],
 ) -> Result<()> {
     let addresses = data.iter().map(|e| e.address.as_str()).collect::<Vec<_>>();
+    let finished_at = data.iter().map(|e| e.finished_at).collect::<Vec<_>>();
+    let tx_lts = data.iter().map(|e| e.tx_lt).collect::<Vec<_>>();
 
     sqlx::query!(
         r#"
         update nft_auction set
+            finished_at = data.finished_at,
+            tx_lt = data.tx_lt,
             status = data.status
         from
         (
             select 
                 unnest($1::varchar[]) as address,
-                $2::auction_status as status
+                unnest($2::timestamp[]) as finished_at,
+                unnest($3::bigint[]) as tx_lt,
+                $4::auction_status as status
         ) as data
         where nft_auction.address = data.address
+          and nft_auction.tx_lt <= data.tx_lt
     "#,
         addresses as _,
+        finished_at as _,
+        tx_lts as _,
         AuctionStatus::Cancelled as _,
     )
     .execute(tx)
     .await
-    .map_err(|e| anyhow!(e))
+    .map_err(IndexerError::db)
     .map(|_| ())
 }
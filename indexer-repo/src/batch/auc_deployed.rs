@@ -1,5 +1,5 @@
+use crate::error::{IndexerError, Result};
 use crate::types::{decoded::AuctionDeployed, AuctionStatus};
-use anyhow::{anyhow, Result};
 use sqlx::{Postgres, Transaction};
 
 pub async fn save_auc_deployed(
@@ -50,6 +50,6 @@ pub async fn save_auc_deployed(
     )
     .execute(tx)
     .await
-    .map_err(|e| anyhow!(e))
+    .map_err(IndexerError::db)
     .map(|_| ())
 }
@@ -1,8 +1,12 @@
-use anyhow::{anyhow, Result};
 use sqlx::{Postgres, Transaction};
 
+use crate::error::{IndexerError, Result};
 use crate::types::decoded::AuctionBid;
 
+/// Moves `min_bid` to the next bid value required by the contract and `max_bid` to
+/// the placed bid. Only the latest bid of an auction in `data` is applied, and not if
+/// the auction was already updated by a later event. The same bids applied again leave
+/// the auction as is
 pub async fn update_auc_maxmin(
     tx: &mut Transaction<'_, Postgres>,
     data: &[AuctionBid],
@@ -20,16 +24,23 @@ pub async fn update_auc_maxmin(
         update nft_auction set
             min_bid = data.min_bid,
             max_bid = data.max_bid,
+            bid_increment = data.min_bid - data.max_bid,
             tx_lt = data.tx_lt
         from
         (
-            select 
-                unnest($1::varchar[]) as address,
-                unnest($2::numeric[]) as min_bid,
-                unnest($3::numeric[]) as max_bid,
-                unnest($4::bigint[]) as tx_lt
+            select distinct on (address) *
+            from
+            (
+                select
+                    unnest($1::varchar[]) as address,
+                    unnest($2::numeric[]) as min_bid,
+                    unnest($3::numeric[]) as max_bid,
+                    unnest($4::bigint[]) as tx_lt
+            ) as bids
+            order by address, tx_lt desc
         ) as data
         where nft_auction.address = data.address
+          and nft_auction.tx_lt <= data.tx_lt
     "#,
         addresses as _,
         min_bids as _,
@@ -38,6 +49,6 @@ pub async fn update_auc_maxmin(
     )
     .execute(tx)
     .await
-    .map_err(|e| anyhow!(e))
+    .map_err(IndexerError::db)
     .map(|_| ())
 }
@@ -1,5 +1,5 @@
+use crate::error::{IndexerError, Result};
 use crate::types::NftCollection;
-use anyhow::{anyhow, Result};
 use sqlx::PgPool;
 
 pub async fn save_collections(pool: &PgPool, collections: &[NftCollection]) -> Result<()> {
@@ -32,6 +32,6 @@ pub async fn save_collections(pool: &PgPool, collections: &[NftCollection]) -> R
     )
     .execute(pool)
     .await
-    .map_err(|e| anyhow!(e))
+    .map_err(IndexerError::db)
     .map(|_| ())
 }
@@ -1,6 +1,6 @@
-use anyhow::{anyhow, Result};
 use sqlx::{Postgres, Transaction};
 
+use crate::error::{IndexerError, Result};
 use crate::types::decoded::CollectionFee;
 
 pub async fn update_collection_fee(
@@ -35,6 +35,6 @@ pub async fn update_collection_fee(
     )
     .execute(tx)
     .await
-    .map_err(|e| anyhow!(e))
+    .map_err(IndexerError::db)
     .map(|_| ())
 }
@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+
+use sqlx::{Postgres, Transaction};
+
+use crate::error::{IndexerError, Result};
+use crate::types::CollectionRoyaltyEarned;
+
+/// `(numerator, denominator)` of the collections whose royalty was read, `None` for
+/// collections without the royalty getter. Collections not read yet are left out
+pub async fn get_collection_royalty_rates(
+    tx: &mut Transaction<'_, Postgres>,
+    collections: &[&str],
+) -> Result<HashMap<String, Option<(i32, i32)>>> {
+    let rows = sqlx::query!(
+        r#"
+            select collection as "collection!", numerator, denominator
+            from collection_royalty
+            where collection = any($1::varchar[])
+        "#,
+        collections as _
+    )
+    .fetch_all(tx)
+    .await
+    .map_err(IndexerError::db)?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| (r.collection, r.numerator.zip(r.denominator)))
+        .collect())
+}
+
+pub async fn save_collection_royalty_earned(
+    tx: &mut Transaction<'_, Postgres>,
+    data: &[CollectionRoyaltyEarned],
+) -> Result<()> {
+    let collections = data
+        .iter()
+        .map(|r| r.collection.as_str())
+        .collect::<Vec<_>>();
+    let tokens = data.iter().map(|r| r.token.as_str()).collect::<Vec<_>>();
+    let amounts = data.iter().map(|r| r.amount.clone()).collect::<Vec<_>>();
+
+    sqlx::query!(
+        r#"
+            insert into collection_royalty_earned (collection, token, amount)
+            select
+                unnest($1::varchar[]),
+                unnest($2::varchar[]),
+                unnest($3::numeric[])
+            on conflict (collection, token) do update set
+                amount = collection_royalty_earned.amount + excluded.amount
+        "#,
+        collections as _,
+        tokens as _,
+        amounts as _,
+    )
+    .execute(tx)
+    .await
+    .map_err(IndexerError::db)
+    .map(|_| ())
+}
@@ -0,0 +1,23 @@
+use std::collections::HashSet;
+
+use sqlx::{Postgres, Transaction};
+
+use crate::error::{IndexerError, Result};
+
+pub async fn get_whitelisted_offers(
+    tx: &mut Transaction<'_, Postgres>,
+    offers: &[&str],
+) -> Result<HashSet<String>> {
+    sqlx::query_scalar!(
+        r#"
+            select address as "address!"
+            from offers_whitelist
+            where address = any($1::varchar[])
+        "#,
+        offers as _
+    )
+    .fetch_all(tx)
+    .await
+    .map(|addresses| addresses.into_iter().collect())
+    .map_err(IndexerError::db)
+}
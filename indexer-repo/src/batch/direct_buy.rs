@@ -1,5 +1,5 @@
+use crate::error::{IndexerError, Result};
 use crate::types::decoded::DirectBuy;
-use anyhow::{anyhow, Result};
 use sqlx::{Postgres, Transaction};
 use std::collections::HashMap;
 
@@ -73,10 +73,11 @@ pub async fn save_direct_buy(tx: &mut Transaction<'_, Postgres>, dbs: &[DirectBu
     )
     .execute(tx)
     .await
-    .map_err(|e| anyhow!(e))
+    .map_err(IndexerError::db)
     .map(|_| ())
 }
 
+/// Same ordering guard as `update_direct_sell_state`
 pub async fn update_direct_buy_state(
     tx: &mut Transaction<'_, Postgres>,
     dbs: &mut [DirectBuy],
@@ -149,6 +150,7 @@ pub async fn update_direct_buy_state(
                 unnest($12::timestamp[]) as created
         ) as data
         where nft_direct_buy.address = data.address
+          and nft_direct_buy.tx_lt <= data.tx_lt
         "#,
         addresses as _,
         states as _,
@@ -165,6 +167,6 @@ pub async fn update_direct_buy_state(
     )
     .execute(tx)
     .await
-    .map_err(|e| anyhow!(e))
+    .map_err(IndexerError::db)
     .map(|_| ())
 }
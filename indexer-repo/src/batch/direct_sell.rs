@@ -1,5 +1,5 @@
+use crate::error::{IndexerError, Result};
 use crate::types::decoded::DirectSell;
-use anyhow::{anyhow, Result};
 use sqlx::{Postgres, Transaction};
 use std::collections::HashMap;
 
@@ -18,7 +18,15 @@ pub async fn save_direct_sell(
         .iter()
         .map(|ds| ds.price_token.as_str())
         .collect::<Vec<_>>();
+    let price_token_symbols = dss
+        .iter()
+        .map(|ds| ds.price_token_symbol.as_deref())
+        .collect::<Vec<_>>();
     let prices = dss.iter().map(|ds| ds.price.clone()).collect::<Vec<_>>();
+    let prices_normalized = dss
+        .iter()
+        .map(|ds| ds.price_normalized.clone())
+        .collect::<Vec<_>>();
     let sellers = dss.iter().map(|ds| ds.seller.as_str()).collect::<Vec<_>>();
     let finished_at = dss.iter().map(|ds| ds.finished_at).collect::<Vec<_>>();
     let expired_at = dss.iter().map(|ds| ds.expired_at).collect::<Vec<_>>();
@@ -42,7 +50,9 @@ pub async fn save_direct_sell(
                 state,
                 created,
                 updated,
-                tx_lt
+                tx_lt,
+                price_normalized,
+                price_token_symbol
             )
             select
                 unnest($1::varchar[]), 
@@ -57,7 +67,9 @@ pub async fn save_direct_sell(
                 unnest($10::direct_sell_state[]),
                 unnest($11::timestamp[]),
                 unnest($12::timestamp[]),
-                unnest($13::bigint[])
+                unnest($13::bigint[]),
+                unnest($14::numeric[]),
+                unnest($15::text[])
             on conflict(address) do nothing
         "#,
         addresses as _,
@@ -73,13 +85,18 @@ pub async fn save_direct_sell(
         created as _,
         updated as _,
         tx_lt as _,
+        prices_normalized as _,
+        price_token_symbols as _,
     )
     .execute(tx)
     .await
-    .map_err(|e| anyhow!(e))
+    .map_err(IndexerError::db)
     .map(|_| ())
 }
 
+/// Only the latest change of a listing in `dss` is applied, and only if it isn't older
+/// than the stored one, so a redelivered event can't roll the listing back. An equal
+/// logical time is the same event, which a replay applies again
 pub async fn update_direct_sell_state(
     tx: &mut Transaction<'_, Postgres>,
     dss: &mut [DirectSell],
@@ -97,7 +114,9 @@ pub async fn update_direct_sell_state(
     let mut nfts = Vec::with_capacity(dss.len());
     let mut collections = Vec::with_capacity(dss.len());
     let mut price_tokens = Vec::with_capacity(dss.len());
+    let mut price_token_symbols = Vec::with_capacity(dss.len());
     let mut prices = Vec::with_capacity(dss.len());
+    let mut prices_normalized = Vec::with_capacity(dss.len());
     let mut sellers = Vec::with_capacity(dss.len());
     let mut expired_at = Vec::with_capacity(dss.len());
     let mut finished_at = Vec::with_capacity(dss.len());
@@ -111,7 +130,9 @@ pub async fn update_direct_sell_state(
         nfts.push(ds.nft.as_str());
         collections.push(ds.collection.as_deref());
         price_tokens.push(ds.price_token.as_str());
+        price_token_symbols.push(ds.price_token_symbol.as_deref());
         prices.push(ds.price.clone());
+        prices_normalized.push(ds.price_normalized.clone());
         sellers.push(ds.seller.as_str());
         expired_at.push(ds.expired_at);
         finished_at.push(ds.finished_at);
@@ -128,7 +149,9 @@ pub async fn update_direct_sell_state(
             nft = data.nft,
             collection = data.collection,
             price_token = data.price_token,
+            price_token_symbol = data.price_token_symbol,
             price = data.price,
+            price_normalized = data.price_normalized,
             seller = data.seller,
             expired_at = data.expired_at,
             finished_at = data.finished_at,
@@ -149,9 +172,12 @@ pub async fn update_direct_sell_state(
                 unnest($9::numeric[]) as price,
                 unnest($10::varchar[]) as seller,
                 unnest($11::timestamp[]) as expired_at,
-                unnest($12::timestamp[]) as created
+                unnest($12::timestamp[]) as created,
+                unnest($13::numeric[]) as price_normalized,
+                unnest($14::text[]) as price_token_symbol
         ) as data
         where nft_direct_sell.address = data.address
+          and nft_direct_sell.tx_lt <= data.tx_lt
         "#,
         addresses as _,
         states as _,
@@ -165,9 +191,44 @@ pub async fn update_direct_sell_state(
         sellers as _,
         expired_at as _,
         created as _,
+        prices_normalized as _,
+        price_token_symbols as _,
+    )
+    .execute(tx)
+    .await
+    .map_err(IndexerError::db)
+    .map(|_| ())
+}
+
+/// Chains a listing to the seller's previous cancelled/expired listing of the
+/// same nft if that one closed less than `window_secs` before the new one started
+pub async fn link_relisted_direct_sells(
+    tx: &mut Transaction<'_, Postgres>,
+    addresses: &[&str],
+    window_secs: f64,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+        update nft_direct_sell as ds set
+            previous_listing_id = (
+                select prev.address
+                from nft_direct_sell as prev
+                where prev.nft = ds.nft
+                    and prev.seller = ds.seller
+                    and prev.address <> ds.address
+                    and prev.state in ('cancelled', 'expired')
+                    and prev.created < ds.created
+                    and prev.updated >= ds.created - make_interval(secs => $2::float8)
+                order by prev.created desc
+                limit 1
+            )
+        where ds.address = any($1::varchar[]) and ds.previous_listing_id is null
+        "#,
+        addresses as _,
+        window_secs,
     )
     .execute(tx)
     .await
-    .map_err(|e| anyhow!(e))
+    .map_err(IndexerError::db)
     .map(|_| ())
 }
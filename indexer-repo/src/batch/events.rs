@@ -1,11 +1,27 @@
-use anyhow::{anyhow, Result};
 use sqlx::{Postgres, Transaction};
 
-use crate::types::decoded::{EventRecord, OfferDeployed};
+use crate::error::{IndexerError, Result};
+use crate::types::decoded::{EventRecord, OfferDeployed, RawEventTransaction};
 
+/// Inserts the events in statements of at most `chunk_size` rows. The chunks share the
+/// caller's transaction, so they run one after another and commit together
 pub async fn save_raw_event(
     tx: &mut Transaction<'_, Postgres>,
     events: &[EventRecord],
+    chunk_size: usize,
+) -> Result<()> {
+    for chunk in events.chunks(chunk_size.max(1)) {
+        insert_raw_events(tx, chunk).await?;
+    }
+
+    Ok(())
+}
+
+/// Events that were already stored are skipped and not counted again in
+/// `event_stats_daily`, which is updated by the same statement
+async fn insert_raw_events(
+    tx: &mut Transaction<'_, Postgres>,
+    events: &[EventRecord],
 ) -> Result<()> {
     let categories = events.iter().map(|e| e.event_category).collect::<Vec<_>>();
     let types = events.iter().map(|e| e.event_type).collect::<Vec<_>>();
@@ -31,6 +47,7 @@ pub async fn save_raw_event(
 
     sqlx::query!(
         r#"
+            with inserted as (
             insert into nft_events (
                 event_cat,  
                 event_type, 
@@ -53,6 +70,17 @@ pub async fn save_raw_event(
                 unnest($8::jsonb[]),
                 unnest($9::text[])
             on conflict(message_hash) do nothing
+            returning event_type, created_at
+            )
+            insert into event_stats_daily (date, event_type, count)
+            select
+                (to_timestamp(created_at) at time zone 'utc')::date,
+                event_type,
+                count(1)
+            from inserted
+            group by 1, 2
+            on conflict (date, event_type) do update set
+                count = event_stats_daily.count + excluded.count
         "#,
         categories as _,
         types as _,
@@ -66,7 +94,7 @@ pub async fn save_raw_event(
     )
     .execute(tx)
     .await
-    .map_err(|e| anyhow!(e))
+    .map_err(IndexerError::db)
     .map(|_| ())
 }
 
@@ -100,6 +128,36 @@ pub async fn save_deployed_offers(
     )
     .execute(tx)
     .await
-    .map_err(|e| anyhow!(e))
+    .map_err(IndexerError::db)
+    .map(|_| ())
+}
+
+pub async fn save_raw_transactions(
+    tx: &mut Transaction<'_, Postgres>,
+    data: &[RawEventTransaction],
+) -> Result<()> {
+    let hashes = data
+        .iter()
+        .map(|e| e.message_hash.as_str())
+        .collect::<Vec<_>>();
+    let bocs = data.iter().map(|e| e.boc.clone()).collect::<Vec<_>>();
+
+    sqlx::query!(
+        r#"
+            update nft_events
+            set raw_tx = data.boc
+            from (
+                select
+                    unnest($1::text[]) as message_hash,
+                    unnest($2::bytea[]) as boc
+            ) as data
+            where nft_events.message_hash = data.message_hash
+        "#,
+        hashes as _,
+        bocs as _,
+    )
+    .execute(tx)
+    .await
+    .map_err(IndexerError::db)
     .map(|_| ())
 }
@@ -0,0 +1,52 @@
+use sqlx::{Postgres, Transaction};
+
+use crate::error::{IndexerError, Result};
+use crate::types::decoded::FailedEvent;
+
+pub async fn save_failed_events(
+    tx: &mut Transaction<'_, Postgres>,
+    data: &[FailedEvent],
+) -> Result<()> {
+    let addresses = data.iter().map(|e| e.address.as_str()).collect::<Vec<_>>();
+    let event_names = data
+        .iter()
+        .map(|e| e.event_name.as_str())
+        .collect::<Vec<_>>();
+    let message_hashes = data
+        .iter()
+        .map(|e| e.message_hash.as_str())
+        .collect::<Vec<_>>();
+    let tx_lts = data.iter().map(|e| e.tx_lt).collect::<Vec<_>>();
+    let tokens = data.iter().map(|e| e.tokens.as_str()).collect::<Vec<_>>();
+    let errors = data.iter().map(|e| e.error.as_str()).collect::<Vec<_>>();
+
+    sqlx::query!(
+        r#"
+            insert into failed_events (
+                address,
+                event_name,
+                message_hash,
+                tx_lt,
+                tokens,
+                error
+            )
+            select
+                unnest($1::varchar[]),
+                unnest($2::text[]),
+                unnest($3::text[]),
+                unnest($4::bigint[]),
+                unnest($5::text[]),
+                unnest($6::text[])
+        "#,
+        addresses as _,
+        event_names as _,
+        message_hashes as _,
+        tx_lts as _,
+        tokens as _,
+        errors as _,
+    )
+    .execute(tx)
+    .await
+    .map_err(IndexerError::db)
+    .map(|_| ())
+}
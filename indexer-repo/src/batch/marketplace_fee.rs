@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+
+use sqlx::{Postgres, Transaction};
+
+use crate::error::{IndexerError, Result};
+use crate::types::decoded::MarketplaceFee;
+
+pub async fn save_marketplace_fees(
+    tx: &mut Transaction<'_, Postgres>,
+    data: &[MarketplaceFee],
+) -> Result<()> {
+    let addresses = data.iter().map(|e| e.address.as_str()).collect::<Vec<_>>();
+    let nums = data.iter().map(|e| e.numerator).collect::<Vec<_>>();
+    let denoms = data.iter().map(|e| e.denominator).collect::<Vec<_>>();
+    let changed_at = data.iter().map(|e| e.changed_at).collect::<Vec<_>>();
+    let changed_lt = data.iter().map(|e| e.changed_lt).collect::<Vec<_>>();
+
+    sqlx::query!(
+        r#"
+            insert into marketplace_fee_history (
+                address,
+                numerator,
+                denominator,
+                changed_at,
+                changed_lt
+            )
+            select
+                unnest($1::varchar[]),
+                unnest($2::integer[]),
+                unnest($3::integer[]),
+                unnest($4::timestamp[]),
+                unnest($5::bigint[])
+            on conflict(address, changed_lt) do nothing
+        "#,
+        addresses as _,
+        nums as _,
+        denoms as _,
+        changed_at as _,
+        changed_lt as _,
+    )
+    .execute(tx)
+    .await
+    .map_err(IndexerError::db)
+    .map(|_| ())
+}
+
+/// Fee changes that may apply to each offer: its own changes, plus its
+/// factory's defaults up to the moment the offer was deployed.
+pub async fn get_marketplace_fees(
+    tx: &mut Transaction<'_, Postgres>,
+    offers: &[&str],
+) -> Result<HashMap<String, Vec<MarketplaceFee>>> {
+    let rows = sqlx::query!(
+        r#"
+        select
+            o.offer as "offer!",
+            h.address as "address!",
+            h.numerator as "numerator!",
+            h.denominator as "denominator!",
+            h.changed_at as "changed_at!",
+            h.changed_lt as "changed_lt!"
+        from unnest($1::varchar[]) as o(offer)
+        left join deployed_offers d on d.address = o.offer
+        join marketplace_fee_history h
+            on h.address = o.offer
+            or (h.address = d.root and h.changed_at <= d.created)
+        "#,
+        offers as _,
+    )
+    .fetch_all(tx)
+    .await
+    .map_err(IndexerError::db)?;
+
+    let mut fees = HashMap::<String, Vec<MarketplaceFee>>::new();
+    for r in rows {
+        fees.entry(r.offer).or_default().push(MarketplaceFee {
+            address: r.address,
+            numerator: r.numerator,
+            denominator: r.denominator,
+            changed_at: r.changed_at,
+            changed_lt: r.changed_lt,
+        });
+    }
+
+    Ok(fees)
+}
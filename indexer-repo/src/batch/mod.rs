@@ -5,16 +5,22 @@ mod auc_deployed;
 mod auc_update_prices;
 mod collection;
 mod collection_fee;
+mod collection_royalty;
+mod collection_volume;
 mod direct_buy;
 mod direct_sell;
 mod events;
+mod failed_events;
+mod marketplace_fee;
 mod nft_burned;
 mod nft_created;
 mod nft_manager_changed;
 mod nft_owner_changed;
+mod nft_transfer_history;
+mod parked_events;
 mod prices;
 
-pub use auc_active::save_auc_active;
+pub use auc_active::{get_auction_price_tokens, save_auc_active};
 pub use auc_bid_save::save_auc_bid;
 pub use auc_complete_cancelled::save_auc_cancelled;
 pub use auc_complete_cancelled::save_auc_complete;
@@ -22,14 +28,22 @@ pub use auc_deployed::save_auc_deployed;
 pub use auc_update_prices::update_auc_maxmin;
 pub use collection::save_collections;
 pub use collection_fee::update_collection_fee;
+pub use collection_royalty::{get_collection_royalty_rates, save_collection_royalty_earned};
+pub use collection_volume::get_whitelisted_offers;
 pub use direct_buy::save_direct_buy;
 pub use direct_buy::update_direct_buy_state;
+pub use direct_sell::link_relisted_direct_sells;
 pub use direct_sell::save_direct_sell;
 pub use direct_sell::update_direct_sell_state;
 pub use events::save_deployed_offers;
 pub use events::save_raw_event;
-pub use nft_burned::save_nft_burned;
-pub use nft_created::save_nft_created;
+pub use events::save_raw_transactions;
+pub use failed_events::save_failed_events;
+pub use marketplace_fee::{get_marketplace_fees, save_marketplace_fees};
+pub use nft_burned::{cancel_burned_nft_offers, save_nft_burned};
+pub use nft_created::{get_nft_collections, save_nft_created};
 pub use nft_manager_changed::save_nft_manager_changed;
 pub use nft_owner_changed::save_nft_owner_changed;
+pub use nft_transfer_history::save_nft_transfer_history;
+pub use parked_events::{delete_parked_events, save_parked_events};
 pub use prices::save_price_history;
@@ -1,6 +1,6 @@
-use anyhow::{anyhow, Result};
 use sqlx::{Postgres, Transaction};
 
+use crate::error::{IndexerError, Result};
 use crate::types::decoded::NftBurned;
 
 pub async fn save_nft_burned(
@@ -41,6 +41,58 @@ pub async fn save_nft_burned(
     )
     .execute(tx)
     .await
-    .map_err(|e| anyhow!(e))
+    .map_err(IndexerError::db)
+    .map(|_| ())
+}
+
+/// Listings and auctions of burned nfts can't be filled anymore, active ones are
+/// cancelled at the burn. Run after the offer state updates of the batch, older state
+/// changes must not bring them back
+pub async fn cancel_burned_nft_offers(
+    tx: &mut Transaction<'_, Postgres>,
+    nft_burned: &[NftBurned],
+) -> Result<()> {
+    let nfts = nft_burned
+        .iter()
+        .map(|n| n.address.as_str())
+        .collect::<Vec<_>>();
+    let burned_at = nft_burned.iter().map(|n| n.burned_at).collect::<Vec<_>>();
+    let tx_lts = nft_burned.iter().map(|n| n.tx_lt).collect::<Vec<_>>();
+
+    sqlx::query!(
+        r#"
+        with data as (
+            select
+                unnest($1::varchar[]) as nft,
+                unnest($2::timestamp[]) as burned_at,
+                unnest($3::bigint[]) as tx_lt
+        ),
+        direct_sells as (
+            update nft_direct_sell set
+                state = 'cancelled',
+                finished_at = data.burned_at,
+                updated = data.burned_at,
+                tx_lt = data.tx_lt
+            from data
+            where nft_direct_sell.nft = data.nft
+              and nft_direct_sell.state = 'active'
+              and nft_direct_sell.tx_lt <= data.tx_lt
+        )
+        update nft_auction set
+            status = 'cancelled',
+            finished_at = data.burned_at,
+            tx_lt = data.tx_lt
+        from data
+        where nft_auction.nft = data.nft
+          and nft_auction.status in ('created', 'active')
+          and nft_auction.tx_lt <= data.tx_lt
+    "#,
+        nfts as _,
+        burned_at as _,
+        tx_lts as _
+    )
+    .execute(tx)
+    .await
+    .map_err(IndexerError::db)
     .map(|_| ())
 }
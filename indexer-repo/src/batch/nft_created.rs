@@ -1,12 +1,22 @@
-use anyhow::{anyhow, Result};
+use std::collections::{HashMap, HashSet};
+
 use sqlx::{Postgres, Transaction};
 
+use crate::error::{IndexerError, Result};
 use crate::types::decoded::NftCreated;
 
 pub async fn save_nft_created(
     tx: &mut Transaction<'_, Postgres>,
     nft_created: &[NftCreated],
 ) -> Result<()> {
+    // A replayed batch may carry the same nft twice, and `on conflict do update`
+    // can't touch one row twice within a statement
+    let mut seen = HashSet::with_capacity(nft_created.len());
+    let nft_created = nft_created
+        .iter()
+        .filter(|n| seen.insert(n.address.as_str()))
+        .collect::<Vec<_>>();
+
     let ids = nft_created.iter().map(|n| n.id.clone()).collect::<Vec<_>>();
     let addresses = nft_created
         .iter()
@@ -33,6 +43,12 @@ pub async fn save_nft_created(
         .iter()
         .map(|n| n.manager_update_lt as i64)
         .collect::<Vec<_>>();
+    let minters = nft_created
+        .iter()
+        .map(|n| n.minter.as_str())
+        .collect::<Vec<_>>();
+    let created_lt = nft_created.iter().map(|n| n.created_lt).collect::<Vec<_>>();
+    let created_at = nft_created.iter().map(|n| n.created_at).collect::<Vec<_>>();
 
     sqlx::query!(
         r#"
@@ -44,7 +60,10 @@ pub async fn save_nft_created(
                 manager, 
                 updated, 
                 owner_update_lt, 
-                manager_update_lt
+                manager_update_lt,
+                minter,
+                created_lt,
+                created_at
             )
             select
                 unnest($1::numeric[]),
@@ -54,8 +73,23 @@ pub async fn save_nft_created(
                 unnest($5::varchar[]), 
                 unnest($6::timestamp[]),
                 unnest($7::bigint[]),
-                unnest($8::bigint[]) 
-            on conflict(address) do nothing
+                unnest($8::bigint[]),
+                unnest($9::varchar[]),
+                unnest($10::bigint[]),
+                unnest($11::timestamp[])
+            on conflict(address) do update set
+                id = excluded.id,
+                collection = coalesce(nft.collection, excluded.collection),
+                owner = case when nft.owner_update_lt < excluded.owner_update_lt
+                    then excluded.owner else nft.owner end,
+                owner_update_lt = greatest(nft.owner_update_lt, excluded.owner_update_lt),
+                manager = case when nft.manager_update_lt < excluded.manager_update_lt
+                    then excluded.manager else nft.manager end,
+                manager_update_lt = greatest(nft.manager_update_lt, excluded.manager_update_lt),
+                updated = greatest(nft.updated, excluded.updated),
+                minter = coalesce(nft.minter, excluded.minter),
+                created_lt = coalesce(nft.created_lt, excluded.created_lt),
+                created_at = coalesce(nft.created_at, excluded.created_at)
         "#,
         ids as _,
         addresses as _,
@@ -65,9 +99,38 @@ pub async fn save_nft_created(
         updated as _,
         owner_update_lt as _,
         manager_update_lt as _,
+        minters as _,
+        created_lt as _,
+        created_at as _,
     )
     .execute(tx)
     .await
-    .map_err(|e| anyhow!(e))
+    .map_err(IndexerError::db)
     .map(|_| ())
 }
+
+/// Collections of the nfts in a single query, nfts that aren't indexed yet are left
+/// out. The collection proven by a TIP-4.3 index wins over the one the nft claimed in
+/// its events
+pub async fn get_nft_collections(
+    tx: &mut Transaction<'_, Postgres>,
+    nfts: &[&str],
+) -> Result<HashMap<String, String>> {
+    let rows = sqlx::query!(
+        r#"
+        select n.address as "address!", coalesce(v.collection, n.collection) as "collection!"
+        from nft n
+        left join nft_collection_verified v on v.nft = n.address
+        where n.address = any($1::varchar[])
+        "#,
+        nfts as _,
+    )
+    .fetch_all(tx)
+    .await
+    .map_err(IndexerError::db)?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| (r.address, r.collection))
+        .collect())
+}
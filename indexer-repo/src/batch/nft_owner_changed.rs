@@ -1,8 +1,8 @@
 use std::collections::HashMap;
 
-use anyhow::{anyhow, Result};
 use sqlx::{Postgres, Transaction};
 
+use crate::error::{IndexerError, Result};
 use crate::types::decoded::AddressChanged;
 
 pub async fn save_nft_owner_changed(
@@ -21,10 +21,12 @@ pub async fn save_nft_owner_changed(
     let mut addresses = Vec::with_capacity(last_addresses.keys().len());
     let mut new_owners = Vec::with_capacity(last_addresses.keys().len());
     let mut timestamps = Vec::with_capacity(last_addresses.keys().len());
+    let mut logical_times = Vec::with_capacity(last_addresses.keys().len());
 
     for val in last_addresses.values() {
         addresses.push(val.id_address.as_str());
         new_owners.push(val.new_address.as_str());
+        logical_times.push(val.logical_time as i64);
         timestamps.push(val.timestamp)
     }
 
@@ -32,22 +34,25 @@ pub async fn save_nft_owner_changed(
         r#"
         update nft set
             owner = data.owner,
+            owner_update_lt = data.lt,
             updated = data.time
         from
         (
             select 
                 unnest($1::varchar[]) as address,
                 unnest($2::varchar[]) as owner,
-                unnest($3::timestamp[]) as time
+                unnest($3::timestamp[]) as time,
+                unnest($4::bigint[]) as lt
         ) as data
-        where nft.address = data.address
+        where nft.address = data.address and nft.owner_update_lt < data.lt
     "#,
         addresses as _,
         new_owners as _,
         timestamps as _,
+        logical_times as _,
     )
     .execute(tx)
     .await
-    .map_err(|e| anyhow!(e))
+    .map_err(IndexerError::db)
     .map(|_| ())
 }
@@ -0,0 +1,60 @@
+use sqlx::{Postgres, Transaction};
+
+use crate::error::{IndexerError, Result};
+use crate::types::{decoded::AddressChanged, NftTransferKind};
+
+/// Every owner or manager change, unlike `nft` which only keeps the latest one
+pub async fn save_nft_transfer_history(
+    tx: &mut Transaction<'_, Postgres>,
+    kind: NftTransferKind,
+    data: &[AddressChanged],
+) -> Result<()> {
+    let nfts = data
+        .iter()
+        .map(|e| e.id_address.as_str())
+        .collect::<Vec<_>>();
+    let old_addresses = data
+        .iter()
+        .map(|e| e.old_address.as_str())
+        .collect::<Vec<_>>();
+    let new_addresses = data
+        .iter()
+        .map(|e| e.new_address.as_str())
+        .collect::<Vec<_>>();
+    let logical_times = data
+        .iter()
+        .map(|e| e.logical_time as i64)
+        .collect::<Vec<_>>();
+    let timestamps = data.iter().map(|e| e.timestamp).collect::<Vec<_>>();
+
+    sqlx::query!(
+        r#"
+            insert into nft_transfer_history (
+                nft,
+                kind,
+                old_address,
+                new_address,
+                created_lt,
+                created_at
+            )
+            select
+                unnest($1::varchar[]),
+                $2::nft_transfer_kind,
+                unnest($3::varchar[]),
+                unnest($4::varchar[]),
+                unnest($5::bigint[]),
+                unnest($6::timestamp[])
+            on conflict (nft, kind, created_lt) do nothing
+        "#,
+        nfts as _,
+        kind as _,
+        old_addresses as _,
+        new_addresses as _,
+        logical_times as _,
+        timestamps as _,
+    )
+    .execute(tx)
+    .await
+    .map_err(IndexerError::db)
+    .map(|_| ())
+}
@@ -0,0 +1,96 @@
+use sqlx::{Postgres, Transaction};
+
+use crate::error::{IndexerError, Result};
+use crate::types::decoded::EventRecord;
+
+/// Events of paused parsers by parser name
+pub async fn save_parked_events(
+    tx: &mut Transaction<'_, Postgres>,
+    events: &[(&str, EventRecord)],
+) -> Result<()> {
+    let parsers = events.iter().map(|(parser, _)| *parser).collect::<Vec<_>>();
+    let categories = events
+        .iter()
+        .map(|(_, e)| e.event_category)
+        .collect::<Vec<_>>();
+    let types = events.iter().map(|(_, e)| e.event_type).collect::<Vec<_>>();
+    let addresses = events
+        .iter()
+        .map(|(_, e)| e.address.as_str())
+        .collect::<Vec<_>>();
+    let nfts = events
+        .iter()
+        .map(|(_, e)| e.nft.as_deref())
+        .collect::<Vec<_>>();
+    let collections = events
+        .iter()
+        .map(|(_, e)| e.collection.as_deref())
+        .collect::<Vec<_>>();
+    let created_lt = events.iter().map(|(_, e)| e.created_lt).collect::<Vec<_>>();
+    let created_at = events.iter().map(|(_, e)| e.created_at).collect::<Vec<_>>();
+    let hashes = events
+        .iter()
+        .map(|(_, e)| e.message_hash.as_str())
+        .collect::<Vec<_>>();
+    let args = events
+        .iter()
+        .map(|(_, e)| e.raw_data.clone())
+        .collect::<Vec<_>>();
+
+    sqlx::query!(
+        r#"
+            insert into parked_events (
+                parser,
+                event_cat,
+                event_type,
+                address,
+                nft,
+                collection,
+                created_lt,
+                created_at,
+                message_hash,
+                args
+            )
+            select
+                unnest($1::text[]),
+                unnest($2::event_category[]),
+                unnest($3::event_type[]),
+                unnest($4::varchar[]),
+                unnest($5::varchar[]),
+                unnest($6::varchar[]),
+                unnest($7::bigint[]),
+                unnest($8::bigint[]),
+                unnest($9::text[]),
+                unnest($10::jsonb[])
+        "#,
+        parsers as _,
+        categories as _,
+        types as _,
+        addresses as _,
+        nfts as _,
+        collections as _,
+        created_lt as _,
+        created_at as _,
+        hashes as _,
+        args as _,
+    )
+    .execute(tx)
+    .await
+    .map_err(IndexerError::db)
+    .map(|_| ())
+}
+
+/// Parked events applied by the same transaction
+pub async fn delete_parked_events(tx: &mut Transaction<'_, Postgres>, ids: &[i64]) -> Result<()> {
+    sqlx::query!(
+        r#"
+            delete from parked_events
+            where id = any($1::bigint[])
+        "#,
+        ids as _,
+    )
+    .execute(tx)
+    .await
+    .map_err(IndexerError::db)
+    .map(|_| ())
+}
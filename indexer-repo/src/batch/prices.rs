@@ -1,12 +1,24 @@
-use anyhow::{anyhow, Result};
+use std::collections::HashSet;
+
+use chrono::NaiveDateTime;
 use sqlx::{Postgres, Transaction};
 
+use crate::error::{IndexerError, Result};
 use crate::types::decoded::NftPriceHistory;
+use crate::types::NftPriceSource;
+
+/// Key of a stored sale, unique in `nft_price_history`
+pub type PriceKey = (String, NftPriceSource, NaiveDateTime);
 
+/// Stores the sales and adds the newly inserted ones of whitelisted offers to
+/// `collection_volume_daily` in the same statement, so a sale is counted exactly when
+/// its row is inserted and a replayed or twice delivered sale is counted once. Sales
+/// without a USD price are counted but add nothing to the volume. Returns the keys of
+/// the inserted sales, already stored ones are skipped
 pub async fn save_price_history(
     tx: &mut Transaction<'_, Postgres>,
     data: &[NftPriceHistory],
-) -> Result<()> {
+) -> Result<HashSet<PriceKey>> {
     let sources = data.iter().map(|e| e.source.as_str()).collect::<Vec<_>>();
     let source_types = data.iter().map(|e| e.source_type).collect::<Vec<_>>();
     let created_at = data.iter().map(|e| e.created_at).collect::<Vec<_>>();
@@ -15,34 +27,71 @@ pub async fn save_price_history(
         .iter()
         .map(|e| e.price_token.as_str())
         .collect::<Vec<_>>();
+    let price_token_symbols = data
+        .iter()
+        .map(|e| e.price_token_symbol.as_deref())
+        .collect::<Vec<_>>();
     let nft = data.iter().map(|e| e.nft.as_str()).collect::<Vec<_>>();
     let usd_prices = data.iter().map(|e| e.usd_price.clone()).collect::<Vec<_>>();
+    let marketplace_fees = data
+        .iter()
+        .map(|e| e.marketplace_fee.clone())
+        .collect::<Vec<_>>();
+    let royalties = data.iter().map(|e| e.royalty.clone()).collect::<Vec<_>>();
     let collections = data
         .iter()
         .map(|e| e.collection.as_str())
         .collect::<Vec<_>>();
+    let buyers = data.iter().map(|e| e.buyer.as_deref()).collect::<Vec<_>>();
+    let sellers = data.iter().map(|e| e.seller.as_deref()).collect::<Vec<_>>();
 
     sqlx::query!(
         r#"
-            insert into nft_price_history (
-                source, 
-                source_type, 
-                ts, 
-                price,
-                price_token, 
-                nft,
-                usd_price,
-                collection
+            with inserted as (
+                insert into nft_price_history (
+                    source, 
+                    source_type, 
+                    ts, 
+                    price,
+                    price_token, 
+                    nft,
+                    usd_price,
+                    collection,
+                    buyer,
+                    seller,
+                    marketplace_fee,
+                    price_token_symbol,
+                    royalty
+                )
+                select
+                    unnest($1::varchar[]),
+                    unnest($2::nft_price_source[]),
+                    unnest($3::timestamp[]),
+                    unnest($4::numeric[]),
+                    unnest($5::varchar[]),
+                    unnest($6::varchar[]),
+                    unnest($7::numeric[]),
+                    unnest($8::varchar[]),
+                    unnest($9::varchar[]),
+                    unnest($10::varchar[]),
+                    unnest($11::numeric[]),
+                    unnest($12::text[]),
+                    unnest($13::numeric[])
+                on conflict (source, source_type, ts) do nothing
+                returning source, source_type, ts, usd_price, collection
+            ),
+            volumes as (
+                insert into collection_volume_daily (collection, day, volume_usd, sales)
+                select inserted.collection, inserted.ts::date, coalesce(sum(inserted.usd_price), 0), count(1)
+                from inserted
+                         join offers_whitelist ow on ow.address = inserted.source
+                group by 1, 2
+                on conflict (collection, day) do update set
+                    volume_usd = collection_volume_daily.volume_usd + excluded.volume_usd,
+                    sales = collection_volume_daily.sales + excluded.sales
             )
-            select
-                unnest($1::varchar[]),
-                unnest($2::nft_price_source[]),
-                unnest($3::timestamp[]),
-                unnest($4::numeric[]),
-                unnest($5::varchar[]),
-                unnest($6::varchar[]),
-                unnest($7::numeric[]),
-                unnest($8::varchar[])
+            select source as "source!", source_type as "source_type!: NftPriceSource", ts as "ts!"
+            from inserted
         "#,
         sources as _,
         source_types as _,
@@ -52,9 +101,18 @@ pub async fn save_price_history(
         nft as _,
         usd_prices as _,
         collections as _,
+        buyers as _,
+        sellers as _,
+        marketplace_fees as _,
+        price_token_symbols as _,
+        royalties as _,
     )
-    .execute(tx)
+    .fetch_all(tx)
     .await
-    .map_err(|e| anyhow!(e))
-    .map(|_| ())
+    .map(|rows| {
+        rows.into_iter()
+            .map(|r| (r.source, r.source_type, r.ts))
+            .collect()
+    })
+    .map_err(IndexerError::db)
 }
@@ -0,0 +1,48 @@
+use anyhow::{anyhow, Result};
+use sqlx::{PgPool, Postgres, Transaction};
+
+/// Last transaction whose events were committed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Checkpoint {
+    pub tx_timestamp: i64,
+    pub tx_lt: i64,
+    pub tx_hash: String,
+}
+
+pub async fn get_checkpoint(pg_pool: &PgPool) -> Result<Option<Checkpoint>> {
+    sqlx::query_as!(
+        Checkpoint,
+        r#"
+        select tx_timestamp, tx_lt, tx_hash
+        from indexer_checkpoint
+        where id = 1
+        "#
+    )
+    .fetch_optional(pg_pool)
+    .await
+    .map_err(|e| anyhow!(e))
+}
+
+pub async fn save_checkpoint(
+    tx: &mut Transaction<'_, Postgres>,
+    checkpoint: &Checkpoint,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+        insert into indexer_checkpoint (id, tx_timestamp, tx_lt, tx_hash, updated)
+        values (1, $1, $2, $3, now())
+        on conflict (id) do update set
+            tx_timestamp = excluded.tx_timestamp,
+            tx_lt = excluded.tx_lt,
+            tx_hash = excluded.tx_hash,
+            updated = excluded.updated
+        "#,
+        checkpoint.tx_timestamp,
+        checkpoint.tx_lt,
+        checkpoint.tx_hash
+    )
+    .execute(tx)
+    .await
+    .map_err(|e| anyhow!(e))
+    .map(|_| ())
+}
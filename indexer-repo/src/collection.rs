@@ -1,5 +1,20 @@
 use anyhow::{anyhow, Result};
-use sqlx::PgPool;
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::Serialize;
+use sqlx::{types::BigDecimal, PgPool};
+
+use crate::types::{CollectionRoyalty, CollectionRoyaltyEarned};
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CollectionRecord {
+    pub address: String,
+    pub owner: Option<String>,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub logo: Option<String>,
+    pub wallpaper: Option<String>,
+    pub verified: bool,
+}
 
 pub async fn get_collections(pg_pool: &PgPool, limit: i64) -> Result<Vec<String>> {
     sqlx::query_scalar!(
@@ -15,3 +30,230 @@ pub async fn get_collections(pg_pool: &PgPool, limit: i64) -> Result<Vec<String>
     .await
     .map_err(|e| anyhow!(e))
 }
+
+pub async fn get_collections_by_addresses(
+    pg_pool: &PgPool,
+    addresses: &[String],
+) -> Result<Vec<CollectionRecord>> {
+    sqlx::query_as!(
+        CollectionRecord,
+        r#"
+        select address, owner, name, description, logo, wallpaper, verified
+        from nft_collection
+        where address = any($1::varchar[])
+        "#,
+        addresses as _
+    )
+    .fetch_all(pg_pool)
+    .await
+    .map_err(|e| anyhow!(e))
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CollectionStats {
+    pub collection: String,
+    pub active_listings: i64,
+    pub total_nfts: i64,
+    /// Last time the counters were checked against `nft_direct_sell` and `nft`
+    pub reconciled_at: Option<NaiveDateTime>,
+}
+
+/// Counters maintained by the `nft_direct_sell` trigger, `None` for collections
+/// without a listing or reconciliation yet
+pub async fn get_collection_stats(
+    pg_pool: &PgPool,
+    collection: &str,
+) -> Result<Option<CollectionStats>> {
+    sqlx::query_as!(
+        CollectionStats,
+        r#"
+        select collection, active_listings, total_nfts, reconciled_at
+        from collection_stats
+        where collection = $1
+        "#,
+        collection as _
+    )
+    .fetch_optional(pg_pool)
+    .await
+    .map_err(|e| anyhow!(e))
+}
+
+/// Recounts active listings and unburned nfts of every collection and overwrites the
+/// counters that drifted, returns how many were corrected. A listing change committed
+/// while this runs may be overwritten by the older count, the next run corrects it
+pub async fn reconcile_collection_stats(pg_pool: &PgPool) -> Result<i64> {
+    sqlx::query_scalar!(
+        r#"
+        with listings as (
+            select collection, count(1) as active_listings
+            from nft_direct_sell
+            where state = 'active' and collection is not null
+            group by collection
+        ),
+        nfts as (
+            select collection, count(1) as total_nfts
+            from nft
+            where not burned and collection is not null
+            group by collection
+        ),
+        counts as (
+            select c.address as collection,
+                   coalesce(l.active_listings, 0) as active_listings,
+                   coalesce(n.total_nfts, 0) as total_nfts
+            from nft_collection c
+                     left join listings l on l.collection = c.address
+                     left join nfts n on n.collection = c.address
+        ),
+        drifted as (
+            select counts.*
+            from counts
+                     left join collection_stats s on s.collection = counts.collection
+            where s.collection is null
+               or s.active_listings <> counts.active_listings
+               or s.total_nfts <> counts.total_nfts
+        ),
+        corrected as (
+            insert into collection_stats (collection, active_listings, total_nfts, reconciled_at)
+            select collection, active_listings, total_nfts, now()
+            from drifted
+            on conflict (collection) do update set
+                active_listings = excluded.active_listings,
+                total_nfts = excluded.total_nfts,
+                reconciled_at = excluded.reconciled_at
+            returning 1
+        )
+        select count(1) as "count!" from corrected
+        "#
+    )
+    .fetch_one(pg_pool)
+    .await
+    .map_err(|e| anyhow!(e))
+}
+
+/// Cheapest active, unexpired listing of the collection in `price_token`. Reads
+/// `nft_direct_sell` directly so listings that expired since the last
+/// `collection_floor` refresh are already excluded
+pub async fn get_collection_floor(
+    pg_pool: &PgPool,
+    collection: &str,
+    price_token: &str,
+) -> Result<Option<BigDecimal>> {
+    sqlx::query_scalar!(
+        r#"
+        select min(ds.price)
+        from nft_direct_sell ds
+                 join offers_whitelist ow on ow.address = ds.address
+                 join nft n on n.address = ds.nft and not n.burned
+        where n.collection = $1
+          and ds.price_token = $2
+          and ds.state = 'active'::direct_sell_state
+          and (ds.expired_at = to_timestamp(0) or ds.expired_at > now()::timestamp)
+        "#,
+        collection as _,
+        price_token as _
+    )
+    .fetch_one(pg_pool)
+    .await
+    .map_err(|e| anyhow!(e))
+}
+
+pub async fn refresh_collection_floor(pg_pool: &PgPool) -> Result<()> {
+    sqlx::query!(
+        r#"
+        refresh materialized view concurrently collection_floor
+        "#
+    )
+    .execute(pg_pool)
+    .await
+    .map_err(|e| anyhow!(e))
+    .map(|_| ())
+}
+
+/// Rebuilds `collection_floor` from the current listings and returns the number of
+/// collections that have a floor. The concurrent refresh keeps the view readable
+/// while the indexer goes on
+pub async fn recompute_collection_floors(pg_pool: &PgPool) -> Result<i64> {
+    let mut tx = pg_pool.begin().await?;
+
+    sqlx::query!(
+        r#"
+        refresh materialized view concurrently collection_floor
+        "#
+    )
+    .execute(&mut tx)
+    .await?;
+
+    let collections = sqlx::query_scalar!(
+        r#"
+        select count(distinct collection) as "count!" from collection_floor
+        "#
+    )
+    .fetch_one(&mut tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(collections)
+}
+
+/// USD volume of the collection's sales on days `from..=to`, from `collection_volume_daily`
+pub async fn get_collection_volume(
+    pg_pool: &PgPool,
+    collection: &str,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<BigDecimal> {
+    sqlx::query_scalar!(
+        r#"
+        select coalesce(sum(volume_usd), 0) as "volume!"
+        from collection_volume_daily
+        where collection = $1
+          and day between $2 and $3
+        "#,
+        collection as _,
+        from,
+        to
+    )
+    .fetch_one(pg_pool)
+    .await
+    .map_err(|e| anyhow!(e))
+}
+
+/// `None` if the collection hasn't been queried yet; a royalty with all fields `None`
+/// if it doesn't implement the royalty getter
+pub async fn get_collection_royalty(
+    pg_pool: &PgPool,
+    collection: &str,
+) -> Result<Option<CollectionRoyalty>> {
+    sqlx::query_as!(
+        CollectionRoyalty,
+        r#"
+        select collection, numerator, denominator, recipient, updated
+        from collection_royalty
+        where collection = $1
+        "#,
+        collection as _
+    )
+    .fetch_optional(pg_pool)
+    .await
+    .map_err(|e| anyhow!(e))
+}
+
+/// Royalties accrued by the collection's whitelisted sales, one row per payment token
+pub async fn get_collection_royalties_earned(
+    pg_pool: &PgPool,
+    collection: &str,
+) -> Result<Vec<CollectionRoyaltyEarned>> {
+    sqlx::query_as!(
+        CollectionRoyaltyEarned,
+        r#"
+        select collection, token, amount
+        from collection_royalty_earned
+        where collection = $1
+        order by token
+        "#,
+        collection as _
+    )
+    .fetch_all(pg_pool)
+    .await
+    .map_err(|e| anyhow!(e))
+}
@@ -0,0 +1,61 @@
+use anyhow::Result;
+use log::LevelFilter;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use sqlx::{ConnectOptions, Executor, PgPool};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Connection pool settings. Every handler of a batch holds a connection while it
+/// runs, so `max_connections` should cover the batch writer plus the API, resolvers
+/// and periodic tasks sharing the pool
+#[derive(Debug, Clone)]
+pub struct DbConfig {
+    pub url: String,
+    pub max_connections: u32,
+    /// How long to wait for a free connection before failing the query
+    pub acquire_timeout: Duration,
+    /// Connections idle for longer are closed, `None` keeps them open
+    pub idle_timeout: Option<Duration>,
+    /// Postgres aborts statements running for longer, `None` uses the server setting
+    pub statement_timeout: Option<Duration>,
+    /// Kill other sessions of the database on startup, e.g. ones left by a crashed run
+    pub terminate_open_connections: bool,
+}
+
+pub async fn connect(config: &DbConfig) -> Result<PgPool> {
+    let mut connect_options = PgConnectOptions::from_str(&config.url)?;
+    connect_options
+        .log_statements(LevelFilter::Debug)
+        .log_slow_statements(LevelFilter::Debug, Duration::from_secs(10));
+    if let Some(timeout) = config.statement_timeout {
+        connect_options =
+            connect_options.options([("statement_timeout", timeout.as_millis().to_string())]);
+    }
+
+    let pool = PgPoolOptions::new()
+        .max_connections(config.max_connections)
+        .acquire_timeout(config.acquire_timeout)
+        .idle_timeout(config.idle_timeout)
+        .after_connect(|conn, _| {
+            Box::pin(async move { conn.execute("select 1").await.map(|_| ()) })
+        })
+        .connect_with(connect_options)
+        .await?;
+
+    if config.terminate_open_connections {
+        sqlx::query(
+            r#"
+            select pg_terminate_backend(pid)
+            from pg_stat_activity
+            where datname = current_database()
+              and query not like 'select pg_terminate_backend%'
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+    }
+
+    log::info!("connected to database");
+
+    Ok(pool)
+}
@@ -0,0 +1,49 @@
+use anyhow::{anyhow, Result};
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::{types::BigDecimal, PgPool};
+
+use crate::types::DirectBuyState;
+
+/// Stored offer fields that are derived from `DirectBuyStateChanged` events
+#[derive(Clone, Debug, Serialize)]
+pub struct DirectBuyRecord {
+    pub address: String,
+    pub nft: String,
+    pub collection: Option<String>,
+    pub price_token: String,
+    pub price: BigDecimal,
+    pub buyer: Option<String>,
+    pub finished_at: Option<NaiveDateTime>,
+    pub expired_at: Option<NaiveDateTime>,
+    pub state: DirectBuyState,
+    pub created: NaiveDateTime,
+}
+
+/// Active, unexpired buy offers for the nft, highest price first
+pub async fn get_nft_offers(pg_pool: &PgPool, nft: &str) -> Result<Vec<DirectBuyRecord>> {
+    sqlx::query_as!(
+        DirectBuyRecord,
+        r#"
+        select address,
+               nft,
+               collection,
+               price_token,
+               price,
+               buyer,
+               finished_at,
+               expired_at,
+               state as "state: DirectBuyState",
+               created
+        from nft_direct_buy
+        where nft = $1
+          and state = 'active'::direct_buy_state
+          and (expired_at = to_timestamp(0) or expired_at > now()::timestamp)
+        order by price desc, created
+        "#,
+        nft as _
+    )
+    .fetch_all(pg_pool)
+    .await
+    .map_err(|e| anyhow!(e))
+}
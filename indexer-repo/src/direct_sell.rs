@@ -0,0 +1,100 @@
+use anyhow::{anyhow, Result};
+use chrono::NaiveDateTime;
+use sqlx::{types::BigDecimal, PgPool};
+
+use crate::types::DirectSellState;
+
+/// Stored listing fields that are derived from `DirectSellStateChanged` events
+#[derive(Clone, Debug)]
+pub struct DirectSellRecord {
+    pub address: String,
+    pub nft: String,
+    pub collection: Option<String>,
+    pub price_token: String,
+    pub price: BigDecimal,
+    pub seller: Option<String>,
+    pub finished_at: Option<NaiveDateTime>,
+    pub expired_at: Option<NaiveDateTime>,
+    pub state: DirectSellState,
+    pub created: Option<NaiveDateTime>,
+}
+
+pub async fn get_direct_sells(
+    pg_pool: &PgPool,
+    addresses: &[&str],
+) -> Result<Vec<DirectSellRecord>> {
+    sqlx::query_as!(
+        DirectSellRecord,
+        r#"
+        select address,
+               nft,
+               collection,
+               price_token,
+               price,
+               seller,
+               finished_at,
+               expired_at,
+               state as "state: DirectSellState",
+               created
+        from nft_direct_sell
+        where address = any($1::varchar[])
+        "#,
+        addresses as _
+    )
+    .fetch_all(pg_pool)
+    .await
+    .map_err(|e| anyhow!(e))
+}
+
+/// Latest active, unexpired listing of each of the nfts that has one
+pub async fn get_active_direct_sells_by_nfts(
+    pg_pool: &PgPool,
+    nfts: &[String],
+) -> Result<Vec<DirectSellRecord>> {
+    sqlx::query_as!(
+        DirectSellRecord,
+        r#"
+        select distinct on (nft)
+               address,
+               nft,
+               collection,
+               price_token,
+               price,
+               seller,
+               finished_at,
+               expired_at,
+               state as "state: DirectSellState",
+               created
+        from nft_direct_sell
+        where nft = any($1::varchar[])
+          and state = 'active'::direct_sell_state
+          and (expired_at = to_timestamp(0) or expired_at > now()::timestamp)
+        order by nft, created desc
+        "#,
+        nfts as _
+    )
+    .fetch_all(pg_pool)
+    .await
+    .map_err(|e| anyhow!(e))
+}
+
+/// Moves active direct sells that ended before `now` to `expired`, as no event
+/// marks a listing that simply ran out. Listings without an end (`expired_at` at
+/// the epoch) stay active. Returns the number of listings expired
+pub async fn expire_stale_direct_sells(pg_pool: &PgPool, now: NaiveDateTime) -> Result<u64> {
+    sqlx::query!(
+        r#"
+        update nft_direct_sell
+        set state = 'expired'::direct_sell_state,
+            updated = expired_at
+        where state = 'active'::direct_sell_state
+          and expired_at != to_timestamp(0)
+          and expired_at < $1
+        "#,
+        now
+    )
+    .execute(pg_pool)
+    .await
+    .map(|result| result.rows_affected())
+    .map_err(|e| anyhow!(e))
+}
@@ -0,0 +1,53 @@
+use std::fmt;
+
+/// Failure kinds of the indexer, so callers can tell a skippable decode error from a
+/// fatal database one. Internals keep using `anyhow` and convert here at the boundary
+#[derive(Debug)]
+pub enum IndexerError {
+    /// A compiled-in contract ABI failed to load
+    Abi(String),
+    Db(sqlx::Error),
+    /// An event or request parameter didn't match the expected shape
+    Decode(String),
+    /// The transaction stream couldn't be consumed
+    Consumer(String),
+    Config(String),
+}
+
+pub type Result<T, E = IndexerError> = std::result::Result<T, E>;
+
+impl IndexerError {
+    /// `Db` of a failed query. The sqlx error is logged here, where it happened: the
+    /// kind is all that `Display` shows, and callers that only print it would lose it
+    pub fn db(e: sqlx::Error) -> Self {
+        log::error!("Database error: {e}");
+        IndexerError::Db(e)
+    }
+}
+
+impl fmt::Display for IndexerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IndexerError::Abi(e) => write!(f, "abi error: {e}"),
+            IndexerError::Db(_) => write!(f, "database error"),
+            IndexerError::Decode(e) => write!(f, "decode error: {e}"),
+            IndexerError::Consumer(e) => write!(f, "consumer error: {e}"),
+            IndexerError::Config(e) => write!(f, "config error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for IndexerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            IndexerError::Db(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<sqlx::Error> for IndexerError {
+    fn from(e: sqlx::Error) -> Self {
+        IndexerError::db(e)
+    }
+}
@@ -0,0 +1,319 @@
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use sqlx::PgPool;
+
+use crate::error::IndexerError;
+use crate::types::decoded::EventRecord;
+use crate::types::{EventCategory, EventStats, EventType};
+
+#[derive(Clone, Debug, Default)]
+pub struct EventFilter {
+    pub address: Option<String>,
+    pub event_type: Option<EventType>,
+    /// Only events with `created_lt >= from_lt`
+    pub from_lt: Option<i64>,
+}
+
+/// Position in the event feed right after the event with this
+/// `(created_at, created_lt, message_hash)`. Rows inserted between page requests can't
+/// shift it, unlike an offset
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EventCursor {
+    pub created_at: i64,
+    pub created_lt: i64,
+    pub message_hash: String,
+}
+
+impl fmt::Display for EventCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}",
+            self.created_at, self.created_lt, self.message_hash
+        )
+    }
+}
+
+impl FromStr for EventCursor {
+    type Err = IndexerError;
+
+    fn from_str(s: &str) -> Result<Self, IndexerError> {
+        let malformed = || IndexerError::Decode(format!("malformed event cursor {s}"));
+        let mut parts = s.splitn(3, ':');
+        let (Some(created_at), Some(created_lt), Some(message_hash)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(malformed());
+        };
+
+        Ok(Self {
+            created_at: created_at.parse().map_err(|_| malformed())?,
+            created_lt: created_lt.parse().map_err(|_| malformed())?,
+            message_hash: message_hash.to_string(),
+        })
+    }
+}
+
+/// Page of the event feed in `(created_at, created_lt, message_hash)` order, starting
+/// after `after`. Logical times only order the transactions of one account, the
+/// timestamp orders the feed across accounts. The returned cursor is set when the page
+/// is full and more events may follow
+pub async fn list_events(
+    pg_pool: &PgPool,
+    filter: &EventFilter,
+    after: Option<&EventCursor>,
+    limit: i64,
+) -> Result<(Vec<EventRecord>, Option<EventCursor>)> {
+    let (after_at, after_lt, after_hash) = match after {
+        Some(cursor) => (
+            Some(cursor.created_at),
+            Some(cursor.created_lt),
+            Some(cursor.message_hash.as_str()),
+        ),
+        None => (None, None, None),
+    };
+
+    let events = sqlx::query_as!(
+        EventRecord,
+        r#"
+        select event_cat as "event_category: EventCategory",
+               event_type as "event_type: EventType",
+               address,
+               created_lt,
+               created_at,
+               message_hash as "message_hash!",
+               nft,
+               collection,
+               args as "raw_data!"
+        from nft_events
+        where ($1::varchar is null or address = $1)
+          and ($2::event_type is null or event_type = $2)
+          and ($3::bigint is null or created_lt >= $3)
+          and ($4::bigint is null
+               or (created_at, created_lt, message_hash) > ($4, $5::bigint, $6::text))
+        order by created_at, created_lt, message_hash
+        limit $7
+        "#,
+        filter.address.as_deref() as _,
+        filter.event_type as _,
+        filter.from_lt,
+        after_at,
+        after_lt,
+        after_hash,
+        limit
+    )
+    .fetch_all(pg_pool)
+    .await
+    .map_err(|e| anyhow!(e))?;
+
+    let next = match events.last() {
+        Some(last) if events.len() as i64 == limit => Some(EventCursor {
+            created_at: last.created_at,
+            created_lt: last.created_lt,
+            message_hash: last.message_hash.clone(),
+        }),
+        _ => None,
+    };
+
+    Ok((events, next))
+}
+
+/// Events of the collection created in `from..to` (unix time) in logical time order,
+/// optionally only of the given types
+pub async fn get_collection_events(
+    pg_pool: &PgPool,
+    collection: &str,
+    from: i64,
+    to: i64,
+    types: Option<&[EventType]>,
+) -> Result<Vec<EventRecord>> {
+    sqlx::query_as!(
+        EventRecord,
+        r#"
+        select event_cat as "event_category: EventCategory",
+               event_type as "event_type: EventType",
+               address,
+               created_lt,
+               created_at,
+               message_hash as "message_hash!",
+               nft,
+               collection,
+               args as "raw_data!"
+        from nft_events
+        where collection = $1
+          and created_at >= $2
+          and created_at < $3
+          and ($4::event_type[] is null or event_type = any($4))
+        order by created_lt, message_hash
+        "#,
+        collection as _,
+        from,
+        to,
+        types as _
+    )
+    .fetch_all(pg_pool)
+    .await
+    .map_err(|e| anyhow!(e))
+}
+
+/// Page of the events of the collection, or of all contracts, with `id > after_id` in
+/// `id` order, with their ids. Ids grow as events are inserted, so events backfilled
+/// after newer ones still follow the last id of a previous page, unlike their logical time
+pub async fn get_events_after_id(
+    pg_pool: &PgPool,
+    collection: Option<&str>,
+    after_id: Option<i64>,
+    limit: i64,
+) -> Result<Vec<(i64, EventRecord)>> {
+    let rows = sqlx::query!(
+        r#"
+        select id,
+               event_cat as "event_category: EventCategory",
+               event_type as "event_type: EventType",
+               address,
+               created_lt,
+               created_at,
+               message_hash as "message_hash!",
+               nft,
+               collection,
+               args as "raw_data!"
+        from nft_events
+        where ($1::varchar is null or collection = $1)
+          and ($2::bigint is null or id > $2)
+        order by id
+        limit $3
+        "#,
+        collection as _,
+        after_id,
+        limit
+    )
+    .fetch_all(pg_pool)
+    .await
+    .map_err(|e| anyhow!(e))?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| {
+            let event = EventRecord {
+                event_category: r.event_category,
+                event_type: r.event_type,
+                address: r.address,
+                created_lt: r.created_lt,
+                created_at: r.created_at,
+                message_hash: r.message_hash,
+                nft: r.nft,
+                collection: r.collection,
+                raw_data: r.raw_data,
+            };
+            (r.id, event)
+        })
+        .collect())
+}
+
+/// Event indexed from the message with this hash, with its decoded `args`. Message
+/// hashes are unique, `save_raw_event` skips duplicates
+pub async fn get_event_by_message_hash(
+    pg_pool: &PgPool,
+    message_hash: &str,
+) -> Result<Option<EventRecord>> {
+    sqlx::query_as!(
+        EventRecord,
+        r#"
+        select event_cat as "event_category: EventCategory",
+               event_type as "event_type: EventType",
+               address,
+               created_lt,
+               created_at,
+               message_hash as "message_hash!",
+               nft,
+               collection,
+               args as "raw_data!"
+        from nft_events
+        where message_hash = $1
+        "#,
+        message_hash
+    )
+    .fetch_optional(pg_pool)
+    .await
+    .map_err(|e| anyhow!(e))
+}
+
+/// Newest `limit` events the address took part in, across direct sells, direct buys,
+/// auctions and transfers. Matched against the `participants` of the event, taken from
+/// its args:
+/// - sellers: `value2.creator` of direct sells, `subject_owner` of the auction details
+///   in `value0`, `value2` or `value3`, `sender` of deployed direct sells
+/// - buyers and bidders: `buyer` of bids and completed auctions, `value2.creator` and
+///   `sender` of direct buys
+/// - owners: `owner` of minted and burned nfts, `old_owner` and `new_owner` of
+///   transfers and offer state changes
+pub async fn get_address_activity(
+    pg_pool: &PgPool,
+    address: &str,
+    limit: i64,
+) -> Result<Vec<EventRecord>> {
+    sqlx::query_as!(
+        EventRecord,
+        r#"
+        select event_cat as "event_category: EventCategory",
+               event_type as "event_type: EventType",
+               address,
+               created_lt,
+               created_at,
+               message_hash as "message_hash!",
+               nft,
+               collection,
+               args as "raw_data!"
+        from nft_events
+        where participants @> array[$1::text]
+        order by created_at desc, created_lt desc
+        limit $2
+        "#,
+        address,
+        limit
+    )
+    .fetch_all(pg_pool)
+    .await
+    .map_err(|e| anyhow!(e))
+}
+
+/// Serialized transaction stored with the event, if storing them was enabled at the time
+pub async fn get_raw_tx(pg_pool: &PgPool, message_hash: &str) -> Result<Option<Vec<u8>>> {
+    sqlx::query_scalar!(
+        r#"
+        select raw_tx
+        from nft_events
+        where message_hash = $1
+        "#,
+        message_hash
+    )
+    .fetch_optional(pg_pool)
+    .await
+    .map(Option::flatten)
+    .map_err(|e| anyhow!(e))
+}
+
+/// Daily event counts on days `from..=to`. Unlike the live metrics they survive restarts
+pub async fn get_event_stats(
+    pg_pool: &PgPool,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<EventStats>> {
+    sqlx::query_as!(
+        EventStats,
+        r#"
+        select date, event_type as "event_type: EventType", count
+        from event_stats_daily
+        where date between $1 and $2
+        order by date, event_type
+        "#,
+        from,
+        to
+    )
+    .fetch_all(pg_pool)
+    .await
+    .map_err(|e| anyhow!(e))
+}
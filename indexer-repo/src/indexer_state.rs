@@ -0,0 +1,84 @@
+use anyhow::{anyhow, Result};
+use chrono::NaiveDateTime;
+use sqlx::{PgPool, Postgres, Transaction};
+
+use crate::types::decoded::EventRecord;
+
+/// Latest saved event of a contract
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IndexerState {
+    pub address: String,
+    pub last_lt: i64,
+    pub last_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+pub async fn get_indexer_state(pg_pool: &PgPool, address: &str) -> Result<Option<IndexerState>> {
+    sqlx::query_as!(
+        IndexerState,
+        r#"
+        select address, last_lt, last_at, updated_at
+        from indexer_state
+        where address = $1
+        "#,
+        address as _
+    )
+    .fetch_optional(pg_pool)
+    .await
+    .map_err(|e| anyhow!(e))
+}
+
+/// Oldest latest event among the marketplace roots, the one that is most behind. Only
+/// the roots emit for as long as the marketplace runs: a finished sale, auction or offer,
+/// or a minted out collection, goes quiet for good and would read as an ever growing lag
+pub async fn get_oldest_root_last_at(pg_pool: &PgPool) -> Result<Option<NaiveDateTime>> {
+    sqlx::query_scalar!(
+        r#"
+        select min(s.last_at)
+        from indexer_state s
+        join roots r on r.event_whitelist_address = s.address
+        "#
+    )
+    .fetch_one(pg_pool)
+    .await
+    .map_err(|e| anyhow!(e))
+}
+
+/// Moves every emitting contract to its latest event, replayed older events leave it as is
+pub async fn save_indexer_state(
+    tx: &mut Transaction<'_, Postgres>,
+    events: &[EventRecord],
+) -> Result<()> {
+    let addresses = events
+        .iter()
+        .map(|e| e.address.as_str())
+        .collect::<Vec<_>>();
+    let created_lt = events.iter().map(|e| e.created_lt).collect::<Vec<_>>();
+    let created_at = events.iter().map(|e| e.created_at).collect::<Vec<_>>();
+
+    sqlx::query!(
+        r#"
+        insert into indexer_state (address, last_lt, last_at, updated_at)
+        select address, max(created_lt), to_timestamp(max(created_at)) at time zone 'utc', now()
+        from (
+            select
+                unnest($1::varchar[]) as address,
+                unnest($2::bigint[]) as created_lt,
+                unnest($3::bigint[]) as created_at
+        ) as events
+        group by address
+        on conflict (address) do update set
+            last_lt = excluded.last_lt,
+            last_at = excluded.last_at,
+            updated_at = excluded.updated_at
+        where indexer_state.last_lt < excluded.last_lt
+        "#,
+        addresses as _,
+        created_lt as _,
+        created_at as _,
+    )
+    .execute(tx)
+    .await
+    .map_err(|e| anyhow!(e))
+    .map(|_| ())
+}
@@ -1,6 +1,20 @@
+pub mod auction;
 pub mod batch;
+pub mod checkpoint;
 pub mod collection;
+pub mod db;
+pub mod direct_buy;
+pub mod direct_sell;
+pub mod error;
+pub mod events;
+pub mod indexer_state;
 pub mod meta;
+pub mod nft;
+pub mod parked;
 pub mod price;
+pub mod rarity;
+pub mod rollback;
+pub mod token_registry;
 pub mod types;
-pub mod utils;
+pub mod webhook;
+pub mod whitelist;
@@ -2,7 +2,7 @@ use anyhow::{anyhow, Result};
 use chrono::NaiveDateTime;
 use sqlx::{PgPool, Postgres, Transaction};
 
-use crate::types::NftCollectionMeta;
+use crate::types::{CollectionRoyalty, NftCollectionMeta};
 
 #[derive(Clone)]
 pub struct MetadataModelService {
@@ -106,7 +106,7 @@ impl MetadataModelService {
                               on nc.address = n.collection and nc.verified*/
                          left join meta_handled_addresses mha on mha.address = n.address
                 where (mha.address is null)
-                   or (mha.updated_at > extract(epoch from now()) - $2 and failed is true)
+                   or (mha.updated_at < extract(epoch from now()) - $2 and failed is true)
                 limit $1
             "#,
             items_per_page,
@@ -129,7 +129,7 @@ impl MetadataModelService {
                 left join meta_handled_addresses mha on mha.address = c.address
                 where
                     /*c.verified and*/
-                    ((mha.address is null) or (mha.updated_at > extract(epoch from now()) - $2 and failed is true))
+                    ((mha.address is null) or (mha.updated_at < extract(epoch from now()) - $2 and failed is true))
                 order by updated desc
                 limit $1
                 "#,
@@ -241,6 +241,34 @@ impl<'a> MetadataModelTransaction<'a> {
         .map_err(|e| anyhow!(e))
     }
 
+    /// Collection proven by the TIP-4.3 index of the nft, see `get_nft_collections`
+    pub async fn update_nft_collection_verified(
+        &mut self,
+        nft: &str,
+        collection: &str,
+        index_address: &str,
+        verified_at: NaiveDateTime,
+    ) -> Result<()> {
+        sqlx::query!(
+            r#"
+            insert into nft_collection_verified (nft, collection, index_address, verified_at)
+            values ($1, $2, $3, $4)
+            on conflict (nft) do update
+            set collection = excluded.collection,
+                index_address = excluded.index_address,
+                verified_at = excluded.verified_at
+            "#,
+            nft as _,
+            collection as _,
+            index_address as _,
+            verified_at
+        )
+        .execute(&mut self.tx)
+        .await
+        .map(|_| ())
+        .map_err(|e| anyhow!(e))
+    }
+
     pub async fn update_collection(&mut self, meta: &NftCollectionMeta) -> Result<()> {
         sqlx::query!(
             r#"
@@ -268,6 +296,29 @@ impl<'a> MetadataModelTransaction<'a> {
         .map_err(|e| anyhow!(e))
     }
 
+    pub async fn update_collection_royalty(&mut self, royalty: &CollectionRoyalty) -> Result<()> {
+        sqlx::query!(
+            r#"
+            insert into collection_royalty (collection, numerator, denominator, recipient, updated)
+            values ($1, $2, $3, $4, $5)
+            on conflict (collection) do update set
+                numerator   = excluded.numerator,
+                denominator = excluded.denominator,
+                recipient   = excluded.recipient,
+                updated     = excluded.updated
+            "#,
+            royalty.collection as _,
+            royalty.numerator,
+            royalty.denominator,
+            royalty.recipient as _,
+            royalty.updated,
+        )
+        .execute(&mut self.tx)
+        .await
+        .map(|_| ())
+        .map_err(|e| anyhow!(e))
+    }
+
     pub async fn add_to_proceeded(&mut self, addr: &str, failed: Option<bool>) -> Result<()> {
         let failed = failed.unwrap_or(false);
 
@@ -0,0 +1,263 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{anyhow, Result};
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::{types::BigDecimal, PgPool};
+
+#[derive(Clone, Debug, Serialize)]
+pub struct NftRecord {
+    pub address: String,
+    pub id: BigDecimal,
+    pub collection: String,
+    pub owner: String,
+    pub manager: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub burned: bool,
+    pub updated: NaiveDateTime,
+    pub owner_update_lt: i64,
+    pub manager_update_lt: i64,
+    /// Unset until the nft's `NftCreated` is indexed
+    pub minter: Option<String>,
+    pub created_lt: Option<i64>,
+    pub created_at: Option<NaiveDateTime>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct NftTransfer {
+    pub old_address: String,
+    pub new_address: String,
+    pub created_lt: i64,
+    pub created_at: NaiveDateTime,
+}
+
+/// Owners of the nft in order of change. Sorted by logical time, as several transfers
+/// can share a block timestamp
+pub async fn get_nft_owner_history(pg_pool: &PgPool, nft: &str) -> Result<Vec<NftTransfer>> {
+    sqlx::query_as!(
+        NftTransfer,
+        r#"
+        select old_address, new_address, created_lt, created_at
+        from nft_transfer_history
+        where nft = $1 and kind = 'owner'::nft_transfer_kind
+        order by created_lt
+        "#,
+        nft as _
+    )
+    .fetch_all(pg_pool)
+    .await
+    .map_err(|e| anyhow!(e))
+}
+
+/// The nft with its current owner and manager and who minted it
+pub async fn get_nft(pg_pool: &PgPool, address: &str) -> Result<Option<NftRecord>> {
+    sqlx::query_as!(
+        NftRecord,
+        r#"
+        select address,
+               id,
+               collection,
+               owner,
+               manager,
+               name,
+               description,
+               burned,
+               updated,
+               owner_update_lt,
+               manager_update_lt,
+               minter,
+               created_lt,
+               created_at
+        from nft
+        where address = $1
+        "#,
+        address as _
+    )
+    .fetch_optional(pg_pool)
+    .await
+    .map_err(|e| anyhow!(e))
+}
+
+/// Page of the collection's nfts in mint order
+pub async fn get_collection_nfts(
+    pg_pool: &PgPool,
+    collection: &str,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<NftRecord>> {
+    sqlx::query_as!(
+        NftRecord,
+        r#"
+        select address,
+               id,
+               collection,
+               owner,
+               manager,
+               name,
+               description,
+               burned,
+               updated,
+               owner_update_lt,
+               manager_update_lt,
+               minter,
+               created_lt,
+               created_at
+        from nft
+        where collection = $1
+        order by id, address
+        limit $2 offset $3
+        "#,
+        collection as _,
+        limit,
+        offset
+    )
+    .fetch_all(pg_pool)
+    .await
+    .map_err(|e| anyhow!(e))
+}
+
+/// Nfts of the collection having every `(trait_type, value)` pair, in address order.
+/// Attribute values are matched as text, so `"1"` matches both `1` and `"1"`
+pub async fn search_nfts_by_attributes(
+    pg_pool: &PgPool,
+    collection: &str,
+    filters: &[(String, String)],
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<String>> {
+    let filters = filters.iter().collect::<HashSet<_>>();
+    if filters.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let (trait_types, values): (Vec<_>, Vec<_>) = filters
+        .iter()
+        .map(|(trait_type, value)| (trait_type.as_str(), value.as_str()))
+        .unzip();
+
+    sqlx::query_scalar!(
+        r#"
+        select na.nft as "nft!"
+        from nft_attributes na
+                 join unnest($2::varchar[], $3::text[]) as f(trait_type, value)
+                      on na.trait_type = f.trait_type and na.value #>> '{}' = f.value
+        where na.collection = $1
+        group by na.nft
+        having count(distinct (f.trait_type, f.value)) = $4
+        order by na.nft
+        limit $5 offset $6
+        "#,
+        collection as _,
+        trait_types as _,
+        values as _,
+        filters.len() as i64,
+        limit,
+        offset
+    )
+    .fetch_all(pg_pool)
+    .await
+    .map_err(|e| anyhow!(e))
+}
+
+/// Nfts whose row or listings have no collection, in address order after `after`
+pub async fn get_nfts_missing_collection(
+    pg_pool: &PgPool,
+    after: Option<&str>,
+    limit: i64,
+) -> Result<Vec<String>> {
+    sqlx::query_scalar!(
+        r#"
+        select m.nft as "nft!"
+        from (select address as nft
+              from nft
+              where collection is null
+              union
+              select nft
+              from nft_direct_sell
+              where collection is null) m
+        where $1::varchar is null or m.nft > $1
+        order by m.nft
+        limit $2
+        "#,
+        after as _,
+        limit
+    )
+    .fetch_all(pg_pool)
+    .await
+    .map_err(|e| anyhow!(e))
+}
+
+/// Collections the database already knows for the nfts: the one proven by a TIP-4.3
+/// index, otherwise the one of the nft row. Nfts without any are left out
+pub async fn get_known_nft_collections(
+    pg_pool: &PgPool,
+    nfts: &[String],
+) -> Result<HashMap<String, String>> {
+    let rows = sqlx::query!(
+        r#"
+        select m.nft as "nft!", coalesce(v.collection, n.collection) as "collection!"
+        from unnest($1::varchar[]) as m(nft)
+                 left join nft_collection_verified v on v.nft = m.nft
+                 left join nft n on n.address = m.nft
+        where coalesce(v.collection, n.collection) is not null
+        "#,
+        nfts as _
+    )
+    .fetch_all(pg_pool)
+    .await
+    .map_err(|e| anyhow!(e))?;
+
+    Ok(rows.into_iter().map(|r| (r.nft, r.collection)).collect())
+}
+
+/// Rows given a collection by `fill_nft_collections`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FilledCollections {
+    pub nfts: u64,
+    pub listings: u64,
+}
+
+/// Sets the collection of the nfts and their listings where it is missing, a
+/// collection already set is kept
+pub async fn fill_nft_collections(
+    pg_pool: &PgPool,
+    collections: &HashMap<String, String>,
+) -> Result<FilledCollections> {
+    let (nfts, collections): (Vec<_>, Vec<_>) = collections
+        .iter()
+        .map(|(nft, collection)| (nft.as_str(), collection.as_str()))
+        .unzip();
+
+    let mut tx = pg_pool.begin().await?;
+    let nft_rows = sqlx::query!(
+        r#"
+        update nft
+        set collection = data.collection
+        from (select unnest($1::varchar[]) as nft, unnest($2::varchar[]) as collection) data
+        where nft.address = data.nft and nft.collection is null
+        "#,
+        nfts as _,
+        collections as _
+    )
+    .execute(&mut tx)
+    .await?;
+    let listing_rows = sqlx::query!(
+        r#"
+        update nft_direct_sell
+        set collection = data.collection
+        from (select unnest($1::varchar[]) as nft, unnest($2::varchar[]) as collection) data
+        where nft_direct_sell.nft = data.nft and nft_direct_sell.collection is null
+        "#,
+        nfts as _,
+        collections as _
+    )
+    .execute(&mut tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(FilledCollections {
+        nfts: nft_rows.rows_affected(),
+        listings: listing_rows.rows_affected(),
+    })
+}
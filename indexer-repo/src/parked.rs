@@ -0,0 +1,67 @@
+use anyhow::{anyhow, Result};
+use sqlx::PgPool;
+
+use crate::types::decoded::EventRecord;
+use crate::types::{EventCategory, EventType};
+
+/// Oldest parked events of `parser` with their ids, in the order they were parked
+pub async fn get_parked_events(
+    pg_pool: &PgPool,
+    parser: &str,
+    limit: i64,
+) -> Result<Vec<(i64, EventRecord)>> {
+    let rows = sqlx::query!(
+        r#"
+        select id,
+               event_cat as "event_category: EventCategory",
+               event_type as "event_type: EventType",
+               address,
+               nft,
+               collection,
+               created_lt,
+               created_at,
+               message_hash,
+               args
+        from parked_events
+        where parser = $1
+        order by id
+        limit $2
+        "#,
+        parser,
+        limit
+    )
+    .fetch_all(pg_pool)
+    .await
+    .map_err(|e| anyhow!(e))?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| {
+            let event = EventRecord {
+                event_category: r.event_category,
+                event_type: r.event_type,
+                address: r.address,
+                created_lt: r.created_lt,
+                created_at: r.created_at,
+                message_hash: r.message_hash,
+                nft: r.nft,
+                collection: r.collection,
+                raw_data: r.args,
+            };
+            (r.id, event)
+        })
+        .collect())
+}
+
+/// Parsers that have parked events
+pub async fn get_parked_parsers(pg_pool: &PgPool) -> Result<Vec<String>> {
+    sqlx::query_scalar!(
+        r#"
+        select distinct parser
+        from parked_events
+        "#
+    )
+    .fetch_all(pg_pool)
+    .await
+    .map_err(|e| anyhow!(e))
+}
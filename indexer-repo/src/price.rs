@@ -1,8 +1,10 @@
 use anyhow::{anyhow, Result};
 use chrono::{Local, NaiveDateTime};
+use serde::Serialize;
 use sqlx::{types::BigDecimal, PgPool};
 
-use crate::types::BcName;
+use crate::types::decoded::NftPriceHistory;
+use crate::types::{BcName, NftPriceSource};
 
 #[derive(Clone)]
 pub struct NftPriceModel {
@@ -16,6 +18,15 @@ pub struct RowWithoutUsdPrice {
     pub created_at: i64,
 }
 
+#[derive(Serialize)]
+pub struct Counterparty {
+    pub address: String,
+    /// Sales where the requested address was the seller
+    pub sold_to: i64,
+    /// Sales where the requested address was the buyer
+    pub bought_from: i64,
+}
+
 pub struct DexPoolInfo {
     pub address: String,
     pub is_l2r: bool,
@@ -91,6 +102,112 @@ impl NftPriceModel {
         .map(|_| ())
     }
 
+    pub async fn get_counterparty_stats(&self, address: &str) -> Result<Vec<Counterparty>> {
+        sqlx::query_as!(
+            Counterparty,
+            r#"
+                select
+                    counterparty as "address!",
+                    count(*) filter (where seller = $1) as "sold_to!",
+                    count(*) filter (where buyer = $1) as "bought_from!"
+                from (
+                    select
+                        buyer,
+                        seller,
+                        case when seller = $1 then buyer else seller end as counterparty
+                    from nft_price_history
+                    where (seller = $1 or buyer = $1)
+                      -- a seller buying back its own listing is not a counterparty
+                      and buyer <> seller
+                ) as sales
+                where counterparty is not null
+                group by counterparty
+                order by count(*) desc
+            "#,
+            address
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| anyhow!(e))
+    }
+
+    /// Page of the nft's price events, newest first
+    pub async fn get_nft_price_history(
+        &self,
+        nft: &str,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<NftPriceHistory>> {
+        sqlx::query_as!(
+            NftPriceHistory,
+            r#"
+                select
+                    source,
+                    source_type as "source_type: NftPriceSource",
+                    ts as "created_at!",
+                    price,
+                    price_token,
+                    price_token_symbol,
+                    usd_price,
+                    marketplace_fee,
+                    royalty,
+                    nft as "nft!",
+                    collection as "collection!",
+                    buyer,
+                    seller
+                from nft_price_history
+                where nft = $1
+                order by ts desc
+                limit $2 offset $3
+            "#,
+            nft,
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| anyhow!(e))
+    }
+
+    /// Same page of the price events of every nft, newest first, in one query
+    pub async fn get_nfts_price_history(
+        &self,
+        nfts: &[String],
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<NftPriceHistory>> {
+        sqlx::query_as!(
+            NftPriceHistory,
+            r#"
+                select
+                    source as "source!",
+                    source_type as "source_type!: NftPriceSource",
+                    ts as "created_at!",
+                    price as "price!",
+                    price_token as "price_token!",
+                    price_token_symbol,
+                    usd_price,
+                    marketplace_fee,
+                    royalty,
+                    nft as "nft!",
+                    collection as "collection!",
+                    buyer,
+                    seller
+                from (select h.*, row_number() over (partition by h.nft order by h.ts desc) as n
+                      from nft_price_history h
+                      where h.nft = any($1::varchar[])) h
+                where n > $3 and n <= $2 + $3
+                order by nft, ts desc
+            "#,
+            nfts as _,
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| anyhow!(e))
+    }
+
     pub async fn get_dex_pair_address(&self, token_addr: &str, bc: BcName) -> Result<DexPoolInfo> {
         match bc {
             BcName::Everscale => self.get_pair_address(token_addr, BcName::Everscale).await,
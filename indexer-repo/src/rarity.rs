@@ -0,0 +1,99 @@
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use sqlx::PgPool;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct NftRarity {
+    pub nft: String,
+    pub score: f64,
+    pub rank: i64,
+    pub provisional: bool,
+}
+
+/// Scores every live nft of the collection with the sum of the inverse frequencies
+/// of its traits and ranks them, rarest first. Ranks are provisional if the
+/// collection minted after `minting_since` (unix time) or some of its nfts have
+/// no attributes yet
+pub async fn recompute_collection_rarity(
+    pg_pool: &PgPool,
+    collection: &str,
+    minting_since: i64,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+        with live as (
+            select address
+            from nft
+            where collection = $1 and not burned
+        ),
+        traits as (
+            select distinct na.nft, na.trait_type, na.value #>> '{}' as value
+            from nft_attributes na
+                     join live on live.address = na.nft
+            where na.collection = $1
+        ),
+        frequencies as (
+            select trait_type, value, count(distinct nft) as nfts
+            from traits
+            group by trait_type, value
+        ),
+        scores as (
+            select t.nft, sum((select count(*) from live)::double precision / f.nfts) as score
+            from traits t
+                     join frequencies f
+                          on f.trait_type = t.trait_type and f.value is not distinct from t.value
+            group by t.nft
+        ),
+        state as (
+            select exists(select 1 from live where address not in (select nft from traits))
+                       or exists(select 1
+                                 from nft_events
+                                 where event_type = 'nft_created'
+                                   and collection = $1
+                                   and created_at > $2) as provisional
+        ),
+        stale as (
+            delete from nft_rarity
+            where collection = $1 and nft not in (select nft from scores)
+        )
+        insert into nft_rarity (nft, collection, score, rank, provisional, updated)
+        select s.nft, $1, s.score, rank() over (order by s.score desc), state.provisional, now()
+        from scores s, state
+        on conflict (nft) do update
+            set collection  = excluded.collection,
+                score       = excluded.score,
+                rank        = excluded.rank,
+                provisional = excluded.provisional,
+                updated     = excluded.updated
+        "#,
+        collection as _,
+        minting_since
+    )
+    .execute(pg_pool)
+    .await
+    .map(|_| ())
+    .map_err(|e| anyhow!(e))
+}
+
+/// The rarest `limit` nfts of the collection
+pub async fn get_collection_rarity_ranking(
+    pg_pool: &PgPool,
+    collection: &str,
+    limit: i64,
+) -> Result<Vec<NftRarity>> {
+    sqlx::query_as!(
+        NftRarity,
+        r#"
+        select nft, score, rank, provisional
+        from nft_rarity
+        where collection = $1
+        order by rank, nft
+        limit $2
+        "#,
+        collection as _,
+        limit
+    )
+    .fetch_all(pg_pool)
+    .await
+    .map_err(|e| anyhow!(e))
+}
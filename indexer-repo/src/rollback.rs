@@ -0,0 +1,308 @@
+use sqlx::{Executor, PgPool, Postgres};
+
+use crate::error::{IndexerError, Result};
+
+/// Timestamp of the oldest event indexed above `lt`, the stream is re-read from there
+pub async fn get_oldest_timestamp_above_lt(pg_pool: &PgPool, lt: i64) -> Result<Option<i64>> {
+    sqlx::query_scalar!(
+        r#"
+        select min(created_at) from nft_events where created_lt > $1
+        "#,
+        lt
+    )
+    .fetch_one(pg_pool)
+    .await
+    .map_err(IndexerError::db)
+}
+
+/// Rows of listings and nfts only take state updates over older logical times, those
+/// updated above `lt` get their logical times moved back to `lt`, so that events above
+/// `lt` applied again overwrite them in order
+pub async fn rewind_lt_guards<'e>(
+    executor: impl Executor<'e, Database = Postgres>,
+    lt: i64,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+        with direct_sells as (
+            update nft_direct_sell set tx_lt = $1 where tx_lt > $1
+        ),
+        direct_buys as (
+            update nft_direct_buy set tx_lt = $1 where tx_lt > $1
+        ),
+        auctions as (
+            update nft_auction set tx_lt = $1 where tx_lt > $1
+        ),
+        update nft set
+            owner_update_lt = least(owner_update_lt, $1),
+            manager_update_lt = least(manager_update_lt, $1)
+        where owner_update_lt > $1 or manager_update_lt > $1
+        "#,
+        lt
+    )
+    .execute(executor)
+    .await
+    .map_err(IndexerError::db)
+    .map(|_| ())
+}
+
+/// Removes everything indexed from transactions with logical time above `lt`, so the
+/// stream can be replayed from there after a reorg. Offers deployed above `lt` are
+/// dropped with their prices; older offers keep their rows and are overwritten by the
+/// replayed state changes. Sales above `lt` are taken back out of the daily volume and
+/// the royalties earned, and nfts get the owner and manager they had at `lt`. Nfts
+/// minted above `lt` keep their row, the replayed mint upserts it
+pub async fn rollback_to_lt(pg_pool: &PgPool, lt: i64) -> Result<()> {
+    let mut tx = pg_pool.begin().await?;
+
+    // Volumes counted the way `save_price_history` accrued them, royalties by what each
+    // sale accrued
+    sqlx::query!(
+        r#"
+        with offers as (
+            select args ->> 'direct_sell' as address
+            from nft_events
+            where event_type = 'direct_sell_deployed' and created_lt > $1
+            union
+            select args ->> 'direct_buy'
+            from nft_events
+            where event_type = 'direct_buy_deployed' and created_lt > $1
+            union
+            select args ->> 'offer'
+            from nft_events
+            where event_type = 'auction_deployed' and created_lt > $1
+        ),
+        removed as (
+            delete from nft_price_history p
+            where p.source in (select address from offers)
+               or exists (
+                   select 1
+                   from nft_events e
+                   where e.created_lt > $1
+                     and e.event_type in (
+                         'auction_complete',
+                         'direct_sell_state_changed',
+                         'direct_buy_state_changed'
+                     )
+                     and e.address = p.source
+                     and to_timestamp(e.created_at) at time zone 'utc' = p.ts
+               )
+            returning p.source, p.source_type, p.ts, p.price, p.price_token, p.usd_price, p.collection, p.royalty
+        ),
+        sales as (
+            select removed.*
+            from removed
+                     join offers_whitelist ow on ow.address = removed.source
+        ),
+        volumes as (
+            update collection_volume_daily set
+                volume_usd = collection_volume_daily.volume_usd - removed.volume_usd,
+                sales = collection_volume_daily.sales - removed.sales
+            from (
+                select collection, ts::date as day, coalesce(sum(usd_price), 0) as volume_usd, count(1) as sales
+                from sales
+                group by 1, 2
+            ) as removed
+            where collection_volume_daily.collection = removed.collection
+              and collection_volume_daily.day = removed.day
+        )
+        update collection_royalty_earned set
+            amount = collection_royalty_earned.amount - removed.amount
+        from (
+            select collection, price_token as token, sum(royalty) as amount
+            from sales
+            where royalty is not null
+            group by 1, 2
+        ) as removed
+        where collection_royalty_earned.collection = removed.collection
+          and collection_royalty_earned.token = removed.token
+        "#,
+        lt
+    )
+    .execute(&mut tx)
+    .await
+    .map_err(IndexerError::db)?;
+
+    sqlx::query!(
+        r#"
+        with offers as (
+            select args ->> 'direct_sell' as address
+            from nft_events
+            where event_type = 'direct_sell_deployed' and created_lt > $1
+            union
+            select args ->> 'direct_buy'
+            from nft_events
+            where event_type = 'direct_buy_deployed' and created_lt > $1
+            union
+            select args ->> 'offer'
+            from nft_events
+            where event_type = 'auction_deployed' and created_lt > $1
+        ),
+        direct_sells as (
+            delete from nft_direct_sell where address in (select address from offers)
+        ),
+        direct_buys as (
+            delete from nft_direct_buy where address in (select address from offers)
+        ),
+        auctions as (
+            delete from nft_auction where address in (select address from offers)
+        )
+        delete from deployed_offers where address in (select address from offers)
+        "#,
+        lt
+    )
+    .execute(&mut tx)
+    .await
+    .map_err(IndexerError::db)?;
+
+    // The first transfer above `lt` tells who held the nft before it. Owner and manager
+    // are separate statements, a statement can't update the same row twice
+    sqlx::query!(
+        r#"
+        update nft set owner = restored.old_address
+        from (
+            select distinct on (nft) nft, old_address
+            from nft_transfer_history
+            where kind = 'owner' and created_lt > $1
+            order by nft, created_lt
+        ) as restored
+        where nft.address = restored.nft
+        "#,
+        lt
+    )
+    .execute(&mut tx)
+    .await
+    .map_err(IndexerError::db)?;
+
+    sqlx::query!(
+        r#"
+        update nft set manager = restored.old_address
+        from (
+            select distinct on (nft) nft, old_address
+            from nft_transfer_history
+            where kind = 'manager' and created_lt > $1
+            order by nft, created_lt
+        ) as restored
+        where nft.address = restored.nft
+        "#,
+        lt
+    )
+    .execute(&mut tx)
+    .await
+    .map_err(IndexerError::db)?;
+
+    sqlx::query!(
+        r#"
+        update nft set burned = false
+        where address in (
+            select nft from nft_events where event_type = 'nft_burned' and created_lt > $1
+        )
+        "#,
+        lt
+    )
+    .execute(&mut tx)
+    .await
+    .map_err(IndexerError::db)?;
+
+    // The kept rows are moved back to `lt` for the replayed state changes to overwrite them
+    rewind_lt_guards(&mut tx, lt).await?;
+
+    sqlx::query!(
+        r#"
+        delete from nft_auction_bid where tx_lt > $1
+        "#,
+        lt
+    )
+    .execute(&mut tx)
+    .await
+    .map_err(IndexerError::db)?;
+
+    sqlx::query!(
+        r#"
+        delete from marketplace_fee_history where changed_lt > $1
+        "#,
+        lt
+    )
+    .execute(&mut tx)
+    .await
+    .map_err(IndexerError::db)?;
+
+    sqlx::query!(
+        r#"
+        delete from failed_events where tx_lt > $1
+        "#,
+        lt
+    )
+    .execute(&mut tx)
+    .await
+    .map_err(IndexerError::db)?;
+
+    sqlx::query!(
+        r#"
+        delete from nft_transfer_history where created_lt > $1
+        "#,
+        lt
+    )
+    .execute(&mut tx)
+    .await
+    .map_err(IndexerError::db)?;
+
+    // Replayed events are counted again when they are stored
+    sqlx::query!(
+        r#"
+        with removed as (
+            delete from nft_events where created_lt > $1
+            returning event_type, created_at
+        )
+        update event_stats_daily
+        set count = event_stats_daily.count - counts.count
+        from (
+            select (to_timestamp(created_at) at time zone 'utc')::date as date,
+                   event_type,
+                   count(1) as count
+            from removed
+            group by 1, 2
+        ) as counts
+        where event_stats_daily.date = counts.date
+          and event_stats_daily.event_type = counts.event_type
+        "#,
+        lt
+    )
+    .execute(&mut tx)
+    .await
+    .map_err(IndexerError::db)?;
+
+    // Contracts behind `lt` are moved forward again by the replayed events
+    sqlx::query!(
+        r#"
+        delete from indexer_state where last_lt > $1
+        "#,
+        lt
+    )
+    .execute(&mut tx)
+    .await
+    .map_err(IndexerError::db)?;
+
+    sqlx::query!(
+        r#"
+        delete from parked_events where created_lt > $1
+        "#,
+        lt
+    )
+    .execute(&mut tx)
+    .await
+    .map_err(IndexerError::db)?;
+
+    // Lts of different accounts don't order the checkpoint against `lt`, it is dropped
+    // so no redelivered transaction is skipped as already committed
+    sqlx::query!(
+        r#"
+        delete from indexer_checkpoint
+        "#
+    )
+    .execute(&mut tx)
+    .await
+    .map_err(IndexerError::db)?;
+
+    tx.commit().await.map_err(IndexerError::db)
+}
@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use bigdecimal::{num_bigint::BigInt, BigDecimal};
+use sqlx::{PgPool, Postgres, Transaction};
+
+pub struct Token {
+    pub address: String,
+    pub symbol: String,
+    pub decimals: i32,
+}
+
+pub async fn get_tokens(
+    tx: &mut Transaction<'_, Postgres>,
+    addresses: &[&str],
+) -> Result<HashMap<String, Token>> {
+    let tokens = sqlx::query_as!(
+        Token,
+        r#"
+        select address as "address!", symbol, decimals
+        from token_registry
+        where address = any($1::varchar[])
+        "#,
+        addresses as _,
+    )
+    .fetch_all(tx)
+    .await
+    .map_err(|e| anyhow!(e))?;
+
+    Ok(tokens.into_iter().map(|t| (t.address.clone(), t)).collect())
+}
+
+/// Scales a raw on-chain amount by the token's decimals. Unknown tokens keep
+/// the raw amount so nothing is lost, the caller reports them.
+pub fn normalize(raw: &BigDecimal, decimals: Option<i32>) -> BigDecimal {
+    match decimals {
+        Some(decimals) => raw / BigDecimal::new(BigInt::from(1), -(decimals as i64)),
+        None => raw.clone(),
+    }
+}
+
+pub async fn normalize_amount(raw: u128, token: &str, pool: &PgPool) -> Result<BigDecimal> {
+    let decimals = sqlx::query_scalar!(
+        r#"
+        select decimals
+        from token_registry
+        where address = $1
+        "#,
+        token
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| anyhow!(e))?;
+    if decimals.is_none() {
+        log::warn!("Token {token} is not in the token registry, keeping the raw amount");
+    }
+
+    Ok(normalize(&BigDecimal::new(BigInt::from(raw), 0), decimals))
+}
+
+/// Queues tokens missing from the registry for the meta reader, already queued ones
+/// are kept
+pub async fn enqueue_tokens(
+    tx: &mut Transaction<'_, Postgres>,
+    addresses: &[String],
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+        insert into token_registry_queue (address)
+        select unnest($1::varchar[])
+        on conflict (address) do nothing
+        "#,
+        addresses as _,
+    )
+    .execute(tx)
+    .await
+    .map_err(|e| anyhow!(e))
+    .map(|_| ())
+}
+
+pub async fn get_queued_tokens(pool: &PgPool, limit: i64) -> Result<Vec<String>> {
+    sqlx::query_scalar!(
+        r#"
+        select address as "address!"
+        from token_registry_queue
+        order by enqueued_at
+        limit $1
+        "#,
+        limit
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| anyhow!(e))
+}
+
+/// Registers the token and replaces the address stored as the symbol of its
+/// listings and prices saved while it was unknown
+pub async fn save_token(pool: &PgPool, token: &Token) -> Result<()> {
+    sqlx::query!(
+        r#"
+        with registered as (
+            insert into token_registry (address, symbol, decimals)
+            values ($1, $2, $3)
+            on conflict (address) do update
+                set symbol = excluded.symbol, decimals = excluded.decimals
+        ),
+        direct_sells as (
+            update nft_direct_sell set price_token_symbol = $2
+            where price_token = $1 and price_token_symbol = $1
+        ),
+        prices as (
+            update nft_price_history set price_token_symbol = $2
+            where price_token = $1 and price_token_symbol = $1
+        )
+        delete from token_registry_queue where address = $1
+        "#,
+        token.address,
+        token.symbol,
+        token.decimals
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| anyhow!(e))
+    .map(|_| ())
+}
+
+pub async fn dequeue_token(pool: &PgPool, address: &str) -> Result<()> {
+    sqlx::query!(
+        r#"
+        delete from token_registry_queue where address = $1
+        "#,
+        address
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| anyhow!(e))
+    .map(|_| ())
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use bigdecimal::BigDecimal;
+
+    use super::normalize;
+
+    #[test]
+    fn test_known_token_is_scaled_by_its_decimals() {
+        let raw = BigDecimal::from(1_500_000_000u64);
+
+        assert_eq!(
+            normalize(&raw, Some(9)),
+            BigDecimal::from_str("1.5").unwrap()
+        );
+        assert_eq!(normalize(&raw, Some(0)), raw);
+    }
+
+    #[test]
+    fn test_unknown_token_keeps_the_raw_amount() {
+        let raw = BigDecimal::from(1_500_000_000u64);
+
+        assert_eq!(normalize(&raw, None), raw);
+    }
+}
@@ -1,8 +1,13 @@
-use chrono::NaiveDateTime;
+use chrono::{NaiveDate, NaiveDateTime};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use sqlx::types::BigDecimal;
 
-#[derive(Copy, Clone, Debug, Serialize, sqlx::Type)]
+/// Parsed by the `/events?type=` filter by its database name, e.g. `direct_sell_deployed`.
+/// Serialized responses keep the variant name
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, JsonSchema, sqlx::Type)]
 #[sqlx(type_name = "event_type", rename_all = "snake_case")]
+#[serde(rename_all(deserialize = "snake_case"))]
 pub enum EventType {
     AuctionDeployed,
     AuctionCreated,
@@ -41,7 +46,7 @@ impl sqlx::postgres::PgHasArrayType for EventType {
     }
 }
 
-#[derive(Copy, Clone, Debug, Serialize, sqlx::Type)]
+#[derive(Copy, Clone, Debug, Serialize, JsonSchema, sqlx::Type)]
 #[sqlx(type_name = "event_category", rename_all = "snake_case")]
 pub enum EventCategory {
     Auction,
@@ -58,7 +63,7 @@ impl sqlx::postgres::PgHasArrayType for EventCategory {
     }
 }
 
-#[derive(Clone, Debug, Serialize, sqlx::Type)]
+#[derive(Clone, Debug, Serialize, JsonSchema, sqlx::Type)]
 #[sqlx(type_name = "auction_status", rename_all = "snake_case")]
 pub enum AuctionStatus {
     Created = 0,
@@ -68,7 +73,7 @@ pub enum AuctionStatus {
     Expired,
 }
 
-#[derive(Clone, Debug, Serialize, PartialEq, Eq, sqlx::Type)]
+#[derive(Clone, Debug, Serialize, JsonSchema, PartialEq, Eq, sqlx::Type)]
 #[sqlx(type_name = "direct_sell_state", rename_all = "snake_case")]
 pub enum DirectSellState {
     Create = 0,
@@ -85,7 +90,7 @@ impl sqlx::postgres::PgHasArrayType for DirectSellState {
     }
 }
 
-#[derive(Clone, Debug, Serialize, PartialEq, Eq, sqlx::Type)]
+#[derive(Clone, Debug, Serialize, JsonSchema, PartialEq, Eq, sqlx::Type)]
 #[sqlx(type_name = "direct_buy_state", rename_all = "snake_case")]
 pub enum DirectBuyState {
     Create = 0,
@@ -102,7 +107,14 @@ impl sqlx::postgres::PgHasArrayType for DirectBuyState {
     }
 }
 
-#[derive(Copy, Clone, Debug, Serialize, PartialEq, Eq, sqlx::Type)]
+#[derive(Copy, Clone, Debug, Serialize, JsonSchema, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "nft_transfer_kind", rename_all = "snake_case")]
+pub enum NftTransferKind {
+    Owner,
+    Manager,
+}
+
+#[derive(Copy, Clone, Debug, Serialize, JsonSchema, PartialEq, Eq, Hash, sqlx::Type)]
 #[sqlx(type_name = "nft_price_source", rename_all = "camelCase")]
 pub enum NftPriceSource {
     AuctionBid = 0,
@@ -156,7 +168,7 @@ impl From<u8> for AuctionStatus {
     }
 }
 
-#[derive(Debug, sqlx::Type, Deserialize, Clone, Copy)]
+#[derive(Debug, sqlx::Type, Deserialize, Clone, Copy, PartialEq, Eq)]
 #[sqlx(type_name = "bc_name", rename_all = "snake_case")]
 pub enum BcName {
     Everscale,
@@ -179,24 +191,60 @@ pub struct NftCollectionMeta {
     pub wallpaper: Option<String>,
 }
 
+/// Royalty of a collection, all `None` if it doesn't implement the royalty getter
+#[derive(Clone, Debug, Serialize)]
+pub struct CollectionRoyalty {
+    pub collection: String,
+    pub numerator: Option<i32>,
+    pub denominator: Option<i32>,
+    pub recipient: Option<String>,
+    pub updated: NaiveDateTime,
+}
+
+/// Royalty accrued by a collection's sales in one payment token
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct CollectionRoyaltyEarned {
+    pub collection: String,
+    pub token: String,
+    pub amount: BigDecimal,
+}
+
+/// Events of one type indexed on one day (UTC), from `event_stats_daily`
+#[derive(Clone, Debug, Serialize)]
+pub struct EventStats {
+    pub date: NaiveDate,
+    pub event_type: EventType,
+    pub count: i64,
+}
+
 pub mod decoded {
     use crate::types::{DirectBuyState, DirectSellState, EventCategory, EventType, NftPriceSource};
     use chrono::NaiveDateTime;
+    use schemars::JsonSchema;
+    use serde::Serialize;
     use sqlx::types::BigDecimal;
 
-    #[derive(Clone, Debug)]
+    #[derive(Clone, Debug, Serialize, JsonSchema)]
     pub struct NftPriceHistory {
         pub source: String,
         pub source_type: NftPriceSource,
         pub created_at: NaiveDateTime,
         pub price: BigDecimal,
         pub price_token: String,
+        /// Symbol of `price_token` from the token registry, filled in before saving
+        pub price_token_symbol: Option<String>,
         pub usd_price: Option<BigDecimal>,
+        pub marketplace_fee: Option<BigDecimal>,
+        /// Royalty accrued into `collection_royalty_earned` for the sale, if any, so a
+        /// rollback takes back exactly that
+        pub royalty: Option<BigDecimal>,
         pub nft: String,
         pub collection: String,
+        pub buyer: Option<String>,
+        pub seller: Option<String>,
     }
 
-    #[derive(Clone, Debug)]
+    #[derive(Clone, Debug, Serialize, JsonSchema)]
     pub struct EventRecord {
         pub event_category: EventCategory,
         pub event_type: EventType,
@@ -211,30 +259,40 @@ pub mod decoded {
         pub raw_data: serde_json::Value,
     }
 
+    #[derive(Clone)]
     pub struct NftCreated {
         pub id: BigDecimal,
         pub address: String,
         pub collection: String,
         pub owner: String,
         pub manager: String,
+        pub minter: String,
         pub updated: NaiveDateTime,
         pub owner_update_lt: u64,
         pub manager_update_lt: u64,
+        pub created_lt: i64,
+        pub created_at: NaiveDateTime,
     }
 
+    #[derive(Clone)]
     pub struct NftBurned {
         pub address: String,
         pub owner: String,
         pub manager: String,
+        pub burned_at: NaiveDateTime,
+        pub tx_lt: i64,
     }
 
+    #[derive(Clone)]
     pub struct AddressChanged {
         pub id_address: String,
+        pub old_address: String,
         pub new_address: String,
         pub logical_time: u64,
         pub timestamp: NaiveDateTime,
     }
 
+    #[derive(Clone)]
     pub struct AuctionDeployed {
         pub address: String,
         pub root: String,
@@ -244,6 +302,7 @@ pub mod decoded {
         pub tx_lt: i64,
     }
 
+    #[derive(Clone, Serialize, JsonSchema)]
     pub struct AuctionActive {
         pub address: String,
         pub nft: String,
@@ -256,6 +315,7 @@ pub mod decoded {
         pub tx_lt: i64,
     }
 
+    #[derive(Clone, Serialize, JsonSchema)]
     pub struct AuctionBid {
         pub address: String,
         pub collection: String,
@@ -263,6 +323,8 @@ pub mod decoded {
         pub nft_owner: String,
         pub price_token: String,
         pub bid_value: BigDecimal,
+        /// `bid_value` scaled by the token decimals, filled in before saving
+        pub bid_value_normalized: Option<BigDecimal>,
         pub next_value: BigDecimal,
         pub buyer: String,
         pub created_at: NaiveDateTime,
@@ -270,15 +332,22 @@ pub mod decoded {
         pub declined: bool,
     }
 
+    #[derive(Clone)]
     pub struct AuctionComplete {
         pub address: String,
         pub max_bid: BigDecimal,
+        pub winner: String,
+        pub tx_lt: i64,
     }
 
+    #[derive(Clone)]
     pub struct AuctionCancelled {
         pub address: String,
+        pub finished_at: NaiveDateTime,
+        pub tx_lt: i64,
     }
 
+    #[derive(Clone)]
     pub struct CollectionFee {
         pub address: String,
         pub timestamp: NaiveDateTime,
@@ -286,6 +355,36 @@ pub mod decoded {
         pub denominator: Option<i32>,
     }
 
+    /// Extracted event that could not be unpacked or decoded, kept for replay
+    #[derive(Clone, Debug)]
+    pub struct FailedEvent {
+        pub address: String,
+        pub event_name: String,
+        pub message_hash: String,
+        pub tx_lt: i64,
+        /// Debug representation of the extracted ABI tokens
+        pub tokens: String,
+        pub error: String,
+    }
+
+    /// Serialized transaction of a raw event, so stored events can be decoded again
+    #[derive(Clone, Debug)]
+    pub struct RawEventTransaction {
+        pub message_hash: String,
+        pub boc: Vec<u8>,
+    }
+
+    /// Marketplace fee set on a factory (default for new offers) or on a single offer
+    #[derive(Clone, Debug)]
+    pub struct MarketplaceFee {
+        pub address: String,
+        pub numerator: i32,
+        pub denominator: i32,
+        pub changed_at: NaiveDateTime,
+        pub changed_lt: i64,
+    }
+
+    #[derive(Clone, Serialize, JsonSchema)]
     pub struct DirectBuy {
         pub address: String,
         pub root: String,
@@ -302,22 +401,31 @@ pub mod decoded {
         pub tx_lt: i64,
     }
 
+    #[derive(Clone, Serialize, JsonSchema)]
     pub struct DirectSell {
         pub address: String,
         pub root: String,
         pub nft: String,
         pub collection: Option<String>,
         pub price_token: String,
+        /// Symbol of `price_token` from the token registry, filled in before saving
+        pub price_token_symbol: Option<String>,
         pub price: BigDecimal,
+        /// `price` scaled by the token decimals, filled in before saving
+        pub price_normalized: Option<BigDecimal>,
         pub seller: String,
         pub finished_at: Option<NaiveDateTime>,
+        /// `to_timestamp(0)` for a listing without an end
         pub expired_at: NaiveDateTime,
         pub state: DirectSellState,
-        pub created: NaiveDateTime,
+        /// Start of the listing, missing until it is activated or if the contract
+        /// reports no start
+        pub created: Option<NaiveDateTime>,
         pub updated: NaiveDateTime,
         pub tx_lt: i64,
     }
 
+    #[derive(Clone)]
     pub struct OfferDeployed {
         pub address: String,
         pub root: String,
@@ -0,0 +1,24 @@
+use anyhow::{anyhow, Result};
+use sqlx::PgPool;
+
+/// Keeps a notification that exhausted its retries so it can be redelivered manually
+pub async fn save_dead_letter(
+    pg_pool: &PgPool,
+    url: &str,
+    payload: &serde_json::Value,
+    error: &str,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+        insert into webhook_dead_letters (url, payload, error)
+        values ($1, $2, $3)
+        "#,
+        url,
+        payload,
+        error
+    )
+    .execute(pg_pool)
+    .await
+    .map_err(|e| anyhow!(e))
+    .map(|_| ())
+}
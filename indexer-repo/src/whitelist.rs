@@ -0,0 +1,34 @@
+use std::collections::HashSet;
+
+use anyhow::{anyhow, Result};
+use sqlx::PgPool;
+
+/// Marketplace factories that still deploy whitelisted offers
+pub async fn get_active_roots(pool: &PgPool) -> Result<HashSet<String>> {
+    sqlx::query_scalar!(
+        r#"
+            select address
+            from roots
+            where expiry_date is null or expiry_date >= now()::timestamp
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .map(|addresses| addresses.into_iter().collect())
+    .map_err(|e| anyhow!(e))
+}
+
+pub async fn filter_whitelisted_offers(pool: &PgPool, offers: &[&str]) -> Result<HashSet<String>> {
+    sqlx::query_scalar!(
+        r#"
+            select address as "address!"
+            from offers_whitelist
+            where address = any($1::varchar[])
+        "#,
+        offers as _
+    )
+    .fetch_all(pool)
+    .await
+    .map(|addresses| addresses.into_iter().collect())
+    .map_err(|e| anyhow!(e))
+}
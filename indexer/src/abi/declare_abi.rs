@@ -1,13 +1,35 @@
+use anyhow::{bail, Result};
 use once_cell::sync::OnceCell;
 use ton_abi::Contract;
 
 macro_rules! declare_abi {
-    ($($contract:ident => $source:literal),*$(,)?) => {$(
-        pub fn $contract() -> &'static Contract {
-            static ABI: OnceCell<Contract> = OnceCell::new();
-            ABI.load(include_str!($source))
+    ($($contract:ident => $source:literal),*$(,)?) => {
+        $(
+            pub fn $contract() -> &'static Contract {
+                static ABI: OnceCell<Contract> = OnceCell::new();
+                ABI.load(stringify!($contract), $source, include_str!($source))
+            }
+        )*
+
+        /// Parses every ABI before the stream opens and reports all broken ones at once,
+        /// instead of panicking on the first getter call
+        pub fn check_abis() -> Result<()> {
+            let errors = [$((stringify!($contract), $source, include_str!($source))),*]
+                .into_iter()
+                .filter_map(|(contract, source, data)| {
+                    Contract::load(data)
+                        .err()
+                        .map(|e| format!("{contract} ({source}): {e}"))
+                })
+                .collect::<Vec<_>>();
+
+            if !errors.is_empty() {
+                bail!("Invalid ABI files:\n{}", errors.join("\n"));
+            }
+
+            Ok(())
         }
-    )*};
+    };
 }
 
 declare_abi! {
@@ -24,11 +46,24 @@ declare_abi! {
 }
 
 trait OnceCellExt {
-    fn load(&self, data: &str) -> &Contract;
+    fn load(&self, contract: &str, source: &str, data: &str) -> &Contract;
 }
 
 impl OnceCellExt for OnceCell<Contract> {
-    fn load(&self, data: &str) -> &Contract {
-        self.get_or_init(|| Contract::load(data).expect("Trust me"))
+    fn load(&self, contract: &str, source: &str, data: &str) -> &Contract {
+        self.get_or_init(|| {
+            Contract::load(data)
+                .unwrap_or_else(|e| panic!("Invalid {contract} ABI ({source}): {e}"))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::check_abis;
+
+    #[test]
+    fn test_bundled_abis_parse() {
+        check_abis().unwrap();
     }
 }
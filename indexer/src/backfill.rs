@@ -0,0 +1,124 @@
+use std::time::{Duration, Instant};
+
+use crate::resume::StreamedTx;
+
+const PROGRESS_LOG_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Transactions with timestamps in `from_ts..to_ts` re-indexed by a backfill run
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BackfillRange {
+    pub from_ts: i64,
+    pub to_ts: i64,
+}
+
+impl BackfillRange {
+    pub fn contains(&self, tx_timestamp: i64) -> bool {
+        (self.from_ts..self.to_ts).contains(&tx_timestamp)
+    }
+
+    /// The stream is ordered by time, so nothing after `to_ts` has to be consumed
+    pub fn is_reached_by(&self, tx_timestamp: i64) -> bool {
+        tx_timestamp >= self.to_ts
+    }
+}
+
+/// Logical times `from_lt..=to_lt` persisted by an audit run, either end is open if
+/// unset. Transactions outside are consumed and committed without being stored
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LtWindow {
+    pub from_lt: Option<i64>,
+    pub to_lt: Option<i64>,
+}
+
+impl LtWindow {
+    pub fn contains(&self, tx_lt: i64) -> bool {
+        self.from_lt.map_or(true, |from_lt| tx_lt >= from_lt)
+            && self.to_lt.map_or(true, |to_lt| tx_lt <= to_lt)
+    }
+
+    /// Drops the transactions of `message` outside the window, returns how many
+    pub fn retain<T: StreamedTx>(&self, message: &mut Vec<T>) -> usize {
+        let before = message.len();
+        message.retain(|tx| self.contains(tx.lt()));
+        before - message.len()
+    }
+}
+
+/// Periodically logs how far a backfill run got
+pub struct BackfillProgress {
+    range: BackfillRange,
+    transactions: u64,
+    last_log: Instant,
+}
+
+impl BackfillProgress {
+    pub fn new(range: BackfillRange) -> Self {
+        Self {
+            range,
+            transactions: 0,
+            last_log: Instant::now(),
+        }
+    }
+
+    pub fn record(&mut self, transactions: usize, newest_timestamp: Option<i64>) {
+        self.transactions += transactions as u64;
+
+        if self.last_log.elapsed() < PROGRESS_LOG_INTERVAL {
+            return;
+        }
+        self.last_log = Instant::now();
+
+        if let Some(ts) = newest_timestamp {
+            let done = ts.saturating_sub(self.range.from_ts) as f64;
+            let total = self.range.to_ts.saturating_sub(self.range.from_ts).max(1) as f64;
+            log::info!(
+                "Backfill at timestamp {ts} of {}..{} ({:.1}%), {} transactions indexed",
+                self.range.from_ts,
+                self.range.to_ts,
+                (done / total * 100.0).min(100.0),
+                self.transactions
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{BackfillRange, LtWindow};
+
+    #[test]
+    fn test_range_bounds() {
+        let range = BackfillRange {
+            from_ts: 100,
+            to_ts: 200,
+        };
+
+        assert!(!range.contains(99));
+        assert!(range.contains(100));
+        assert!(range.contains(199));
+        assert!(!range.contains(200));
+
+        assert!(!range.is_reached_by(199));
+        assert!(range.is_reached_by(200));
+    }
+
+    #[test]
+    fn test_lt_window_bounds() {
+        let window = LtWindow {
+            from_lt: Some(100),
+            to_lt: Some(200),
+        };
+
+        assert!(!window.contains(99));
+        assert!(window.contains(100));
+        assert!(window.contains(200));
+        assert!(!window.contains(201));
+
+        let open_end = LtWindow {
+            from_lt: Some(100),
+            to_lt: None,
+        };
+        assert!(!open_end.contains(99));
+        assert!(open_end.contains(i64::MAX));
+    }
+}
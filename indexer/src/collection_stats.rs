@@ -0,0 +1,25 @@
+use std::time::Duration;
+
+use indexer_repo::collection::reconcile_collection_stats;
+use sqlx::PgPool;
+use tokio::sync::watch;
+
+/// Recounts the `collection_stats` counters every `period`, correcting listing counts
+/// that drifted from `nft_direct_sell` and refreshing the nft counts
+pub async fn run(pool: PgPool, period: Duration, mut shutdown: watch::Receiver<bool>) {
+    let mut interval = tokio::time::interval(period);
+
+    loop {
+        tokio::select! {
+            biased;
+            Ok(()) = shutdown.changed() => break,
+            _ = interval.tick() => {}
+        }
+
+        match reconcile_collection_stats(&pool).await {
+            Ok(0) => {}
+            Ok(corrected) => log::info!("Corrected stats of {corrected} collections"),
+            Err(e) => log::error!("Failed to reconcile collection stats: {:#?}", e),
+        }
+    }
+}
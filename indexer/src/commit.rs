@@ -0,0 +1,195 @@
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use futures::channel::mpsc::Sender;
+use futures::SinkExt;
+use tokio::time::Instant;
+
+/// When saved batches are acknowledged to the consumer, which commits their offsets.
+/// Without limits every batch is acknowledged as soon as it is saved; with either
+/// limit set the acknowledgements wait until that many transactions are saved or the
+/// oldest of them waited that long, whichever comes first. A crash then replays the
+/// saved but uncommitted transactions, which the live checkpoint skips; a backfill has
+/// no checkpoint, so it always commits after each batch
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CommitPolicy {
+    pub max_transactions: Option<usize>,
+    pub max_delay: Option<Duration>,
+}
+
+impl CommitPolicy {
+    /// A transaction limit needs a delay limit too: the consumer may hold back the next
+    /// batch until the previous one is committed, and an idle stream never reaches the
+    /// limit, so only the delay flushes what is pending
+    pub fn new(max_transactions: Option<usize>, max_delay: Option<Duration>) -> Result<Self> {
+        if max_transactions.is_some() && max_delay.is_none() {
+            bail!("COMMIT_MAX_TRANSACTIONS needs COMMIT_MAX_DELAY_MS to be set as well");
+        }
+
+        Ok(Self {
+            max_transactions,
+            max_delay,
+        })
+    }
+
+    pub fn is_batching(&self) -> bool {
+        self.max_transactions.is_some() || self.max_delay.is_some()
+    }
+
+    pub fn is_due(&self, pending: &PendingCommits, now: Instant) -> bool {
+        if pending.batches == 0 {
+            return false;
+        }
+        if !self.is_batching() {
+            return true;
+        }
+
+        self.max_transactions
+            .map_or(false, |max| pending.transactions >= max)
+            || self.deadline(pending).map_or(false, |at| at <= now)
+    }
+
+    /// Latest time the pending batches are acknowledged, `None` if nothing is pending
+    /// or there is no delay limit
+    pub fn deadline(&self, pending: &PendingCommits) -> Option<Instant> {
+        pending
+            .since
+            .zip(self.max_delay)
+            .map(|(since, delay)| since + delay)
+    }
+}
+
+/// Saved batches that are not acknowledged yet
+#[derive(Debug, Default)]
+pub struct PendingCommits {
+    batches: usize,
+    transactions: usize,
+    since: Option<Instant>,
+    newest_lt: Option<i64>,
+    /// Checkpoint lt of the latest acknowledged batch
+    committed_lt: Option<i64>,
+}
+
+impl PendingCommits {
+    pub fn add(&mut self, batches: usize, transactions: usize, newest_lt: Option<i64>) {
+        self.batches += batches;
+        self.transactions += transactions;
+        self.since.get_or_insert_with(Instant::now);
+        if newest_lt.is_some() {
+            self.newest_lt = newest_lt;
+        }
+    }
+
+    /// Acknowledges every pending batch
+    pub async fn flush(&mut self, tx_commit: &mut Sender<()>) {
+        if self.batches == 0 {
+            return;
+        }
+
+        for _ in 0..self.batches {
+            // A terminated stream redelivers the batches after the reconnect, where they
+            // are skipped by the checkpoint
+            if tx_commit.send(()).await.is_err() {
+                log::warn!(
+                    "Transactions stream is gone, {} saved batches are not committed",
+                    self.batches
+                );
+                self.clear();
+                return;
+            }
+        }
+
+        if self.newest_lt.is_some() {
+            self.committed_lt = self.newest_lt;
+        }
+        log::debug!(
+            "Committed {} batches ({} transactions), committed lt: {:?}",
+            self.batches,
+            self.transactions,
+            self.committed_lt
+        );
+        self.clear();
+    }
+
+    pub fn committed_lt(&self) -> Option<i64> {
+        self.committed_lt
+    }
+
+    fn clear(&mut self) {
+        self.batches = 0;
+        self.transactions = 0;
+        self.since = None;
+        self.newest_lt = None;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use tokio::time::Instant;
+
+    use super::{CommitPolicy, PendingCommits};
+
+    #[test]
+    fn test_transaction_limit_needs_a_delay() {
+        assert!(CommitPolicy::new(Some(100), None).is_err());
+        assert!(CommitPolicy::new(None, Some(Duration::from_secs(5))).is_ok());
+        assert_eq!(
+            CommitPolicy::new(None, None).unwrap(),
+            CommitPolicy::default()
+        );
+    }
+
+    #[test]
+    fn test_every_batch_is_due_without_limits() {
+        let policy = CommitPolicy::default();
+        let mut pending = PendingCommits::default();
+        assert!(!policy.is_due(&pending, Instant::now()));
+
+        pending.add(1, 1, Some(10));
+        assert!(policy.is_due(&pending, Instant::now()));
+        assert_eq!(policy.deadline(&pending), None);
+    }
+
+    #[test]
+    fn test_batched_commit_is_due_at_the_first_limit() {
+        let policy = CommitPolicy {
+            max_transactions: Some(100),
+            max_delay: Some(Duration::from_secs(5)),
+        };
+        let mut pending = PendingCommits::default();
+
+        pending.add(2, 60, Some(10));
+        let since = pending.since.unwrap();
+        assert_eq!(
+            policy.deadline(&pending),
+            Some(since + Duration::from_secs(5))
+        );
+        assert!(!policy.is_due(&pending, since));
+        assert!(policy.is_due(&pending, since + Duration::from_secs(5)));
+
+        pending.add(1, 40, None);
+        assert!(policy.is_due(&pending, since));
+        assert_eq!(pending.newest_lt, Some(10));
+    }
+
+    #[tokio::test]
+    async fn test_flush_acknowledges_every_pending_batch() {
+        let (mut tx_commit, mut rx_commit) = futures::channel::mpsc::channel(8);
+        let mut pending = PendingCommits::default();
+        pending.add(2, 10, Some(10));
+        pending.add(1, 5, Some(20));
+
+        pending.flush(&mut tx_commit).await;
+        assert_eq!(pending.committed_lt(), Some(20));
+        assert_eq!(pending.batches, 0);
+        assert_eq!(pending.since, None);
+
+        let mut acknowledged = 0;
+        while let Ok(Some(())) = rx_commit.try_next() {
+            acknowledged += 1;
+        }
+        assert_eq!(acknowledged, 3);
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+/// Counts which accounts emitted extracted events during a time window,
+/// so operators can spot factories/offers that aren't whitelisted yet.
+///
+/// Transactions don't carry the contract code, so accounts are reported
+/// instead of code hashes.
+pub struct SeenContracts {
+    started: Instant,
+    window: Duration,
+    accounts: HashMap<String, AccountStats>,
+}
+
+#[derive(Default)]
+struct AccountStats {
+    total: u64,
+    unhandled: u64,
+    events: BTreeMap<String, u64>,
+}
+
+impl SeenContracts {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            started: Instant::now(),
+            window,
+            accounts: HashMap::new(),
+        }
+    }
+
+    /// `handled` is false when no entity decoder accepted the event
+    pub fn record(&mut self, account: &str, event: &str, handled: bool) {
+        let stats = self.accounts.entry(account.to_string()).or_default();
+        stats.total += 1;
+        if !handled {
+            stats.unhandled += 1;
+        }
+        *stats.events.entry(event.to_string()).or_default() += 1;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.started.elapsed() >= self.window
+    }
+
+    pub fn report(&self) -> String {
+        let mut accounts = self.accounts.iter().collect::<Vec<_>>();
+        accounts.sort_by(|(a, x), (b, y)| y.total.cmp(&x.total).then_with(|| a.cmp(b)));
+
+        let mut report = format!(
+            "Seen contracts for the last {}s ({} accounts):",
+            self.window.as_secs(),
+            accounts.len()
+        );
+        for (account, stats) in accounts {
+            let events = stats
+                .events
+                .iter()
+                .map(|(name, count)| format!("{name}: {count}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            report.push_str(&format!(
+                "\n  {account} total: {}, unhandled: {} ({events})",
+                stats.total, stats.unhandled
+            ));
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::SeenContracts;
+
+    #[test]
+    fn test_report_counts_per_account() {
+        let mut seen = SeenContracts::new(Duration::from_secs(60));
+        seen.record("0:a", "DirectSellDeployed", true);
+        seen.record("0:a", "DirectSellDeployed", true);
+        seen.record("0:b", "OfferCreated", false);
+
+        let report = seen.report();
+        let lines = report.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[1],
+            "  0:a total: 2, unhandled: 0 (DirectSellDeployed: 2)"
+        );
+        assert_eq!(lines[2], "  0:b total: 1, unhandled: 1 (OfferCreated: 1)");
+    }
+}
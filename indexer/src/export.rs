@@ -0,0 +1,142 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use anyhow::{anyhow, Result};
+use indexer_repo::events::get_events_after_id;
+use sqlx::PgPool;
+
+/// Events read per query, each page is a short statement rather than one for the table
+const PAGE_SIZE: i64 = 10_000;
+
+/// `export [--collection <address>] [--since-id <id>] [--output <file>]`: writes the
+/// stored events as newline-delimited JSON, one `EventRecord` per line, to stdout or
+/// the file
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ExportArgs {
+    pub collection: Option<String>,
+    /// Only events stored after this one, the last id of a previous export
+    pub since_id: Option<i64>,
+    pub output: Option<String>,
+}
+
+impl ExportArgs {
+    /// `None` unless the process was started with the `export` command
+    pub fn parse(args: &[String]) -> Result<Option<Self>> {
+        let Some((command, options)) = args.split_first() else {
+            return Ok(None);
+        };
+        if command != "export" {
+            return Ok(None);
+        }
+
+        let mut export = Self::default();
+        let mut options = options.iter();
+        while let Some(option) = options.next() {
+            let mut value = || {
+                options
+                    .next()
+                    .cloned()
+                    .ok_or_else(|| anyhow!("{option} expects a value"))
+            };
+            match option.as_str() {
+                "--collection" => export.collection = Some(value()?),
+                "--since-id" => {
+                    export.since_id = Some(
+                        value()?
+                            .parse()
+                            .map_err(|_| anyhow!("--since-id expects an event id"))?,
+                    );
+                }
+                "--output" => export.output = Some(value()?),
+                _ => return Err(anyhow!("unknown export option {option}")),
+            }
+        }
+
+        Ok(Some(export))
+    }
+
+    /// Logs go to stderr while stdout carries the export
+    pub fn writes_to_stdout(args: &[String]) -> bool {
+        matches!(Self::parse(args), Ok(Some(Self { output: None, .. })))
+    }
+}
+
+pub async fn run(pool: &PgPool, export: &ExportArgs) -> Result<()> {
+    let mut out: BufWriter<Box<dyn Write>> = BufWriter::new(match &export.output {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(std::io::stdout()),
+    });
+
+    let (mut exported, mut last_id) = (0u64, export.since_id);
+    loop {
+        let events =
+            get_events_after_id(pool, export.collection.as_deref(), last_id, PAGE_SIZE).await?;
+        let Some((id, _)) = events.last() else {
+            break;
+        };
+        last_id = Some(*id);
+
+        for (_, event) in &events {
+            serde_json::to_writer(&mut out, event)?;
+            out.write_all(b"\n")?;
+        }
+        exported += events.len() as u64;
+    }
+    out.flush()?;
+
+    match last_id {
+        Some(id) if exported > 0 => {
+            log::info!("Exported {exported} events, continue with --since-id {id}")
+        }
+        _ => log::info!("No events to export"),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::ExportArgs;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn test_export_command_is_parsed() {
+        assert_eq!(ExportArgs::parse(&args(&["replay"])).unwrap(), None);
+        assert_eq!(
+            ExportArgs::parse(&args(&["export"])).unwrap(),
+            Some(ExportArgs::default())
+        );
+        assert_eq!(
+            ExportArgs::parse(&args(&[
+                "export",
+                "--collection",
+                "0:c",
+                "--since-id",
+                "42",
+                "--output",
+                "events.ndjson"
+            ]))
+            .unwrap(),
+            Some(ExportArgs {
+                collection: Some("0:c".to_string()),
+                since_id: Some(42),
+                output: Some("events.ndjson".to_string()),
+            })
+        );
+        assert!(ExportArgs::parse(&args(&["export", "--since-id", "x"])).is_err());
+        assert!(ExportArgs::parse(&args(&["export", "--collection"])).is_err());
+        assert!(ExportArgs::parse(&args(&["export", "--to-lt", "1"])).is_err());
+    }
+
+    #[test]
+    fn test_logs_move_to_stderr_only_when_exporting_to_stdout() {
+        assert!(ExportArgs::writes_to_stdout(&args(&["export"])));
+        assert!(!ExportArgs::writes_to_stdout(&args(&[
+            "export", "--output", "f"
+        ])));
+        assert!(!ExportArgs::writes_to_stdout(&args(&["replay"])));
+    }
+}
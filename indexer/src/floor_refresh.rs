@@ -0,0 +1,70 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use indexer_repo::collection::refresh_collection_floor;
+use sqlx::PgPool;
+use tokio::sync::watch;
+use tokio::time::MissedTickBehavior;
+
+/// Set by batches that deployed or changed direct sells. `collection_floor` is rebuilt
+/// from every listing, so it is refreshed on a schedule rather than after each of them
+#[derive(Clone, Default)]
+pub struct FloorRefresh(Arc<AtomicBool>);
+
+impl FloorRefresh {
+    pub fn mark(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether a refresh was marked since the last call
+    pub fn take(&self) -> bool {
+        self.0.swap(false, Ordering::Relaxed)
+    }
+}
+
+/// Refreshes `collection_floor` every `period` if a batch marked it since the last
+/// refresh. A failed refresh is retried at the next tick
+pub async fn run(
+    pool: PgPool,
+    refresh: FloorRefresh,
+    period: Duration,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    let mut interval = tokio::time::interval(period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            biased;
+            Ok(()) = shutdown.changed() => break,
+            _ = interval.tick() => {}
+        }
+
+        if !refresh.take() {
+            continue;
+        }
+        if let Err(e) = refresh_collection_floor(&pool).await {
+            log::error!("Failed to refresh collection_floor: {:#?}", e);
+            refresh.mark();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::FloorRefresh;
+
+    #[test]
+    fn test_marks_are_taken_once() {
+        let refresh = FloorRefresh::default();
+        assert!(!refresh.take());
+
+        // the writer and the refresh task share the flag
+        let writer = refresh.clone();
+        writer.mark();
+        writer.mark();
+        assert!(refresh.take());
+        assert!(!refresh.take());
+    }
+}
@@ -0,0 +1,61 @@
+use std::time::Instant;
+
+use anyhow::{bail, Result};
+use indexer_repo::collection::recompute_collection_floors;
+use sqlx::PgPool;
+
+/// `recompute-floors`: rebuilds every collection floor from the active listings, safe
+/// to run next to a running indexer
+#[derive(Debug, PartialEq, Eq)]
+pub struct RecomputeFloorsArgs;
+
+impl RecomputeFloorsArgs {
+    /// `None` unless the process was started with the `recompute-floors` command
+    pub fn parse(args: &[String]) -> Result<Option<Self>> {
+        let Some((command, options)) = args.split_first() else {
+            return Ok(None);
+        };
+        if command != "recompute-floors" {
+            return Ok(None);
+        }
+        if !options.is_empty() {
+            bail!("recompute-floors takes no options");
+        }
+
+        Ok(Some(Self))
+    }
+}
+
+pub async fn run(pool: &PgPool) -> Result<()> {
+    let started = Instant::now();
+    let collections = recompute_collection_floors(pool).await?;
+    log::info!(
+        "Recomputed floors of {collections} collections in {}ms",
+        started.elapsed().as_millis()
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::RecomputeFloorsArgs;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn test_recompute_floors_command_is_parsed() {
+        assert_eq!(RecomputeFloorsArgs::parse(&args(&[])).unwrap(), None);
+        assert_eq!(
+            RecomputeFloorsArgs::parse(&args(&["export"])).unwrap(),
+            None
+        );
+        assert_eq!(
+            RecomputeFloorsArgs::parse(&args(&["recompute-floors"])).unwrap(),
+            Some(RecomputeFloorsArgs)
+        );
+        assert!(RecomputeFloorsArgs::parse(&args(&["recompute-floors", "--all"])).is_err());
+    }
+}
@@ -0,0 +1,98 @@
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::sync::Arc;
+
+use serde::Serialize;
+use sqlx::PgPool;
+use tokio::sync::watch;
+
+/// Liveness and readiness state shared by the indexer loop and the metrics server
+#[derive(Clone)]
+pub struct Health {
+    inner: Arc<HealthState>,
+}
+
+struct HealthState {
+    pool: PgPool,
+    shutdown: watch::Receiver<bool>,
+    stream_connected: AtomicBool,
+    /// Logical time of the last committed transaction, -1 before the first commit
+    last_committed_lt: AtomicI64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Readiness {
+    pub database: bool,
+    pub stream: bool,
+    pub shutting_down: bool,
+    pub last_committed_lt: Option<i64>,
+}
+
+impl Readiness {
+    pub fn is_ready(&self) -> bool {
+        self.database && self.stream && !self.shutting_down
+    }
+}
+
+impl Health {
+    pub fn new(pool: PgPool, shutdown: watch::Receiver<bool>) -> Self {
+        Self {
+            inner: Arc::new(HealthState {
+                pool,
+                shutdown,
+                stream_connected: AtomicBool::new(false),
+                last_committed_lt: AtomicI64::new(-1),
+            }),
+        }
+    }
+
+    pub fn set_stream_connected(&self) {
+        self.inner.stream_connected.store(true, Ordering::Relaxed);
+    }
+
+    pub fn set_stream_disconnected(&self) {
+        self.inner.stream_connected.store(false, Ordering::Relaxed);
+    }
+
+    pub fn set_committed_lt(&self, lt: i64) {
+        self.inner.last_committed_lt.store(lt, Ordering::Relaxed);
+    }
+
+    pub async fn readiness(&self) -> Readiness {
+        let database = sqlx::query("select 1")
+            .execute(&self.inner.pool)
+            .await
+            .map_err(|e| log::warn!("Readiness check can't reach Postgres: {e}"))
+            .is_ok();
+        let lt = self.inner.last_committed_lt.load(Ordering::Relaxed);
+
+        Readiness {
+            database,
+            stream: self.inner.stream_connected.load(Ordering::Relaxed),
+            shutting_down: *self.inner.shutdown.borrow(),
+            last_committed_lt: (lt >= 0).then_some(lt),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Readiness;
+
+    #[test]
+    fn test_not_ready_while_shutting_down() {
+        let mut readiness = Readiness {
+            database: true,
+            stream: true,
+            shutting_down: false,
+            last_committed_lt: Some(42),
+        };
+        assert!(readiness.is_ready());
+
+        readiness.shutting_down = true;
+        assert!(!readiness.is_ready());
+
+        readiness.shutting_down = false;
+        readiness.stream = false;
+        assert!(!readiness.is_ready());
+    }
+}
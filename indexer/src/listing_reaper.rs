@@ -0,0 +1,31 @@
+use std::time::Duration;
+
+use indexer_repo::collection::refresh_collection_floor;
+use indexer_repo::direct_sell::expire_stale_direct_sells;
+use sqlx::PgPool;
+use tokio::sync::watch;
+
+/// Expires direct sells past their end every `period`, so floor prices and active
+/// listing queries don't count them
+pub async fn run(pool: PgPool, period: Duration, mut shutdown: watch::Receiver<bool>) {
+    let mut interval = tokio::time::interval(period);
+
+    loop {
+        tokio::select! {
+            biased;
+            Ok(()) = shutdown.changed() => break,
+            _ = interval.tick() => {}
+        }
+
+        match expire_stale_direct_sells(&pool, chrono::Utc::now().naive_utc()).await {
+            Ok(0) => {}
+            Ok(expired) => {
+                log::info!("Expired {expired} stale direct sells");
+                if let Err(e) = refresh_collection_floor(&pool).await {
+                    log::error!("Failed to refresh collection_floor: {:#?}", e);
+                }
+            }
+            Err(e) => log::error!("Failed to expire stale direct sells: {:#?}", e),
+        }
+    }
+}
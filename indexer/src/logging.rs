@@ -0,0 +1,181 @@
+use std::fmt;
+
+use serde_json::{Map, Value};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::fmt::format::{JsonFields, Writer};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields, FormattedFields};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::EnvFilter;
+
+const DEFAULT_FILTER: &str = "info";
+
+/// Installs the global subscriber. By default every line is a JSON object in the
+/// format of Google Cloud Logging: `severity`, `time`, `target`, the message and the
+/// fields of the event and of its spans (account, lt, hash of the current
+/// transaction). `LOG_FORMAT=text` prints human-readable lines instead. `log` records
+/// of the dependencies are forwarded to the same subscriber. With `stderr` set stdout
+/// is left for the output of a command
+pub fn init(stderr: bool) {
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+    let writer = if stderr {
+        BoxMakeWriter::new(std::io::stderr)
+    } else {
+        BoxMakeWriter::new(std::io::stdout)
+    };
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(writer);
+
+    match std::env::var("LOG_FORMAT").as_deref() {
+        Ok("text") => builder.init(),
+        _ => builder
+            .fmt_fields(JsonFields::new())
+            .event_format(StackdriverJson)
+            .init(),
+    }
+}
+
+/// One JSON object per event, `severity` instead of the `level` of the tracing JSON
+/// format so the log agent picks up the level
+struct StackdriverJson;
+
+impl<S, N> FormatEvent<S, N> for StackdriverJson
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let mut entry = Map::new();
+        entry.insert(
+            "severity".to_string(),
+            severity(event.metadata().level()).into(),
+        );
+        entry.insert(
+            "time".to_string(),
+            chrono::Utc::now()
+                .to_rfc3339_opts(chrono::SecondsFormat::Micros, true)
+                .into(),
+        );
+        entry.insert("target".to_string(), event.metadata().target().into());
+
+        // Span fields are kept formatted by `JsonFields`, the innermost span wins
+        for span in ctx.event_scope().into_iter().flat_map(|s| s.from_root()) {
+            let extensions = span.extensions();
+            let fields = extensions
+                .get::<FormattedFields<N>>()
+                .and_then(|f| serde_json::from_str::<Map<String, Value>>(f).ok());
+            entry.extend(fields.into_iter().flatten());
+        }
+        event.record(&mut JsonVisitor(&mut entry));
+
+        writeln!(writer, "{}", Value::Object(entry))
+    }
+}
+
+/// `LogSeverity` of Cloud Logging, which has no trace level
+fn severity(level: &Level) -> &'static str {
+    match *level {
+        Level::ERROR => "ERROR",
+        Level::WARN => "WARNING",
+        Level::INFO => "INFO",
+        _ => "DEBUG",
+    }
+}
+
+struct JsonVisitor<'a>(&'a mut Map<String, Value>);
+
+impl JsonVisitor<'_> {
+    fn insert(&mut self, field: &Field, value: Value) {
+        match field.name() {
+            // forwarded `log` records carry their target as a field
+            "log.target" => {
+                self.0.insert("target".to_string(), value);
+            }
+            name if name.starts_with("log.") => {}
+            name => {
+                self.0.insert(name.to_string(), value);
+            }
+        }
+    }
+}
+
+impl Visit for JsonVisitor<'_> {
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.insert(field, format!("{value:?}").into());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::Value;
+    use tracing_subscriber::fmt::format::JsonFields;
+
+    use super::StackdriverJson;
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_json_lines_carry_the_severity_and_span_fields() {
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .fmt_fields(JsonFields::new())
+            .event_format(StackdriverJson)
+            .with_writer(move || writer.clone())
+            .finish();
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("transaction", account = "0:account", lt = 5);
+            let _span = span.enter();
+            tracing::warn!(event = "NftCreated", "Error while decode");
+        });
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let line: Value = serde_json::from_str(output.trim_end()).unwrap();
+        assert_eq!(line["severity"], "WARNING");
+        assert_eq!(line["message"], "Error while decode");
+        assert_eq!(line["event"], "NftCreated");
+        assert_eq!(line["account"], "0:account");
+        assert_eq!(line["lt"], 5);
+        assert!(line["time"].is_string());
+        assert!(line.get("level").is_none());
+    }
+}
@@ -1,21 +1,55 @@
+use crate::health::Health;
 use crate::settings::config::Config;
 use anyhow::Result;
-use data_reader::{MetaReaderContext, PriceReader};
-use indexer_api::run_api;
+use data_reader::{
+    GetterClient, MetaReaderContext, MetadataJrpcService, PriceReader, RarityQueue, RpcLimiter,
+};
+use indexer_api::{run_api, ApiConfig, ParserControl};
+use indexer_repo::error::IndexerError;
 use std::net::SocketAddr;
 use std::panic;
 use std::str::FromStr;
+use std::time::Duration;
 
 mod abi;
+mod backfill;
+mod collection_stats;
+mod commit;
+mod discovery;
+mod export;
+mod floor_refresh;
+mod floors;
+mod health;
+mod listing_reaper;
+mod logging;
+mod metrics;
+mod missing_collections;
 mod models;
 mod parser;
 mod persistence;
+mod price;
+mod rarity;
+mod reconnect;
+mod replay;
+mod resume;
+mod rollback;
 mod settings;
+mod shutdown;
+mod sinks;
+#[cfg(test)]
+mod test_harness;
 mod utils;
+mod verify;
+mod whitelist;
 
 extern crate num;
 extern crate num_derive;
 
+const DEFAULT_JRPC_MAX_CONCURRENCY: usize = 4;
+const DEFAULT_GETTER_CACHE_TTL_SECS: u64 = 300;
+const DEFAULT_EXPIRE_LISTINGS_INTERVAL_SECS: u64 = 60;
+const DEFAULT_RECONCILE_STATS_INTERVAL_SECS: u64 = 3600;
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let default_hook = panic::take_hook();
@@ -24,25 +58,41 @@ async fn main() -> Result<()> {
         std::process::exit(1);
     }));
 
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+
     dotenv::dotenv().ok();
-    stackdriver_logger::init_with_cargo!();
+    logging::init(export::ExportArgs::writes_to_stdout(&args));
     log::info!("Indexer is preparing to start");
 
-    let config = Config::new();
-    let pg_pool = indexer_repo::utils::init_pg_pool(
-        &config.database_url,
-        config.database_max_connections,
-        config.terminate_open_connections,
-    )
-    .await
-    .expect("Postgres connection failed");
+    let config = Config::try_new().map_err(|e| IndexerError::Config(e.to_string()))?;
+    let pg_pool = indexer_repo::db::connect(&config.db())
+        .await
+        .expect("Postgres connection failed");
 
     sqlx::migrate!("../indexer-repo/migrations")
         .run(&pg_pool)
         .await?;
 
-    let jrpc_client = settings::get_jrpc_client(&config).await?;
-    log::info!("Connected to jrpc endpoint");
+    if let Some(export) = export::ExportArgs::parse(&args)? {
+        return export::run(&pg_pool, &export).await;
+    }
+
+    if let Some(rollback) = rollback::RollbackArgs::parse(&args)? {
+        return rollback::run(&pg_pool, &config.kafka(), rollback.to_lt).await;
+    }
+
+    if floors::RecomputeFloorsArgs::parse(&args)?.is_some() {
+        return floors::run(&pg_pool).await;
+    }
+
+    if let Some(verify) = verify::VerifyArgs::parse(&args)? {
+        return verify::run(
+            &pg_pool,
+            &settings::runtime::RuntimeConfig::from(&config),
+            &verify.target,
+        )
+        .await;
+    }
 
     let price_reader = PriceReader::new(
         pg_pool.clone(),
@@ -54,27 +104,122 @@ async fn main() -> Result<()> {
 
     tokio::spawn(price_reader.clone().run_db_updater());
 
+    if let Some(replay) = replay::ReplayArgs::parse(&args)? {
+        return replay::run(
+            pg_pool,
+            price::UsdConverter::new(price_reader),
+            settings::runtime::RuntimeConfig::from(&config),
+            replay.from_lt,
+        )
+        .await;
+    }
+
+    let jrpc_client = settings::get_jrpc_client(&config).await?;
+    log::info!("Connected to jrpc endpoint");
+
+    let rpc_limiter = RpcLimiter::new(
+        config
+            .jrpc_max_concurrency
+            .unwrap_or(DEFAULT_JRPC_MAX_CONCURRENCY),
+    );
+    let getter_client = GetterClient::new(
+        jrpc_client,
+        rpc_limiter,
+        config.getter_rate_limit_per_sec,
+        Duration::from_secs(
+            config
+                .getter_cache_ttl_secs
+                .unwrap_or(DEFAULT_GETTER_CACHE_TTL_SECS),
+        ),
+    );
+
+    if missing_collections::BackfillCollectionsArgs::parse(&args)?.is_some() {
+        return missing_collections::run(
+            &pg_pool,
+            &MetadataJrpcService::new(getter_client.clone()),
+        )
+        .await;
+    }
+
+    let shutdown = shutdown::shutdown_signal();
+    let health = Health::new(pg_pool.clone(), shutdown.clone());
+
+    if let Some(port) = config.metrics_port {
+        tokio::spawn(metrics::serve(
+            SocketAddr::from(([0, 0, 0, 0], port)),
+            getter_client.clone(),
+            pg_pool.clone(),
+            health.clone(),
+        ));
+    }
+
+    // Marked by the parser on mints and burns and by the meta reader on new attributes
+    let rarity_queue = RarityQueue::default();
     let meta_reader_context = MetaReaderContext {
-        jrpc_client: jrpc_client.clone(),
+        getter_client,
         pool: pg_pool.clone(),
+        rarity_queue: rarity_queue.clone(),
         jrpc_req_latency_millis: config.jrpc_req_latency_millis,
         idle_after_loop: config.idle_after_meta_loop_sec,
     };
 
     tokio::spawn(data_reader::run_meta_reader(meta_reader_context.clone()));
 
-    tokio::spawn(parser::start_parsing(
+    if config.backfill_from_ts.is_none() {
+        tokio::spawn(listing_reaper::run(
+            pg_pool.clone(),
+            Duration::from_secs(
+                config
+                    .expire_listings_interval_secs
+                    .unwrap_or(DEFAULT_EXPIRE_LISTINGS_INTERVAL_SECS),
+            ),
+            shutdown.clone(),
+        ));
+        tokio::spawn(collection_stats::run(
+            pg_pool.clone(),
+            Duration::from_secs(
+                config
+                    .reconcile_stats_interval_secs
+                    .unwrap_or(DEFAULT_RECONCILE_STATS_INTERVAL_SECS),
+            ),
+            shutdown.clone(),
+        ));
+    }
+
+    let parser_control = ParserControl::default();
+
+    let parsing = tokio::spawn(parser::start_parsing(
         config.clone(),
         pg_pool.clone(),
         price_reader,
+        parser_control.clone(),
+        rarity_queue,
+        health,
+        shutdown,
     ));
 
+    // Backfill runs exit once the range is indexed, the API is served by the live indexer
+    if config.backfill_from_ts.is_some() {
+        return Ok(parsing.await??);
+    }
+
     let socket_addr: SocketAddr =
         SocketAddr::from_str(&config.server_api_url).expect("Invalid socket addr");
 
-    run_api(&socket_addr, meta_reader_context)
-        .await
-        .expect("Failed to run server");
+    let api_config = ApiConfig {
+        counterparty_api_enabled: config.counterparty_api_enabled.unwrap_or_default(),
+        admin_api_enabled: config.admin_api_enabled.unwrap_or_default(),
+    };
+
+    run_api(
+        &socket_addr,
+        meta_reader_context,
+        api_config,
+        parser_control,
+    )
+    .await
+    .expect("Failed to run server");
 
-    Ok(())
+    // The API server stops on SIGTERM/SIGINT too; wait for the in-flight batch to commit
+    Ok(parsing.await??)
 }
@@ -0,0 +1,246 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+
+use anyhow::{anyhow, Result};
+use data_reader::GetterClient;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{header, Body, Request, Response, Server, StatusCode};
+use indexer_repo::indexer_state::get_oldest_root_last_at;
+use once_cell::sync::Lazy;
+use prometheus::{
+    register_histogram, register_int_counter, register_int_counter_vec, register_int_gauge,
+    Encoder, Histogram, IntCounter, IntCounterVec, IntGauge, TextEncoder,
+};
+use sqlx::PgPool;
+
+use crate::health::Health;
+
+pub static TRANSACTIONS_PROCESSED: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "nft_indexer_transactions_processed_total",
+        "Transactions taken from the consumer"
+    )
+    .unwrap()
+});
+
+pub static EVENTS_MATCHED: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "nft_indexer_events_matched_total",
+        "Events unpacked into an entity, by parser group",
+        &["parser"]
+    )
+    .unwrap()
+});
+
+pub static EVENTS_NOT_WHITELISTED: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "nft_indexer_events_not_whitelisted_total",
+        "Marketplace events emitted by contracts missing from the whitelist"
+    )
+    .unwrap()
+});
+
+pub static PARSE_FAILURES: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "nft_indexer_parse_failures_total",
+        "Events that failed to decode"
+    )
+    .unwrap()
+});
+
+pub static COMMIT_FAILURES: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "nft_indexer_commit_failures_total",
+        "Postgres transactions that failed to commit"
+    )
+    .unwrap()
+});
+
+pub static HANDLER_LATENCY: Lazy<Histogram> = Lazy::new(|| {
+    register_histogram!(
+        "nft_indexer_handler_latency_seconds",
+        "Time spent decoding the events of one transaction"
+    )
+    .unwrap()
+});
+
+pub static LISTING_ENDS_CLAMPED: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "nft_indexer_listing_ends_clamped_total",
+        "Listing and auction ends clamped to the lifetime horizon"
+    )
+    .unwrap()
+});
+
+pub static SINK_EVENTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "nft_indexer_sink_events_total",
+        "Events handed to CDC sinks, by outcome",
+        &["outcome"]
+    )
+    .unwrap()
+});
+
+pub static SERIALIZATION_FAILURES: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "nft_indexer_serialization_failures_total",
+        "Events stored with null args because they failed to serialize, by event type",
+        &["event_type"]
+    )
+    .unwrap()
+});
+
+static RPC_IN_FLIGHT: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "nft_indexer_rpc_in_flight",
+        "Node RPC calls currently holding a limiter permit"
+    )
+    .unwrap()
+});
+
+static GETTER_CACHE_HITS: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "nft_indexer_getter_cache_hits_total",
+        "Contract getter calls answered from the cache"
+    )
+    .unwrap()
+});
+
+static GETTER_CACHE_MISSES: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "nft_indexer_getter_cache_misses_total",
+        "Contract getter calls that went to the node"
+    )
+    .unwrap()
+});
+
+static DB_POOL_CONNECTIONS: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "nft_indexer_db_pool_connections",
+        "Open Postgres connections of the pool"
+    )
+    .unwrap()
+});
+
+static DB_POOL_IDLE: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "nft_indexer_db_pool_idle",
+        "Open Postgres connections not used by a query"
+    )
+    .unwrap()
+});
+
+static CONTRACT_MAX_LAG: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "nft_indexer_contract_max_lag_seconds",
+        "Age of the latest event of the marketplace root that is most behind"
+    )
+    .unwrap()
+});
+
+/// Serves `/metrics` in the Prometheus text format, `/healthz` and `/readyz`
+pub async fn serve(
+    addr: SocketAddr,
+    getter_client: GetterClient,
+    pool: PgPool,
+    health: Health,
+) -> Result<()> {
+    log::info!("Serving metrics on {addr}");
+
+    let make_service = make_service_fn(move |_| {
+        let getter_client = getter_client.clone();
+        let pool = pool.clone();
+        let health = health.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req: Request<Body>| {
+                let getter_client = getter_client.clone();
+                let pool = pool.clone();
+                let health = health.clone();
+                async move { respond(req.uri().path(), &getter_client, &pool, &health).await }
+            }))
+        }
+    });
+
+    Server::bind(&addr)
+        .serve(make_service)
+        .await
+        .map_err(|e| anyhow!(e))
+}
+
+async fn respond(
+    path: &str,
+    getter_client: &GetterClient,
+    pool: &PgPool,
+    health: &Health,
+) -> hyper::http::Result<Response<Body>> {
+    match path {
+        "/metrics" => {
+            RPC_IN_FLIGHT.set(getter_client.rpc_limiter().in_flight() as i64);
+            // The client counts on its own, the counters catch up on each scrape
+            GETTER_CACHE_HITS.inc_by(
+                getter_client
+                    .cache_hits()
+                    .saturating_sub(GETTER_CACHE_HITS.get()),
+            );
+            GETTER_CACHE_MISSES.inc_by(
+                getter_client
+                    .cache_misses()
+                    .saturating_sub(GETTER_CACHE_MISSES.get()),
+            );
+            DB_POOL_CONNECTIONS.set(pool.size() as i64);
+            DB_POOL_IDLE.set(pool.num_idle() as i64);
+            match get_oldest_root_last_at(pool).await {
+                Ok(Some(last_at)) => {
+                    CONTRACT_MAX_LAG.set((chrono::Utc::now().naive_utc() - last_at).num_seconds())
+                }
+                Ok(None) => {}
+                Err(e) => log::error!("Failed to read indexer state: {e:#}"),
+            }
+            Response::builder()
+                .header(header::CONTENT_TYPE, TextEncoder::new().format_type())
+                .body(Body::from(render()))
+        }
+        "/healthz" => Response::builder().body(Body::empty()),
+        "/readyz" => {
+            let readiness = health.readiness().await;
+            let status = match readiness.is_ready() {
+                true => StatusCode::OK,
+                false => StatusCode::SERVICE_UNAVAILABLE,
+            };
+            Response::builder()
+                .status(status)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(
+                    serde_json::to_string(&readiness).unwrap_or_default(),
+                ))
+        }
+        _ => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty()),
+    }
+}
+
+fn render() -> String {
+    let mut buffer = Vec::new();
+    if let Err(e) = TextEncoder::new().encode(&prometheus::gather(), &mut buffer) {
+        log::error!("Failed to encode metrics: {e:?}");
+    }
+
+    String::from_utf8(buffer).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{render, EVENTS_MATCHED, TRANSACTIONS_PROCESSED};
+
+    #[test]
+    fn test_render_exposes_counters() {
+        TRANSACTIONS_PROCESSED.inc();
+        EVENTS_MATCHED.with_label_values(&["auction"]).inc();
+
+        let text = render();
+
+        assert!(text.contains("nft_indexer_transactions_processed_total"));
+        assert!(text.contains("nft_indexer_events_matched_total{parser=\"auction\"}"));
+    }
+}
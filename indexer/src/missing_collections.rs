@@ -0,0 +1,161 @@
+use std::time::Instant;
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use data_reader::MetadataJrpcService;
+use indexer_repo::nft::{
+    fill_nft_collections, get_known_nft_collections, get_nfts_missing_collection,
+};
+use sqlx::PgPool;
+use ton_block::MsgAddressInt;
+
+/// Nfts resolved and written per round
+const BATCH_SIZE: i64 = 500;
+
+/// Collection of an nft read from the chain, injectable so tests don't need a node
+#[async_trait]
+pub trait CollectionResolver: Send + Sync {
+    async fn resolve_collection(&self, nft: &MsgAddressInt) -> Result<Option<String>>;
+}
+
+#[async_trait]
+impl CollectionResolver for MetadataJrpcService {
+    async fn resolve_collection(&self, nft: &MsgAddressInt) -> Result<Option<String>> {
+        Ok(self
+            .get_verified_collection(nft)
+            .await?
+            .map(|(collection, _)| collection.to_string()))
+    }
+}
+
+/// Nft and listing rows given a collection, and nfts no collection was found for
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct CollectionsBackfill {
+    pub nfts: u64,
+    pub listings: u64,
+    pub unresolved: usize,
+}
+
+/// `backfill-collections`: fills the collection of nfts and listings that were
+/// indexed without one, safe to run next to a running indexer
+#[derive(Debug, PartialEq, Eq)]
+pub struct BackfillCollectionsArgs;
+
+impl BackfillCollectionsArgs {
+    /// `None` unless the process was started with the `backfill-collections` command
+    pub fn parse(args: &[String]) -> Result<Option<Self>> {
+        let Some((command, options)) = args.split_first() else {
+            return Ok(None);
+        };
+        if command != "backfill-collections" {
+            return Ok(None);
+        }
+        if !options.is_empty() {
+            bail!("backfill-collections takes no options");
+        }
+
+        Ok(Some(Self))
+    }
+}
+
+pub async fn run(pool: &PgPool, resolver: &dyn CollectionResolver) -> Result<()> {
+    let started = Instant::now();
+    let backfill = backfill_missing_collections(pool, resolver).await?;
+    log::info!(
+        "Filled the collection of {} nfts and {} listings, {} nfts remain unresolved, in {}ms",
+        backfill.nfts,
+        backfill.listings,
+        backfill.unresolved,
+        started.elapsed().as_millis()
+    );
+
+    Ok(())
+}
+
+/// Resolves the nfts missing a collection in batches, from the TIP-4.3 verified
+/// collection or the nft row and otherwise from the TIP-4.3 index of the nft. Nfts that can't be resolved
+/// are counted and skipped, a later run retries them
+pub async fn backfill_missing_collections(
+    pool: &PgPool,
+    resolver: &dyn CollectionResolver,
+) -> Result<CollectionsBackfill> {
+    let mut backfill = CollectionsBackfill::default();
+    let mut after = None;
+
+    loop {
+        let nfts = get_nfts_missing_collection(pool, after.as_deref(), BATCH_SIZE).await?;
+        let Some(last) = nfts.last().cloned() else {
+            break;
+        };
+
+        let mut collections = get_known_nft_collections(pool, &nfts).await?;
+        let unknown = nfts
+            .into_iter()
+            .filter(|nft| !collections.contains_key(nft))
+            .collect::<Vec<_>>();
+        for nft in unknown {
+            match resolve(resolver, &nft).await {
+                Some(collection) => {
+                    collections.insert(nft, collection);
+                }
+                None => backfill.unresolved += 1,
+            }
+        }
+
+        if !collections.is_empty() {
+            let filled = fill_nft_collections(pool, &collections).await?;
+            backfill.nfts += filled.nfts;
+            backfill.listings += filled.listings;
+        }
+        log::info!(
+            "Collections backfill: {} nfts and {} listings filled, {} unresolved, up to nft {last}",
+            backfill.nfts,
+            backfill.listings,
+            backfill.unresolved
+        );
+        after = Some(last);
+    }
+
+    Ok(backfill)
+}
+
+async fn resolve(resolver: &dyn CollectionResolver, nft: &str) -> Option<String> {
+    let address = match nft.parse::<MsgAddressInt>() {
+        Ok(address) => address,
+        Err(e) => {
+            log::warn!("Nft {nft} has an invalid address: {e:#}");
+            return None;
+        }
+    };
+
+    match resolver.resolve_collection(&address).await {
+        Ok(collection) => collection,
+        Err(e) => {
+            log::warn!("Error while resolving the collection of {nft}: {e:#}");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::BackfillCollectionsArgs;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn test_backfill_collections_command_is_parsed() {
+        assert_eq!(BackfillCollectionsArgs::parse(&args(&[])).unwrap(), None);
+        assert_eq!(
+            BackfillCollectionsArgs::parse(&args(&["recompute-floors"])).unwrap(),
+            None
+        );
+        assert_eq!(
+            BackfillCollectionsArgs::parse(&args(&["backfill-collections"])).unwrap(),
+            Some(BackfillCollectionsArgs)
+        );
+        assert!(BackfillCollectionsArgs::parse(&args(&["backfill-collections", "-n"])).is_err());
+    }
+}
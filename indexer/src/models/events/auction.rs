@@ -1,25 +1,25 @@
 use nekoton_abi::{PackAbiPlain, UnpackAbiPlain};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use ton_block::MsgAddressInt;
 
-use crate::{models::types::*, utils::serialize_msg_address_int};
+use crate::{models::types::*, utils::msg_address_int};
 
-#[derive(Clone, UnpackAbiPlain, PackAbiPlain, PartialEq, Eq, Debug, Serialize)]
+#[derive(Clone, UnpackAbiPlain, PackAbiPlain, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct AuctionCreated {
     #[abi]
     pub value0: AuctionDetails,
 }
 
-#[derive(Clone, UnpackAbiPlain, PackAbiPlain, PartialEq, Eq, Debug, Serialize)]
+#[derive(Clone, UnpackAbiPlain, PackAbiPlain, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct AuctionActive {
     #[abi]
     pub value0: AuctionDetails,
 }
 
-#[derive(Clone, UnpackAbiPlain, PackAbiPlain, PartialEq, Eq, Debug, Serialize)]
+#[derive(Clone, UnpackAbiPlain, PackAbiPlain, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct BidPlaced {
     #[abi]
-    #[serde(serialize_with = "serialize_msg_address_int")]
+    #[serde(with = "msg_address_int")]
     pub buyer: MsgAddressInt,
     #[abi]
     pub value: u128,
@@ -29,10 +29,10 @@ pub struct BidPlaced {
     pub value3: AuctionDetails,
 }
 
-#[derive(Clone, UnpackAbiPlain, PackAbiPlain, PartialEq, Eq, Debug, Serialize)]
+#[derive(Clone, UnpackAbiPlain, PackAbiPlain, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct BidDeclined {
     #[abi]
-    #[serde(serialize_with = "serialize_msg_address_int")]
+    #[serde(with = "msg_address_int")]
     pub buyer: MsgAddressInt,
     #[abi]
     pub value: u128,
@@ -40,10 +40,10 @@ pub struct BidDeclined {
     pub value2: AuctionDetails,
 }
 
-#[derive(Clone, UnpackAbiPlain, PackAbiPlain, PartialEq, Eq, Debug, Serialize)]
+#[derive(Clone, UnpackAbiPlain, PackAbiPlain, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct AuctionComplete {
     #[abi]
-    #[serde(serialize_with = "serialize_msg_address_int")]
+    #[serde(with = "msg_address_int")]
     pub buyer: MsgAddressInt,
     #[abi]
     pub value: u128,
@@ -51,7 +51,7 @@ pub struct AuctionComplete {
     pub value2: AuctionDetails,
 }
 
-#[derive(Clone, UnpackAbiPlain, PackAbiPlain, PartialEq, Eq, Debug, Serialize)]
+#[derive(Clone, UnpackAbiPlain, PackAbiPlain, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct AuctionCancelled {
     #[abi]
     pub value0: AuctionDetails,
@@ -1,41 +1,41 @@
 use nekoton_abi::{PackAbiPlain, UnpackAbiPlain};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use ton_block::MsgAddressInt;
 use ton_types::UInt256;
 
-use crate::utils::{serialize_msg_address_int, serialize_uint256};
+use crate::utils::{msg_address_int, uint256};
 
-#[derive(Clone, UnpackAbiPlain, PackAbiPlain, PartialEq, Eq, Debug, Serialize)]
+#[derive(Clone, UnpackAbiPlain, PackAbiPlain, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct NftCreated {
     #[abi]
-    #[serde(serialize_with = "serialize_uint256")]
+    #[serde(with = "uint256")]
     pub id: UInt256,
     #[abi]
-    #[serde(serialize_with = "serialize_msg_address_int")]
+    #[serde(with = "msg_address_int")]
     pub nft: MsgAddressInt,
     #[abi]
-    #[serde(serialize_with = "serialize_msg_address_int")]
+    #[serde(with = "msg_address_int")]
     pub owner: MsgAddressInt,
     #[abi]
-    #[serde(serialize_with = "serialize_msg_address_int")]
+    #[serde(with = "msg_address_int")]
     pub manager: MsgAddressInt,
     #[abi]
-    #[serde(serialize_with = "serialize_msg_address_int")]
+    #[serde(with = "msg_address_int")]
     pub creator: MsgAddressInt,
 }
 
-#[derive(Clone, UnpackAbiPlain, PackAbiPlain, PartialEq, Eq, Debug, Serialize)]
+#[derive(Clone, UnpackAbiPlain, PackAbiPlain, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct NftBurned {
     #[abi]
-    #[serde(serialize_with = "serialize_uint256")]
+    #[serde(with = "uint256")]
     pub id: UInt256,
     #[abi]
-    #[serde(serialize_with = "serialize_msg_address_int")]
+    #[serde(with = "msg_address_int")]
     pub nft: MsgAddressInt,
     #[abi]
-    #[serde(serialize_with = "serialize_msg_address_int")]
+    #[serde(with = "msg_address_int")]
     pub owner: MsgAddressInt,
     #[abi]
-    #[serde(serialize_with = "serialize_msg_address_int")]
+    #[serde(with = "msg_address_int")]
     pub manager: MsgAddressInt,
 }
@@ -1,8 +1,8 @@
 use nekoton_abi::{PackAbiPlain, UnpackAbiPlain};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use ton_block::MsgAddressInt;
 
-use crate::{models::types::*, utils::serialize_msg_address_int};
+use crate::{models::types::*, utils::msg_address_int};
 
 /*
    FactoryAuction,
@@ -11,13 +11,13 @@ use crate::{models::types::*, utils::serialize_msg_address_int};
    FactoryDirectSell,
    MintAndSell,
 */
-#[derive(Clone, UnpackAbiPlain, PackAbiPlain, PartialEq, Eq, Debug, Serialize)]
+#[derive(Clone, UnpackAbiPlain, PackAbiPlain, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct OwnershipTransferred {
     #[abi(name = "oldOwner")]
-    #[serde(serialize_with = "serialize_msg_address_int")]
+    #[serde(with = "msg_address_int")]
     pub old_owner: MsgAddressInt,
     #[abi(name = "newOwner")]
-    #[serde(serialize_with = "serialize_msg_address_int")]
+    #[serde(with = "msg_address_int")]
     pub new_owner: MsgAddressInt,
 }
 
@@ -26,7 +26,7 @@ pub struct OwnershipTransferred {
    FactoryDirectBuy,
    FactoryDirectSell,
 */
-#[derive(Clone, UnpackAbiPlain, PackAbiPlain, PartialEq, Eq, Debug, Serialize)]
+#[derive(Clone, UnpackAbiPlain, PackAbiPlain, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct MarketFeeDefaultChanged {
     #[abi]
     pub fee: MarketFee,
@@ -40,10 +40,10 @@ pub struct MarketFeeDefaultChanged {
    FactoryDirectBuy,
    FactoryDirectSell,
 */
-#[derive(Clone, UnpackAbiPlain, PackAbiPlain, PartialEq, Eq, Debug, Serialize)]
+#[derive(Clone, UnpackAbiPlain, PackAbiPlain, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct MarketFeeChanged {
     #[abi]
-    #[serde(serialize_with = "serialize_msg_address_int")]
+    #[serde(with = "msg_address_int")]
     pub auction: MsgAddressInt,
     #[abi]
     pub fee: MarketFee,
@@ -54,10 +54,10 @@ pub struct MarketFeeChanged {
    FactoryDirectBuy,
    FactoryDirectSell,
 */
-#[derive(Clone, UnpackAbiPlain, PackAbiPlain, PartialEq, Eq, Debug, Serialize)]
+#[derive(Clone, UnpackAbiPlain, PackAbiPlain, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct AddCollectionRules {
     #[abi]
-    #[serde(serialize_with = "serialize_msg_address_int")]
+    #[serde(with = "msg_address_int")]
     pub collection: MsgAddressInt,
     #[abi(name = "collectionFeeInfo")]
     pub collection_fee_info: CollectionFeeInfo,
@@ -68,9 +68,9 @@ pub struct AddCollectionRules {
    FactoryDirectBuy,
    FactoryDirectSell,
 */
-#[derive(Clone, UnpackAbiPlain, PackAbiPlain, PartialEq, Eq, Debug, Serialize)]
+#[derive(Clone, UnpackAbiPlain, PackAbiPlain, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct RemoveCollectionRules {
     #[abi]
-    #[serde(serialize_with = "serialize_msg_address_int")]
+    #[serde(with = "msg_address_int")]
     pub collection: MsgAddressInt,
 }
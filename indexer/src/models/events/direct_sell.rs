@@ -1,10 +1,10 @@
 use nekoton_abi::{PackAbiPlain, UnpackAbiPlain};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use ton_block::MsgAddressInt;
 
-use crate::{models::types::*, utils::serialize_msg_address_int};
+use crate::{models::types::*, utils::msg_address_int};
 
-#[derive(Clone, UnpackAbiPlain, PackAbiPlain, PartialEq, Eq, Debug, Serialize)]
+#[derive(Clone, UnpackAbiPlain, PackAbiPlain, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct DirectSellStateChanged {
     #[abi]
     pub from: u8,
@@ -13,9 +13,9 @@ pub struct DirectSellStateChanged {
     #[abi]
     pub value2: DirectSellInfo,
     #[abi]
-    #[serde(serialize_with = "serialize_msg_address_int")]
+    #[serde(with = "msg_address_int")]
     pub old_owner: MsgAddressInt,
     #[abi]
-    #[serde(serialize_with = "serialize_msg_address_int")]
+    #[serde(with = "msg_address_int")]
     pub new_owner: MsgAddressInt,
 }
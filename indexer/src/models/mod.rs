@@ -1,2 +1,2 @@
 pub mod events;
-mod types;
+pub(crate) mod types;
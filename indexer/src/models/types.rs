@@ -2,26 +2,26 @@ use nekoton_abi::{BuildTokenValue, PackAbi, UnpackAbi, UnpackerError};
 use num::BigUint;
 use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::FromPrimitive;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use ton_abi::TokenValue;
 use ton_block::MsgAddressInt;
 use ton_types::UInt256;
 
-use crate::utils::{serialize_msg_address_int, serialize_uint256};
+use crate::utils::{msg_address_int, uint256};
 
-#[derive(UnpackAbi, PackAbi, PartialEq, Eq, Clone, Debug, Serialize)]
+#[derive(UnpackAbi, PackAbi, PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
 pub struct MarketOffer {
     #[abi]
-    #[serde(serialize_with = "serialize_msg_address_int")]
+    #[serde(with = "msg_address_int")]
     pub collection: MsgAddressInt,
     #[abi(name = "nftOwner")]
-    #[serde(serialize_with = "serialize_msg_address_int")]
+    #[serde(with = "msg_address_int")]
     pub nft_owner: MsgAddressInt,
     #[abi]
-    #[serde(serialize_with = "serialize_msg_address_int")]
+    #[serde(with = "msg_address_int")]
     pub nft: MsgAddressInt,
     #[abi]
-    #[serde(serialize_with = "serialize_msg_address_int")]
+    #[serde(with = "msg_address_int")]
     pub offer: MsgAddressInt,
     #[abi]
     pub price: u128,
@@ -31,7 +31,7 @@ pub struct MarketOffer {
     pub deploy_nonce: u64,
 }
 
-#[derive(FromPrimitive, ToPrimitive, PartialEq, Eq, Clone, Debug, Serialize)]
+#[derive(FromPrimitive, ToPrimitive, PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
 pub enum AuctionStatus {
     Created = 1,
     Active,
@@ -57,19 +57,19 @@ impl BuildTokenValue for AuctionStatus {
     }
 }
 
-#[derive(UnpackAbi, PackAbi, PartialEq, Eq, Clone, Debug, Serialize)]
+#[derive(UnpackAbi, PackAbi, PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
 pub struct AuctionDetails {
     #[abi(name = "auctionSubject")]
-    #[serde(serialize_with = "serialize_msg_address_int")]
+    #[serde(with = "msg_address_int")]
     pub auction_subject: MsgAddressInt,
     #[abi(name = "subjectOwner")]
-    #[serde(serialize_with = "serialize_msg_address_int")]
+    #[serde(with = "msg_address_int")]
     pub subject_owner: MsgAddressInt,
     #[abi(name = "paymentToken")]
-    #[serde(serialize_with = "serialize_msg_address_int")]
+    #[serde(with = "msg_address_int")]
     pub payment_token: MsgAddressInt,
     #[abi(name = "walletForBids")]
-    #[serde(serialize_with = "serialize_msg_address_int")]
+    #[serde(with = "msg_address_int")]
     pub wallet_for_bids: MsgAddressInt,
     #[abi(name = "startTime")]
     pub start_time: u64,
@@ -84,30 +84,30 @@ pub struct AuctionDetails {
     #[abi]
     pub status: AuctionStatus,
     #[abi]
-    #[serde(serialize_with = "serialize_msg_address_int")]
+    #[serde(with = "msg_address_int")]
     pub collection: MsgAddressInt,
 }
 
-#[derive(UnpackAbi, PackAbi, PartialEq, Eq, Clone, Debug, Serialize)]
+#[derive(UnpackAbi, PackAbi, PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
 pub struct DirectBuyInfo {
     #[abi]
-    #[serde(serialize_with = "serialize_msg_address_int")]
+    #[serde(with = "msg_address_int")]
     pub factory: MsgAddressInt,
     #[abi]
-    #[serde(serialize_with = "serialize_msg_address_int")]
+    #[serde(with = "msg_address_int")]
     pub creator: MsgAddressInt,
     #[abi(name = "spentToken")]
-    #[serde(serialize_with = "serialize_msg_address_int")]
+    #[serde(with = "msg_address_int")]
     pub spent_token: MsgAddressInt,
     #[abi]
-    #[serde(serialize_with = "serialize_msg_address_int")]
+    #[serde(with = "msg_address_int")]
     pub nft: MsgAddressInt,
     #[abi(name = "_timeTx")]
     pub _time_tx: u64,
     #[abi]
     pub _price: u128,
     #[abi(name = "spentWallet")]
-    #[serde(serialize_with = "serialize_msg_address_int")]
+    #[serde(with = "msg_address_int")]
     pub spent_wallet: MsgAddressInt,
     #[abi]
     pub status: u8,
@@ -118,23 +118,23 @@ pub struct DirectBuyInfo {
     #[abi(name = "endTimeBuy")]
     pub end_time_buy: u64,
     #[abi]
-    #[serde(serialize_with = "serialize_msg_address_int")]
+    #[serde(with = "msg_address_int")]
     pub collection: MsgAddressInt,
 }
 
-#[derive(UnpackAbi, PackAbi, PartialEq, Eq, Clone, Debug, Serialize)]
+#[derive(UnpackAbi, PackAbi, PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
 pub struct DirectSellInfo {
     #[abi]
-    #[serde(serialize_with = "serialize_msg_address_int")]
+    #[serde(with = "msg_address_int")]
     pub factory: MsgAddressInt,
     #[abi]
-    #[serde(serialize_with = "serialize_msg_address_int")]
+    #[serde(with = "msg_address_int")]
     pub creator: MsgAddressInt,
     #[abi]
-    #[serde(serialize_with = "serialize_msg_address_int")]
+    #[serde(with = "msg_address_int")]
     pub token: MsgAddressInt,
     #[abi]
-    #[serde(serialize_with = "serialize_msg_address_int")]
+    #[serde(with = "msg_address_int")]
     pub nft: MsgAddressInt,
     #[abi(name = "_timeTx")]
     pub _time_tx: u64,
@@ -145,16 +145,16 @@ pub struct DirectSellInfo {
     #[abi]
     pub _price: u128,
     #[abi]
-    #[serde(serialize_with = "serialize_msg_address_int")]
+    #[serde(with = "msg_address_int")]
     pub wallet: MsgAddressInt,
     #[abi]
     pub status: u8,
     #[abi]
-    #[serde(serialize_with = "serialize_msg_address_int")]
+    #[serde(with = "msg_address_int")]
     pub collection: MsgAddressInt,
 }
 
-#[derive(UnpackAbi, PackAbi, PartialEq, Eq, Clone, Debug, Serialize)]
+#[derive(UnpackAbi, PackAbi, PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
 pub struct MarketFee {
     #[abi]
     pub numerator: u32,
@@ -162,10 +162,10 @@ pub struct MarketFee {
     pub denominator: u32,
 }
 
-#[derive(UnpackAbi, PackAbi, PartialEq, Eq, Clone, Debug, Serialize)]
+#[derive(UnpackAbi, PackAbi, PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
 pub struct CollectionFeeInfo {
     #[abi(name = "codeHash")]
-    #[serde(serialize_with = "serialize_uint256")]
+    #[serde(with = "uint256")]
     pub code_hash: UInt256,
     #[abi(name = "codeDepth")]
     pub code_depth: u16,
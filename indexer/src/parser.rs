@@ -1,27 +1,108 @@
+use crate::abi::declare_abi::nft;
+use crate::backfill::{BackfillProgress, BackfillRange, LtWindow};
+use crate::commit::{CommitPolicy, PendingCommits};
+use crate::discovery::SeenContracts;
+use crate::floor_refresh::{self, FloorRefresh};
+use crate::health::Health;
+use crate::metrics;
 use crate::models::events::*;
+use crate::persistence::collection_cache::NftCollectionCache;
 use crate::persistence::collections_queue::CollectionsQueue;
 use crate::persistence::entities::*;
+use crate::persistence::retry::{with_retry, RetryPolicy};
+use crate::price::UsdConverter;
+use crate::rarity;
+use crate::reconnect::StreamReconnect;
+use crate::replay;
+use crate::resume::{checkpoint_of, ReplayGuard};
 use crate::settings;
-use crate::utils::DecodeContext;
+use crate::settings::config::{OffsetFallback, WhitelistMode};
+use crate::settings::runtime::{self, RuntimeConfig, SharedRuntimeConfig};
+use crate::sinks::EventSinks;
+use crate::utils::{DecodeContext, KeyInfo};
+use crate::whitelist::Whitelist;
 use anyhow::Result;
-use data_reader::PriceReader;
+use arc_swap::ArcSwap;
+use bigdecimal::BigDecimal;
+use chrono::NaiveDateTime;
+use data_reader::{PriceReader, RarityQueue};
 use futures::channel::mpsc::{Receiver, Sender};
-use futures::{future, SinkExt, StreamExt};
+use futures::{SinkExt, StreamExt};
+use indexer_api::ParserControl;
 use indexer_repo::batch::*;
-use indexer_repo::types::NftCollection;
+use indexer_repo::checkpoint::{get_checkpoint, save_checkpoint, Checkpoint};
+use indexer_repo::error::IndexerError;
+use indexer_repo::indexer_state::save_indexer_state;
+use indexer_repo::parked::{get_parked_events, get_parked_parsers};
+use indexer_repo::token_registry::{enqueue_tokens, get_tokens, normalize, Token};
+use indexer_repo::types::decoded::{
+    AuctionBid, DirectBuy, DirectSell, EventRecord, FailedEvent, MarketplaceFee, NftPriceHistory,
+    RawEventTransaction,
+};
+use indexer_repo::types::{
+    CollectionRoyaltyEarned, DirectSellState, NftCollection, NftPriceSource, NftTransferKind,
+};
 use nekoton_abi::transaction_parser::{ExtractedOwned, ParsedType};
 use nekoton_abi::UnpackAbiPlain;
 use sqlx::PgPool;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch, Mutex};
+use ton_block::{CommonMsgInfo, Serializable};
 use transaction_buffer::models::{BufferedConsumerChannels, RawTransaction};
 
 const EVENTS_PER_ITERATION: usize = 1000;
+const DEFAULT_DB_MAX_RETRIES: u32 = 5;
+const DEFAULT_DB_RETRY_BASE_DELAY_MS: u64 = 100;
+const DEFAULT_RARITY_INTERVAL_SECS: u64 = 300;
+const DEFAULT_FLOOR_REFRESH_INTERVAL_SECS: u64 = 10;
+const DEFAULT_PIPELINE_DEPTH: usize = 2;
+/// Queued batches the writer saves in a single database transaction at most
+const MAX_MERGED_BATCHES: usize = 8;
+/// Parked events of a resumed parser applied per batch at most
+const UNPARKED_PER_BATCH: i64 = 1000;
 
 pub async fn start_parsing(
     config: settings::config::Config,
     pg_pool: PgPool,
     price_reader: Arc<PriceReader>,
-) -> Result<()> {
+    parser_control: ParserControl,
+    rarity_queue: RarityQueue,
+    health: Health,
+    shutdown: watch::Receiver<bool>,
+) -> Result<(), IndexerError> {
+    // Backfill reads the topic from the start under its own consumer group, so the live
+    // group's offsets are untouched. Replayed rows that are already stored are skipped
+    // by the `on conflict` clauses, which lets both modes run side by side
+    let backfill = config.backfill_range();
+    // Reloads are compared against the config as loaded, not the backfill overrides
+    let startup_config = config.clone();
+    let config = match backfill {
+        Some(range) => {
+            log::info!(
+                "Backfill mode: indexing transactions from {} to {}",
+                range.from_ts,
+                range.to_ts
+            );
+            settings::config::Config {
+                kafka_consumer_group: format!("{}-backfill", config.kafka_consumer_group),
+                kafka_offset_fallback: Some(OffsetFallback::Earliest),
+                ..config
+            }
+        }
+        None => config,
+    };
+
+    let lt_window = config.lt_window();
+    if let Some(window) = &lt_window {
+        log::info!(
+            "Audit window: persisting only transactions with lt from {:?} to {:?}",
+            window.from_lt,
+            window.to_lt
+        );
+    }
+
     let BufferedConsumerChannels {
         rx_parsed_events,
         tx_commit,
@@ -29,33 +110,301 @@ pub async fn start_parsing(
     } = settings::init_transaction_buffer(&config, &pg_pool).await?;
 
     log::info!("Connected to kafka");
+    health.set_stream_connected();
+
+    let seen_contracts = config.discovery_window_secs.map(|secs| {
+        log::info!("Discovery mode: reporting seen contracts after {secs}s");
+        SeenContracts::new(Duration::from_secs(secs))
+    });
+
+    let runtime_config: SharedRuntimeConfig =
+        Arc::new(ArcSwap::from_pointee(RuntimeConfig::from(&config)));
+    tokio::spawn(runtime::watch_sighup(
+        startup_config,
+        runtime_config.clone(),
+    ));
+
+    // Replayed events were already delivered by the live indexer
+    let sinks = match backfill {
+        Some(_) => EventSinks::default(),
+        None => EventSinks::from_config(&config, &pg_pool)
+            .await
+            .map_err(|e| IndexerError::Config(format!("{e:#}")))?,
+    };
 
-    tokio::spawn(run_nft_indexer(
+    // Without a checkpoint a crash replays whatever was saved and not committed yet
+    let commit_policy = match backfill {
+        Some(_) => CommitPolicy::default(),
+        None => CommitPolicy::new(
+            config.commit_max_transactions,
+            config.commit_max_delay_ms.map(Duration::from_millis),
+        )
+        .map_err(|e| IndexerError::Config(format!("{e:#}")))?,
+    };
+
+    tokio::spawn(rarity::run(
+        pg_pool.clone(),
+        rarity_queue.clone(),
+        Duration::from_secs(
+            config
+                .rarity_interval_secs
+                .unwrap_or(DEFAULT_RARITY_INTERVAL_SECS),
+        ),
+        shutdown.clone(),
+    ));
+    let floor_refresh = FloorRefresh::default();
+    tokio::spawn(floor_refresh::run(
+        pg_pool.clone(),
+        floor_refresh.clone(),
+        Duration::from_secs(
+            config
+                .floor_refresh_interval_secs
+                .unwrap_or(DEFAULT_FLOOR_REFRESH_INTERVAL_SECS),
+        ),
+        shutdown.clone(),
+    ));
+
+    let reconnect = StreamReconnect::new(&config, &pg_pool);
+    let indexer = tokio::spawn(run_nft_indexer(
         rx_parsed_events,
         tx_commit,
+        reconnect,
         pg_pool,
-        price_reader,
+        UsdConverter::new(price_reader),
+        seen_contracts,
+        runtime_config,
+        config.strict_mode.unwrap_or_default(),
+        config.store_raw_transactions.unwrap_or_default(),
+        Whitelist::new(config.whitelist_mode.unwrap_or_default()),
+        Duration::from_secs(config.finality_delay_secs.unwrap_or_default()),
+        sinks,
+        rarity_queue,
+        floor_refresh,
+        parser_control,
+        RetryPolicy {
+            max_retries: config.db_max_retries.unwrap_or(DEFAULT_DB_MAX_RETRIES),
+            base_delay: Duration::from_millis(
+                config
+                    .db_retry_base_delay_ms
+                    .unwrap_or(DEFAULT_DB_RETRY_BASE_DELAY_MS),
+            ),
+        },
+        config.pipeline_depth.unwrap_or(DEFAULT_PIPELINE_DEPTH),
+        commit_policy,
+        backfill,
+        lt_window,
+        health,
+        shutdown,
     ));
 
-    notify_for_services.notified().await;
+    // A backfill stops at its end timestamp and doesn't wait for the buffer to catch up
+    if backfill.is_none() {
+        notify_for_services.notified().await;
+    }
 
-    future::pending().await
+    indexer
+        .await
+        .map_err(|e| IndexerError::Consumer(e.to_string()))?
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn run_nft_indexer(
     mut rx_raw_transactions: Receiver<Vec<(Vec<ExtractedOwned>, RawTransaction)>>,
-    mut tx_commit: Sender<()>,
+    tx_commit: Sender<()>,
+    mut reconnect: StreamReconnect,
     pool: PgPool,
-    price_reader: Arc<PriceReader>,
-) {
-    log::info!("Start nft indexer...");
+    usd_converter: UsdConverter,
+    mut seen_contracts: Option<SeenContracts>,
+    runtime_config: SharedRuntimeConfig,
+    strict_mode: bool,
+    store_raw_transactions: bool,
+    mut whitelist: Whitelist,
+    finality_delay: Duration,
+    sinks: EventSinks,
+    rarity_queue: RarityQueue,
+    floor_refresh: FloorRefresh,
+    parser_control: ParserControl,
+    retry_policy: RetryPolicy,
+    pipeline_depth: usize,
+    commit_policy: CommitPolicy,
+    backfill: Option<BackfillRange>,
+    lt_window: Option<LtWindow>,
+    health: Health,
+    mut shutdown: watch::Receiver<bool>,
+) -> Result<(), IndexerError> {
+    log::info!(
+        "Start nft indexer (strict mode: {strict_mode}, whitelist: {:?})...",
+        whitelist.mode()
+    );
+    if commit_policy.is_batching() {
+        log::info!(
+            "Committing offsets every {:?} transactions or {:?}",
+            commit_policy.max_transactions,
+            commit_policy.max_delay
+        );
+    }
 
-    let mut collection_queue = CollectionsQueue::new(pool.clone()).await;
+    // Events of a parser stay parked behind its parked ones across restarts
+    for parser in get_parked_parsers(&pool)
+        .await
+        .expect("Failed to read the parked events")
+    {
+        log::warn!("Parser {parser} has parked events");
+        parser_control.set_parked(&parser, true);
+    }
+
+    let writer = BatchWriter {
+        pool: pool.clone(),
+        usd_converter,
+        collection_queue: Mutex::new(CollectionsQueue::new(pool.clone()).await),
+        collection_cache: NftCollectionCache::default(),
+        sinks,
+        rarity_queue,
+        floor_refresh,
+        retry_policy,
+        commit_policy,
+        parser_control: parser_control.clone(),
+        health: health.clone(),
+    };
+    let (mut tx_decoded, rx_decoded) = mpsc::channel(pipeline_depth.max(1));
+    let mut writer = tokio::spawn(writer.run(rx_decoded, tx_commit));
+
+    // The checkpoint tracks the live stream, a backfill neither skips by it nor moves it
+    let checkpoint = match backfill {
+        Some(_) => None,
+        None => get_checkpoint(&pool)
+            .await
+            .expect("Failed to read the indexer checkpoint"),
+    };
+    let mut backfill_progress = backfill.map(BackfillProgress::new);
+    if let Some(c) = &checkpoint {
+        log::info!(
+            "Resuming after transaction {} (timestamp: {}, lt: {})",
+            c.tx_hash,
+            c.tx_timestamp,
+            c.tx_lt
+        );
+        health.set_committed_lt(c.tx_lt);
+    }
+    let mut replay_guard = ReplayGuard::new(checkpoint);
+
+    loop {
+        // Up to `pipeline_depth` decoded batches wait for the writer, decoding the next
+        // one overlaps with saving the previous ones
+        let mut message = tokio::select! {
+            biased;
+            Ok(()) = shutdown.changed() => break,
+            message = rx_raw_transactions.next() => match message {
+                Some(message) => message,
+                None => {
+                    log::warn!("Transactions stream terminated.");
+                    health.set_stream_disconnected();
+
+                    // Batches of the terminated stream are saved first, acknowledging them
+                    // to the new consumer would commit its offsets before its own batches
+                    drop(tx_decoded);
+                    let stopped = writer.await.expect("Batch writer failed");
+
+                    let Some(channels) = reconnect.connect(&mut shutdown).await? else {
+                        log_stopped();
+                        return Ok(());
+                    };
+                    log::info!("Reconnected to kafka");
+                    health.set_stream_connected();
+
+                    let (new_tx_decoded, rx_decoded) = mpsc::channel(pipeline_depth.max(1));
+                    tx_decoded = new_tx_decoded;
+                    writer = tokio::spawn(stopped.run(rx_decoded, channels.tx_commit));
+                    rx_raw_transactions = channels.rx_parsed_events;
+
+                    // The new consumer resumes from the committed offsets, anything it
+                    // redelivers up to the checkpoint is skipped again
+                    if backfill.is_none() {
+                        replay_guard = ReplayGuard::new(
+                            get_checkpoint(&pool)
+                                .await
+                                .expect("Failed to read the indexer checkpoint"),
+                        );
+                    }
+                    continue;
+                }
+            },
+        };
+        reconnect.resumed();
+        let transactions = message.len();
+
+        let redelivered = replay_guard.retain_new(&mut message);
+        if redelivered > 0 {
+            log::info!(
+                "Skipped {redelivered} transactions already committed before the checkpoint"
+            );
+        }
+
+        let mut backfill_done = false;
+        if let Some(range) = &backfill {
+            backfill_done = message
+                .iter()
+                .any(|(_, tx)| range.is_reached_by(tx.data.get_timestamp()));
+            message.retain(|(_, tx)| range.contains(tx.data.get_timestamp()));
+        }
+
+        let next_checkpoint = checkpoint_of(&message).filter(|_| backfill.is_none());
+
+        // Transactions outside the audit window still move the checkpoint and are
+        // committed with the batch
+        if let Some(window) = &lt_window {
+            let outside = window.retain(&mut message);
+            if outside > 0 {
+                log::debug!("Skipped {outside} transactions outside the audit window");
+            }
+        }
+
+        let newest = message.iter().map(|(_, tx)| tx.data.get_timestamp()).max();
+        if let Some(progress) = backfill_progress.as_mut() {
+            progress.record(message.len(), newest);
+        }
+        if let Some(wait) =
+            newest.and_then(|ts| finality_wait(ts, chrono::Utc::now().timestamp(), finality_delay))
+        {
+            log::debug!("Waiting {}s for the batch to become final", wait.as_secs());
+            // The batch is not saved, the next consumer reads it again
+            tokio::select! {
+                biased;
+                Ok(()) = shutdown.changed() => break,
+                _ = tokio::time::sleep(wait) => {}
+            }
+        }
+
+        let accounts = message
+            .iter()
+            .map(|(_, tx)| tx.data.get_account())
+            .collect::<Vec<_>>();
+        let emitted = message
+            .iter()
+            .zip(&accounts)
+            .flat_map(|((out, _), account)| {
+                out.iter().map(move |e| (e.name.as_str(), account.as_str()))
+            })
+            .collect::<Vec<_>>();
+        whitelist
+            .prepare(&pool, &emitted)
+            .await
+            .expect("Failed to load the whitelist");
 
-    while let Some(message) = rx_raw_transactions.next().await {
         let mut data = Vec::with_capacity(EVENTS_PER_ITERATION * 3);
+        let runtime = runtime_config.load_full();
+
+        for ((out, tx), account) in message.into_iter().zip(accounts) {
+            metrics::TRANSACTIONS_PROCESSED.inc();
+            let timer = metrics::HANDLER_LATENCY.start_timer();
+            let span = tracing::info_span!(
+                "transaction",
+                account = %account,
+                lt = tx.data.logical_time(),
+                hash = %tx.data.get_hash().map(hex::encode).unwrap_or_default(),
+            );
+            let _span = span.enter();
 
-        for (out, tx) in message {
             let mut events = Vec::new();
             let mut function_inputs = Vec::new();
 
@@ -70,48 +419,258 @@ pub async fn run_nft_indexer(
                     _ => {}
                 }
             }
+            order_by_emission(&mut events);
+            dedup_events(&mut events);
+            let tx_decoded_from = data.len();
 
-            for event in events {
+            for event in events
+                .into_iter()
+                .filter(|e| is_whitelisted(&whitelist, &e.name, &account))
+            {
                 let ctx = DecodeContext {
                     tx_data: tx.data.clone(),
                     function_inputs: function_inputs.clone(),
                     message_hash: event.message_hash,
+                    max_listing_lifetime_secs: runtime.max_listing_lifetime_secs,
                 };
 
-                if let Ok(Some(entity)) = unpack_entity(&event) {
-                    if let Ok(decoded) = entity.decode(&ctx) {
-                        data.push(decoded);
-                    } else {
-                        log::error!("Error while decode");
-                    }
-                    if let Ok(event) = entity.decode_event(&ctx) {
-                        data.push(event);
-                    } else {
-                        log::error!("Error while decode_event");
+                let entity = unpack_entity(&event);
+                if let Some(seen) = seen_contracts.as_mut() {
+                    seen.record(&account, &event.name, matches!(entity, Ok(Some(_))));
+                }
+
+                match entity {
+                    Ok(Some(entity)) => {
+                        metrics::EVENTS_MATCHED
+                            .with_label_values(&[parser_of(&event.name)])
+                            .inc();
+
+                        let decoded = decode_entity(entity.as_ref(), &event, &ctx, strict_mode)
+                            .into_iter()
+                            .filter(|decoded| !is_blocked_event(&runtime, decoded))
+                            .collect::<Vec<_>>();
+                        let parser = parser_of(&event.name);
+                        if parser_control.is_active(parser) {
+                            data.extend(decoded);
+                        } else {
+                            whitelist.record_deployed(&decoded);
+                            data.extend(decoded.into_iter().filter_map(|d| park(parser, d)));
+                        }
                     }
+                    Ok(None) => log::debug!(
+                        "Extracted {} of {account} has no handler, skipping",
+                        event.name
+                    ),
+                    Err(e) => data.extend(unpack_failure(&event, &ctx, e)),
                 }
             }
+
+            whitelist.record_deployed(&data[tx_decoded_from..]);
+
+            if store_raw_transactions {
+                let raw_transactions = raw_transaction_records(&data[tx_decoded_from..], &tx.data);
+                data.extend(raw_transactions);
+            }
+
+            timer.observe_duration();
         }
 
-        let now = std::time::Instant::now();
-        save_to_db(&pool, &price_reader, data, &mut collection_queue)
+        let batch = DecodedBatch {
+            data,
+            checkpoint: next_checkpoint,
+            runtime,
+            transactions,
+        };
+        if tx_decoded.send(batch).await.is_err() {
+            break;
+        }
+
+        if backfill_done {
+            log::info!("Backfill reached its end timestamp");
+            break;
+        }
+
+        if let Some(seen) = seen_contracts.as_ref().filter(|s| s.is_finished()) {
+            log::info!("{}", seen.report());
+            seen_contracts = None;
+        }
+    }
+
+    // Batches already decoded are still saved and committed
+    drop(tx_decoded);
+    writer.await.expect("Batch writer failed");
+    log_stopped();
+
+    Ok(())
+}
+
+fn log_stopped() {
+    log::info!(
+        "METRIC | Indexer stopped, processed {} transactions, {} parse failures, {} commit failures",
+        metrics::TRANSACTIONS_PROCESSED.get(),
+        metrics::PARSE_FAILURES.get(),
+        metrics::COMMIT_FAILURES.get()
+    );
+}
+
+/// Decoded events of one consumed batch, committed to the consumer once saved
+struct DecodedBatch {
+    data: Vec<Decoded>,
+    checkpoint: Option<Checkpoint>,
+    runtime: Arc<RuntimeConfig>,
+    /// Consumed transactions, including the skipped ones
+    transactions: usize,
+}
+
+/// Persistence stage of the indexer, saves decoded batches in the order they were
+/// consumed. There is a single writer: batches must be applied in logical time order
+/// and the checkpoint only moves forward
+struct BatchWriter {
+    pool: PgPool,
+    usd_converter: UsdConverter,
+    collection_queue: Mutex<CollectionsQueue>,
+    collection_cache: NftCollectionCache,
+    sinks: EventSinks,
+    rarity_queue: RarityQueue,
+    floor_refresh: FloorRefresh,
+    retry_policy: RetryPolicy,
+    commit_policy: CommitPolicy,
+    parser_control: ParserControl,
+    health: Health,
+}
+
+impl BatchWriter {
+    /// Saves batches until the decoder is gone. Batches queued behind the current one
+    /// are merged into its database transaction, and each is acknowledged to the
+    /// consumer only once that transaction committed, batched by the commit policy.
+    /// Returns the writer to be run again for a reconnected stream
+    async fn run(
+        self,
+        mut rx_decoded: mpsc::Receiver<DecodedBatch>,
+        mut tx_commit: Sender<()>,
+    ) -> Self {
+        let mut pending = PendingCommits::default();
+        loop {
+            let next = match self.commit_policy.deadline(&pending) {
+                Some(deadline) => {
+                    match tokio::time::timeout_at(deadline, rx_decoded.recv()).await {
+                        Ok(next) => next,
+                        Err(_) => {
+                            pending.flush(&mut tx_commit).await;
+                            continue;
+                        }
+                    }
+                }
+                None => rx_decoded.recv().await,
+            };
+            let Some(first) = next else {
+                break;
+            };
+
+            let mut batches = vec![first];
+            while batches.len() < MAX_MERGED_BATCHES {
+                match rx_decoded.try_recv() {
+                    Ok(batch) => batches.push(batch),
+                    Err(_) => break,
+                }
+            }
+
+            let acknowledged = batches.len();
+            let DecodedBatch {
+                data,
+                checkpoint,
+                runtime,
+                transactions,
+            } = merge_batches(batches);
+
+            let now = std::time::Instant::now();
+            let (data, drained) = with_retry(&self.retry_policy, || {
+                unpark_events(&self.pool, &self.parser_control, data.clone(), &runtime)
+            })
+            .await
+            .expect("Error loading parked events");
+            with_retry(&self.retry_policy, || {
+                save_to_db(
+                    &self.pool,
+                    &self.usd_converter,
+                    data.clone(),
+                    &self.collection_queue,
+                    &self.collection_cache,
+                    &runtime,
+                    &self.sinks,
+                    &self.rarity_queue,
+                    &self.floor_refresh,
+                    checkpoint.as_ref(),
+                )
+            })
             .await
             .expect("Error saving to DB");
-        let elapsed = now.elapsed();
+            update_parked(&self.parser_control, &data, &drained);
+            if let Some(c) = &checkpoint {
+                self.health.set_committed_lt(c.tx_lt);
+            }
+
+            log::info!(
+                "METRIC | Saving to db, {} batches, elapsed {}ms",
+                acknowledged,
+                now.elapsed().as_millis()
+            );
+
+            pending.add(
+                acknowledged,
+                transactions,
+                checkpoint.as_ref().map(|c| c.tx_lt),
+            );
+            if self
+                .commit_policy
+                .is_due(&pending, tokio::time::Instant::now())
+            {
+                pending.flush(&mut tx_commit).await;
+            }
+        }
 
-        log::info!("METRIC | Saving to db, elapsed {}ms", elapsed.as_millis());
+        // The decoder is gone on shutdown and when the stream terminated, everything
+        // saved is acknowledged before the writer stops
+        pending.flush(&mut tx_commit).await;
+        if let Some(lt) = pending.committed_lt() {
+            log::info!("Batch writer stopped, offsets committed up to lt {lt}");
+        }
 
-        tx_commit.send(()).await.expect("dead commit sender");
+        self
     }
+}
+
+/// Events of consecutive batches in consumption order, with the newest checkpoint
+/// and runtime config
+fn merge_batches(batches: Vec<DecodedBatch>) -> DecodedBatch {
+    let checkpoint = batches.iter().rev().find_map(|b| b.checkpoint.clone());
+    let runtime = batches
+        .last()
+        .map(|b| b.runtime.clone())
+        .unwrap_or_default();
+    let transactions = batches.iter().map(|b| b.transactions).sum();
+    let data = batches.into_iter().flat_map(|b| b.data).collect();
 
-    panic!("rip kafka consumer");
+    DecodedBatch {
+        data,
+        checkpoint,
+        runtime,
+        transactions,
+    }
 }
 
-async fn save_to_db(
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn save_to_db(
     pool: &PgPool,
-    price_reader: &PriceReader,
+    usd_converter: &UsdConverter,
     data: Vec<Decoded>,
-    collections_queue: &mut CollectionsQueue,
+    collections_queue: &Mutex<CollectionsQueue>,
+    collection_cache: &NftCollectionCache,
+    runtime_config: &RuntimeConfig,
+    sinks: &EventSinks,
+    rarity_queue: &RarityQueue,
+    floor_refresh: &FloorRefresh,
+    checkpoint: Option<&Checkpoint>,
 ) -> Result<()> {
     let mut collections = Vec::with_capacity(EVENTS_PER_ITERATION);
     let mut nft_created = Vec::with_capacity(EVENTS_PER_ITERATION);
@@ -127,11 +686,16 @@ async fn save_to_db(
     let mut auc_cancelled = Vec::with_capacity(EVENTS_PER_ITERATION);
     let mut raw_events = Vec::with_capacity(EVENTS_PER_ITERATION);
     let mut fees_update = Vec::with_capacity(EVENTS_PER_ITERATION);
+    let mut marketplace_fees = Vec::with_capacity(EVENTS_PER_ITERATION);
     let mut direct_sell_deployed = Vec::with_capacity(EVENTS_PER_ITERATION);
     let mut direct_sell_state_changed = Vec::with_capacity(EVENTS_PER_ITERATION);
     let mut direct_buy_deployed = Vec::with_capacity(EVENTS_PER_ITERATION);
     let mut direct_buy_state_changed = Vec::with_capacity(EVENTS_PER_ITERATION);
     let mut deployed_offers = Vec::with_capacity(EVENTS_PER_ITERATION);
+    let mut failed_events = Vec::new();
+    let mut raw_transactions = Vec::new();
+    let mut parked_events = Vec::new();
+    let mut unparked_events = Vec::new();
 
     for element in data {
         match element {
@@ -159,6 +723,7 @@ async fn save_to_db(
             Decoded::AuctionCancelled(a) => auc_cancelled.push(a),
             Decoded::RawEventRecord(e) => raw_events.push(e),
             Decoded::AuctionRulesChanged(rules) => fees_update.push(rules),
+            Decoded::MarketFeeChanged(fee) => marketplace_fees.push(fee),
             Decoded::DirectSellDeployed((ds, offer)) => {
                 direct_sell_deployed.push(ds);
                 deployed_offers.push(offer);
@@ -179,6 +744,10 @@ async fn save_to_db(
                     prices.push(price.unwrap());
                 }
             }
+            Decoded::DecodeFailed(e) => failed_events.push(e),
+            Decoded::RawTransaction(t) => raw_transactions.push(t),
+            Decoded::ParkedEvent(e) => parked_events.push(e),
+            Decoded::UnparkedEvents(ids) => unparked_events.extend(ids),
             Decoded::ShouldSkip => (),
         }
     }
@@ -199,11 +768,15 @@ async fn save_to_db(
         auc_complete: {},
         auc_cancelled: {},
         fees_update: {},
+        marketplace_fees: {},
         direct_sell_deployed: {},
         direct_sell_state_changed: {},
         direct_buy_deployed: {},
         direct_buy_state_changed: {},
         deployed_offers: {},
+        failed_events: {},
+        parked_events: {},
+        unparked_events: {},
         "#,
         raw_events.len(),
         collections.len(),
@@ -219,25 +792,88 @@ async fn save_to_db(
         auc_complete.len(),
         auc_cancelled.len(),
         fees_update.len(),
+        marketplace_fees.len(),
         direct_sell_deployed.len(),
         direct_sell_state_changed.len(),
         direct_buy_deployed.len(),
         direct_buy_state_changed.len(),
         deployed_offers.len(),
+        failed_events.len(),
+        parked_events.len(),
+        unparked_events.len(),
     );
 
-    // IMPORTANT: Order matters!
+    // IMPORTANT: Order matters! All statements share one Postgres transaction, so they
+    // can't run concurrently anyway, and later ones read rows written earlier in it:
+    // offers before auctions and listings, auctions before their bids and prices.
 
-    collections_queue.add_collections(collections).await?;
+    collections_queue
+        .lock()
+        .await
+        .add_collections(collections)
+        .await?;
 
     let mut pg_pool_tx = pool.begin().await?;
 
+    let without_collection = direct_sell_deployed
+        .iter()
+        .filter(|ds| ds.collection.is_none())
+        .map(|ds| ds.nft.as_str())
+        .chain(
+            direct_buy_deployed
+                .iter()
+                .filter(|db| db.collection.is_none())
+                .map(|db| db.nft.as_str()),
+        )
+        .chain(
+            raw_events
+                .iter()
+                .filter(|e| e.collection.is_none())
+                .filter_map(|e| e.nft.as_deref()),
+        )
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    for nft in &nft_created {
+        collection_cache.insert(nft.address.clone(), nft.collection.clone());
+    }
+    if !without_collection.is_empty() {
+        let (mut nft_collections, missing) = collection_cache.lookup(&without_collection);
+        if !missing.is_empty() {
+            let found = get_nft_collections(&mut pg_pool_tx, &missing).await?;
+            for (nft, collection) in &found {
+                collection_cache.insert(nft.clone(), collection.clone());
+            }
+            nft_collections.extend(found);
+        }
+        fill_missing_collections(
+            &nft_collections,
+            &mut direct_sell_deployed,
+            &mut direct_buy_deployed,
+            &mut raw_events,
+        );
+    }
+
     if !fees_update.is_empty() {
         update_collection_fee(&mut pg_pool_tx, &fees_update).await?;
     }
 
+    if !marketplace_fees.is_empty() {
+        save_marketplace_fees(&mut pg_pool_tx, &marketplace_fees).await?;
+    }
+
     if !raw_events.is_empty() {
-        save_raw_event(&mut pg_pool_tx, &raw_events).await?;
+        save_raw_event(
+            &mut pg_pool_tx,
+            &raw_events,
+            runtime_config.raw_event_chunk_size,
+        )
+        .await?;
+        save_indexer_state(&mut pg_pool_tx, &raw_events).await?;
+    }
+
+    if !raw_transactions.is_empty() {
+        save_raw_transactions(&mut pg_pool_tx, &raw_transactions).await?;
     }
 
     if !nft_created.is_empty() {
@@ -250,10 +886,18 @@ async fn save_to_db(
 
     if !nft_owner_changed.is_empty() {
         save_nft_owner_changed(&mut pg_pool_tx, &mut nft_owner_changed).await?;
+        save_nft_transfer_history(&mut pg_pool_tx, NftTransferKind::Owner, &nft_owner_changed)
+            .await?;
     }
 
     if !nft_manager_changed.is_empty() {
         save_nft_manager_changed(&mut pg_pool_tx, &mut nft_manager_changed).await?;
+        save_nft_transfer_history(
+            &mut pg_pool_tx,
+            NftTransferKind::Manager,
+            &nft_manager_changed,
+        )
+        .await?;
     }
 
     if !deployed_offers.is_empty() {
@@ -268,6 +912,66 @@ async fn save_to_db(
         save_auc_active(&mut pg_pool_tx, &auc_active).await?;
     }
 
+    let auctions = auc_bid_placed
+        .iter()
+        .chain(auc_bid_declined.iter())
+        .map(|b| b.address.as_str())
+        .chain(
+            prices
+                .iter()
+                .filter(|p| p.source_type == NftPriceSource::AuctionBid)
+                .map(|p| p.source.as_str()),
+        )
+        .collect::<Vec<_>>();
+    if !auctions.is_empty() {
+        let auction_tokens = get_auction_price_tokens(&mut pg_pool_tx, &auctions).await?;
+        drop_mismatched_auction_tokens(&auction_tokens, &mut auc_bid_placed, &mut prices);
+        drop_mismatched_auction_tokens(&auction_tokens, &mut auc_bid_declined, &mut Vec::new());
+    }
+
+    let tokens = direct_sell_deployed
+        .iter()
+        .chain(direct_sell_state_changed.iter())
+        .map(|ds| ds.price_token.as_str())
+        .chain(
+            auc_bid_placed
+                .iter()
+                .chain(auc_bid_declined.iter())
+                .map(|b| b.price_token.as_str()),
+        )
+        .chain(prices.iter().map(|p| p.price_token.as_str()))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    if !tokens.is_empty() {
+        let registry = get_tokens(&mut pg_pool_tx, &tokens).await?;
+        for token in tokens.iter().filter(|t| !registry.contains_key(**t)) {
+            log::warn!("Token {token} is not in the token registry, keeping the raw amounts");
+        }
+        let decimals = |token: &str| registry.get(token).map(|t| t.decimals);
+
+        for ds in direct_sell_deployed
+            .iter_mut()
+            .chain(direct_sell_state_changed.iter_mut())
+        {
+            ds.price_normalized = Some(normalize(&ds.price, decimals(&ds.price_token)));
+        }
+        for bid in auc_bid_placed.iter_mut().chain(auc_bid_declined.iter_mut()) {
+            bid.bid_value_normalized = Some(normalize(&bid.bid_value, decimals(&bid.price_token)));
+        }
+
+        let unknown = fill_token_symbols(
+            &registry,
+            direct_sell_deployed
+                .iter_mut()
+                .chain(direct_sell_state_changed.iter_mut()),
+            &mut prices,
+        );
+        if !unknown.is_empty() {
+            enqueue_tokens(&mut pg_pool_tx, &unknown).await?;
+        }
+    }
+
     if !auc_bid_placed.is_empty() {
         save_auc_bid(&mut pg_pool_tx, &auc_bid_placed).await?;
         update_auc_maxmin(&mut pg_pool_tx, &auc_bid_placed).await?;
@@ -295,89 +999,686 @@ async fn save_to_db(
 
     if !direct_sell_state_changed.is_empty() {
         update_direct_sell_state(&mut pg_pool_tx, &mut direct_sell_state_changed).await?;
+
+        let listed = direct_sell_state_changed
+            .iter()
+            .filter(|ds| ds.state == DirectSellState::Active)
+            .map(|ds| ds.address.as_str())
+            .collect::<Vec<_>>();
+        if !listed.is_empty() {
+            link_relisted_direct_sells(
+                &mut pg_pool_tx,
+                &listed,
+                runtime_config.relist_window_secs as f64,
+            )
+            .await?;
+        }
     }
 
     if !direct_buy_state_changed.is_empty() {
         update_direct_buy_state(&mut pg_pool_tx, &mut direct_buy_state_changed).await?;
     }
 
+    if !nft_burned.is_empty() {
+        cancel_burned_nft_offers(&mut pg_pool_tx, &nft_burned).await?;
+    }
+
     if !prices.is_empty() {
         for price in prices.iter_mut() {
-            price.usd_price = price_reader
-                .get_current_usd_price(
+            price.usd_price = usd_converter
+                .to_usd(
                     price.price_token.as_str(),
+                    &price.price,
                     price.created_at.timestamp() as u64,
                 )
                 .await;
         }
-        save_price_history(&mut pg_pool_tx, &prices).await?;
+
+        let sources = prices.iter().map(|p| p.source.as_str()).collect::<Vec<_>>();
+        let fees = get_marketplace_fees(&mut pg_pool_tx, &sources).await?;
+        let whitelisted = get_whitelisted_offers(&mut pg_pool_tx, &sources).await?;
+        apply_marketplace_fees(&fees, &mut prices);
+
+        let collections = prices
+            .iter()
+            .map(|p| p.collection.as_str())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        let rates = get_collection_royalty_rates(&mut pg_pool_tx, &collections).await?;
+        apply_royalties(&rates, &whitelisted, &mut prices);
+
+        // Saving also counts the sales into the daily volume. Replayed sales are already
+        // stored and accrued their royalty, a sale delivered twice within the batch is
+        // stored and accrues it once
+        let mut inserted = save_price_history(&mut pg_pool_tx, &prices).await?;
+        prices.retain(|p| inserted.remove(&(p.source.clone(), p.source_type, p.created_at)));
+
+        let royalties = royalties_earned(&prices);
+        if !royalties.is_empty() {
+            save_collection_royalty_earned(&mut pg_pool_tx, &royalties).await?;
+        }
     }
 
-    pg_pool_tx.commit().await?;
+    if !failed_events.is_empty() {
+        save_failed_events(&mut pg_pool_tx, &failed_events).await?;
+    }
+
+    if !parked_events.is_empty() {
+        save_parked_events(&mut pg_pool_tx, &parked_events).await?;
+    }
+
+    if !unparked_events.is_empty() {
+        delete_parked_events(&mut pg_pool_tx, &unparked_events).await?;
+    }
+
+    if let Some(checkpoint) = checkpoint {
+        save_checkpoint(&mut pg_pool_tx, checkpoint).await?;
+    }
+
+    if let Err(e) = pg_pool_tx.commit().await {
+        metrics::COMMIT_FAILURES.inc();
+        return Err(e.into());
+    }
+
+    sinks.publish(&raw_events);
+    rarity_queue.mark_supply_changes(&raw_events);
+
+    if !direct_sell_deployed.is_empty() || !direct_sell_state_changed.is_empty() {
+        floor_refresh.mark();
+    }
 
     Ok(())
 }
 
-macro_rules! try_unpack_entity {
-    ($msg:ident, $($entity:ty),+) => {
-        match $msg.name.as_str() {
-            $(stringify!($entity) => Ok(
-                Some(Box::new(UnpackAbiPlain::<$entity>::unpack($msg.tokens.clone())?))
-            ),)+
-            _ => Ok(None),
+/// Parser group of an event, see `indexer_api::PARSERS`
+pub(crate) fn parser_of(event_name: &str) -> &'static str {
+    match event_name {
+        "AuctionDeployed" | "AuctionDeclined" | "AuctionCreated" | "AuctionActive"
+        | "BidPlaced" | "BidDeclined" | "AuctionComplete" | "AuctionCancelled" => "auction",
+        "DirectBuyStateChanged" | "DirectBuyDeployed" | "DirectBuyDeclined" => "direct_buy",
+        "DirectSellStateChanged" | "DirectSellDeployed" | "DirectSellDeclined" => "direct_sell",
+        "ManagerChanged" | "OwnerChanged" => "nft",
+        "NftCreated" | "NftBurned" => "collection",
+        _ => "common",
+    }
+}
+
+/// Serialized transaction for every raw event decoded from it
+fn raw_transaction_records(decoded: &[Decoded], tx: &ton_block::Transaction) -> Vec<Decoded> {
+    let hashes = decoded
+        .iter()
+        .filter_map(|d| match d {
+            Decoded::RawEventRecord(record) => Some(record.message_hash.clone()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if hashes.is_empty() {
+        return Vec::new();
+    }
+
+    match tx.write_to_bytes() {
+        Ok(boc) => hashes
+            .into_iter()
+            .map(|message_hash| {
+                Decoded::RawTransaction(RawEventTransaction {
+                    message_hash,
+                    boc: boc.clone(),
+                })
+            })
+            .collect(),
+        Err(e) => {
+            log::error!(
+                "Failed to serialize transaction {}: {:?}",
+                tx.logical_time(),
+                e
+            );
+            Vec::new()
         }
-    };
+    }
+}
+
+/// Events of a transaction in the order its out messages were created, which the
+/// extractor doesn't guarantee. A factory deploy and the events of the deployed
+/// contract are never in one transaction, all events of a transaction come from its
+/// account; across transactions `save_to_db` saves deploys before their dependents.
+/// The sort is stable, extractables of one message keep their order
+fn order_by_emission(events: &mut [ExtractedOwned]) {
+    events.sort_by_key(|e| match e.message.header() {
+        CommonMsgInfo::ExtOutMsgInfo(header) => header.created_lt,
+        CommonMsgInfo::IntMsgInfo(header) => header.created_lt,
+        CommonMsgInfo::ExtInMsgInfo(_) => 0,
+    });
+}
+
+/// Extractables of ABIs that share an event signature (`OwnershipTransferred`, the
+/// marketplace fee events) all match the same emitted message. Only one copy is kept,
+/// the entity is chosen by name and attributed to the emitting account anyway
+fn dedup_events(events: &mut Vec<ExtractedOwned>) {
+    let mut seen = HashSet::with_capacity(events.len());
+    events.retain(|e| seen.insert((e.message_hash, e.name.clone())));
+}
+
+/// Events of a paused parser are parked as their raw record, the entity is decoded
+/// again from it once the parser is resumed. Failures are dead-lettered as usual
+pub(crate) fn park(parser: &'static str, decoded: Decoded) -> Option<Decoded> {
+    match decoded {
+        Decoded::RawEventRecord(record) => Some(Decoded::ParkedEvent((parser, record))),
+        failed @ Decoded::DecodeFailed(_) => Some(failed),
+        _ => None,
+    }
+}
+
+/// Entity and raw record of a parked event, as the decoder would have produced them
+fn apply_parked(event: EventRecord, runtime_config: &RuntimeConfig) -> Vec<Decoded> {
+    match replay::redecode(&event, runtime_config) {
+        Ok(entity) => vec![entity, Decoded::RawEventRecord(event)],
+        Err(e) => {
+            log::error!(
+                "Failed to decode parked event {} (lt: {}): {e:#}",
+                event.message_hash,
+                event.created_lt
+            );
+            vec![Decoded::RawEventRecord(event)]
+        }
+    }
+}
+
+/// Puts the oldest parked events of every resumed parser ahead of the batch, they were
+/// consumed before it. Events the decoder parked while the writer was still behind are
+/// applied in place once their parser has nothing left in the table. Returns the
+/// parsers whose parked events are all applied with this batch
+pub(crate) async fn unpark_events(
+    pool: &PgPool,
+    parser_control: &ParserControl,
+    data: Vec<Decoded>,
+    runtime_config: &RuntimeConfig,
+) -> Result<(Vec<Decoded>, Vec<&'static str>)> {
+    let mut unparked = Vec::new();
+    let mut drained = Vec::new();
+
+    for parser in parser_control
+        .parked()
+        .into_iter()
+        .filter(|p| !parser_control.is_paused(p))
+    {
+        let parked = get_parked_events(pool, parser, UNPARKED_PER_BATCH).await?;
+        if parked.len() < UNPARKED_PER_BATCH as usize {
+            drained.push(parser);
+        }
+        if parked.is_empty() {
+            continue;
+        }
+
+        log::info!("Applying {} parked events of {parser}", parked.len());
+        let ids = parked.iter().map(|(id, _)| *id).collect();
+        for (_, event) in parked {
+            unparked.extend(apply_parked(event, runtime_config));
+        }
+        unparked.push(Decoded::UnparkedEvents(ids));
+    }
+
+    for decoded in data {
+        match decoded {
+            Decoded::ParkedEvent((parser, event))
+                if !parser_control.is_paused(parser)
+                    && (!parser_control.has_parked(parser) || drained.contains(&parser)) =>
+            {
+                unparked.extend(apply_parked(event, runtime_config));
+            }
+            decoded => unparked.push(decoded),
+        }
+    }
+
+    Ok((unparked, drained))
+}
+
+/// Parked flags once a batch is saved: raised for parsers it parked events of, cleared
+/// for the drained ones it didn't
+pub(crate) fn update_parked(
+    parser_control: &ParserControl,
+    data: &[Decoded],
+    drained: &[&'static str],
+) {
+    let parked = data
+        .iter()
+        .filter_map(|d| match d {
+            Decoded::ParkedEvent((parser, _)) => Some(*parser),
+            _ => None,
+        })
+        .collect::<HashSet<_>>();
+
+    for parser in &parked {
+        parser_control.set_parked(parser, true);
+    }
+    for parser in drained.iter().filter(|p| !parked.contains(*p)) {
+        parser_control.set_parked(parser, false);
+    }
+}
+
+/// Events of contracts missing from the whitelist are always counted, but only
+/// skipped in enforce mode
+fn is_whitelisted(whitelist: &Whitelist, event_name: &str, account: &str) -> bool {
+    if whitelist.allows(event_name, account) {
+        return true;
+    }
+
+    metrics::EVENTS_NOT_WHITELISTED.inc();
+    match whitelist.mode() {
+        WhitelistMode::Enforce => {
+            log::debug!("Skipping {event_name} of not whitelisted contract {account}");
+            false
+        }
+        _ => {
+            log::warn!("{event_name} emitted by not whitelisted contract {account}");
+            true
+        }
+    }
+}
+
+/// Time left until a transaction made at `tx_timestamp` is older than `delay`
+fn finality_wait(tx_timestamp: i64, now: i64, delay: Duration) -> Option<Duration> {
+    let final_at = tx_timestamp.saturating_add(delay.as_secs() as i64);
+    (final_at > now).then(|| Duration::from_secs((final_at - now) as u64))
+}
+
+/// Entity and raw event records of an unpacked event, failures are dead-lettered
+pub(crate) fn decode_entity(
+    entity: &dyn Decode,
+    event: &ExtractedOwned,
+    ctx: &DecodeContext,
+    strict_mode: bool,
+) -> Vec<Decoded> {
+    let mut decoded = Vec::with_capacity(2);
+
+    match entity.decode(ctx) {
+        Ok(entity) => decoded.push(entity),
+        Err(e) => decoded.push(report_decode_failure(
+            strict_mode,
+            event,
+            ctx,
+            format!("Error while decode: {:#?}", e),
+        )),
+    }
+    match entity.decode_event(ctx) {
+        Ok(raw_event) => {
+            if matches!(&raw_event, Decoded::RawEventRecord(r) if r.raw_data.is_null()) {
+                decoded.push(report_decode_failure(
+                    strict_mode,
+                    event,
+                    ctx,
+                    "Failed to serialize raw data".to_string(),
+                ));
+            }
+            decoded.push(raw_event);
+        }
+        Err(e) => decoded.push(report_decode_failure(
+            strict_mode,
+            event,
+            ctx,
+            format!("Error while decode_event: {:#?}", e),
+        )),
+    }
+
+    decoded
+}
+
+/// Logs the failure and returns its `failed_events` record. In strict mode the process
+/// stops before the batch offset is committed, so the failed transaction is consumed
+/// again after a fix/restart
+fn report_decode_failure(
+    strict_mode: bool,
+    event: &ExtractedOwned,
+    ctx: &DecodeContext,
+    message: String,
+) -> Decoded {
+    let failed = dead_letter(event, ctx, message);
+    if strict_mode {
+        panic!(
+            "Strict mode: {} of {} (message hash: {})",
+            failed.error, failed.event_name, failed.message_hash
+        );
+    }
+
+    Decoded::DecodeFailed(failed)
 }
 
-fn unpack_entity(event: &ExtractedOwned) -> Result<Option<Box<dyn Decode>>> {
-    try_unpack_entity!(
+/// Record of an event that doesn't unpack. Unpack errors are dead-lettered in strict
+/// mode as well, they never halt the indexer. The `NftCreated` of Nft.abi is skipped:
+/// it shares the name with the one of Collection.abi and never unpacks
+pub(crate) fn unpack_failure(
+    event: &ExtractedOwned,
+    ctx: &DecodeContext,
+    error: anyhow::Error,
+) -> Option<Decoded> {
+    if is_nft_abi_nft_created(event) {
+        log::debug!("Skipping NftCreated of Nft.abi");
+        return None;
+    }
+
+    Some(Decoded::DecodeFailed(dead_letter(
         event,
-        /* FactoryAuction */
-        AuctionDeployed,
-        AuctionDeclined,
-        /* Auction */
-        AuctionCreated,
-        AuctionActive,
-        BidPlaced,
-        BidDeclined,
-        AuctionComplete,
-        AuctionCancelled,
-        /* Collection */
-        NftCreated,
-        NftBurned,
-        /* DirectBuy */
-        DirectBuyStateChanged,
-        /* DirectSell */
-        DirectSellStateChanged,
-        /* FactoryDirectBuy */
-        DirectBuyDeployed,
-        DirectBuyDeclined,
-        /* FactoryDirectSell */
-        DirectSellDeployed,
-        DirectSellDeclined,
-        /* Nft */
-        ManagerChanged,
-        OwnerChanged,
-        /* common for all events */
-        OwnershipTransferred,
-        MarketFeeDefaultChanged,
-        MarketFeeChanged,
-        AddCollectionRules,
-        RemoveCollectionRules
-    )
+        ctx,
+        format!("Error while unpack: {:#?}", error),
+    )))
+}
+
+fn is_nft_abi_nft_created(event: &ExtractedOwned) -> bool {
+    event.name == "NftCreated"
+        && nft().events.get("NftCreated").map_or(false, |e| {
+            e.inputs
+                .iter()
+                .map(|p| &p.name)
+                .eq(event.tokens.iter().map(|t| &t.name))
+        })
+}
+
+fn dead_letter(event: &ExtractedOwned, ctx: &DecodeContext, message: String) -> FailedEvent {
+    metrics::PARSE_FAILURES.inc();
+    tracing::error!(
+        event = %event.name,
+        message_hash = %ctx.message_hash.to_string(),
+        "{}",
+        message
+    );
+
+    FailedEvent {
+        address: ctx.tx_data.get_account(),
+        event_name: event.name.clone(),
+        message_hash: ctx.message_hash.to_string(),
+        tx_lt: ctx.tx_data.logical_time() as i64,
+        tokens: format!("{:?}", event.tokens),
+        error: message,
+    }
+}
+
+fn is_blocked_event(runtime_config: &RuntimeConfig, event: &Decoded) -> bool {
+    match event {
+        Decoded::RawEventRecord(record) => record
+            .collection
+            .as_deref()
+            .map_or(false, |c| runtime_config.is_collection_blocked(c)),
+        _ => false,
+    }
 }
 
+/// Bids and auction sales must be priced in the token the auction was activated with.
+/// An amount in another token can't be compared with the other bids, mismatches are
+/// logged and dropped; their raw events are still stored
+fn drop_mismatched_auction_tokens(
+    auction_tokens: &HashMap<String, String>,
+    bids: &mut Vec<AuctionBid>,
+    prices: &mut Vec<NftPriceHistory>,
+) {
+    let matches = |auction: &str, token: &str, kind: &str| match auction_tokens.get(auction) {
+        Some(expected) if expected != token => {
+            log::warn!(
+                "Auction {} {} uses token {}, but the auction was created with {}, dropping it",
+                auction,
+                kind,
+                token,
+                expected
+            );
+            false
+        }
+        _ => true,
+    };
+
+    bids.retain(|b| matches(&b.address, &b.price_token, "bid"));
+    prices.retain(|p| {
+        p.source_type != NftPriceSource::AuctionBid || matches(&p.source, &p.price_token, "sale")
+    });
+}
+
+/// Symbols of the price tokens from the registry. A token missing from it keeps its
+/// address as the symbol until the meta reader registers it, the missing tokens are
+/// returned to be queued
+fn fill_token_symbols<'a>(
+    tokens: &HashMap<String, Token>,
+    direct_sells: impl Iterator<Item = &'a mut DirectSell>,
+    prices: &mut [NftPriceHistory],
+) -> Vec<String> {
+    let mut unknown = BTreeSet::new();
+    let mut symbol = |token: &str| match tokens.get(token) {
+        Some(t) => t.symbol.clone(),
+        None => {
+            unknown.insert(token.to_string());
+            token.to_string()
+        }
+    };
+
+    for ds in direct_sells {
+        ds.price_token_symbol = Some(symbol(&ds.price_token));
+    }
+    for price in prices.iter_mut() {
+        price.price_token_symbol = Some(symbol(&price.price_token));
+    }
+
+    unknown.into_iter().collect()
+}
+
+/// Offers deployed by factories and some NFT events don't name the
+/// collection, so it is taken from the NFT they refer to
+fn fill_missing_collections(
+    nft_collections: &HashMap<String, String>,
+    direct_sells: &mut [DirectSell],
+    direct_buys: &mut [DirectBuy],
+    events: &mut [EventRecord],
+) {
+    let offers = direct_sells
+        .iter_mut()
+        .map(|ds| (&ds.nft, &mut ds.collection))
+        .chain(
+            direct_buys
+                .iter_mut()
+                .map(|db| (&db.nft, &mut db.collection)),
+        );
+    for (nft, collection) in offers {
+        if collection.is_none() {
+            *collection = nft_collections.get(nft).cloned();
+        }
+    }
+
+    for event in events.iter_mut().filter(|e| e.collection.is_none()) {
+        event.collection = event
+            .nft
+            .as_ref()
+            .and_then(|nft| nft_collections.get(nft).cloned());
+    }
+}
+
+/// A sale pays the latest fee set on the offer itself before the sale,
+/// otherwise the factory default the offer was deployed with.
+fn marketplace_fee_of<'a>(
+    source: &str,
+    history: &'a [MarketplaceFee],
+    at: NaiveDateTime,
+) -> Option<&'a MarketplaceFee> {
+    history
+        .iter()
+        .filter(|f| f.changed_at <= at)
+        .max_by_key(|f| (f.address == source, f.changed_lt))
+}
+
+fn apply_marketplace_fees(
+    fees: &HashMap<String, Vec<MarketplaceFee>>,
+    prices: &mut [NftPriceHistory],
+) {
+    for price in prices.iter_mut() {
+        let fee = fees
+            .get(&price.source)
+            .and_then(|history| marketplace_fee_of(&price.source, history, price.created_at))
+            .filter(|fee| fee.denominator != 0)
+            .map(|fee| {
+                &price.price * BigDecimal::from(fee.numerator) / BigDecimal::from(fee.denominator)
+            });
+        price.marketplace_fee = fee;
+    }
+}
+
+/// Royalty of a completed auction or filled direct sell of a whitelisted offer. Sales
+/// of collections whose royalty wasn't read yet accrue nothing
+fn royalty_of(
+    price: &NftPriceHistory,
+    whitelisted: &HashSet<String>,
+    rates: &HashMap<String, Option<(i32, i32)>>,
+) -> Option<BigDecimal> {
+    if !whitelisted.contains(&price.source) || price.source_type == NftPriceSource::DirectBuy {
+        return None;
+    }
+
+    match rates.get(&price.collection) {
+        Some(Some((numerator, denominator))) if *denominator > 0 => {
+            Some(&price.price * BigDecimal::from(*numerator) / BigDecimal::from(*denominator))
+        }
+        Some(Some((numerator, denominator))) => {
+            log::warn!(
+                "Royalty of {} is {numerator}/{denominator}, not accrued for sale {}",
+                price.collection,
+                price.source
+            );
+            None
+        }
+        Some(None) => {
+            log::debug!(
+                "Collection {} takes no royalty, nothing accrued for sale {}",
+                price.collection,
+                price.source
+            );
+            None
+        }
+        None => {
+            log::warn!(
+                "Royalty of {} is unknown, not accrued for sale {}",
+                price.collection,
+                price.source
+            );
+            None
+        }
+    }
+}
+
+fn apply_royalties(
+    rates: &HashMap<String, Option<(i32, i32)>>,
+    whitelisted: &HashSet<String>,
+    prices: &mut [NftPriceHistory],
+) {
+    for price in prices.iter_mut() {
+        price.royalty = royalty_of(price, whitelisted, rates);
+    }
+}
+
+/// Royalties of the sales summed per collection and payment token
+fn royalties_earned(prices: &[NftPriceHistory]) -> Vec<CollectionRoyaltyEarned> {
+    let mut earned: BTreeMap<(&str, &str), BigDecimal> = BTreeMap::new();
+
+    for price in prices {
+        if let Some(royalty) = &price.royalty {
+            *earned
+                .entry((price.collection.as_str(), price.price_token.as_str()))
+                .or_default() += royalty;
+        }
+    }
+
+    earned
+        .into_iter()
+        .map(|((collection, token), amount)| CollectionRoyaltyEarned {
+            collection: collection.to_string(),
+            token: token.to_string(),
+            amount,
+        })
+        .collect()
+}
+
+/// Declares `unpack_entity` and `UNPACKED_EVENTS` from one list, so the startup
+/// scope check sees exactly the names the parser dispatches on
+macro_rules! unpack_entities {
+    ($($entity:ty),+) => {
+        /// Events `unpack_entity` decodes, every other extracted event is ignored
+        pub(crate) const UNPACKED_EVENTS: &[&str] = &[$(stringify!($entity)),+];
+
+        pub(crate) fn unpack_entity(event: &ExtractedOwned) -> Result<Option<Box<dyn Decode>>> {
+            match event.name.as_str() {
+                $(stringify!($entity) => Ok(
+                    Some(Box::new(UnpackAbiPlain::<$entity>::unpack(event.tokens.clone())?))
+                ),)+
+                _ => Ok(None),
+            }
+        }
+    };
+}
+
+unpack_entities!(
+    /* FactoryAuction */
+    AuctionDeployed,
+    AuctionDeclined,
+    /* Auction */
+    AuctionCreated,
+    AuctionActive,
+    BidPlaced,
+    BidDeclined,
+    AuctionComplete,
+    AuctionCancelled,
+    /* Collection */
+    NftCreated,
+    NftBurned,
+    /* DirectBuy */
+    DirectBuyStateChanged,
+    /* DirectSell */
+    DirectSellStateChanged,
+    /* FactoryDirectBuy */
+    DirectBuyDeployed,
+    DirectBuyDeclined,
+    /* FactoryDirectSell */
+    DirectSellDeployed,
+    DirectSellDeclined,
+    /* Nft */
+    ManagerChanged,
+    OwnerChanged,
+    /* common for all events */
+    OwnershipTransferred,
+    MarketFeeDefaultChanged,
+    MarketFeeChanged,
+    AddCollectionRules,
+    RemoveCollectionRules
+);
+
 #[cfg(test)]
 mod test {
-    use std::collections::BTreeMap;
+    use std::collections::{BTreeMap, HashMap, HashSet};
+    use std::time::Duration;
 
+    use bigdecimal::BigDecimal;
+    use chrono::NaiveDateTime;
+    use indexer_api::{ParserControl, PARSERS};
+    use indexer_repo::checkpoint::Checkpoint;
+    use indexer_repo::types::{
+        decoded::{
+            AuctionBid, DirectSell, EventRecord, FailedEvent, MarketplaceFee, NftPriceHistory,
+        },
+        DirectSellState, EventCategory, EventType, NftPriceSource,
+    };
     use nekoton_abi::{transaction_parser::ExtractedOwned, PackAbiPlain, UnpackAbiPlain};
     use num::{BigInt, BigUint};
+    use std::str::FromStr;
     use ton_abi::{Int, Param, ParamType, Token, TokenValue, Uint};
-    use ton_block::{Grams, Message, MsgAddrStd, MsgAddress, Transaction};
+    use ton_block::{
+        ExtOutMessageHeader, Grams, Message, MsgAddrStd, MsgAddress, MsgAddressInt, Transaction,
+    };
     use ton_types::{Cell, UInt256};
 
-    use crate::{abi::scope::events, models::events::*, parser::unpack_entity};
+    use crate::{
+        abi::{declare_abi::nft, scope::events},
+        models::events::*,
+        parser::{
+            apply_marketplace_fees, apply_royalties, dedup_events, drop_mismatched_auction_tokens,
+            fill_missing_collections, fill_token_symbols, finality_wait, merge_batches,
+            order_by_emission, park, parser_of, raw_transaction_records, report_decode_failure,
+            royalties_earned, unpack_entity, unpack_failure, DecodedBatch,
+        },
+        persistence::entities::Decoded,
+        utils::DecodeContext,
+    };
 
     fn create_default_token_value(param_kind: &ParamType) -> TokenValue {
         match &param_kind {
@@ -408,7 +1709,7 @@ mod test {
             ParamType::Map(k, v) => {
                 TokenValue::Map((**k).clone(), (**v).clone(), BTreeMap::default())
             }
-            ParamType::Address => TokenValue::Address(MsgAddress::AddrStd(MsgAddrStd::default())),
+            ParamType::Address => TokenValue::Address(MsgAddress::AddrStd(non_zero_address())),
             ParamType::Bytes => TokenValue::Bytes(Vec::default()),
             ParamType::FixedBytes(_) => TokenValue::FixedBytes(Vec::default()),
             ParamType::String => TokenValue::String(String::default()),
@@ -423,6 +1724,18 @@ mod test {
         }
     }
 
+    // Zero addresses are skipped by some decoders
+    fn non_zero_address() -> MsgAddrStd {
+        match MsgAddressInt::from_str(
+            "0:0101010101010101010101010101010101010101010101010101010101010101",
+        )
+        .unwrap()
+        {
+            MsgAddressInt::AddrStd(addr) => addr,
+            _ => unreachable!(),
+        }
+    }
+
     fn build_default_event(params: &Vec<Param>) -> Vec<Token> {
         let mut tokens = Vec::with_capacity(params.len());
 
@@ -437,10 +1750,7 @@ mod test {
         tokens
     }
 
-    #[test]
-    fn test_correct_parsing() {
-        let mut total_events_parsed = 0;
-
+    fn load_nft_events() -> HashMap<String, ton_abi::Event> {
         let auction_root_tip3_contract =
             ton_abi::Contract::load(include_str!("abi/json/FactoryAuction.abi.json")).unwrap();
         let auction_tip3_contract =
@@ -472,7 +1782,14 @@ mod test {
         nft_events.extend(factory_direct_sell_contract.events);
         nft_events.extend(mint_and_sell_contract.events);
 
-        for (name, event) in nft_events {
+        nft_events
+    }
+
+    #[test]
+    fn test_correct_parsing() {
+        let mut total_events_parsed = 0;
+
+        for (name, event) in load_nft_events() {
             let event_raw = build_default_event(event.input_params());
 
             let extracted = ExtractedOwned {
@@ -555,4 +1872,633 @@ mod test {
 
         assert_eq!(total_events_parsed, events().len())
     }
+
+    #[test]
+    fn test_mismatched_auction_token_is_dropped() {
+        let auction_tokens = HashMap::from([("0:auction".to_string(), "0:wever".to_string())]);
+
+        let mut bids = vec![AuctionBid {
+            address: "0:auction".to_string(),
+            collection: "0:collection".to_string(),
+            nft: "0:nft".to_string(),
+            nft_owner: "0:owner".to_string(),
+            price_token: "0:other".to_string(),
+            bid_value: BigDecimal::from(10),
+            bid_value_normalized: None,
+            next_value: BigDecimal::from(11),
+            buyer: "0:buyer".to_string(),
+            created_at: NaiveDateTime::default(),
+            tx_lt: 1,
+            declined: false,
+        }];
+        let mut prices = vec![NftPriceHistory {
+            source: "0:auction".to_string(),
+            source_type: NftPriceSource::AuctionBid,
+            created_at: NaiveDateTime::default(),
+            price: BigDecimal::from(10),
+            price_token: "0:other".to_string(),
+            price_token_symbol: None,
+            usd_price: None,
+            marketplace_fee: None,
+            royalty: None,
+            nft: "0:nft".to_string(),
+            collection: "0:collection".to_string(),
+            buyer: None,
+            seller: None,
+        }];
+
+        let mut matching = bids[0].clone();
+        matching.price_token = "0:wever".to_string();
+        bids.push(matching);
+
+        drop_mismatched_auction_tokens(&auction_tokens, &mut bids, &mut prices);
+
+        assert_eq!(bids.len(), 1);
+        assert_eq!(bids[0].price_token, "0:wever");
+        assert!(prices.is_empty());
+    }
+
+    #[test]
+    fn test_sale_after_fee_change_uses_new_fee() {
+        let fee = |address: &str, numerator, changed_at, changed_lt| MarketplaceFee {
+            address: address.to_string(),
+            numerator,
+            denominator: 100,
+            changed_at: NaiveDateTime::from_timestamp_opt(changed_at, 0).unwrap(),
+            changed_lt,
+        };
+        let sale = |created_at| NftPriceHistory {
+            source: "0:sell".to_string(),
+            source_type: NftPriceSource::DirectSell,
+            created_at: NaiveDateTime::from_timestamp_opt(created_at, 0).unwrap(),
+            price: BigDecimal::from(1000),
+            price_token: "0:wever".to_string(),
+            price_token_symbol: None,
+            usd_price: None,
+            marketplace_fee: None,
+            royalty: None,
+            nft: "0:nft".to_string(),
+            collection: "0:collection".to_string(),
+            buyer: None,
+            seller: None,
+        };
+
+        // deployed with the factory default of 2%, then changed to 5%
+        let fees = HashMap::from([(
+            "0:sell".to_string(),
+            vec![fee("0:factory", 2, 100, 1), fee("0:sell", 5, 200, 2)],
+        )]);
+        let mut prices = vec![sale(150), sale(250)];
+
+        apply_marketplace_fees(&fees, &mut prices);
+
+        assert_eq!(prices[0].marketplace_fee, Some(BigDecimal::from(20)));
+        assert_eq!(prices[1].marketplace_fee, Some(BigDecimal::from(50)));
+    }
+
+    #[test]
+    fn test_royalties_are_accrued_per_collection_and_token() {
+        let sale = |source: &str, source_type, collection: &str, token: &str| NftPriceHistory {
+            source: source.to_string(),
+            source_type,
+            created_at: NaiveDateTime::default(),
+            price: BigDecimal::from(1000),
+            price_token: token.to_string(),
+            price_token_symbol: None,
+            usd_price: None,
+            marketplace_fee: None,
+            royalty: None,
+            nft: "0:nft".to_string(),
+            collection: collection.to_string(),
+            buyer: None,
+            seller: None,
+        };
+        let whitelisted = ["0:auc", "0:sell", "0:buy", "0:other"]
+            .into_iter()
+            .map(str::to_string)
+            .collect::<HashSet<_>>();
+        let rates = HashMap::from([
+            ("0:royalty".to_string(), Some((5, 100))),
+            ("0:free".to_string(), None),
+        ]);
+
+        let mut prices = vec![
+            sale("0:auc", NftPriceSource::AuctionBid, "0:royalty", "0:wever"),
+            sale("0:sell", NftPriceSource::DirectSell, "0:royalty", "0:wever"),
+            sale("0:other", NftPriceSource::DirectSell, "0:royalty", "0:usdt"),
+            sale("0:buy", NftPriceSource::DirectBuy, "0:royalty", "0:wever"),
+            sale("0:fake", NftPriceSource::DirectSell, "0:royalty", "0:wever"),
+            sale("0:sell", NftPriceSource::DirectSell, "0:free", "0:wever"),
+            sale("0:sell", NftPriceSource::DirectSell, "0:unknown", "0:wever"),
+        ];
+
+        apply_royalties(&rates, &whitelisted, &mut prices);
+        // each sale keeps what it accrued, the rollback takes back exactly that
+        assert_eq!(
+            prices.iter().map(|p| p.royalty.clone()).collect::<Vec<_>>(),
+            [Some(50), Some(50), Some(50), None, None, None, None].map(|r| r.map(BigDecimal::from))
+        );
+
+        let royalties = royalties_earned(&prices)
+            .into_iter()
+            .map(|r| (r.collection, r.token, r.amount))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            royalties,
+            vec![
+                (
+                    "0:royalty".to_string(),
+                    "0:usdt".to_string(),
+                    BigDecimal::from(50)
+                ),
+                (
+                    "0:royalty".to_string(),
+                    "0:wever".to_string(),
+                    BigDecimal::from(100)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_deployed_direct_sell_gets_collection_of_its_nft() {
+        let nft_collections = HashMap::from([("0:nft".to_string(), "0:collection".to_string())]);
+
+        let mut direct_sells = vec![DirectSell {
+            address: "0:sell".to_string(),
+            root: "0:factory".to_string(),
+            nft: "0:nft".to_string(),
+            collection: None,
+            price_token: "0:wever".to_string(),
+            price_token_symbol: None,
+            price: BigDecimal::from(10),
+            price_normalized: None,
+            seller: "0:seller".to_string(),
+            finished_at: None,
+            expired_at: NaiveDateTime::default(),
+            state: DirectSellState::Create,
+            created: None,
+            updated: NaiveDateTime::default(),
+            tx_lt: 1,
+        }];
+        let mut events = vec![EventRecord {
+            event_category: EventCategory::DirectSell,
+            event_type: EventType::DirectSellDeployed,
+            address: "0:factory".to_string(),
+            created_lt: 1,
+            created_at: 0,
+            message_hash: "hash".to_string(),
+            nft: Some("0:nft".to_string()),
+            collection: None,
+            raw_data: serde_json::Value::Null,
+        }];
+
+        fill_missing_collections(&nft_collections, &mut direct_sells, &mut [], &mut events);
+
+        assert_eq!(direct_sells[0].collection.as_deref(), Some("0:collection"));
+        assert_eq!(events[0].collection.as_deref(), Some("0:collection"));
+    }
+
+    #[test]
+    fn test_unknown_token_keeps_its_address_as_the_symbol() {
+        let tokens = HashMap::from([(
+            "0:wever".to_string(),
+            indexer_repo::token_registry::Token {
+                address: "0:wever".to_string(),
+                symbol: "WEVER".to_string(),
+                decimals: 9,
+            },
+        )]);
+        let direct_sell = |token: &str| DirectSell {
+            address: "0:sell".to_string(),
+            root: "0:factory".to_string(),
+            nft: "0:nft".to_string(),
+            collection: None,
+            price_token: token.to_string(),
+            price_token_symbol: None,
+            price: BigDecimal::from(10),
+            price_normalized: None,
+            seller: "0:seller".to_string(),
+            finished_at: None,
+            expired_at: NaiveDateTime::default(),
+            state: DirectSellState::Active,
+            created: None,
+            updated: NaiveDateTime::default(),
+            tx_lt: 1,
+        };
+        let mut direct_sells = vec![direct_sell("0:wever"), direct_sell("0:new")];
+        let mut prices = vec![NftPriceHistory {
+            source: "0:sell".to_string(),
+            source_type: NftPriceSource::DirectSell,
+            created_at: NaiveDateTime::default(),
+            price: BigDecimal::from(10),
+            price_token: "0:new".to_string(),
+            price_token_symbol: None,
+            usd_price: None,
+            marketplace_fee: None,
+            royalty: None,
+            nft: "0:nft".to_string(),
+            collection: "0:collection".to_string(),
+            buyer: None,
+            seller: None,
+        }];
+
+        let unknown = fill_token_symbols(&tokens, direct_sells.iter_mut(), &mut prices);
+
+        assert_eq!(unknown, vec!["0:new".to_string()]);
+        assert_eq!(direct_sells[0].price_token_symbol.as_deref(), Some("WEVER"));
+        assert_eq!(direct_sells[1].price_token_symbol.as_deref(), Some("0:new"));
+        assert_eq!(prices[0].price_token_symbol.as_deref(), Some("0:new"));
+    }
+
+    #[test]
+    fn test_only_nfts_with_known_collections_are_filled() {
+        let nft_collections = HashMap::from([("0:known".to_string(), "0:collection".to_string())]);
+        let event = |nft: &str| EventRecord {
+            event_category: EventCategory::Nft,
+            event_type: EventType::NftOwnerChanged,
+            address: nft.to_string(),
+            created_lt: 1,
+            created_at: 0,
+            message_hash: format!("{nft}-hash"),
+            nft: Some(nft.to_string()),
+            collection: None,
+            raw_data: serde_json::Value::Null,
+        };
+        let mut events = vec![event("0:known"), event("0:unknown")];
+
+        fill_missing_collections(&nft_collections, &mut [], &mut [], &mut events);
+
+        assert_eq!(events[0].collection.as_deref(), Some("0:collection"));
+        assert_eq!(events[1].collection, None);
+    }
+
+    #[test]
+    fn test_raw_transaction_is_kept_per_raw_event() {
+        let record = |message_hash: &str| {
+            Decoded::RawEventRecord(EventRecord {
+                event_category: EventCategory::Nft,
+                event_type: EventType::NftOwnerChanged,
+                address: "0:nft".to_string(),
+                created_lt: 1,
+                created_at: 0,
+                message_hash: message_hash.to_string(),
+                nft: Some("0:nft".to_string()),
+                collection: None,
+                raw_data: serde_json::Value::Null,
+            })
+        };
+        let decoded = vec![record("first"), Decoded::ShouldSkip, record("second")];
+
+        let raw_transactions = raw_transaction_records(&decoded, &Transaction::default())
+            .into_iter()
+            .map(|d| match d {
+                Decoded::RawTransaction(t) => t,
+                _ => panic!("Not a raw transaction record"),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(raw_transactions.len(), 2);
+        assert_eq!(raw_transactions[0].message_hash, "first");
+        assert_eq!(raw_transactions[1].message_hash, "second");
+        assert!(!raw_transactions[0].boc.is_empty());
+        assert_eq!(raw_transactions[0].boc, raw_transactions[1].boc);
+    }
+
+    #[test]
+    fn test_malformed_event_is_dead_lettered() {
+        let extracted = ExtractedOwned {
+            function_id: 0,
+            name: "NftCreated".to_string(),
+            bounced: false,
+            tokens: Vec::default(),
+            message_hash: UInt256::default(),
+            message: Message::default(),
+            tx: Transaction::default(),
+            is_in_message: false,
+            parsed_type: nekoton_abi::transaction_parser::ParsedType::Event,
+            decoded_headers: Vec::default(),
+        };
+        let ctx = DecodeContext {
+            tx_data: Transaction::default(),
+            function_inputs: Vec::default(),
+            message_hash: UInt256::default(),
+            max_listing_lifetime_secs: u64::MAX,
+        };
+
+        let Err(e) = unpack_entity(&extracted) else {
+            panic!("NftCreated without tokens must not unpack");
+        };
+        let error = format!("Error while unpack: {:#?}", e);
+        let Some(Decoded::DecodeFailed(failed)) = unpack_failure(&extracted, &ctx, e) else {
+            panic!("Failure is not dead-lettered");
+        };
+
+        assert_eq!(failed.event_name, "NftCreated");
+        assert_eq!(failed.message_hash, UInt256::default().to_string());
+        assert_eq!(failed.tokens, "[]");
+        assert_eq!(failed.error, error);
+
+        let Decoded::DecodeFailed(failed) =
+            report_decode_failure(false, &extracted, &ctx, "Error while decode".to_string())
+        else {
+            panic!("Failure is not dead-lettered");
+        };
+        assert_eq!(failed.error, "Error while decode");
+    }
+
+    #[test]
+    fn test_nft_abi_nft_created_is_skipped() {
+        let nft_created = nft().events.get("NftCreated").unwrap();
+        let extracted = ExtractedOwned {
+            function_id: nft_created.id,
+            name: "NftCreated".to_string(),
+            bounced: false,
+            tokens: build_default_event(&nft_created.inputs),
+            message_hash: UInt256::default(),
+            message: Message::default(),
+            tx: Transaction::default(),
+            is_in_message: false,
+            parsed_type: nekoton_abi::transaction_parser::ParsedType::Event,
+            decoded_headers: Vec::default(),
+        };
+        let ctx = DecodeContext {
+            tx_data: Transaction::default(),
+            function_inputs: Vec::default(),
+            message_hash: UInt256::default(),
+            max_listing_lifetime_secs: u64::MAX,
+        };
+
+        // Emitted on every mint next to the NftCreated of the collection
+        let Err(e) = unpack_entity(&extracted) else {
+            panic!("NftCreated of Nft.abi must not unpack into the collection's");
+        };
+        assert!(unpack_failure(&extracted, &ctx, e).is_none());
+    }
+
+    #[test]
+    fn test_queued_batches_are_merged_in_order() {
+        let checkpoint = |tx_lt| Checkpoint {
+            tx_timestamp: 0,
+            tx_lt,
+            tx_hash: format!("tx{tx_lt}"),
+        };
+        let failed = |message_hash: &str| {
+            Decoded::DecodeFailed(FailedEvent {
+                address: "0:account".to_string(),
+                event_name: "NftCreated".to_string(),
+                message_hash: message_hash.to_string(),
+                tx_lt: 1,
+                tokens: String::new(),
+                error: String::new(),
+            })
+        };
+        let batch = |data, checkpoint| DecodedBatch {
+            data,
+            checkpoint,
+            runtime: Default::default(),
+            transactions: 1,
+        };
+
+        let merged = merge_batches(vec![
+            batch(vec![failed("a"), failed("b")], Some(checkpoint(1))),
+            batch(vec![failed("c")], Some(checkpoint(2))),
+            // Nothing left after the checkpoint filter
+            batch(vec![], None),
+        ]);
+
+        let hashes = merged
+            .data
+            .iter()
+            .map(|d| match d {
+                Decoded::DecodeFailed(e) => e.message_hash.as_str(),
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(hashes, ["a", "b", "c"]);
+        assert_eq!(merged.checkpoint, Some(checkpoint(2)));
+        assert_eq!(merged.transactions, 3);
+    }
+
+    #[test]
+    fn test_finality_wait() {
+        let delay = Duration::from_secs(30);
+
+        assert_eq!(
+            finality_wait(100, 110, delay),
+            Some(Duration::from_secs(20))
+        );
+        assert_eq!(finality_wait(100, 130, delay), None);
+        assert_eq!(finality_wait(100, 100, Duration::ZERO), None);
+    }
+
+    /// Raw event each handled ABI event must be recorded as in `nft_events`
+    fn expected_raw_event(name: &str) -> (&'static str, &'static str) {
+        match name {
+            "AuctionDeployed" => ("AuctionDeployed", "Auction"),
+            "AuctionDeclined" => ("AuctionDeclined", "Auction"),
+            "AuctionCreated" => ("AuctionCreated", "Auction"),
+            "AuctionActive" => ("AuctionActive", "Auction"),
+            "BidPlaced" => ("AuctionBidPlaced", "Auction"),
+            "BidDeclined" => ("AuctionBidDeclined", "Auction"),
+            "AuctionComplete" => ("AuctionComplete", "Auction"),
+            "AuctionCancelled" => ("AuctionCancelled", "Auction"),
+            "NftCreated" => ("NftCreated", "Collection"),
+            "NftBurned" => ("NftBurned", "Collection"),
+            "DirectBuyStateChanged" => ("DirectBuyStateChanged", "DirectBuy"),
+            "DirectSellStateChanged" => ("DirectSellStateChanged", "DirectSell"),
+            "DirectBuyDeployed" => ("DirectBuyDeployed", "DirectBuy"),
+            "DirectBuyDeclined" => ("DirectBuyDeclined", "DirectBuy"),
+            "DirectSellDeployed" => ("DirectSellDeployed", "DirectSell"),
+            "DirectSellDeclined" => ("DirectSellDeclined", "DirectSell"),
+            "ManagerChanged" => ("NftManagerChanged", "Nft"),
+            "OwnerChanged" => ("NftOwnerChanged", "Nft"),
+            "OwnershipTransferred" => ("OwnershipTransferred", "Common"),
+            "MarketFeeDefaultChanged" => ("MarketFeeDefaultChanged", "Collection"),
+            "MarketFeeChanged" => ("MarketFeeChanged", "Collection"),
+            "AddCollectionRules" => ("AddCollectionRules", "Collection"),
+            "RemoveCollectionRules" => ("RemoveCollectionRules", "Collection"),
+            _ => panic!("No raw event expectation for {}", name),
+        }
+    }
+
+    #[test]
+    fn test_shared_event_matched_by_several_abis_is_handled_once() {
+        let extracted = |name: &str, hash: u8| ExtractedOwned {
+            function_id: 0,
+            name: name.to_string(),
+            bounced: false,
+            tokens: Vec::default(),
+            message_hash: UInt256::from([hash; 32]),
+            message: Message::default(),
+            tx: Transaction::default(),
+            is_in_message: false,
+            parsed_type: nekoton_abi::transaction_parser::ParsedType::Event,
+            decoded_headers: Vec::default(),
+        };
+
+        // Collection, FactoryDirectBuy and FactoryDirectSell ABIs all declare it
+        let mut events = vec![
+            extracted("OwnershipTransferred", 1),
+            extracted("OwnershipTransferred", 1),
+            extracted("OwnershipTransferred", 1),
+            extracted("MarketFeeChanged", 1),
+            extracted("OwnershipTransferred", 2),
+        ];
+        dedup_events(&mut events);
+
+        let events = events
+            .iter()
+            .map(|e| (e.name.as_str(), e.message_hash.as_slice()[0]))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            vec![
+                ("OwnershipTransferred", 1),
+                ("MarketFeeChanged", 1),
+                ("OwnershipTransferred", 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_events_are_ordered_by_their_out_message() {
+        let extracted = |name: &str, created_lt: u64| {
+            let mut header = ExtOutMessageHeader::default();
+            header.created_lt = created_lt;
+            ExtractedOwned {
+                function_id: 0,
+                name: name.to_string(),
+                bounced: false,
+                tokens: Vec::default(),
+                message_hash: UInt256::from([created_lt as u8; 32]),
+                message: Message::with_ext_out_header(header),
+                tx: Transaction::default(),
+                is_in_message: false,
+                parsed_type: nekoton_abi::transaction_parser::ParsedType::Event,
+                decoded_headers: Vec::default(),
+            }
+        };
+
+        // as the extractor may hand them over, with two extractables of one message
+        let mut events = vec![
+            extracted("OwnerChanged", 12),
+            extracted("DirectSellStateChanged", 11),
+            extracted("OwnershipTransferred", 13),
+            extracted("MarketFeeChanged", 11),
+        ];
+        order_by_emission(&mut events);
+
+        let events = events.iter().map(|e| e.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            vec![
+                "DirectSellStateChanged",
+                "MarketFeeChanged",
+                "OwnerChanged",
+                "OwnershipTransferred"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_every_event_produces_raw_event() {
+        let nft_events = load_nft_events();
+        let mut event_types = HashSet::new();
+
+        for name in events() {
+            let event = nft_events
+                .get(name)
+                .unwrap_or_else(|| panic!("{} is missing in ABIs", name));
+
+            let extracted = ExtractedOwned {
+                function_id: 0,
+                name: name.to_string(),
+                bounced: false,
+                tokens: build_default_event(event.input_params()),
+                message_hash: UInt256::default(),
+                message: Message::default(),
+                tx: Transaction::default(),
+                is_in_message: false,
+                parsed_type: nekoton_abi::transaction_parser::ParsedType::Event,
+                decoded_headers: Vec::default(),
+            };
+            let ctx = DecodeContext {
+                tx_data: Transaction::default(),
+                function_inputs: Vec::default(),
+                message_hash: UInt256::default(),
+                max_listing_lifetime_secs: u64::MAX,
+            };
+
+            let entity = unpack_entity(&extracted).unwrap().unwrap();
+            let Decoded::RawEventRecord(record) = entity.decode_event(&ctx).unwrap() else {
+                panic!("{} doesn't produce a raw event", name);
+            };
+
+            let (event_type, event_category) = expected_raw_event(name);
+            assert_eq!(format!("{:?}", record.event_type), event_type, "{}", name);
+            assert_eq!(
+                format!("{:?}", record.event_category),
+                event_category,
+                "{}",
+                name
+            );
+            assert!(!record.raw_data.is_null(), "{}", name);
+
+            event_types.insert(event_type);
+        }
+
+        assert_eq!(event_types.len(), events().len());
+    }
+
+    #[test]
+    fn test_paused_parser_parks_only_its_events() {
+        let parser_control = ParserControl::default();
+        assert!(parser_control.set_paused("auction", true));
+
+        assert!(!parser_control.is_active(parser_of("BidPlaced")));
+        assert!(!parser_control.is_active(parser_of("AuctionComplete")));
+        assert!(parser_control.is_active(parser_of("DirectSellStateChanged")));
+        assert!(parser_control.is_active(parser_of("OwnerChanged")));
+
+        // resumed, but its parked events are applied first
+        parser_control.set_parked("auction", true);
+        assert!(parser_control.set_paused("auction", false));
+        assert!(!parser_control.is_active(parser_of("BidPlaced")));
+
+        parser_control.set_parked("auction", false);
+        assert!(parser_control.is_active(parser_of("BidPlaced")));
+    }
+
+    #[test]
+    fn test_only_raw_records_and_failures_are_parked() {
+        let record = EventRecord {
+            event_category: EventCategory::Auction,
+            event_type: EventType::AuctionBidPlaced,
+            address: "0:01".to_string(),
+            created_lt: 10,
+            created_at: 100,
+            message_hash: "hash".to_string(),
+            nft: None,
+            collection: None,
+            raw_data: serde_json::json!({}),
+        };
+
+        let Some(Decoded::ParkedEvent((parser, parked))) =
+            park("auction", Decoded::RawEventRecord(record))
+        else {
+            panic!("raw record not parked");
+        };
+        assert_eq!(parser, "auction");
+        assert_eq!(parked.message_hash, "hash");
+        assert!(park("auction", Decoded::ShouldSkip).is_none());
+    }
+
+    #[test]
+    fn test_every_event_has_a_known_parser() {
+        for name in events() {
+            assert!(PARSERS.contains(&parser_of(name)), "{}", name);
+        }
+    }
 }
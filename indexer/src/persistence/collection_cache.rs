@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use moka::sync::Cache;
+
+const MAX_CACHED_NFTS: u64 = 200_000;
+/// An nft never moves to another collection, the ttl only bounds stale entries
+/// left behind by a database rollback
+const CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Nft to collection lookups shared across batches, so offers and events of
+/// actively traded nfts don't query Postgres on every batch
+#[derive(Clone)]
+pub struct NftCollectionCache {
+    cache: Cache<String, String>,
+}
+
+impl Default for NftCollectionCache {
+    fn default() -> Self {
+        Self::new(MAX_CACHED_NFTS, CACHE_TTL)
+    }
+}
+
+impl NftCollectionCache {
+    pub fn new(capacity: u64, ttl: Duration) -> Self {
+        Self {
+            cache: Cache::builder()
+                .max_capacity(capacity)
+                .time_to_live(ttl)
+                .build(),
+        }
+    }
+
+    /// Returns the cached collections and the nfts that still have to be read from the database
+    pub fn lookup<'a>(&self, nfts: &[&'a str]) -> (HashMap<String, String>, Vec<&'a str>) {
+        let mut found = HashMap::with_capacity(nfts.len());
+        let mut missing = Vec::new();
+
+        for &nft in nfts {
+            match self.cache.get(nft) {
+                Some(collection) => {
+                    found.insert(nft.to_string(), collection);
+                }
+                None => missing.push(nft),
+            }
+        }
+
+        (found, missing)
+    }
+
+    pub fn insert(&self, nft: String, collection: String) {
+        self.cache.insert(nft, collection);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    use super::NftCollectionCache;
+
+    #[test]
+    fn test_repeated_nfts_are_looked_up_once() {
+        let db = (0..10)
+            .map(|i| (format!("0:nft{i}"), format!("0:collection{}", i % 3)))
+            .collect::<HashMap<_, _>>();
+        let cache = NftCollectionCache::new(100, Duration::from_secs(60));
+
+        let mut db_rows_read = 0;
+        let mut events = 0;
+        for batch in 0..100 {
+            let nfts = (0..5)
+                .map(|i| format!("0:nft{}", (batch + i) % 10))
+                .collect::<Vec<_>>();
+            let nfts = nfts.iter().map(String::as_str).collect::<Vec<_>>();
+            events += nfts.len();
+
+            let (mut found, missing) = cache.lookup(&nfts);
+            for nft in missing {
+                db_rows_read += 1;
+                let collection = db[nft].clone();
+                cache.insert(nft.to_string(), collection.clone());
+                found.insert(nft.to_string(), collection);
+            }
+
+            for nft in nfts {
+                assert_eq!(found[nft], db[nft]);
+            }
+        }
+
+        assert_eq!(events, 500);
+        assert_eq!(db_rows_read, 10);
+    }
+}
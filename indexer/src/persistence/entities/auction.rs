@@ -9,7 +9,7 @@ use crate::{
     utils::{DecodeContext, KeyInfo},
 };
 
-use super::{Decode, Decoded};
+use super::{raw_data, Decode, Decoded};
 
 impl Decode for AuctionCreated {
     fn decode(&self, _ctx: &DecodeContext) -> Result<Decoded> {
@@ -27,7 +27,7 @@ impl Decode for AuctionCreated {
             message_hash: ctx.message_hash.to_string(),
             nft: Some(self.value0.auction_subject.to_string()),
             collection: Some(self.value0.collection.to_string()),
-            raw_data: serde_json::to_value(self).unwrap_or_default(),
+            raw_data: raw_data(self, EventType::AuctionCreated),
         }))
     }
 }
@@ -42,7 +42,7 @@ impl Decode for AuctionActive {
             start_price: u128_to_bigdecimal(self.value0.price),
             min_bid: u128_to_bigdecimal(self.value0.price),
             created_at: timestamp_to_datetime(self.value0.start_time.try_into()?),
-            finished_at: timestamp_to_datetime(self.value0.end_time.try_into()?),
+            finished_at: ctx.listing_end(self.value0.start_time, self.value0.end_time),
             tx_lt: ctx.tx_data.logical_time() as i64,
         };
 
@@ -60,7 +60,7 @@ impl Decode for AuctionActive {
             message_hash: ctx.message_hash.to_string(),
             nft: Some(self.value0.auction_subject.to_string()),
             collection: Some(self.value0.collection.to_string()),
-            raw_data: serde_json::to_value(self).unwrap_or_default(),
+            raw_data: raw_data(self, EventType::AuctionActive),
         }))
     }
 }
@@ -74,6 +74,7 @@ impl Decode for BidPlaced {
             nft_owner: self.value3.subject_owner.to_string(),
             price_token: self.value3.payment_token.to_string(),
             bid_value: u128_to_bigdecimal(self.value),
+            bid_value_normalized: None,
             next_value: u128_to_bigdecimal(self.next_bid_value),
             buyer: self.buyer.to_string(),
             created_at: timestamp_to_datetime(ctx.tx_data.get_timestamp()),
@@ -96,7 +97,7 @@ impl Decode for BidPlaced {
             nft: Some(self.value3.auction_subject.to_string()),
             collection: Some(self.value3.collection.to_string()),
 
-            raw_data: serde_json::to_value(self).unwrap_or_default(),
+            raw_data: raw_data(self, EventType::AuctionBidPlaced),
         }))
     }
 }
@@ -110,6 +111,7 @@ impl Decode for BidDeclined {
             nft_owner: self.value2.subject_owner.to_string(),
             price_token: self.value2.payment_token.to_string(),
             bid_value: u128_to_bigdecimal(self.value),
+            bid_value_normalized: None,
             next_value: Default::default(),
             buyer: self.buyer.to_string(),
             created_at: timestamp_to_datetime(ctx.tx_data.get_timestamp()),
@@ -132,7 +134,7 @@ impl Decode for BidDeclined {
             nft: Some(self.value2.auction_subject.to_string()),
             collection: Some(self.value2.collection.to_string()),
 
-            raw_data: serde_json::to_value(self).unwrap_or_default(),
+            raw_data: raw_data(self, EventType::AuctionBidDeclined),
         }))
     }
 }
@@ -142,6 +144,8 @@ impl Decode for AuctionComplete {
         let auc = decoded::AuctionComplete {
             address: ctx.tx_data.get_account(),
             max_bid: u128_to_bigdecimal(self.value),
+            winner: self.buyer.to_string(),
+            tx_lt: ctx.tx_data.logical_time() as i64,
         };
 
         let price_hist = decoded::NftPriceHistory {
@@ -150,9 +154,14 @@ impl Decode for AuctionComplete {
             created_at: timestamp_to_datetime(self.value2.start_time.try_into()?),
             price: u128_to_bigdecimal(self.value),
             price_token: self.value2.payment_token.to_string(),
+            price_token_symbol: None,
             usd_price: None,
+            marketplace_fee: None,
+            royalty: None,
             nft: self.value2.auction_subject.to_string(),
             collection: self.value2.collection.to_string(),
+            buyer: Some(self.buyer.to_string()),
+            seller: Some(self.value2.subject_owner.to_string()),
         };
 
         Ok(Decoded::AuctionComplete((auc, price_hist)))
@@ -170,7 +179,7 @@ impl Decode for AuctionComplete {
             nft: Some(self.value2.auction_subject.to_string()),
             collection: Some(self.value2.collection.to_string()),
 
-            raw_data: serde_json::to_value(self).unwrap_or_default(),
+            raw_data: raw_data(self, EventType::AuctionComplete),
         }))
     }
 }
@@ -179,6 +188,8 @@ impl Decode for AuctionCancelled {
     fn decode(&self, ctx: &DecodeContext) -> Result<Decoded> {
         let auc = decoded::AuctionCancelled {
             address: ctx.tx_data.get_account(),
+            finished_at: timestamp_to_datetime(ctx.tx_data.get_timestamp()),
+            tx_lt: ctx.tx_data.logical_time() as i64,
         };
 
         Ok(Decoded::AuctionCancelled(auc))
@@ -196,7 +207,88 @@ impl Decode for AuctionCancelled {
             nft: Some(self.value0.auction_subject.to_string()),
             collection: Some(self.value0.collection.to_string()),
 
-            raw_data: serde_json::to_value(self).unwrap_or_default(),
+            raw_data: raw_data(self, EventType::AuctionCancelled),
         }))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use ton_block::{MsgAddressInt, Transaction};
+    use ton_types::UInt256;
+
+    use crate::models::events::{AuctionActive, AuctionComplete, AuctionDeployed};
+    use crate::models::types::{AuctionDetails, AuctionStatus, MarketOffer};
+    use crate::persistence::entities::{Decode, Decoded};
+    use crate::test_harness::address;
+    use crate::utils::{timestamp_to_datetime, DecodeContext};
+
+    fn auction_details(status: AuctionStatus) -> AuctionDetails {
+        AuctionDetails {
+            auction_subject: address(1),
+            subject_owner: address(2),
+            payment_token: address(3),
+            wallet_for_bids: address(4),
+            start_time: 1_700_000_000,
+            duration: 3600,
+            end_time: 1_700_003_600,
+            price: 100,
+            nonce: 0,
+            status,
+            collection: address(5),
+        }
+    }
+
+    #[test]
+    fn test_auction_lifecycle() {
+        // The auction emits Active/Complete from its own account, which is the offer
+        // address announced by the factory in AuctionDeployed
+        let ctx = DecodeContext {
+            tx_data: Transaction::default(),
+            function_inputs: Vec::new(),
+            message_hash: UInt256::default(),
+            max_listing_lifetime_secs: u64::MAX,
+        };
+        let auction = MsgAddressInt::default().to_string();
+
+        let deployed = AuctionDeployed {
+            offer: MsgAddressInt::default(),
+            offer_info: MarketOffer {
+                collection: address(5),
+                nft_owner: address(2),
+                nft: address(1),
+                offer: MsgAddressInt::default(),
+                price: 100,
+                auction_duration: 3600,
+                deploy_nonce: 0,
+            },
+        };
+        let Decoded::AuctionDeployed((created, _)) = deployed.decode(&ctx).unwrap() else {
+            panic!("AuctionDeployed must seed the auction");
+        };
+        assert_eq!(created.address, auction);
+
+        let active = AuctionActive {
+            value0: auction_details(AuctionStatus::Active),
+        };
+        let Decoded::AuctionActive(active) = active.decode(&ctx).unwrap() else {
+            panic!("AuctionActive must open the auction");
+        };
+        assert_eq!(active.address, auction);
+        assert_eq!(active.finished_at, timestamp_to_datetime(1_700_003_600));
+
+        let complete = AuctionComplete {
+            buyer: address(6),
+            value: 250,
+            value2: auction_details(AuctionStatus::Complete),
+        };
+        let Decoded::AuctionComplete((complete, sale)) = complete.decode(&ctx).unwrap() else {
+            panic!("AuctionComplete must close the auction");
+        };
+        assert_eq!(complete.address, auction);
+        assert_eq!(complete.winner, address(6).to_string());
+        assert_eq!(complete.max_bid, 250.into());
+        assert_eq!(sale.source, auction);
+        assert_eq!(sale.buyer, Some(complete.winner.clone()));
+    }
+}
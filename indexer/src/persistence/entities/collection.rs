@@ -7,7 +7,7 @@ use crate::{
     utils::{DecodeContext, KeyInfo},
 };
 
-use super::{types::Decoded, Decode};
+use super::{raw_data, types::Decoded, Decode};
 
 impl Decode for NftCreated {
     fn decode(&self, ctx: &DecodeContext) -> Result<Decoded> {
@@ -17,9 +17,12 @@ impl Decode for NftCreated {
             collection: ctx.tx_data.get_account(),
             owner: self.owner.to_string(),
             manager: self.manager.to_string(),
+            minter: self.creator.to_string(),
             updated: timestamp_to_datetime(ctx.tx_data.get_timestamp()),
             owner_update_lt: ctx.tx_data.logical_time(),
             manager_update_lt: ctx.tx_data.logical_time(),
+            created_lt: ctx.tx_data.logical_time() as i64,
+            created_at: timestamp_to_datetime(ctx.tx_data.get_timestamp()),
         }))
     }
 
@@ -35,17 +38,19 @@ impl Decode for NftCreated {
             nft: Some(self.nft.to_string()),
             collection: Some(ctx.tx_data.get_account()),
 
-            raw_data: serde_json::to_value(self).unwrap_or_default(),
+            raw_data: raw_data(self, EventType::NftCreated),
         }))
     }
 }
 
 impl Decode for NftBurned {
-    fn decode(&self, _: &DecodeContext) -> Result<Decoded> {
+    fn decode(&self, ctx: &DecodeContext) -> Result<Decoded> {
         let record = decoded::NftBurned {
             address: self.nft.to_string(),
             owner: self.owner.to_string(),
             manager: self.manager.to_string(),
+            burned_at: timestamp_to_datetime(ctx.tx_data.get_timestamp()),
+            tx_lt: ctx.tx_data.logical_time() as i64,
         };
 
         Ok(Decoded::BurnNft(record))
@@ -63,7 +68,47 @@ impl Decode for NftBurned {
             nft: Some(self.nft.to_string()),
             collection: Some(ctx.tx_data.get_account()),
 
-            raw_data: serde_json::to_value(self).unwrap_or_default(),
+            raw_data: raw_data(self, EventType::NftBurned),
         }))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use ton_block::{MsgAddressInt, Transaction};
+    use ton_types::UInt256;
+
+    use crate::models::events::NftBurned;
+    use crate::persistence::entities::{Decode, Decoded};
+    use crate::test_harness::address;
+    use crate::utils::{timestamp_to_datetime, DecodeContext};
+
+    #[test]
+    fn test_burn_is_decoded_with_its_logical_time() {
+        let nft = address(1);
+        let burned = NftBurned {
+            id: UInt256::default(),
+            nft: nft.clone(),
+            owner: MsgAddressInt::default(),
+            manager: MsgAddressInt::default(),
+        };
+        let mut tx_data = Transaction::default();
+        tx_data.set_logical_time(42);
+        tx_data.now = 1_700_000_000;
+        let ctx = DecodeContext {
+            tx_data,
+            function_inputs: Vec::new(),
+            message_hash: UInt256::default(),
+            max_listing_lifetime_secs: u64::MAX,
+        };
+
+        let Decoded::BurnNft(burn) = burned.decode(&ctx).unwrap() else {
+            panic!("NftBurned must decode to a burn");
+        };
+
+        // Active offers of the nft are cancelled at this point
+        assert_eq!(burn.address, nft.to_string());
+        assert_eq!(burn.tx_lt, 42);
+        assert_eq!(burn.burned_at, timestamp_to_datetime(1_700_000_000));
+    }
+}
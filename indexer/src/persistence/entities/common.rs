@@ -10,7 +10,7 @@ use crate::{
     utils::{DecodeContext, KeyInfo},
 };
 
-use super::{Decode, Decoded};
+use super::{raw_data, Decode, Decoded};
 
 impl Decode for OwnershipTransferred {
     fn decode(&self, _: &DecodeContext) -> Result<Decoded> {
@@ -29,14 +29,20 @@ impl Decode for OwnershipTransferred {
             nft: None,
             collection: None,
 
-            raw_data: serde_json::to_value(self).unwrap_or_default(),
+            raw_data: raw_data(self, EventType::OwnershipTransferred),
         }))
     }
 }
 
 impl Decode for MarketFeeDefaultChanged {
-    fn decode(&self, _: &DecodeContext) -> Result<Decoded> {
-        Ok(Decoded::ShouldSkip)
+    fn decode(&self, ctx: &DecodeContext) -> Result<Decoded> {
+        Ok(Decoded::MarketFeeChanged(decoded::MarketplaceFee {
+            address: ctx.tx_data.get_account(),
+            numerator: self.fee.numerator.try_into()?,
+            denominator: self.fee.denominator.try_into()?,
+            changed_at: timestamp_to_datetime(ctx.tx_data.get_timestamp()),
+            changed_lt: ctx.tx_data.logical_time() as i64,
+        }))
     }
 
     fn decode_event(&self, ctx: &DecodeContext) -> Result<Decoded> {
@@ -51,14 +57,20 @@ impl Decode for MarketFeeDefaultChanged {
             nft: None,
             collection: None,
 
-            raw_data: serde_json::to_value(self).unwrap_or_default(),
+            raw_data: raw_data(self, EventType::MarketFeeDefaultChanged),
         }))
     }
 }
 
 impl Decode for MarketFeeChanged {
-    fn decode(&self, _: &DecodeContext) -> Result<Decoded> {
-        Ok(Decoded::ShouldSkip)
+    fn decode(&self, ctx: &DecodeContext) -> Result<Decoded> {
+        Ok(Decoded::MarketFeeChanged(decoded::MarketplaceFee {
+            address: self.auction.to_string(),
+            numerator: self.fee.numerator.try_into()?,
+            denominator: self.fee.denominator.try_into()?,
+            changed_at: timestamp_to_datetime(ctx.tx_data.get_timestamp()),
+            changed_lt: ctx.tx_data.logical_time() as i64,
+        }))
     }
 
     fn decode_event(&self, ctx: &DecodeContext) -> Result<Decoded> {
@@ -73,7 +85,7 @@ impl Decode for MarketFeeChanged {
             nft: None,
             collection: None,
 
-            raw_data: serde_json::to_value(self).unwrap_or_default(),
+            raw_data: raw_data(self, EventType::MarketFeeChanged),
         }))
     }
 }
@@ -100,7 +112,7 @@ impl Decode for AddCollectionRules {
             nft: None,
             collection: Some(self.collection.to_string()),
 
-            raw_data: serde_json::to_value(self).unwrap_or_default(),
+            raw_data: raw_data(self, EventType::AddCollectionRules),
         }))
     }
 }
@@ -127,7 +139,7 @@ impl Decode for RemoveCollectionRules {
             nft: None,
             collection: Some(self.collection.to_string()),
 
-            raw_data: serde_json::to_value(self).unwrap_or_default(),
+            raw_data: raw_data(self, EventType::RemoveCollectionRules),
         }))
     }
 }
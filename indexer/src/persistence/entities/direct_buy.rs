@@ -1,7 +1,7 @@
 use anyhow::Result;
 use indexer_repo::types::{decoded, DirectBuyState, EventCategory, EventType, NftPriceSource};
 
-use crate::persistence::entities::{Decode, Decoded};
+use crate::persistence::entities::{raw_data, Decode, Decoded};
 use crate::utils::{timestamp_to_datetime, u128_to_bigdecimal};
 use crate::{
     models::events::DirectBuyStateChanged,
@@ -29,9 +29,14 @@ impl Decode for DirectBuyStateChanged {
                 created_at: finished_at.unwrap(),
                 price: u128_to_bigdecimal(self.value2._price),
                 price_token: self.value2.spent_token.to_string(),
+                price_token_symbol: None,
                 usd_price: None,
+                marketplace_fee: None,
+                royalty: None,
                 nft: self.value2.nft.to_string(),
                 collection: self.value2.collection.to_string(),
+                buyer: Some(self.value2.creator.to_string()),
+                seller: Some(self.old_owner.to_string()),
             })
         } else {
             None
@@ -46,7 +51,7 @@ impl Decode for DirectBuyStateChanged {
             price: u128_to_bigdecimal(self.value2._price),
             buyer: self.value2.creator.to_string(),
             finished_at,
-            expired_at: timestamp_to_datetime(self.value2.end_time_buy.try_into()?),
+            expired_at: ctx.listing_end(self.value2.start_time_buy, self.value2.end_time_buy),
             state,
             created: timestamp_to_datetime(self.value2.start_time_buy.try_into()?),
             updated: timestamp_to_datetime(ctx.tx_data.get_timestamp()),
@@ -67,7 +72,86 @@ impl Decode for DirectBuyStateChanged {
             message_hash: ctx.message_hash.to_string(),
             nft: Some(self.value2.nft.to_string()),
             collection: Some(self.value2.collection.to_string()),
-            raw_data: serde_json::to_value(self).unwrap_or_default(),
+            raw_data: raw_data(self, EventType::DirectBuyStateChanged),
         }))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use indexer_repo::types::NftPriceSource;
+    use ton_block::{MsgAddressInt, Transaction};
+    use ton_types::UInt256;
+
+    use crate::models::events::DirectBuyStateChanged;
+    use crate::models::types::DirectBuyInfo;
+    use crate::persistence::entities::{Decode, Decoded};
+    use crate::test_harness::address;
+    use crate::utils::DecodeContext;
+
+    fn direct_buy_changed(to: u8) -> DirectBuyStateChanged {
+        DirectBuyStateChanged {
+            from: 2,
+            to,
+            value2: DirectBuyInfo {
+                factory: MsgAddressInt::default(),
+                creator: address(1),
+                spent_token: MsgAddressInt::default(),
+                nft: address(2),
+                _time_tx: 0,
+                _price: 10,
+                spent_wallet: MsgAddressInt::default(),
+                status: 0,
+                start_time_buy: 0,
+                duration_time_buy: 0,
+                end_time_buy: 0,
+                collection: address(3),
+            },
+            old_owner: address(4),
+            new_owner: address(1),
+        }
+    }
+
+    fn decode_context() -> DecodeContext {
+        DecodeContext {
+            tx_data: Transaction::default(),
+            function_inputs: Vec::new(),
+            message_hash: UInt256::default(),
+            max_listing_lifetime_secs: u64::MAX,
+        }
+    }
+
+    #[test]
+    fn test_pending_direct_buy_is_skipped() {
+        for to in [0, 1] {
+            assert!(matches!(
+                direct_buy_changed(to).decode(&decode_context()).unwrap(),
+                Decoded::ShouldSkip
+            ));
+        }
+    }
+
+    #[test]
+    fn test_only_filled_direct_buy_records_a_sale() {
+        let Decoded::DirectBuyStateChanged((_, price)) =
+            direct_buy_changed(2).decode(&decode_context()).unwrap()
+        else {
+            panic!("Active direct buy must be decoded");
+        };
+        assert!(price.is_none());
+
+        let Decoded::DirectBuyStateChanged((direct_buy, Some(price))) =
+            direct_buy_changed(3).decode(&decode_context()).unwrap()
+        else {
+            panic!("Filled direct buy must be decoded with its sale");
+        };
+
+        assert_eq!(price.source, direct_buy.address);
+        assert_eq!(price.source_type, NftPriceSource::DirectBuy);
+        assert_eq!(price.nft, address(2).to_string());
+        assert_eq!(price.collection, address(3).to_string());
+        assert_eq!(price.buyer, Some(address(1).to_string()));
+        assert_eq!(price.seller, Some(address(4).to_string()));
+        assert_eq!(Some(price.created_at), direct_buy.finished_at);
+    }
+}
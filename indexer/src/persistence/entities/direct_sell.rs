@@ -1,8 +1,8 @@
 use anyhow::Result;
 use indexer_repo::types::{decoded, DirectSellState, EventCategory, EventType, NftPriceSource};
 
-use crate::persistence::entities::{Decode, Decoded};
-use crate::utils::{timestamp_to_datetime, u128_to_bigdecimal};
+use crate::persistence::entities::{raw_data, Decode, Decoded};
+use crate::utils::{is_zero_address, timestamp_to_datetime, to_datetime, u128_to_bigdecimal};
 use crate::{
     models::events::DirectSellStateChanged,
     utils::{DecodeContext, KeyInfo},
@@ -10,31 +10,45 @@ use crate::{
 
 impl Decode for DirectSellStateChanged {
     fn decode(&self, ctx: &DecodeContext) -> Result<Decoded> {
-        let state = self.to.into();
-
-        if state == DirectSellState::Create || state == DirectSellState::AwaitNft {
+        if is_zero_address(&self.value2.nft) {
+            log::warn!(
+                "DirectSellStateChanged with zero nft address, skipping (address: {}, message hash: {})",
+                ctx.tx_data.get_account(),
+                ctx.message_hash.to_string()
+            );
             return Ok(Decoded::ShouldSkip);
         }
 
-        let finished_at = if state == DirectSellState::Filled {
-            Some(timestamp_to_datetime(ctx.tx_data.get_timestamp()))
-        } else {
-            None
-        };
+        let state = self.to.into();
 
-        let price_history = if state == DirectSellState::Filled {
-            Some(decoded::NftPriceHistory {
-                source: ctx.tx_data.get_account(),
-                source_type: NftPriceSource::DirectSell,
-                created_at: finished_at.unwrap(),
-                price: u128_to_bigdecimal(self.value2._price),
-                price_token: self.value2.token.to_string(),
-                usd_price: None,
-                nft: self.value2.nft.to_string(),
-                collection: self.value2.collection.to_string(),
-            })
-        } else {
-            None
+        // Only a sale finishes the listing and is a price point, a cancelled or expired
+        // listing just ends
+        let (finished_at, price_history) = match state {
+            DirectSellState::Create | DirectSellState::AwaitNft => {
+                return Ok(Decoded::ShouldSkip);
+            }
+            DirectSellState::Filled => {
+                let finished_at = timestamp_to_datetime(ctx.tx_data.get_timestamp());
+                let price_history = decoded::NftPriceHistory {
+                    source: ctx.tx_data.get_account(),
+                    source_type: NftPriceSource::DirectSell,
+                    created_at: finished_at,
+                    price: u128_to_bigdecimal(self.value2._price),
+                    price_token: self.value2.token.to_string(),
+                    price_token_symbol: None,
+                    usd_price: None,
+                    marketplace_fee: None,
+                    royalty: None,
+                    nft: self.value2.nft.to_string(),
+                    collection: self.value2.collection.to_string(),
+                    buyer: Some(self.new_owner.to_string()),
+                    seller: Some(self.value2.creator.to_string()),
+                };
+                (Some(finished_at), Some(price_history))
+            }
+            DirectSellState::Active | DirectSellState::Cancelled | DirectSellState::Expired => {
+                (None, None)
+            }
         };
 
         let direct_sell = decoded::DirectSell {
@@ -43,12 +57,14 @@ impl Decode for DirectSellStateChanged {
             nft: self.value2.nft.to_string(),
             collection: Some(self.value2.collection.to_string()),
             price_token: self.value2.token.to_string(),
+            price_token_symbol: None,
             price: u128_to_bigdecimal(self.value2._price),
+            price_normalized: None,
             seller: self.value2.creator.to_string(),
             finished_at,
-            expired_at: timestamp_to_datetime(self.value2.end.try_into()?),
+            expired_at: ctx.listing_end(self.value2.start, self.value2.end),
             state,
-            created: timestamp_to_datetime(self.value2.start.try_into()?),
+            created: to_datetime(self.value2.start),
             updated: timestamp_to_datetime(ctx.tx_data.get_timestamp()),
             tx_lt: ctx.tx_data.logical_time() as i64,
         };
@@ -60,6 +76,10 @@ impl Decode for DirectSellStateChanged {
     }
 
     fn decode_event(&self, ctx: &DecodeContext) -> Result<Decoded> {
+        if is_zero_address(&self.value2.nft) {
+            return Ok(Decoded::ShouldSkip);
+        }
+
         Ok(Decoded::RawEventRecord(decoded::EventRecord {
             event_category: EventCategory::DirectSell,
             event_type: EventType::DirectSellStateChanged,
@@ -70,7 +90,183 @@ impl Decode for DirectSellStateChanged {
             message_hash: ctx.message_hash.to_string(),
             nft: Some(self.value2.nft.to_string()),
             collection: Some(self.value2.collection.to_string()),
-            raw_data: serde_json::to_value(self).unwrap_or_default(),
+            raw_data: raw_data(self, EventType::DirectSellStateChanged),
         }))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use indexer_repo::types::DirectSellState;
+    use ton_block::{MsgAddressInt, Transaction};
+    use ton_types::UInt256;
+
+    use crate::models::events::DirectSellStateChanged;
+    use crate::models::types::DirectSellInfo;
+    use crate::persistence::entities::{Decode, Decoded};
+    use crate::test_harness::address;
+    use crate::utils::{timestamp_to_datetime, DecodeContext};
+
+    fn direct_sell_changed(nft: MsgAddressInt, start: u64, end: u64) -> DirectSellStateChanged {
+        DirectSellStateChanged {
+            from: 1,
+            to: 2,
+            value2: DirectSellInfo {
+                factory: MsgAddressInt::default(),
+                creator: MsgAddressInt::default(),
+                token: MsgAddressInt::default(),
+                nft,
+                _time_tx: 0,
+                start,
+                end,
+                _price: 1,
+                wallet: MsgAddressInt::default(),
+                status: 0,
+                collection: MsgAddressInt::default(),
+            },
+            old_owner: MsgAddressInt::default(),
+            new_owner: MsgAddressInt::default(),
+        }
+    }
+
+    fn decode_context(max_listing_lifetime_secs: u64) -> DecodeContext {
+        DecodeContext {
+            tx_data: Transaction::default(),
+            function_inputs: Vec::new(),
+            message_hash: UInt256::default(),
+            max_listing_lifetime_secs,
+        }
+    }
+
+    #[test]
+    fn test_zero_nft_address_is_skipped() {
+        let event = direct_sell_changed(MsgAddressInt::default(), 0, 0);
+        let ctx = decode_context(u64::MAX);
+
+        assert!(matches!(event.decode(&ctx).unwrap(), Decoded::ShouldSkip));
+        assert!(matches!(
+            event.decode_event(&ctx).unwrap(),
+            Decoded::ShouldSkip
+        ));
+    }
+
+    #[test]
+    fn test_absurd_end_is_clamped() {
+        let nft = address(1);
+        let start = 1_700_000_000;
+        let year = 365 * 24 * 60 * 60;
+        let event = direct_sell_changed(nft, start, u64::MAX);
+
+        let Decoded::DirectSellStateChanged((direct_sell, _)) =
+            event.decode(&decode_context(year)).unwrap()
+        else {
+            panic!("Active direct sell must be decoded");
+        };
+
+        assert_eq!(
+            direct_sell.expired_at,
+            timestamp_to_datetime((start + year) as i64)
+        );
+    }
+
+    #[test]
+    fn test_missing_start_is_not_the_epoch() {
+        let decoded = |start| {
+            let Decoded::DirectSellStateChanged((direct_sell, _)) =
+                direct_sell_changed(address(1), start, 0)
+                    .decode(&decode_context(u64::MAX))
+                    .unwrap()
+            else {
+                panic!("Active direct sell must be decoded");
+            };
+            direct_sell
+        };
+
+        assert_eq!(decoded(0).created, None);
+        assert_eq!(decoded(u64::MAX).created, None);
+        assert_eq!(
+            decoded(1_700_000_000).created,
+            Some(timestamp_to_datetime(1_700_000_000))
+        );
+    }
+
+    #[test]
+    fn test_collection_is_not_the_nft() {
+        let nft = address(1);
+        let collection = address(2);
+        let mut event = direct_sell_changed(nft, 0, 0);
+        event.to = 3;
+        event.value2.collection = collection.clone();
+
+        let Decoded::DirectSellStateChanged((direct_sell, Some(price))) =
+            event.decode(&decode_context(u64::MAX)).unwrap()
+        else {
+            panic!("Filled direct sell must be decoded with its sale");
+        };
+
+        assert_eq!(direct_sell.collection, Some(collection.to_string()));
+        assert_eq!(price.collection, collection.to_string());
+    }
+
+    fn decode_transition(to: DirectSellState) -> Decoded {
+        let mut event = direct_sell_changed(address(1), 0, 0);
+        event.from = DirectSellState::Active as u8;
+        event.to = to as u8;
+        let mut ctx = decode_context(u64::MAX);
+        ctx.tx_data.now = 1_700_000_000;
+
+        event.decode(&ctx).unwrap()
+    }
+
+    #[test]
+    fn test_filled_direct_sell_is_finished_with_a_sale() {
+        let Decoded::DirectSellStateChanged((direct_sell, Some(price))) =
+            decode_transition(DirectSellState::Filled)
+        else {
+            panic!("Filled direct sell must be decoded with its sale");
+        };
+
+        let sold_at = timestamp_to_datetime(1_700_000_000);
+        assert_eq!(direct_sell.state, DirectSellState::Filled);
+        assert_eq!(direct_sell.finished_at, Some(sold_at));
+        assert_eq!(price.created_at, sold_at);
+    }
+
+    #[test]
+    fn test_cancelled_direct_sell_is_not_a_sale() {
+        let Decoded::DirectSellStateChanged((direct_sell, price)) =
+            decode_transition(DirectSellState::Cancelled)
+        else {
+            panic!("Cancelled direct sell must be decoded");
+        };
+
+        assert_eq!(direct_sell.state, DirectSellState::Cancelled);
+        assert_eq!(direct_sell.finished_at, None);
+        assert!(price.is_none());
+    }
+
+    #[test]
+    fn test_expired_direct_sell_is_not_a_sale() {
+        let Decoded::DirectSellStateChanged((direct_sell, price)) =
+            decode_transition(DirectSellState::Expired)
+        else {
+            panic!("Expired direct sell must be decoded");
+        };
+
+        assert_eq!(direct_sell.state, DirectSellState::Expired);
+        assert_eq!(direct_sell.finished_at, None);
+        assert!(price.is_none());
+    }
+
+    #[test]
+    fn test_pending_direct_sell_is_skipped() {
+        assert!(matches!(
+            decode_transition(DirectSellState::Create),
+            Decoded::ShouldSkip
+        ));
+        assert!(matches!(
+            decode_transition(DirectSellState::AwaitNft),
+            Decoded::ShouldSkip
+        ));
+    }
+}
@@ -7,7 +7,7 @@ use crate::{
     utils::{DecodeContext, KeyInfo},
 };
 
-use super::{Decode, Decoded};
+use super::{raw_data, Decode, Decoded};
 
 impl Decode for AuctionDeployed {
     fn decode(&self, ctx: &DecodeContext) -> Result<Decoded> {
@@ -39,7 +39,7 @@ impl Decode for AuctionDeployed {
             nft: Some(self.offer_info.nft.to_string()),
             collection: Some(self.offer_info.collection.to_string()),
 
-            raw_data: serde_json::to_value(self).unwrap_or_default(),
+            raw_data: raw_data(self, EventType::AuctionDeployed),
         }))
     }
 }
@@ -61,7 +61,7 @@ impl Decode for AuctionDeclined {
             nft: Some(self.nft.to_string()),
             collection: None,
 
-            raw_data: serde_json::to_value(self).unwrap_or_default(),
+            raw_data: raw_data(self, EventType::AuctionDeclined),
         }))
     }
 }
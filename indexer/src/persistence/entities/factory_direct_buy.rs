@@ -1,7 +1,7 @@
 use anyhow::Result;
 use indexer_repo::types::{decoded, DirectBuyState, EventCategory, EventType};
 
-use crate::persistence::entities::{Decode, Decoded};
+use crate::persistence::entities::{raw_data, Decode, Decoded};
 use crate::utils::{timestamp_to_datetime, u128_to_bigdecimal};
 use crate::{
     models::events::{DirectBuyDeclined, DirectBuyDeployed},
@@ -37,7 +37,7 @@ impl Decode for DirectBuyDeployed {
     fn decode_event(&self, ctx: &DecodeContext) -> Result<Decoded> {
         Ok(Decoded::RawEventRecord(decoded::EventRecord {
             event_category: EventCategory::DirectBuy,
-            event_type: EventType::DirectBuyDeclined,
+            event_type: EventType::DirectBuyDeployed,
 
             address: ctx.tx_data.get_account(),
             created_lt: ctx.tx_data.logical_time() as i64,
@@ -46,7 +46,7 @@ impl Decode for DirectBuyDeployed {
             nft: Some(self.nft.to_string()),
             collection: None,
 
-            raw_data: serde_json::to_value(self).unwrap_or_default(),
+            raw_data: raw_data(self, EventType::DirectBuyDeployed),
         }))
     }
 }
@@ -68,7 +68,7 @@ impl Decode for DirectBuyDeclined {
             nft: Some(self.nft.to_string()),
             collection: None,
 
-            raw_data: serde_json::to_value(self).unwrap_or_default(),
+            raw_data: raw_data(self, EventType::DirectBuyDeclined),
         }))
     }
 }
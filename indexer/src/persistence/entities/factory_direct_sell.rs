@@ -1,7 +1,7 @@
 use anyhow::Result;
 use indexer_repo::types::{decoded, DirectSellState, EventCategory, EventType};
 
-use crate::persistence::entities::{Decode, Decoded};
+use crate::persistence::entities::{raw_data, Decode, Decoded};
 use crate::utils::{timestamp_to_datetime, u128_to_bigdecimal};
 use crate::{
     models::events::{DirectSellDeclined, DirectSellDeployed},
@@ -17,13 +17,15 @@ impl Decode for DirectSellDeployed {
                 nft: self.nft.to_string(),
                 collection: None,
                 price_token: self.payment_token.to_string(),
+                price_token_symbol: None,
                 price: u128_to_bigdecimal(self.price),
+                price_normalized: None,
                 seller: self.sender.to_string(),
                 finished_at: None,
                 expired_at: Default::default(),
                 state: DirectSellState::Create,
-                created: Default::default(),
-                updated: Default::default(),
+                created: None,
+                updated: timestamp_to_datetime(ctx.tx_data.get_timestamp()),
                 tx_lt: ctx.tx_data.logical_time() as i64,
             },
             decoded::OfferDeployed {
@@ -45,7 +47,7 @@ impl Decode for DirectSellDeployed {
             message_hash: ctx.message_hash.to_string(),
             nft: Some(self.nft.to_string()),
             collection: None,
-            raw_data: serde_json::to_value(self).unwrap_or_default(),
+            raw_data: raw_data(self, EventType::DirectSellDeployed),
         }))
     }
 }
@@ -67,7 +69,7 @@ impl Decode for DirectSellDeclined {
             nft: Some(self.nft.to_string()),
             collection: None,
 
-            raw_data: serde_json::to_value(self).unwrap_or_default(),
+            raw_data: raw_data(self, EventType::DirectSellDeclined),
         }))
     }
 }
@@ -1,5 +1,8 @@
 use anyhow::Result;
+use indexer_repo::types::EventType;
+use serde::Serialize;
 
+use crate::metrics;
 use crate::utils::DecodeContext;
 
 pub use self::types::Decoded;
@@ -19,3 +22,49 @@ pub trait Decode {
     fn decode(&self, ctx: &DecodeContext) -> Result<Decoded>;
     fn decode_event(&self, ctx: &DecodeContext) -> Result<Decoded>;
 }
+
+/// `args` stored with the raw event. An event that fails to serialize is still stored,
+/// with null args, but is logged and counted so it can't pass for an empty one
+fn raw_data<T: Serialize>(event: &T, event_type: EventType) -> serde_json::Value {
+    serde_json::to_value(event).unwrap_or_else(|e| {
+        metrics::SERIALIZATION_FAILURES
+            .with_label_values(&[&format!("{event_type:?}")])
+            .inc();
+        log::warn!("METRIC | Failed to serialize {event_type:?} args: {e}");
+        serde_json::Value::Null
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use indexer_repo::types::EventType;
+
+    use crate::metrics::SERIALIZATION_FAILURES;
+
+    use super::raw_data;
+
+    #[test]
+    fn test_unserializable_args_are_counted_and_stored_as_null() {
+        let failures = || {
+            SERIALIZATION_FAILURES
+                .with_label_values(&["NftCreated"])
+                .get()
+        };
+        let before = failures();
+
+        let unserializable = HashMap::from([((1, 2), "map keys must be strings")]);
+        assert_eq!(
+            raw_data(&unserializable, EventType::NftCreated),
+            serde_json::Value::Null
+        );
+        assert_eq!(failures(), before + 1);
+
+        assert_eq!(
+            raw_data(&HashMap::from([("id", 1)]), EventType::NftCreated),
+            serde_json::json!({ "id": 1 })
+        );
+        assert_eq!(failures(), before + 1);
+    }
+}
@@ -7,12 +7,13 @@ use crate::{
     utils::{DecodeContext, KeyInfo},
 };
 
-use super::{types::Decoded, Decode};
+use super::{raw_data, types::Decoded, Decode};
 
 impl Decode for OwnerChanged {
     fn decode(&self, ctx: &DecodeContext) -> Result<Decoded> {
         let nft_new_owner = decoded::AddressChanged {
             id_address: ctx.tx_data.get_account(),
+            old_address: self.old_owner.to_string(),
             new_address: self.new_owner.to_string(),
             logical_time: ctx.tx_data.logical_time(),
             timestamp: timestamp_to_datetime(ctx.tx_data.get_timestamp()),
@@ -32,7 +33,7 @@ impl Decode for OwnerChanged {
             message_hash: ctx.message_hash.to_string(),
             nft: Some(ctx.tx_data.get_account()),
             collection: None,
-            raw_data: serde_json::to_value(self).unwrap_or_default(),
+            raw_data: raw_data(self, EventType::NftOwnerChanged),
         }))
     }
 }
@@ -41,6 +42,7 @@ impl Decode for ManagerChanged {
     fn decode(&self, ctx: &DecodeContext) -> Result<Decoded> {
         let nft_new_manager = decoded::AddressChanged {
             id_address: ctx.tx_data.get_account(),
+            old_address: self.old_manager.to_string(),
             new_address: self.new_manager.to_string(),
             logical_time: ctx.tx_data.logical_time(),
             timestamp: timestamp_to_datetime(ctx.tx_data.get_timestamp()),
@@ -60,7 +62,7 @@ impl Decode for ManagerChanged {
             message_hash: ctx.message_hash.to_string(),
             nft: Some(ctx.tx_data.get_account()),
             collection: None,
-            raw_data: serde_json::to_value(self).unwrap_or_default(),
+            raw_data: raw_data(self, EventType::NftManagerChanged),
         }))
     }
 }
@@ -1,5 +1,6 @@
 use indexer_repo::types::decoded::*;
 
+#[derive(Clone)]
 pub enum Decoded {
     ShouldSkip,
     CreateNft(NftCreated),
@@ -14,8 +15,15 @@ pub enum Decoded {
     AuctionCancelled(AuctionCancelled),
     RawEventRecord(EventRecord),
     AuctionRulesChanged(CollectionFee),
+    MarketFeeChanged(MarketplaceFee),
     DirectBuyDeployed((DirectBuy, OfferDeployed)),
     DirectBuyStateChanged((DirectBuy, Option<NftPriceHistory>)),
     DirectSellDeployed((DirectSell, OfferDeployed)),
     DirectSellStateChanged((DirectSell, Option<NftPriceHistory>)),
+    DecodeFailed(FailedEvent),
+    RawTransaction(RawEventTransaction),
+    /// Raw record of a paused parser's event, by parser
+    ParkedEvent((&'static str, EventRecord)),
+    /// Ids of the parked events applied in the same batch
+    UnparkedEvents(Vec<i64>),
 }
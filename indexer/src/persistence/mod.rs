@@ -1,2 +1,4 @@
+pub mod collection_cache;
 pub mod collections_queue;
 pub(crate) mod entities;
+pub mod retry;
@@ -0,0 +1,118 @@
+use std::future::Future;
+use std::time::Duration;
+
+use anyhow::Result;
+
+/// Serialization failure and deadlock; class 08 (connection exception) is matched by prefix
+const RETRYABLE_SQLSTATES: &[&str] = &["40001", "40P01"];
+
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+}
+
+/// Re-runs `f` with exponential backoff while it fails with a transient
+/// database error. Other errors, e.g. constraint violations, are returned as is.
+pub async fn with_retry<T, F, Fut>(policy: &RetryPolicy, mut f: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut attempt = 0;
+    loop {
+        match f().await {
+            Err(e) if attempt < policy.max_retries && is_retryable(&e) => {
+                let delay = policy
+                    .base_delay
+                    .saturating_mul(2u32.saturating_pow(attempt));
+                attempt += 1;
+                log::warn!(
+                    "Transient database error, retrying in {}ms ({}/{}): {:#}",
+                    delay.as_millis(),
+                    attempt,
+                    policy.max_retries,
+                    e
+                );
+                tokio::time::sleep(delay).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+pub fn is_retryable(error: &anyhow::Error) -> bool {
+    error
+        .chain()
+        .any(|cause| match cause.downcast_ref::<sqlx::Error>() {
+            Some(sqlx::Error::Database(e)) => e.code().map_or(false, |code| {
+                RETRYABLE_SQLSTATES.contains(&code.as_ref()) || code.starts_with("08")
+            }),
+            Some(sqlx::Error::Io(_) | sqlx::Error::PoolTimedOut) => true,
+            _ => false,
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::Cell;
+    use std::time::Duration;
+
+    use anyhow::anyhow;
+
+    use super::{with_retry, RetryPolicy};
+
+    const POLICY: RetryPolicy = RetryPolicy {
+        max_retries: 3,
+        base_delay: Duration::ZERO,
+    };
+
+    fn connection_reset() -> sqlx::Error {
+        sqlx::Error::Io(std::io::Error::from(std::io::ErrorKind::ConnectionReset))
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_errors() {
+        let calls = Cell::new(0);
+        let result = with_retry(&POLICY, || {
+            calls.set(calls.get() + 1);
+            let call = calls.get();
+            async move {
+                if call < 3 {
+                    Err(anyhow!(connection_reset()))
+                } else {
+                    Ok(call)
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(result.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_does_not_retry_other_errors() {
+        let calls = Cell::new(0);
+        let result: anyhow::Result<()> = with_retry(&POLICY, || {
+            calls.set(calls.get() + 1);
+            async { Err(anyhow!(sqlx::Error::RowNotFound)) }
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_retries() {
+        let calls = Cell::new(0);
+        let result: anyhow::Result<()> = with_retry(&POLICY, || {
+            calls.set(calls.get() + 1);
+            async { Err(anyhow!(connection_reset())) }
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(calls.get(), 4);
+    }
+}
@@ -0,0 +1,85 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bigdecimal::BigDecimal;
+use data_reader::PriceReader;
+
+/// Source of token rates, injectable so tests can use a fixed table
+#[async_trait]
+pub trait UsdRates: Send + Sync {
+    /// USD value of one minimal unit of `token` (token decimals applied)
+    async fn usd_rate(&self, token: &str, timestamp: u64) -> Option<BigDecimal>;
+}
+
+#[async_trait]
+impl UsdRates for PriceReader {
+    async fn usd_rate(&self, token: &str, timestamp: u64) -> Option<BigDecimal> {
+        self.get_current_usd_price(token, timestamp).await
+    }
+}
+
+#[derive(Clone)]
+pub struct UsdConverter {
+    rates: Arc<dyn UsdRates>,
+}
+
+impl UsdConverter {
+    pub fn new(rates: Arc<dyn UsdRates>) -> Self {
+        Self { rates }
+    }
+
+    /// USD value of a raw `price` in `price_token`, `None` if the token has no rate
+    pub async fn to_usd(
+        &self,
+        price_token: &str,
+        price: &BigDecimal,
+        timestamp: u64,
+    ) -> Option<BigDecimal> {
+        self.rates
+            .usd_rate(price_token, timestamp)
+            .await
+            .map(|rate| price * rate)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::str::FromStr;
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+    use bigdecimal::BigDecimal;
+
+    use super::{UsdConverter, UsdRates};
+
+    struct FixedRates(HashMap<&'static str, BigDecimal>);
+
+    #[async_trait]
+    impl UsdRates for FixedRates {
+        async fn usd_rate(&self, token: &str, _: u64) -> Option<BigDecimal> {
+            self.0.get(token).cloned()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_converts_with_fixed_rates() {
+        // 9 decimals, 0.25 USD per whole token
+        let rates = FixedRates(HashMap::from([(
+            "0:wever",
+            BigDecimal::from_str("0.00000000025").unwrap(),
+        )]));
+        let converter = UsdConverter::new(Arc::new(rates));
+
+        assert_eq!(
+            converter
+                .to_usd("0:wever", &BigDecimal::from(4_000_000_000u64), 0)
+                .await,
+            Some(BigDecimal::from(1))
+        );
+        assert_eq!(
+            converter.to_usd("0:unknown", &BigDecimal::from(1), 0).await,
+            None
+        );
+    }
+}
@@ -0,0 +1,35 @@
+use std::time::Duration;
+
+use data_reader::RarityQueue;
+use indexer_repo::rarity::recompute_collection_rarity;
+use sqlx::PgPool;
+use tokio::sync::watch;
+
+/// Ranks of a collection that minted within this window are provisional
+const MINTING_WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Recomputes the rarity of marked collections every `period`
+pub async fn run(
+    pool: PgPool,
+    queue: RarityQueue,
+    period: Duration,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    let mut interval = tokio::time::interval(period);
+
+    loop {
+        tokio::select! {
+            biased;
+            Ok(()) = shutdown.changed() => break,
+            _ = interval.tick() => {}
+        }
+
+        let minting_since = chrono::Utc::now().timestamp() - MINTING_WINDOW.as_secs() as i64;
+        for collection in queue.take() {
+            if let Err(e) = recompute_collection_rarity(&pool, &collection, minting_since).await {
+                log::error!("Failed to recompute rarity of {collection}: {:#?}", e);
+                queue.mark(collection);
+            }
+        }
+    }
+}
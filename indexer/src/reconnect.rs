@@ -0,0 +1,152 @@
+use std::time::Duration;
+
+use indexer_repo::error::IndexerError;
+use sqlx::PgPool;
+use tokio::sync::watch;
+use transaction_buffer::models::BufferedConsumerChannels;
+
+use crate::settings;
+use crate::settings::config::Config;
+
+const DEFAULT_MAX_ATTEMPTS: u32 = 10;
+const DEFAULT_BASE_DELAY_MS: u64 = 1_000;
+const MAX_DELAY: Duration = Duration::from_secs(300);
+
+/// Delays between reconnect attempts, doubling from `base_delay` up to `MAX_DELAY`
+#[derive(Clone, Debug)]
+pub struct Backoff {
+    max_attempts: u32,
+    base_delay: Duration,
+    attempt: u32,
+}
+
+impl Backoff {
+    pub fn new(max_attempts: u32, base_delay: Duration) -> Self {
+        Self {
+            max_attempts,
+            base_delay,
+            attempt: 0,
+        }
+    }
+
+    /// Delay before the next attempt, `None` once every attempt is used up
+    pub fn next_delay(&mut self) -> Option<Duration> {
+        if self.attempt >= self.max_attempts {
+            return None;
+        }
+
+        let delay = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(self.attempt))
+            .min(MAX_DELAY);
+        self.attempt += 1;
+        Some(delay)
+    }
+
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+}
+
+/// Starts a new transaction buffer after the stream terminated. The backoff is only
+/// reset once the new stream delivered a batch, so a stream that keeps terminating
+/// right away still runs out of attempts
+pub struct StreamReconnect {
+    config: Config,
+    pool: PgPool,
+    backoff: Backoff,
+}
+
+impl StreamReconnect {
+    pub fn new(config: &Config, pool: &PgPool) -> Self {
+        Self {
+            config: config.clone(),
+            pool: pool.clone(),
+            backoff: Backoff::new(
+                config
+                    .stream_reconnect_max_attempts
+                    .unwrap_or(DEFAULT_MAX_ATTEMPTS),
+                Duration::from_millis(
+                    config
+                        .stream_reconnect_base_delay_ms
+                        .unwrap_or(DEFAULT_BASE_DELAY_MS),
+                ),
+            ),
+        }
+    }
+
+    /// `None` when the shutdown is signalled while waiting for the next attempt
+    pub async fn connect(
+        &mut self,
+        shutdown: &mut watch::Receiver<bool>,
+    ) -> Result<Option<BufferedConsumerChannels>, IndexerError> {
+        loop {
+            let Some(delay) = self.backoff.next_delay() else {
+                return Err(IndexerError::Consumer(format!(
+                    "transactions stream terminated, gave up after {} reconnect attempts",
+                    self.backoff.max_attempts
+                )));
+            };
+
+            log::warn!(
+                "Reconnecting to kafka in {}ms ({}/{})",
+                delay.as_millis(),
+                self.backoff.attempt(),
+                self.backoff.max_attempts
+            );
+            tokio::select! {
+                biased;
+                Ok(()) = shutdown.changed() => return Ok(None),
+                _ = tokio::time::sleep(delay) => {}
+            }
+
+            match settings::init_transaction_buffer(&self.config, &self.pool).await {
+                Ok(channels) => return Ok(Some(channels)),
+                Err(e) => log::error!("Reconnect attempt {} failed: {e}", self.backoff.attempt()),
+            }
+        }
+    }
+
+    /// The stream delivered a batch since the last reconnect
+    pub fn resumed(&mut self) {
+        if self.backoff.attempt() > 0 {
+            log::info!(
+                "Transactions stream resumed after {} reconnect attempts",
+                self.backoff.attempt()
+            );
+            self.backoff.reset();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::{Backoff, MAX_DELAY};
+
+    #[test]
+    fn test_delay_doubles_until_attempts_run_out() {
+        let mut backoff = Backoff::new(3, Duration::from_millis(100));
+
+        assert_eq!(backoff.next_delay(), Some(Duration::from_millis(100)));
+        assert_eq!(backoff.next_delay(), Some(Duration::from_millis(200)));
+        assert_eq!(backoff.next_delay(), Some(Duration::from_millis(400)));
+        assert_eq!(backoff.next_delay(), None);
+
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), Some(Duration::from_millis(100)));
+    }
+
+    #[test]
+    fn test_delay_is_capped() {
+        let mut backoff = Backoff::new(40, Duration::from_secs(1));
+
+        let last = std::iter::from_fn(|| backoff.next_delay()).last();
+        assert_eq!(last, Some(MAX_DELAY));
+    }
+}
@@ -0,0 +1,335 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use data_reader::RarityQueue;
+use indexer_repo::collection::refresh_collection_floor;
+use indexer_repo::events::{list_events, EventFilter};
+use indexer_repo::rollback::rewind_lt_guards;
+use indexer_repo::types::decoded::EventRecord;
+use indexer_repo::types::EventType;
+use serde::de::DeserializeOwned;
+use sqlx::PgPool;
+use tokio::sync::Mutex;
+use ton_block::MsgAddressInt;
+use ton_types::UInt256;
+
+use crate::floor_refresh::FloorRefresh;
+use crate::models::events::*;
+use crate::parser::save_to_db;
+use crate::persistence::collection_cache::NftCollectionCache;
+use crate::persistence::collections_queue::CollectionsQueue;
+use crate::persistence::entities::{Decode, Decoded};
+use crate::price::UsdConverter;
+use crate::settings::runtime::RuntimeConfig;
+use crate::sinks::EventSinks;
+use crate::utils::DecodeContext;
+
+const EVENTS_PER_PAGE: i64 = 1000;
+
+/// `replay --from-lt <lt>`: re-decodes the events stored in `nft_events` and saves the
+/// derived tables again, e.g. after a decoding fix. Nothing is read from the stream
+#[derive(Debug, PartialEq, Eq)]
+pub struct ReplayArgs {
+    pub from_lt: i64,
+}
+
+impl ReplayArgs {
+    /// `None` unless the process was started with the `replay` command
+    pub fn parse(args: &[String]) -> Result<Option<Self>> {
+        let Some((command, options)) = args.split_first() else {
+            return Ok(None);
+        };
+        if command != "replay" {
+            return Ok(None);
+        }
+
+        let mut from_lt = 0;
+        let mut options = options.iter();
+        while let Some(option) = options.next() {
+            match option.as_str() {
+                "--from-lt" => {
+                    from_lt = options
+                        .next()
+                        .and_then(|lt| lt.parse().ok())
+                        .ok_or_else(|| anyhow!("--from-lt expects a logical time"))?;
+                }
+                _ => return Err(anyhow!("unknown replay option {option}")),
+            }
+        }
+
+        Ok(Some(Self { from_lt }))
+    }
+}
+
+/// Replays the stored events with `created_lt >= from_lt` in the order of the feed. Rows
+/// are upserted, so a failed or repeated run can simply be started again. Sinks are not
+/// notified, the events were delivered when they were indexed. Listings and nfts last
+/// updated from `from_lt` on are rewound first, their guards would keep the replayed
+/// state changes from applying
+pub async fn run(
+    pool: PgPool,
+    usd_converter: UsdConverter,
+    runtime_config: RuntimeConfig,
+    from_lt: i64,
+) -> Result<()> {
+    log::warn!("Replaying stored events from lt {from_lt}");
+
+    rewind_lt_guards(&pool, from_lt - 1).await?;
+
+    let collection_queue = Mutex::new(CollectionsQueue::new(pool.clone()).await);
+    let collection_cache = NftCollectionCache::default();
+    let sinks = EventSinks::default();
+    let rarity_queue = RarityQueue::default();
+    let floor_refresh = FloorRefresh::default();
+
+    let filter = EventFilter {
+        from_lt: Some(from_lt),
+        ..Default::default()
+    };
+    let mut cursor = None;
+    let (mut replayed, mut failed) = (0, 0);
+
+    loop {
+        let (events, next) = list_events(&pool, &filter, cursor.as_ref(), EVENTS_PER_PAGE).await?;
+
+        let mut data = Vec::with_capacity(events.len());
+        for event in &events {
+            match redecode(event, &runtime_config) {
+                Ok(decoded) => data.push(decoded),
+                Err(e) => {
+                    failed += 1;
+                    log::error!(
+                        "Failed to replay {:?} {} (lt: {}): {:#}",
+                        event.event_type,
+                        event.message_hash,
+                        event.created_lt,
+                        e
+                    );
+                }
+            }
+        }
+        replayed += data.len();
+
+        save_to_db(
+            &pool,
+            &usd_converter,
+            data,
+            &collection_queue,
+            &collection_cache,
+            &runtime_config,
+            &sinks,
+            &rarity_queue,
+            &floor_refresh,
+            None,
+        )
+        .await?;
+
+        if let Some(last) = events.last() {
+            log::info!(
+                "Replayed up to timestamp {} (lt: {}), {replayed} events",
+                last.created_at,
+                last.created_lt
+            );
+        }
+        match next {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+
+    // Once for the whole replay rather than per page
+    if floor_refresh.take() {
+        refresh_collection_floor(&pool).await?;
+    }
+
+    log::info!("Replay finished, {replayed} events replayed, {failed} failed");
+
+    Ok(())
+}
+
+/// Derived entity of a stored event, decoded by the current `Decode` implementation.
+/// The raw event itself is already stored and is not produced again
+pub(crate) fn redecode(event: &EventRecord, runtime_config: &RuntimeConfig) -> Result<Decoded> {
+    let ctx = replay_context(event, runtime_config.max_listing_lifetime_secs)?;
+    entity_from_args(event.event_type, &event.raw_data)?.decode(&ctx)
+}
+
+fn args<T>(raw_data: &serde_json::Value) -> Result<Box<dyn Decode>>
+where
+    T: Decode + DeserializeOwned + 'static,
+{
+    Ok(Box::new(serde_json::from_value::<T>(raw_data.clone())?))
+}
+
+/// Event struct stored as `args` of an `nft_events` row
+fn entity_from_args(
+    event_type: EventType,
+    raw_data: &serde_json::Value,
+) -> Result<Box<dyn Decode>> {
+    match event_type {
+        EventType::AuctionDeployed => args::<AuctionDeployed>(raw_data),
+        EventType::AuctionDeclined => args::<AuctionDeclined>(raw_data),
+        EventType::AuctionCreated => args::<AuctionCreated>(raw_data),
+        EventType::AuctionActive => args::<AuctionActive>(raw_data),
+        EventType::AuctionBidPlaced => args::<BidPlaced>(raw_data),
+        EventType::AuctionBidDeclined => args::<BidDeclined>(raw_data),
+        EventType::AuctionComplete => args::<AuctionComplete>(raw_data),
+        EventType::AuctionCancelled => args::<AuctionCancelled>(raw_data),
+        EventType::NftCreated => args::<NftCreated>(raw_data),
+        EventType::NftBurned => args::<NftBurned>(raw_data),
+        EventType::DirectBuyStateChanged => args::<DirectBuyStateChanged>(raw_data),
+        EventType::DirectSellStateChanged => args::<DirectSellStateChanged>(raw_data),
+        EventType::DirectBuyDeployed => args::<DirectBuyDeployed>(raw_data),
+        EventType::DirectBuyDeclined => args::<DirectBuyDeclined>(raw_data),
+        EventType::DirectSellDeployed => args::<DirectSellDeployed>(raw_data),
+        EventType::DirectSellDeclined => args::<DirectSellDeclined>(raw_data),
+        EventType::NftManagerChanged => args::<ManagerChanged>(raw_data),
+        EventType::NftOwnerChanged => args::<OwnerChanged>(raw_data),
+        EventType::OwnershipTransferred => args::<OwnershipTransferred>(raw_data),
+        EventType::MarketFeeDefaultChanged => args::<MarketFeeDefaultChanged>(raw_data),
+        EventType::MarketFeeChanged => args::<MarketFeeChanged>(raw_data),
+        EventType::AddCollectionRules => args::<AddCollectionRules>(raw_data),
+        EventType::RemoveCollectionRules => args::<RemoveCollectionRules>(raw_data),
+    }
+}
+
+/// Decoders only read the account, logical time and timestamp of the transaction, the
+/// stored event has all of them
+fn replay_context(event: &EventRecord, max_listing_lifetime_secs: u64) -> Result<DecodeContext> {
+    let account = MsgAddressInt::from_str(&event.address)
+        .map_err(|e| anyhow!("invalid address {}: {e}", event.address))?;
+
+    let mut tx_data = ton_block::Transaction::default();
+    tx_data.set_logical_time(event.created_lt as u64);
+    tx_data.account_addr = account.address();
+    tx_data.now = event.created_at.try_into()?;
+
+    Ok(DecodeContext {
+        tx_data,
+        function_inputs: Vec::new(),
+        message_hash: UInt256::from_str(&event.message_hash)
+            .map_err(|e| anyhow!("invalid message hash {}: {e}", event.message_hash))?,
+        max_listing_lifetime_secs,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use indexer_repo::types::decoded::EventRecord;
+    use ton_types::UInt256;
+
+    use crate::models::events::{BidPlaced, NftCreated};
+    use crate::models::types::{AuctionDetails, AuctionStatus};
+    use crate::persistence::entities::Decoded;
+    use crate::test_harness::address;
+    use crate::utils::KeyInfo;
+
+    use super::{entity_from_args, replay_context, ReplayArgs};
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn test_replay_command_is_parsed() {
+        assert_eq!(ReplayArgs::parse(&args(&[])).unwrap(), None);
+        assert_eq!(
+            ReplayArgs::parse(&args(&["replay", "--from-lt", "42"])).unwrap(),
+            Some(ReplayArgs { from_lt: 42 })
+        );
+        assert_eq!(
+            ReplayArgs::parse(&args(&["replay"])).unwrap(),
+            Some(ReplayArgs { from_lt: 0 })
+        );
+        assert!(ReplayArgs::parse(&args(&["replay", "--from-lt"])).is_err());
+        assert!(ReplayArgs::parse(&args(&["replay", "--to-lt", "1"])).is_err());
+    }
+
+    #[test]
+    fn test_events_round_trip_through_stored_args() {
+        let created = NftCreated {
+            id: UInt256::from([7; 32]),
+            nft: address(1),
+            owner: address(2),
+            manager: address(3),
+            creator: address(4),
+        };
+        let bid = BidPlaced {
+            buyer: address(5),
+            value: 1_000_000_000,
+            next_bid_value: 1_100_000_000,
+            value3: AuctionDetails {
+                auction_subject: address(1),
+                subject_owner: address(2),
+                payment_token: address(6),
+                wallet_for_bids: address(7),
+                start_time: 100,
+                duration: 200,
+                end_time: 300,
+                price: 900_000_000,
+                nonce: 1,
+                status: AuctionStatus::Active,
+                collection: address(8),
+            },
+        };
+
+        let stored = serde_json::to_value(&created).unwrap();
+        assert_eq!(
+            serde_json::from_value::<NftCreated>(stored).unwrap(),
+            created
+        );
+        let stored = serde_json::to_value(&bid).unwrap();
+        assert_eq!(serde_json::from_value::<BidPlaced>(stored).unwrap(), bid);
+    }
+
+    #[test]
+    fn test_stored_event_is_decoded_in_its_transaction_context() {
+        let bid = BidPlaced {
+            buyer: address(5),
+            value: 10,
+            next_bid_value: 11,
+            value3: AuctionDetails {
+                auction_subject: address(1),
+                subject_owner: address(2),
+                payment_token: address(6),
+                wallet_for_bids: address(7),
+                start_time: 100,
+                duration: 200,
+                end_time: 300,
+                price: 5,
+                nonce: 1,
+                status: AuctionStatus::Active,
+                collection: address(8),
+            },
+        };
+        let stored = EventRecord {
+            event_category: indexer_repo::types::EventCategory::Auction,
+            event_type: indexer_repo::types::EventType::AuctionBidPlaced,
+            address: address(9).to_string(),
+            created_lt: 12345,
+            created_at: 1_700_000_000,
+            message_hash: UInt256::from([3; 32]).to_string(),
+            nft: Some(address(1).to_string()),
+            collection: Some(address(8).to_string()),
+            raw_data: serde_json::to_value(&bid).unwrap(),
+        };
+
+        let ctx = replay_context(&stored, u64::MAX).unwrap();
+        assert_eq!(ctx.tx_data.get_account(), stored.address);
+        assert_eq!(ctx.tx_data.logical_time(), 12345);
+        assert_eq!(ctx.tx_data.get_timestamp(), 1_700_000_000);
+        assert_eq!(ctx.message_hash.to_string(), stored.message_hash);
+
+        let Ok(Decoded::AuctionBidPlaced(replayed)) =
+            entity_from_args(stored.event_type, &stored.raw_data)
+                .unwrap()
+                .decode(&ctx)
+        else {
+            panic!("BidPlaced must decode to a placed bid");
+        };
+        assert_eq!(replayed.address, stored.address);
+        assert_eq!(replayed.bid_value, 10.into());
+        assert_eq!(replayed.next_value, 11.into());
+        assert_eq!(replayed.tx_lt, 12345);
+    }
+}
@@ -0,0 +1,144 @@
+use indexer_repo::checkpoint::Checkpoint;
+use nekoton_abi::transaction_parser::ExtractedOwned;
+use transaction_buffer::models::RawTransaction;
+
+/// Transaction of the stream, as far as the checkpoint needs it
+pub trait StreamedTx {
+    fn timestamp(&self) -> i64;
+    fn lt(&self) -> i64;
+    fn hash(&self) -> String;
+}
+
+impl StreamedTx for (Vec<ExtractedOwned>, RawTransaction) {
+    fn timestamp(&self) -> i64 {
+        self.1.data.get_timestamp()
+    }
+
+    fn lt(&self) -> i64 {
+        self.1.data.logical_time() as i64
+    }
+
+    fn hash(&self) -> String {
+        self.1.data.get_hash().map(hex::encode).unwrap_or_default()
+    }
+}
+
+/// Checkpoint of the newest transaction of a batch
+pub fn checkpoint_of<T: StreamedTx>(message: &[T]) -> Option<Checkpoint> {
+    message
+        .iter()
+        .max_by_key(|tx| (tx.timestamp(), tx.lt()))
+        .map(|tx| Checkpoint {
+            tx_timestamp: tx.timestamp(),
+            tx_lt: tx.lt(),
+            tx_hash: tx.hash(),
+        })
+}
+
+/// Skips what a new consumer redelivers from the committed offsets, right after the
+/// start or a reconnect. Logical times are per account, so only the timestamp orders
+/// transactions across accounts: a transaction strictly older than the checkpoint was
+/// saved, and so was the checkpoint transaction itself. The others sharing its
+/// timestamp are saved again: state is upserted behind lt guards, and the rows a sale
+/// or a bid inserts are unique per source and transaction, a second insert is skipped.
+/// The guard disarms at the first newer transaction, the stream is past the saved ones
+/// from there
+#[derive(Debug, Default)]
+pub struct ReplayGuard {
+    checkpoint: Option<Checkpoint>,
+}
+
+impl ReplayGuard {
+    pub fn new(checkpoint: Option<Checkpoint>) -> Self {
+        Self { checkpoint }
+    }
+
+    /// Drops the redelivered transactions of `message`, returns how many
+    pub fn retain_new<T: StreamedTx>(&mut self, message: &mut Vec<T>) -> usize {
+        let Some(checkpoint) = &self.checkpoint else {
+            return 0;
+        };
+
+        let before = message.len();
+        message.retain(|tx| !is_redelivered(checkpoint, tx));
+        if message
+            .iter()
+            .any(|tx| tx.timestamp() > checkpoint.tx_timestamp)
+        {
+            self.checkpoint = None;
+        }
+
+        before - message.len()
+    }
+
+    pub fn is_armed(&self) -> bool {
+        self.checkpoint.is_some()
+    }
+}
+
+fn is_redelivered<T: StreamedTx>(checkpoint: &Checkpoint, tx: &T) -> bool {
+    tx.timestamp() < checkpoint.tx_timestamp
+        || (tx.timestamp() == checkpoint.tx_timestamp && tx.hash() == checkpoint.tx_hash)
+}
+
+#[cfg(test)]
+mod test {
+    use indexer_repo::checkpoint::Checkpoint;
+
+    use super::{checkpoint_of, ReplayGuard, StreamedTx};
+
+    /// (timestamp, lt, hash)
+    struct Tx(i64, i64, &'static str);
+
+    impl StreamedTx for Tx {
+        fn timestamp(&self) -> i64 {
+            self.0
+        }
+
+        fn lt(&self) -> i64 {
+            self.1
+        }
+
+        fn hash(&self) -> String {
+            self.2.to_string()
+        }
+    }
+
+    fn hashes(message: &[Tx]) -> Vec<&'static str> {
+        message.iter().map(|tx| tx.2).collect()
+    }
+
+    #[test]
+    fn test_redelivered_transactions_are_skipped_once() {
+        let checkpoint = Checkpoint {
+            tx_timestamp: 100,
+            tx_lt: 50,
+            tx_hash: "c".to_string(),
+        };
+        let mut guard = ReplayGuard::new(Some(checkpoint));
+
+        let mut redelivered = vec![Tx(99, 70, "a"), Tx(100, 10, "b"), Tx(100, 50, "c")];
+        assert_eq!(guard.retain_new(&mut redelivered), 2);
+        // shares the checkpoint timestamp, its lt of another account proves nothing
+        assert_eq!(hashes(&redelivered), ["b"]);
+        assert!(guard.is_armed());
+
+        let mut live = vec![Tx(100, 60, "d"), Tx(101, 5, "e")];
+        assert_eq!(guard.retain_new(&mut live), 0);
+        assert!(!guard.is_armed());
+
+        // an older transaction of another account later on is not a redelivery
+        let mut later = vec![Tx(99, 1, "f")];
+        assert_eq!(guard.retain_new(&mut later), 0);
+        assert_eq!(hashes(&later), ["f"]);
+    }
+
+    #[test]
+    fn test_checkpoint_is_the_newest_transaction() {
+        let message = [Tx(100, 80, "a"), Tx(101, 5, "b"), Tx(101, 3, "c")];
+
+        let checkpoint = checkpoint_of(&message).unwrap();
+        assert_eq!(checkpoint.tx_hash, "b");
+        assert_eq!(checkpoint_of::<Tx>(&[]), None);
+    }
+}
@@ -0,0 +1,86 @@
+use anyhow::{anyhow, Result};
+use indexer_repo::rollback::{get_oldest_timestamp_above_lt, rollback_to_lt};
+use sqlx::PgPool;
+
+use crate::settings::{self, config::KafkaConfig};
+
+/// `rollback --to-lt <lt>`: deletes everything indexed above `lt`, e.g. after a reorg, and
+/// rewinds the consumer group so the next start indexes the stream again from there.
+/// Run it with the indexer stopped, Kafka rejects the commit of a group in use
+#[derive(Debug, PartialEq, Eq)]
+pub struct RollbackArgs {
+    pub to_lt: i64,
+}
+
+impl RollbackArgs {
+    /// `None` unless the process was started with the `rollback` command
+    pub fn parse(args: &[String]) -> Result<Option<Self>> {
+        let Some((command, options)) = args.split_first() else {
+            return Ok(None);
+        };
+        if command != "rollback" {
+            return Ok(None);
+        }
+
+        let mut to_lt = None;
+        let mut options = options.iter();
+        while let Some(option) = options.next() {
+            match option.as_str() {
+                "--to-lt" => {
+                    to_lt = Some(
+                        options
+                            .next()
+                            .and_then(|lt| lt.parse().ok())
+                            .ok_or_else(|| anyhow!("--to-lt expects a logical time"))?,
+                    );
+                }
+                _ => return Err(anyhow!("unknown rollback option {option}")),
+            }
+        }
+
+        let to_lt = to_lt.ok_or_else(|| anyhow!("rollback needs --to-lt"))?;
+        Ok(Some(Self { to_lt }))
+    }
+}
+
+/// The offsets are rewound before the data is deleted: rolled back data can't tell
+/// where to rewind to anymore, so after a failure the command is simply run again
+pub async fn run(pool: &PgPool, kafka: &KafkaConfig, to_lt: i64) -> Result<()> {
+    let Some(timestamp) = get_oldest_timestamp_above_lt(pool, to_lt).await? else {
+        log::info!("Nothing is indexed above lt {to_lt}, nothing to roll back");
+        return Ok(());
+    };
+
+    log::warn!(
+        "Rewinding group {} to the messages of timestamp {timestamp}",
+        kafka.consumer_group
+    );
+    settings::rewind_consumer_group(kafka, timestamp * 1000).await?;
+
+    log::warn!("Rolling back data indexed above lt {to_lt}");
+    rollback_to_lt(pool, to_lt).await?;
+    log::info!("Rolled back to lt {to_lt}, the next start indexes the stream from there");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::RollbackArgs;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn test_rollback_command_is_parsed() {
+        assert_eq!(RollbackArgs::parse(&args(&[])).unwrap(), None);
+        assert_eq!(
+            RollbackArgs::parse(&args(&["rollback", "--to-lt", "42"])).unwrap(),
+            Some(RollbackArgs { to_lt: 42 })
+        );
+        assert!(RollbackArgs::parse(&args(&["rollback"])).is_err());
+        assert!(RollbackArgs::parse(&args(&["rollback", "--to-lt"])).is_err());
+        assert!(RollbackArgs::parse(&args(&["rollback", "--from-lt", "1"])).is_err());
+    }
+}
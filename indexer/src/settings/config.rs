@@ -1,24 +1,111 @@
+use crate::backfill::{BackfillRange, LtWindow};
+use indexer_repo::db::DbConfig;
 use indexer_repo::types::BcName;
 use serde::Deserialize;
 use std::collections::HashMap;
+use std::time::Duration;
 use url::Url;
 
+const DEFAULT_DATABASE_ACQUIRE_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_DATABASE_IDLE_TIMEOUT_SECS: u64 = 600;
+/// Long enough for the rarity recompute and migrations on a full database
+const DEFAULT_DATABASE_STATEMENT_TIMEOUT_MS: u64 = 300_000;
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct Config {
     pub database_url: String,
     pub database_max_connections: u32,
+    /// Seconds to wait for a free pooled connection
+    pub database_acquire_timeout_secs: Option<u64>,
+    /// Pooled connections idle for this many seconds are closed, 0 keeps them open
+    pub database_idle_timeout_secs: Option<u64>,
+    /// Postgres aborts statements running for longer, 0 disables the limit
+    pub database_statement_timeout_ms: Option<u64>,
     pub kafka_topic: String,
     pub kafka_consumer_group: String,
     pub kafka_reset: Option<bool>,
+    pub kafka_offset_fallback: Option<OffsetFallback>,
     pub states_rpc_endpoints: Vec<Url>,
     pub kafka_settings: HashMap<String, String>,
     pub server_api_url: String,
     pub terminate_open_connections: Option<bool>,
     pub jrpc_req_latency_millis: u64,
+    /// Upper bound of simultaneous node RPC calls across all resolver jobs
+    pub jrpc_max_concurrency: Option<usize>,
+    /// Contract getter calls started per second, unlimited if unset or 0
+    pub getter_rate_limit_per_sec: Option<u32>,
+    /// How long a getter result is served from the cache
+    pub getter_cache_ttl_secs: Option<u64>,
     pub bc_name: BcName,
     pub idle_after_price_loop_sec: u64,
     pub idle_after_meta_loop_sec: u64,
     pub price_update_frequency_sec: u64,
+    pub counterparty_api_enabled: Option<bool>,
+    pub admin_api_enabled: Option<bool>,
+    /// Serve Prometheus metrics on this port when set
+    pub metrics_port: Option<u16>,
+    /// Log a report of accounts seen in extracted events after this many seconds
+    pub discovery_window_secs: Option<u64>,
+    /// Collections whose events are not written to the event feed, reloadable on SIGHUP
+    pub blocked_collections: Option<Vec<String>>,
+    pub relist_window_secs: Option<u64>,
+    pub max_listing_lifetime_secs: Option<u64>,
+    /// Raw events are inserted in statements of at most this many rows
+    pub raw_event_chunk_size: Option<usize>,
+    /// How often active direct sells past their end are marked expired
+    pub expire_listings_interval_secs: Option<u64>,
+    /// How often the `collection_stats` counters are recounted to correct drift
+    pub reconcile_stats_interval_secs: Option<u64>,
+    /// How often the rarity of collections that minted or burned nfts is recomputed
+    pub rarity_interval_secs: Option<u64>,
+    /// How often `collection_floor` is refreshed after listings changed
+    pub floor_refresh_interval_secs: Option<u64>,
+    /// Halt instead of skipping events that failed to decode
+    pub strict_mode: Option<bool>,
+    /// Keep the serialized transaction of every raw event, for re-decoding later
+    pub store_raw_transactions: Option<bool>,
+    /// Skip marketplace events of contracts that are not whitelisted, or only log them
+    pub whitelist_mode: Option<WhitelistMode>,
+    /// Parser groups whose events are extracted (see `indexer_api::PARSERS`), all of
+    /// them when unset
+    pub enabled_parsers: Option<Vec<String>>,
+    /// Transient Postgres errors (deadlocks, serialization failures, dropped
+    /// connections) retry the whole batch with exponential backoff
+    pub db_max_retries: Option<u32>,
+    pub db_retry_base_delay_ms: Option<u64>,
+    /// Reconnects to Kafka after the transaction stream terminated, the delay doubles
+    /// after each failed attempt
+    pub stream_reconnect_max_attempts: Option<u32>,
+    pub stream_reconnect_base_delay_ms: Option<u64>,
+    /// Decoded batches allowed to wait for the database writer
+    pub pipeline_depth: Option<usize>,
+    /// Commit offsets once this many transactions are saved instead of after each batch
+    pub commit_max_transactions: Option<usize>,
+    /// Commit offsets at the latest this long after the oldest uncommitted batch was saved
+    pub commit_max_delay_ms: Option<u64>,
+    /// Only persist batches whose newest transaction is at least this old
+    pub finality_delay_secs: Option<u64>,
+    /// Backfill mode: re-index transactions from this unix timestamp and exit
+    pub backfill_from_ts: Option<i64>,
+    /// End of the backfilled range (exclusive), defaults to the start of the run
+    pub backfill_to_ts: Option<i64>,
+    /// Audit window: only transactions with logical times in `index_from_lt..=index_to_lt`
+    /// are persisted, in live and backfill mode
+    pub index_from_lt: Option<i64>,
+    pub index_to_lt: Option<i64>,
+    /// Change data capture: committed raw events are also sent to these sinks
+    pub cdc_webhook_url: Option<String>,
+    pub cdc_kafka_brokers: Option<String>,
+    pub cdc_kafka_topic: Option<String>,
+    pub cdc_nats_url: Option<String>,
+    pub cdc_nats_subject: Option<String>,
+    pub cdc_channel_capacity: Option<usize>,
+    /// Signed notifications for events matching the filters below
+    pub webhook_url: Option<String>,
+    pub webhook_secret: Option<String>,
+    pub webhook_event_types: Option<Vec<String>>,
+    pub webhook_collections: Option<Vec<String>>,
+    pub webhook_max_retries: Option<u32>,
 }
 
 impl Default for Config {
@@ -29,22 +116,26 @@ impl Default for Config {
 
 impl Config {
     pub fn new() -> Config {
+        Self::try_new().unwrap_or_else(|e| panic!("Error parsing config: {}", e))
+    }
+
+    pub fn try_new() -> Result<Config, config::ConfigError> {
         let mut conf_builder = config::Config::builder().add_source(
             config::Environment::default()
                 .separator("__")
                 .list_separator(",")
                 .with_list_parse_key("states_rpc_endpoints")
+                .with_list_parse_key("blocked_collections")
+                .with_list_parse_key("webhook_event_types")
+                .with_list_parse_key("webhook_collections")
+                .with_list_parse_key("enabled_parsers")
                 .try_parsing(true),
         );
         if std::path::Path::new("Settings.toml").exists() {
             conf_builder = conf_builder.add_source(config::File::with_name("./Settings.toml"));
         }
 
-        let mut conf = conf_builder
-            .build()
-            .unwrap()
-            .try_deserialize::<Config>()
-            .unwrap_or_else(|e| panic!("Error parsing config: {}", e));
+        let mut conf = conf_builder.build()?.try_deserialize::<Config>()?;
 
         conf.kafka_settings = conf
             .kafka_settings
@@ -52,6 +143,276 @@ impl Config {
             .map(|(k, v)| (k.replace('_', "."), v))
             .collect();
 
-        conf
+        Ok(conf)
+    }
+
+    pub fn lt_window(&self) -> Option<LtWindow> {
+        (self.index_from_lt.is_some() || self.index_to_lt.is_some()).then_some(LtWindow {
+            from_lt: self.index_from_lt,
+            to_lt: self.index_to_lt,
+        })
+    }
+
+    pub fn backfill_range(&self) -> Option<BackfillRange> {
+        self.backfill_from_ts.map(|from_ts| BackfillRange {
+            from_ts,
+            to_ts: self
+                .backfill_to_ts
+                .unwrap_or_else(|| chrono::Utc::now().timestamp()),
+        })
+    }
+
+    pub fn db(&self) -> DbConfig {
+        let idle_timeout = self
+            .database_idle_timeout_secs
+            .unwrap_or(DEFAULT_DATABASE_IDLE_TIMEOUT_SECS);
+        let statement_timeout = self
+            .database_statement_timeout_ms
+            .unwrap_or(DEFAULT_DATABASE_STATEMENT_TIMEOUT_MS);
+
+        DbConfig {
+            url: self.database_url.clone(),
+            max_connections: self.database_max_connections,
+            acquire_timeout: Duration::from_secs(
+                self.database_acquire_timeout_secs
+                    .unwrap_or(DEFAULT_DATABASE_ACQUIRE_TIMEOUT_SECS),
+            ),
+            idle_timeout: (idle_timeout > 0).then(|| Duration::from_secs(idle_timeout)),
+            statement_timeout: (statement_timeout > 0)
+                .then(|| Duration::from_millis(statement_timeout)),
+            terminate_open_connections: self.terminate_open_connections.unwrap_or_default(),
+        }
+    }
+
+    pub fn kafka(&self) -> KafkaConfig {
+        KafkaConfig::new(
+            &self.kafka_topic,
+            &self.kafka_consumer_group,
+            &self.kafka_settings,
+            self.kafka_offset_fallback,
+        )
+    }
+}
+
+/// Where to start reading when the consumer group has no committed offset yet
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OffsetFallback {
+    /// Re-ingest the whole topic history
+    Earliest,
+    /// Skip everything produced before the first consume
+    #[default]
+    Latest,
+    /// Resume from the messages of the indexer checkpoint, the earliest one if nothing
+    /// was indexed yet
+    Checkpoint,
+}
+
+impl OffsetFallback {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OffsetFallback::Earliest => "earliest",
+            OffsetFallback::Latest => "latest",
+            OffsetFallback::Checkpoint => "checkpoint",
+        }
+    }
+
+    /// `auto.offset.reset` of the consumer. The group is rewound to the checkpoint
+    /// before it starts, so the reset only applies when there is no checkpoint
+    pub fn offset_reset(&self) -> &'static str {
+        match self {
+            OffsetFallback::Earliest | OffsetFallback::Checkpoint => "earliest",
+            OffsetFallback::Latest => "latest",
+        }
+    }
+}
+
+/// How events of marketplace contracts missing from the whitelist are handled
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum WhitelistMode {
+    /// Index every event
+    #[default]
+    Off,
+    /// Index every event, warn about the ones that would be skipped
+    Log,
+    /// Skip the events
+    Enforce,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct KafkaConfig {
+    pub topic: String,
+    pub consumer_group: String,
+    pub brokers: Vec<String>,
+    pub offset_fallback: OffsetFallback,
+    pub security_protocol: Option<String>,
+    pub sasl: Option<KafkaSaslConfig>,
+    pub tls: Option<KafkaTlsConfig>,
+    /// Any other librdkafka options, passed as is
+    pub options: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct KafkaSaslConfig {
+    pub mechanism: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct KafkaTlsConfig {
+    pub ca_location: Option<String>,
+    pub keystore_location: Option<String>,
+    pub keystore_password: Option<String>,
+    pub key_password: Option<String>,
+}
+
+impl KafkaConfig {
+    /// Builds typed config from `kafka_settings` (keys are already in `a.b.c` form)
+    pub fn new(
+        topic: &str,
+        consumer_group: &str,
+        settings: &HashMap<String, String>,
+        offset_fallback: Option<OffsetFallback>,
+    ) -> Self {
+        let mut options = settings.clone();
+
+        // Typed setting wins over the raw `auto.offset.reset` option
+        let raw_fallback = options
+            .remove("auto.offset.reset")
+            .map(|v| match v.as_str() {
+                "earliest" | "smallest" | "beginning" => OffsetFallback::Earliest,
+                _ => OffsetFallback::Latest,
+            });
+        let offset_fallback = offset_fallback.or(raw_fallback).unwrap_or_default();
+
+        let brokers = options
+            .remove("bootstrap.servers")
+            .map(|s| {
+                s.split(',')
+                    .map(|b| b.trim().to_string())
+                    .filter(|b| !b.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let security_protocol = options.remove("security.protocol");
+
+        let sasl = KafkaSaslConfig {
+            mechanism: options.remove("sasl.mechanism"),
+            username: options.remove("sasl.username"),
+            password: options.remove("sasl.password"),
+        };
+        let tls = KafkaTlsConfig {
+            ca_location: options.remove("ssl.ca.location"),
+            keystore_location: options.remove("ssl.keystore.location"),
+            keystore_password: options.remove("ssl.keystore.password"),
+            key_password: options.remove("ssl.key.password"),
+        };
+
+        Self {
+            topic: topic.to_string(),
+            consumer_group: consumer_group.to_string(),
+            brokers,
+            offset_fallback,
+            security_protocol,
+            sasl: (sasl.mechanism.is_some() || sasl.username.is_some()).then_some(sasl),
+            tls: (tls.ca_location.is_some() || tls.keystore_location.is_some()).then_some(tls),
+            options,
+        }
+    }
+
+    /// Flattens config back into librdkafka options
+    pub fn kafka_options(&self) -> HashMap<String, String> {
+        let mut options = self.options.clone();
+
+        options.insert("bootstrap.servers".to_string(), self.brokers.join(","));
+        options.insert(
+            "auto.offset.reset".to_string(),
+            self.offset_fallback.offset_reset().to_string(),
+        );
+
+        let mut insert = |key: &str, value: &Option<String>| {
+            if let Some(value) = value {
+                options.insert(key.to_string(), value.clone());
+            }
+        };
+
+        insert("security.protocol", &self.security_protocol);
+
+        if let Some(sasl) = &self.sasl {
+            insert("sasl.mechanism", &sasl.mechanism);
+            insert("sasl.username", &sasl.username);
+            insert("sasl.password", &sasl.password);
+        }
+
+        if let Some(tls) = &self.tls {
+            insert("ssl.ca.location", &tls.ca_location);
+            insert("ssl.keystore.location", &tls.keystore_location);
+            insert("ssl.keystore.password", &tls.keystore_password);
+            insert("ssl.key.password", &tls.key_password);
+        }
+
+        options
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::{KafkaConfig, OffsetFallback};
+
+    fn offset_reset(config: &KafkaConfig) -> String {
+        config.kafka_options()["auto.offset.reset"].clone()
+    }
+
+    #[test]
+    fn test_offset_fallback_earliest() {
+        let config = KafkaConfig::new(
+            "topic",
+            "group",
+            &HashMap::new(),
+            Some(OffsetFallback::Earliest),
+        );
+
+        assert_eq!(offset_reset(&config), "earliest");
+    }
+
+    #[test]
+    fn test_offset_fallback_latest() {
+        let settings = HashMap::from([("auto.offset.reset".to_string(), "earliest".to_string())]);
+        let config = KafkaConfig::new("topic", "group", &settings, Some(OffsetFallback::Latest));
+
+        assert_eq!(offset_reset(&config), "latest");
+    }
+
+    #[test]
+    fn test_offset_fallback_checkpoint() {
+        let config = KafkaConfig::new(
+            "topic",
+            "group",
+            &HashMap::new(),
+            Some(OffsetFallback::Checkpoint),
+        );
+
+        // without a checkpoint to rewind to, nothing was indexed yet
+        assert_eq!(offset_reset(&config), "earliest");
+        assert_eq!(
+            serde_json::from_str::<OffsetFallback>("\"checkpoint\"").unwrap(),
+            OffsetFallback::Checkpoint
+        );
+    }
+
+    #[test]
+    fn test_offset_fallback_from_raw_settings() {
+        let settings = HashMap::from([("auto.offset.reset".to_string(), "earliest".to_string())]);
+        let config = KafkaConfig::new("topic", "group", &settings, None);
+
+        assert_eq!(config.offset_fallback, OffsetFallback::Earliest);
+        assert_eq!(
+            offset_reset(&KafkaConfig::new("topic", "group", &HashMap::new(), None)),
+            "latest"
+        );
     }
 }
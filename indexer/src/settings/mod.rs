@@ -1,9 +1,18 @@
 use crate::abi::declare_abi::*;
 use crate::abi::scope;
-use crate::settings::config::Config;
-use anyhow::Result;
+use crate::parser::{parser_of, UNPACKED_EVENTS};
+use crate::settings::config::{Config, KafkaConfig, OffsetFallback};
+use anyhow::{anyhow, bail, Result};
+use indexer_api::PARSERS;
+use indexer_repo::checkpoint::get_checkpoint;
+use indexer_repo::error::IndexerError;
+use rdkafka::consumer::{BaseConsumer, CommitMode, Consumer};
+use rdkafka::{ClientConfig, Offset, TopicPartitionList};
 use sqlx::PgPool;
-use std::{collections::HashMap, sync::Arc};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use ton_abi::Contract;
 use transaction_buffer::models::{
     AnyExtractable, BufferedConsumerChannels, BufferedConsumerConfig,
 };
@@ -13,12 +22,28 @@ use transaction_consumer::JrpcClientOptions;
 use transaction_consumer::{ConsumerOptions, TransactionConsumer};
 
 pub mod config;
-pub async fn init_consumer(config: &Config) -> Result<Arc<TransactionConsumer>> {
-    log::info!("initializing transaction consumer");
+pub mod runtime;
 
+const KAFKA_CONNECT_TIMEOUT_SECS: u64 = 10;
+const KAFKA_REQUEST_TIMEOUT_SECS: u64 = 30;
+
+pub async fn build_consumer(config: &KafkaConfig) -> Result<Arc<TransactionConsumer>> {
+    log::info!(
+        "initializing transaction consumer (topic: {}, group: {})",
+        config.topic,
+        config.consumer_group
+    );
+    log::info!(
+        "if group {} has no stored offset, consuming starts from the {} message",
+        config.consumer_group,
+        config.offset_fallback.as_str()
+    );
+
+    check_brokers(config).await?;
+
+    let options = config.kafka_options();
     let kafka_options = HashMap::from_iter(
-        config
-            .kafka_settings
+        options
             .iter()
             .map(|(param, val)| (param.as_str(), val.as_str())),
     );
@@ -28,37 +53,190 @@ pub async fn init_consumer(config: &Config) -> Result<Arc<TransactionConsumer>>
         skip_0_partition: true,
     };
 
-    TransactionConsumer::without_jrpc_client(
-        &config.kafka_consumer_group,
-        &config.kafka_topic,
-        con_opt,
-    )
-    .await
+    TransactionConsumer::without_jrpc_client(&config.consumer_group, &config.topic, con_opt).await
+}
+
+/// With `OffsetFallback::Checkpoint`, rewinds a group without committed offsets to the
+/// messages of the indexer checkpoint, e.g. after the group was renamed or expired.
+/// Without a checkpoint the group starts from the earliest message
+async fn start_from_checkpoint(config: &KafkaConfig, pg_pool: &PgPool) -> Result<()> {
+    if config.offset_fallback != OffsetFallback::Checkpoint {
+        return Ok(());
+    }
+    if has_committed_offsets(config).await? {
+        return Ok(());
+    }
+
+    match get_checkpoint(pg_pool).await? {
+        Some(checkpoint) => {
+            log::warn!(
+                "Group {} has no stored offset, starting from the checkpoint at timestamp {}",
+                config.consumer_group,
+                checkpoint.tx_timestamp
+            );
+            rewind_consumer_group(config, checkpoint.tx_timestamp * 1000).await
+        }
+        None => {
+            log::warn!(
+                "Group {} has no stored offset and nothing is indexed, starting from the earliest message",
+                config.consumer_group
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Whether the consumer group committed an offset for any partition of the topic
+async fn has_committed_offsets(config: &KafkaConfig) -> Result<bool> {
+    check_brokers(config).await?;
+
+    let config = config.clone();
+    tokio::task::spawn_blocking(move || {
+        let timeout = Duration::from_secs(KAFKA_REQUEST_TIMEOUT_SECS);
+        let consumer = group_consumer(&config)?;
+
+        let mut partitions = TopicPartitionList::new();
+        for partition in topic_partitions(&consumer, &config.topic, timeout)? {
+            partitions.add_partition(&config.topic, partition);
+        }
+
+        let committed = consumer.committed_offsets(partitions, timeout)?;
+        Ok(committed
+            .elements()
+            .iter()
+            .any(|e| matches!(e.offset(), Offset::Offset(_))))
+    })
+    .await?
+}
+
+/// Commits the offsets of the first messages produced at or after `timestamp_ms` for
+/// the consumer group, so the next consumer reads the topic again from there. Kafka
+/// only accepts the commit while no consumer of the group is running
+pub async fn rewind_consumer_group(config: &KafkaConfig, timestamp_ms: i64) -> Result<()> {
+    check_brokers(config).await?;
+
+    let config = config.clone();
+    tokio::task::spawn_blocking(move || {
+        let timeout = Duration::from_secs(KAFKA_REQUEST_TIMEOUT_SECS);
+        let consumer = group_consumer(&config)?;
+
+        let mut partitions = TopicPartitionList::new();
+        for partition in topic_partitions(&consumer, &config.topic, timeout)? {
+            partitions.add_partition_offset(
+                &config.topic,
+                partition,
+                Offset::Offset(timestamp_ms),
+            )?;
+        }
+
+        // Partitions without a message that new are rewound to their end
+        let mut offsets = consumer.offsets_for_times(partitions, timeout)?;
+        let at_end = offsets
+            .elements()
+            .iter()
+            .filter(|e| e.offset() == Offset::End)
+            .map(|e| e.partition())
+            .collect::<Vec<_>>();
+        for partition in at_end {
+            let (_, high) = consumer.fetch_watermarks(&config.topic, partition, timeout)?;
+            offsets.set_partition_offset(&config.topic, partition, Offset::Offset(high))?;
+        }
+
+        consumer.commit(&offsets, CommitMode::Sync)?;
+        log::info!(
+            "Rewound group {} of {} to {:?}",
+            config.consumer_group,
+            config.topic,
+            offsets
+                .elements()
+                .iter()
+                .map(|e| (e.partition(), e.offset()))
+                .collect::<Vec<_>>()
+        );
+
+        Ok(())
+    })
+    .await?
+}
+
+/// Consumer of the group that only reads and commits offsets, it never subscribes
+fn group_consumer(config: &KafkaConfig) -> Result<BaseConsumer> {
+    let mut client = ClientConfig::new();
+    for (param, val) in config.kafka_options() {
+        client.set(param, val);
+    }
+
+    Ok(client
+        .set("group.id", &config.consumer_group)
+        .set("enable.auto.commit", "false")
+        .create()?)
+}
+
+fn topic_partitions(consumer: &BaseConsumer, topic: &str, timeout: Duration) -> Result<Vec<i32>> {
+    let metadata = consumer.fetch_metadata(Some(topic), timeout)?;
+    let topic = metadata
+        .topics()
+        .iter()
+        .find(|t| t.name() == topic)
+        .ok_or_else(|| anyhow!("Topic {topic} not found"))?;
+
+    Ok(topic.partitions().iter().map(|p| p.id()).collect())
+}
+
+/// Fetches the cluster metadata with the client settings of the consumer, so TLS and
+/// SASL brokers are checked the way the consumer will connect to them
+async fn check_brokers(config: &KafkaConfig) -> Result<()> {
+    if config.brokers.is_empty() {
+        bail!("No kafka brokers configured, set KAFKA_SETTINGS__BOOTSTRAP_SERVERS");
+    }
+
+    let config = config.clone();
+    tokio::task::spawn_blocking(move || {
+        let consumer = group_consumer(&config)?;
+        consumer
+            .fetch_metadata(None, Duration::from_secs(KAFKA_CONNECT_TIMEOUT_SECS))
+            .map_err(|e| {
+                anyhow!(
+                    "Kafka brokers {} are unreachable: {e}",
+                    config.brokers.join(",")
+                )
+            })?;
+
+        Ok(())
+    })
+    .await?
 }
 
 pub async fn init_transaction_buffer(
     config: &Config,
     pg_pool: &PgPool,
-) -> Result<BufferedConsumerChannels> {
-    let transaction_consumer = init_consumer(config).await?;
+) -> Result<BufferedConsumerChannels, IndexerError> {
+    check_abis().map_err(|e| IndexerError::Abi(format!("{e:#}")))?;
+    check_scope(parser_abis, UNPACKED_EVENTS).map_err(|e| IndexerError::Abi(format!("{e:#}")))?;
+    let parsers = enabled_parsers(config.enabled_parsers.as_deref())
+        .map_err(|e| IndexerError::Config(format!("{e:#}")))?;
+
+    let kafka = config.kafka();
+    start_from_checkpoint(&kafka, pg_pool)
+        .await
+        .map_err(|e| IndexerError::Consumer(format!("{e:#}")))?;
+    let transaction_consumer = build_consumer(&kafka)
+        .await
+        .map_err(|e| IndexerError::Consumer(format!("{e:#}")))?;
 
     log::info!("starting transaction buffer");
     Ok(start_parsing_and_get_channels(BufferedConsumerConfig {
         transaction_consumer,
         pg_pool: pg_pool.clone(),
-        any_extractable: get_any_extractable(),
+        any_extractable: get_any_extractable(&parsers),
         buff_size: 100_000,
         commit_time_secs: 100,
         cache_timer: 60,
     }))
 }
 
-fn get_any_extractable() -> Vec<AnyExtractable> {
-    // NOTE: из-за того, что есть два ивента NftCreated,
-    // но с разными полями, будет выскакивать ошибка
-    // для ивента из Nft.abi.json
-
-    let extractables = vec![
+fn contracts() -> Vec<&'static Contract> {
+    vec![
         auction_root_tip3(),
         auction_tip3(),
         callbacks(),
@@ -70,22 +248,103 @@ fn get_any_extractable() -> Vec<AnyExtractable> {
         nft(),
         collection(),
     ]
-    .into_iter()
-    .flat_map(|c| {
-        c.events
-            .clone()
-            .into_values()
-            .filter(|e| scope::events().contains(&e.name.as_str()))
-            .map(AnyExtractable::Event)
-            .chain(
-                c.functions
-                    .clone()
-                    .into_values()
-                    .filter(|f| scope::functions().contains(&f.name.as_str()))
-                    .map(AnyExtractable::Function),
-            )
-    })
-    .collect::<Vec<_>>();
+}
+
+/// ABIs of the contracts emitting the events of a parser group, common events are
+/// emitted by any of them
+fn parser_abis(parser: &str) -> Vec<&'static Contract> {
+    match parser {
+        "auction" => vec![auction_root_tip3(), auction_tip3()],
+        "direct_buy" => vec![factory_direct_buy(), direct_buy()],
+        "direct_sell" => vec![factory_direct_sell(), direct_sell()],
+        "nft" => vec![nft()],
+        "collection" => vec![collection()],
+        _ => contracts(),
+    }
+}
+
+/// Scoped events are checked against the ABIs of their parser and against the names
+/// `unpack_entity` dispatches on: an event missing from those ABIs is never extracted
+/// for its parser, e.g. after a typo or an ABI update that renamed it, and an extracted
+/// event the parser doesn't unpack is silently dropped. Unpacked events must be
+/// scoped too, otherwise their decoder never runs
+fn check_scope(abis: impl Fn(&str) -> Vec<&'static Contract>, unpacked: &[&str]) -> Result<()> {
+    let events = scope::events();
+    let mut missing = Vec::new();
+
+    for name in &events {
+        let parser = parser_of(name);
+        if !abis(parser).iter().any(|c| c.events.contains_key(*name)) {
+            missing.push(format!("{name} (event, not in the {parser} ABIs)"));
+        }
+        if !unpacked.contains(name) {
+            missing.push(format!("{name} (event, not unpacked by the parser)"));
+        }
+    }
+    for name in unpacked.iter().filter(|name| !events.contains(*name)) {
+        missing.push(format!("{name} (event unpacked by the parser, not scoped)"));
+    }
+    for name in scope::functions() {
+        if !contracts().iter().any(|c| c.functions.contains_key(name)) {
+            missing.push(format!("{name} (function, not in any ABI)"));
+        }
+    }
+
+    if !missing.is_empty() {
+        bail!("Scope doesn't match the ABIs:\n{}", missing.join("\n"));
+    }
+
+    Ok(())
+}
+
+/// Parser groups to index, every group of `indexer_api::PARSERS` unless configured
+fn enabled_parsers(configured: Option<&[String]>) -> Result<HashSet<&'static str>> {
+    let Some(configured) = configured else {
+        return Ok(PARSERS.into_iter().collect());
+    };
+
+    let mut parsers = HashSet::with_capacity(configured.len());
+    for name in configured.iter().map(|name| name.trim()) {
+        match PARSERS.into_iter().find(|parser| *parser == name) {
+            Some(parser) => parsers.insert(parser),
+            None => bail!(
+                "Unknown parser {name}, expected one of {}",
+                PARSERS.join(", ")
+            ),
+        };
+    }
+    if parsers.is_empty() {
+        bail!("No parsers enabled");
+    }
+
+    log::info!("Enabled parsers: {parsers:?}");
+    Ok(parsers)
+}
+
+/// Events of disabled parsers are not extracted, so their handlers never run
+fn get_any_extractable(parsers: &HashSet<&str>) -> Vec<AnyExtractable> {
+    // NOTE: из-за того, что есть два ивента NftCreated,
+    // но с разными полями, ивент из Nft.abi.json не распаковывается,
+    // parser::unpack_failure его пропускает
+
+    let extractables = contracts()
+        .into_iter()
+        .flat_map(|c| {
+            c.events
+                .clone()
+                .into_values()
+                .filter(|e| scope::events().contains(&e.name.as_str()))
+                .filter(|e| parsers.contains(parser_of(&e.name)))
+                .map(AnyExtractable::Event)
+                .chain(
+                    c.functions
+                        .clone()
+                        .into_values()
+                        .filter(|f| scope::functions().contains(&f.name.as_str()))
+                        .map(AnyExtractable::Function),
+                )
+        })
+        .collect::<Vec<_>>();
 
     log::info!(
         "List of extractables to parse:\n{:#?}",
@@ -112,3 +371,79 @@ pub async fn get_jrpc_client(config: &Config) -> Result<JrpcClient> {
     )
     .await
 }
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use transaction_buffer::models::AnyExtractable;
+
+    use crate::parser::parser_of;
+
+    use crate::parser::UNPACKED_EVENTS;
+
+    use super::{check_scope, enabled_parsers, get_any_extractable, parser_abis};
+
+    #[test]
+    fn test_scope_matches_the_parser_abis_and_dispatch() {
+        check_scope(parser_abis, UNPACKED_EVENTS).unwrap();
+        assert!(check_scope(|_| Vec::new(), UNPACKED_EVENTS).is_err());
+        // BidPlaced is only in the auction ABIs
+        assert!(check_scope(|_| vec![super::nft()], UNPACKED_EVENTS).is_err());
+
+        let without_bids = UNPACKED_EVENTS
+            .iter()
+            .copied()
+            .filter(|name| *name != "BidPlaced")
+            .collect::<Vec<_>>();
+        assert!(check_scope(parser_abis, &without_bids).is_err());
+
+        let mut with_unscoped = UNPACKED_EVENTS.to_vec();
+        with_unscoped.push("AuctionUpgrade");
+        assert!(check_scope(parser_abis, &with_unscoped).is_err());
+    }
+
+    #[test]
+    fn test_enabled_parsers_are_validated() {
+        let configured = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(enabled_parsers(None).unwrap().len(), 6);
+        assert_eq!(
+            enabled_parsers(Some(&configured(&["direct_sell", " nft"]))).unwrap(),
+            HashSet::from(["direct_sell", "nft"])
+        );
+        assert!(enabled_parsers(Some(&configured(&["auctions"]))).is_err());
+        assert!(enabled_parsers(Some(&[])).is_err());
+    }
+
+    #[test]
+    fn test_disabled_parsers_are_not_extracted() {
+        let event_names = |parsers: &[&'static str]| {
+            get_any_extractable(&parsers.iter().copied().collect::<HashSet<_>>())
+                .into_iter()
+                .filter_map(|e| match e {
+                    AnyExtractable::Event(event) => Some(event.name),
+                    AnyExtractable::Function(_) => None,
+                })
+                .collect::<HashSet<_>>()
+        };
+
+        let direct_sells = event_names(&["direct_sell"]);
+        assert!(direct_sells.contains("DirectSellStateChanged"));
+        assert!(direct_sells.contains("DirectSellDeployed"));
+        assert!(direct_sells
+            .iter()
+            .all(|name| parser_of(name) == "direct_sell"));
+
+        let all = event_names(&[
+            "auction",
+            "direct_buy",
+            "direct_sell",
+            "nft",
+            "collection",
+            "common",
+        ]);
+        assert!(all.contains("BidPlaced"));
+        assert!(all.is_superset(&direct_sells));
+    }
+}
@@ -0,0 +1,261 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+use tokio::signal::unix::{signal, SignalKind};
+
+use crate::settings::config::Config;
+
+/// Subset of the config that can be changed without a restart
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RuntimeConfig {
+    pub blocked_collections: HashSet<String>,
+    /// How long after a listing closed a new one by the same seller counts as a relist
+    pub relist_window_secs: u64,
+    /// Listing/auction ends beyond this horizon from their start are clamped
+    pub max_listing_lifetime_secs: u64,
+    /// Rows per raw event insert, bounds the statement size of large backfill batches
+    pub raw_event_chunk_size: usize,
+}
+
+const DEFAULT_RELIST_WINDOW_SECS: u64 = 7 * 24 * 60 * 60;
+const DEFAULT_MAX_LISTING_LIFETIME_SECS: u64 = 5 * 365 * 24 * 60 * 60;
+const DEFAULT_RAW_EVENT_CHUNK_SIZE: usize = 5_000;
+
+pub type SharedRuntimeConfig = Arc<ArcSwap<RuntimeConfig>>;
+
+impl From<&Config> for RuntimeConfig {
+    fn from(config: &Config) -> Self {
+        Self {
+            blocked_collections: config
+                .blocked_collections
+                .iter()
+                .flatten()
+                .cloned()
+                .collect(),
+            relist_window_secs: config
+                .relist_window_secs
+                .unwrap_or(DEFAULT_RELIST_WINDOW_SECS),
+            max_listing_lifetime_secs: config
+                .max_listing_lifetime_secs
+                .unwrap_or(DEFAULT_MAX_LISTING_LIFETIME_SECS),
+            raw_event_chunk_size: config
+                .raw_event_chunk_size
+                .unwrap_or(DEFAULT_RAW_EVENT_CHUNK_SIZE),
+        }
+    }
+}
+
+impl RuntimeConfig {
+    pub fn is_collection_blocked(&self, collection: &str) -> bool {
+        self.blocked_collections.contains(collection)
+    }
+}
+
+/// Swaps in the new values and returns the names of the ones that changed
+pub fn apply(current: &ArcSwap<RuntimeConfig>, new: RuntimeConfig) -> Vec<&'static str> {
+    let mut changed = Vec::new();
+    if current.load().blocked_collections != new.blocked_collections {
+        changed.push("blocked_collections");
+    }
+    if current.load().relist_window_secs != new.relist_window_secs {
+        changed.push("relist_window_secs");
+    }
+    if current.load().max_listing_lifetime_secs != new.max_listing_lifetime_secs {
+        changed.push("max_listing_lifetime_secs");
+    }
+    if current.load().raw_event_chunk_size != new.raw_event_chunk_size {
+        changed.push("raw_event_chunk_size");
+    }
+
+    current.store(Arc::new(new));
+
+    changed
+}
+
+/// Values read once at startup; changing them has no effect until restart. Every
+/// field of `Config` is either listed here or hot-reloaded by `RuntimeConfig`, the
+/// exhaustive destructuring stops a new field from being missed
+fn restart_required(old: &Config, new: &Config) -> Vec<&'static str> {
+    macro_rules! startup_only {
+        ($($field:ident),* $(,)?) => {{
+            let Config {
+                $($field: _,)*
+                blocked_collections: _,
+                relist_window_secs: _,
+                max_listing_lifetime_secs: _,
+                raw_event_chunk_size: _,
+            } = old;
+
+            let mut changed = Vec::new();
+            $(
+                if old.$field != new.$field {
+                    changed.push(stringify!($field));
+                }
+            )*
+            changed
+        }};
+    }
+
+    startup_only!(
+        database_url,
+        database_max_connections,
+        database_acquire_timeout_secs,
+        database_idle_timeout_secs,
+        database_statement_timeout_ms,
+        kafka_topic,
+        kafka_consumer_group,
+        kafka_reset,
+        kafka_offset_fallback,
+        states_rpc_endpoints,
+        kafka_settings,
+        server_api_url,
+        terminate_open_connections,
+        jrpc_req_latency_millis,
+        jrpc_max_concurrency,
+        getter_rate_limit_per_sec,
+        getter_cache_ttl_secs,
+        bc_name,
+        idle_after_price_loop_sec,
+        idle_after_meta_loop_sec,
+        price_update_frequency_sec,
+        counterparty_api_enabled,
+        admin_api_enabled,
+        metrics_port,
+        discovery_window_secs,
+        expire_listings_interval_secs,
+        reconcile_stats_interval_secs,
+        rarity_interval_secs,
+        floor_refresh_interval_secs,
+        strict_mode,
+        store_raw_transactions,
+        whitelist_mode,
+        enabled_parsers,
+        db_max_retries,
+        db_retry_base_delay_ms,
+        stream_reconnect_max_attempts,
+        stream_reconnect_base_delay_ms,
+        pipeline_depth,
+        commit_max_transactions,
+        commit_max_delay_ms,
+        finality_delay_secs,
+        backfill_from_ts,
+        backfill_to_ts,
+        index_from_lt,
+        index_to_lt,
+        cdc_webhook_url,
+        cdc_kafka_brokers,
+        cdc_kafka_topic,
+        cdc_nats_url,
+        cdc_nats_subject,
+        cdc_channel_capacity,
+        webhook_url,
+        webhook_secret,
+        webhook_event_types,
+        webhook_collections,
+        webhook_max_retries,
+    )
+}
+
+/// Reloads the config on every SIGHUP and applies the hot-swappable subset. The other
+/// values keep running with what the process started with, so every reload is compared
+/// against the startup config: a change stays pending until the restart
+pub async fn watch_sighup(startup: Config, current: SharedRuntimeConfig) {
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(e) => {
+            log::error!("Can't listen for SIGHUP, config reload disabled: {:#?}", e);
+            return;
+        }
+    };
+
+    while hangup.recv().await.is_some() {
+        let new_config = match Config::try_new() {
+            Ok(new_config) => new_config,
+            Err(e) => {
+                log::error!("Config reload failed, keeping current values: {}", e);
+                continue;
+            }
+        };
+
+        let changed = apply(&current, RuntimeConfig::from(&new_config));
+        log::info!("Config reloaded, changed values: {:?}", changed);
+
+        let ignored = restart_required(&startup, &new_config);
+        if !ignored.is_empty() {
+            log::warn!("Changes to {:?} require a restart to apply", ignored);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use arc_swap::ArcSwap;
+
+    use super::{apply, restart_required, RuntimeConfig};
+    use crate::settings::config::{Config, WhitelistMode};
+
+    fn config() -> Config {
+        serde_json::from_value(serde_json::json!({
+            "database_url": "postgres://localhost/nft",
+            "database_max_connections": 10,
+            "kafka_topic": "topic",
+            "kafka_consumer_group": "group",
+            "states_rpc_endpoints": ["http://localhost:8080/rpc"],
+            "kafka_settings": {},
+            "server_api_url": "http://localhost:3001",
+            "jrpc_req_latency_millis": 100,
+            "bc_name": "Venom",
+            "idle_after_price_loop_sec": 10,
+            "idle_after_meta_loop_sec": 10,
+            "price_update_frequency_sec": 10,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_reload_applies_new_blocklist() {
+        let current = ArcSwap::from_pointee(RuntimeConfig::default());
+        let new = RuntimeConfig {
+            blocked_collections: HashSet::from(["0:blocked".to_string()]),
+            ..Default::default()
+        };
+
+        assert_eq!(apply(&current, new.clone()), vec!["blocked_collections"]);
+        assert!(current.load().is_collection_blocked("0:blocked"));
+        assert!(apply(&current, new).is_empty());
+    }
+
+    #[test]
+    fn test_startup_only_changes_are_reported() {
+        let startup = config();
+        let reloaded = Config {
+            strict_mode: Some(true),
+            whitelist_mode: Some(WhitelistMode::Enforce),
+            enabled_parsers: Some(vec!["auction".to_string()]),
+            webhook_url: Some("http://localhost/hook".to_string()),
+            finality_delay_secs: Some(30),
+            metrics_port: Some(9000),
+            database_idle_timeout_secs: Some(0),
+            blocked_collections: Some(vec!["0:blocked".to_string()]),
+            raw_event_chunk_size: Some(100),
+            ..config()
+        };
+
+        assert_eq!(
+            restart_required(&startup, &reloaded),
+            vec![
+                "database_idle_timeout_secs",
+                "metrics_port",
+                "strict_mode",
+                "whitelist_mode",
+                "enabled_parsers",
+                "finality_delay_secs",
+                "webhook_url",
+            ]
+        );
+        assert!(restart_required(&startup, &config()).is_empty());
+    }
+}
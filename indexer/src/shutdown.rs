@@ -0,0 +1,31 @@
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::watch;
+
+/// Flips to `true` on the first SIGTERM or SIGINT
+pub fn shutdown_signal() -> watch::Receiver<bool> {
+    let (tx, rx) = watch::channel(false);
+
+    tokio::spawn(async move {
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = terminate.recv() => log::info!("Received SIGTERM, shutting down"),
+                    _ = tokio::signal::ctrl_c() => log::info!("Received SIGINT, shutting down"),
+                }
+            }
+            Err(e) => {
+                log::error!(
+                    "Can't listen for SIGTERM, only SIGINT stops the indexer: {:#?}",
+                    e
+                );
+                if tokio::signal::ctrl_c().await.is_ok() {
+                    log::info!("Received SIGINT, shutting down");
+                }
+            }
+        }
+
+        tx.send(true).ok();
+    });
+
+    rx
+}
@@ -0,0 +1,51 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use indexer_repo::types::decoded::EventRecord;
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::ClientConfig;
+
+use super::EventSink;
+
+const KAFKA_SEND_TIMEOUT_SECS: u64 = 10;
+
+/// Produces one message per event, keyed by the message hash
+pub struct KafkaSink {
+    producer: FutureProducer,
+    topic: String,
+}
+
+impl KafkaSink {
+    pub fn new(brokers: &str, topic: String) -> Result<Self> {
+        let producer: FutureProducer = ClientConfig::new()
+            .set("bootstrap.servers", brokers)
+            .create()?;
+
+        Ok(Self { producer, topic })
+    }
+}
+
+#[async_trait]
+impl EventSink for KafkaSink {
+    fn name(&self) -> &'static str {
+        "kafka"
+    }
+
+    async fn send(&self, events: &[EventRecord]) -> Result<()> {
+        for event in events {
+            let payload = serde_json::to_vec(event)?;
+            self.producer
+                .send(
+                    FutureRecord::to(&self.topic)
+                        .key(&event.message_hash)
+                        .payload(&payload),
+                    Duration::from_secs(KAFKA_SEND_TIMEOUT_SECS),
+                )
+                .await
+                .map_err(|(e, _)| anyhow!(e))?;
+        }
+
+        Ok(())
+    }
+}
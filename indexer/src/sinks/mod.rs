@@ -0,0 +1,275 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use indexer_repo::types::decoded::EventRecord;
+use sqlx::PgPool;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
+
+use crate::metrics;
+use crate::settings::config::Config;
+
+#[cfg(feature = "kafka-sink")]
+mod kafka;
+#[cfg(feature = "nats-sink")]
+mod nats;
+mod notifier;
+mod webhook;
+
+const DEFAULT_SINK_CHANNEL_CAPACITY: usize = 1_000;
+const DEFAULT_WEBHOOK_MAX_RETRIES: u32 = 5;
+
+/// Destination for committed raw events (change data capture)
+#[async_trait]
+pub trait EventSink: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    async fn send(&self, events: &[EventRecord]) -> Result<()>;
+
+    /// Keeps events that didn't fit in the channel of the lagging sink. A sink with no
+    /// dead letters drops them
+    async fn dead_letter(&self, _events: &[EventRecord], _error: &str) -> Result<()> {
+        Err(anyhow!("{} keeps no dead letters", self.name()))
+    }
+}
+
+#[derive(Default)]
+pub struct SinkStats {
+    pub sent: AtomicU64,
+    pub failed: AtomicU64,
+    pub dropped: AtomicU64,
+    pub dead_lettered: AtomicU64,
+}
+
+/// Fans committed events out to every configured sink.
+///
+/// Each sink reads its own bounded channel in a separate task, so a slow or
+/// failing sink doesn't block ingestion: batches that don't fit in its channel
+/// go to the sink's dead letters, or are dropped.
+#[derive(Default)]
+pub struct EventSinks {
+    senders: Vec<(Arc<dyn EventSink>, mpsc::Sender<Arc<Vec<EventRecord>>>)>,
+    pub stats: Arc<SinkStats>,
+}
+
+impl EventSinks {
+    pub async fn from_config(config: &Config, pool: &PgPool) -> Result<Self> {
+        let mut sinks: Vec<Box<dyn EventSink>> = Vec::new();
+
+        if let Some(url) = &config.webhook_url {
+            let filter = notifier::WebhookFilter {
+                event_types: config
+                    .webhook_event_types
+                    .iter()
+                    .flatten()
+                    .cloned()
+                    .collect(),
+                collections: config
+                    .webhook_collections
+                    .iter()
+                    .flatten()
+                    .cloned()
+                    .collect(),
+            };
+            sinks.push(Box::new(notifier::WebhookNotifier::new(
+                pool.clone(),
+                url.clone(),
+                config.webhook_secret.clone().unwrap_or_default(),
+                filter,
+                config
+                    .webhook_max_retries
+                    .unwrap_or(DEFAULT_WEBHOOK_MAX_RETRIES),
+            )));
+        }
+
+        if let Some(url) = &config.cdc_webhook_url {
+            sinks.push(Box::new(webhook::WebhookSink::new(url.clone())));
+        }
+
+        #[cfg(feature = "kafka-sink")]
+        if let (Some(brokers), Some(topic)) = (&config.cdc_kafka_brokers, &config.cdc_kafka_topic) {
+            sinks.push(Box::new(kafka::KafkaSink::new(brokers, topic.clone())?));
+        }
+
+        #[cfg(feature = "nats-sink")]
+        if let (Some(url), Some(subject)) = (&config.cdc_nats_url, &config.cdc_nats_subject) {
+            sinks.push(Box::new(
+                nats::NatsSink::connect(url, subject.clone()).await?,
+            ));
+        }
+
+        Ok(Self::start(
+            sinks,
+            config
+                .cdc_channel_capacity
+                .unwrap_or(DEFAULT_SINK_CHANNEL_CAPACITY),
+        ))
+    }
+
+    pub fn start(sinks: Vec<Box<dyn EventSink>>, capacity: usize) -> Self {
+        let stats = Arc::new(SinkStats::default());
+        let mut senders = Vec::with_capacity(sinks.len());
+
+        for sink in sinks {
+            let sink = Arc::<dyn EventSink>::from(sink);
+            let (tx, rx) = mpsc::channel(capacity.max(1));
+            log::info!("Event sink {} enabled", sink.name());
+            senders.push((sink.clone(), tx));
+            tokio::spawn(run_sink(sink, rx, stats.clone()));
+        }
+
+        Self { senders, stats }
+    }
+
+    pub fn publish(&self, events: &[EventRecord]) {
+        if self.senders.is_empty() || events.is_empty() {
+            return;
+        }
+
+        let batch = Arc::new(events.to_vec());
+        for (sink, sender) in &self.senders {
+            if let Err(TrySendError::Full(batch) | TrySendError::Closed(batch)) =
+                sender.try_send(batch.clone())
+            {
+                tokio::spawn(dead_letter(sink.clone(), batch, self.stats.clone()));
+            }
+        }
+    }
+}
+
+async fn dead_letter(
+    sink: Arc<dyn EventSink>,
+    batch: Arc<Vec<EventRecord>>,
+    stats: Arc<SinkStats>,
+) {
+    let count = batch.len() as u64;
+    match sink.dead_letter(&batch, "sink channel is full").await {
+        Ok(()) => {
+            stats.dead_lettered.fetch_add(count, Ordering::Relaxed);
+            metrics::SINK_EVENTS
+                .with_label_values(&["dead_lettered"])
+                .inc_by(count);
+            log::warn!(
+                "Event sink {} is lagging, dead-lettered {} events",
+                sink.name(),
+                count
+            );
+        }
+        Err(e) => {
+            let dropped = stats.dropped.fetch_add(count, Ordering::Relaxed);
+            metrics::SINK_EVENTS
+                .with_label_values(&["dropped"])
+                .inc_by(count);
+            log::warn!(
+                "Event sink {} is lagging, dropped {} events (total dropped: {}): {:#}",
+                sink.name(),
+                count,
+                dropped + count,
+                e
+            );
+        }
+    }
+}
+
+async fn run_sink(
+    sink: Arc<dyn EventSink>,
+    mut rx: mpsc::Receiver<Arc<Vec<EventRecord>>>,
+    stats: Arc<SinkStats>,
+) {
+    while let Some(batch) = rx.recv().await {
+        match sink.send(&batch).await {
+            Ok(()) => {
+                stats.sent.fetch_add(batch.len() as u64, Ordering::Relaxed);
+                metrics::SINK_EVENTS
+                    .with_label_values(&["sent"])
+                    .inc_by(batch.len() as u64);
+            }
+            Err(e) => {
+                stats
+                    .failed
+                    .fetch_add(batch.len() as u64, Ordering::Relaxed);
+                metrics::SINK_EVENTS
+                    .with_label_values(&["failed"])
+                    .inc_by(batch.len() as u64);
+                log::error!(
+                    "Event sink {} failed to deliver {} events: {:#?}",
+                    sink.name(),
+                    batch.len(),
+                    e
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::Ordering;
+    use std::sync::{Arc, Mutex};
+
+    use anyhow::Result;
+    use async_trait::async_trait;
+    use indexer_repo::types::decoded::EventRecord;
+    use indexer_repo::types::{EventCategory, EventType};
+
+    use super::{EventSink, EventSinks};
+
+    /// Takes the first batch and never finishes sending it
+    struct StuckSink {
+        dead_letters: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl EventSink for StuckSink {
+        fn name(&self) -> &'static str {
+            "stuck"
+        }
+
+        async fn send(&self, _: &[EventRecord]) -> Result<()> {
+            std::future::pending().await
+        }
+
+        async fn dead_letter(&self, events: &[EventRecord], _: &str) -> Result<()> {
+            let mut dead_letters = self.dead_letters.lock().unwrap();
+            dead_letters.extend(events.iter().map(|e| e.message_hash.clone()));
+            Ok(())
+        }
+    }
+
+    fn record(message_hash: &str) -> EventRecord {
+        EventRecord {
+            event_category: EventCategory::Nft,
+            event_type: EventType::NftOwnerChanged,
+            address: "0:nft".to_string(),
+            created_lt: 1,
+            created_at: 0,
+            message_hash: message_hash.to_string(),
+            nft: Some("0:nft".to_string()),
+            collection: None,
+            raw_data: serde_json::Value::Null,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batch_of_a_full_channel_is_dead_lettered() {
+        let dead_letters = Arc::new(Mutex::new(Vec::new()));
+        let sinks = EventSinks::start(
+            vec![Box::new(StuckSink {
+                dead_letters: dead_letters.clone(),
+            })],
+            1,
+        );
+
+        sinks.publish(&[record("sending")]);
+        tokio::task::yield_now().await;
+        sinks.publish(&[record("queued")]);
+        sinks.publish(&[record("overflow")]);
+        tokio::task::yield_now().await;
+
+        assert_eq!(*dead_letters.lock().unwrap(), ["overflow"]);
+        assert_eq!(sinks.stats.dead_lettered.load(Ordering::Relaxed), 1);
+        assert_eq!(sinks.stats.dropped.load(Ordering::Relaxed), 0);
+    }
+}
@@ -0,0 +1,39 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use indexer_repo::types::decoded::EventRecord;
+
+use super::EventSink;
+
+/// Publishes one message per event to `<subject>.<event_category>`
+pub struct NatsSink {
+    client: async_nats::Client,
+    subject: String,
+}
+
+impl NatsSink {
+    pub async fn connect(url: &str, subject: String) -> Result<Self> {
+        let client = async_nats::connect(url).await?;
+
+        Ok(Self { client, subject })
+    }
+}
+
+#[async_trait]
+impl EventSink for NatsSink {
+    fn name(&self) -> &'static str {
+        "nats"
+    }
+
+    async fn send(&self, events: &[EventRecord]) -> Result<()> {
+        for event in events {
+            let category = serde_json::to_value(event.event_category)?;
+            let subject = format!("{}.{}", self.subject, category.as_str().unwrap_or_default());
+            self.client
+                .publish(subject, serde_json::to_vec(event)?.into())
+                .await?;
+        }
+        self.client.flush().await?;
+
+        Ok(())
+    }
+}
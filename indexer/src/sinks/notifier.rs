@@ -0,0 +1,336 @@
+use std::collections::HashSet;
+use std::future::Future;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::{stream, StreamExt};
+use hmac::{Hmac, Mac};
+use indexer_repo::types::decoded::EventRecord;
+use indexer_repo::types::{DirectBuyState, DirectSellState, EventType};
+use sha2::Sha256;
+use sqlx::PgPool;
+
+use super::EventSink;
+
+const SIGNATURE_HEADER: &str = "X-Signature-256";
+const NOTIFY_TIMEOUT_SECS: u64 = 10;
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+/// Retries of an endpoint that is down wait out their backoff side by side
+const MAX_DELIVERIES_IN_FLIGHT: usize = 8;
+
+/// Which events a webhook is interested in; an empty set matches everything.
+/// State changes can be narrowed to the new state, e.g. `DirectSellStateChanged:Filled`
+#[derive(Default)]
+pub struct WebhookFilter {
+    pub event_types: HashSet<String>,
+    pub collections: HashSet<String>,
+}
+
+impl WebhookFilter {
+    pub fn matches(&self, event: &EventRecord) -> bool {
+        let type_matches = self.event_types.is_empty()
+            || event_type_keys(event)
+                .iter()
+                .any(|key| self.event_types.contains(key));
+        let collection_matches = self.collections.is_empty()
+            || event
+                .collection
+                .as_ref()
+                .map_or(false, |c| self.collections.contains(c));
+
+        type_matches && collection_matches
+    }
+}
+
+/// `EventType`, and `EventType:State` for offer state changes
+fn event_type_keys(event: &EventRecord) -> Vec<String> {
+    let event_type = format!("{:?}", event.event_type);
+    // Both offer state enums go up to `Expired` = 5 and panic on anything else
+    let to = event.raw_data["to"].as_u64().filter(|to| *to <= 5);
+    let state = match (event.event_type, to) {
+        (EventType::DirectSellStateChanged, Some(to)) => {
+            Some(format!("{:?}", DirectSellState::from(to as u8)))
+        }
+        (EventType::DirectBuyStateChanged, Some(to)) => {
+            Some(format!("{:?}", DirectBuyState::from(to as u8)))
+        }
+        _ => None,
+    };
+
+    match state {
+        Some(state) => vec![format!("{event_type}:{state}"), event_type],
+        None => vec![event_type],
+    }
+}
+
+/// POSTs matching events, up to `MAX_DELIVERIES_IN_FLIGHT` at a time, signed with
+/// HMAC-SHA256 of the body.
+/// Notifications still failing after all retries go to `webhook_dead_letters`,
+/// and so do the ones that don't fit in the sink channel.
+pub struct WebhookNotifier {
+    client: reqwest::Client,
+    pool: PgPool,
+    url: String,
+    secret: String,
+    filter: WebhookFilter,
+    max_retries: u32,
+}
+
+impl WebhookNotifier {
+    pub fn new(
+        pool: PgPool,
+        url: String,
+        secret: String,
+        filter: WebhookFilter,
+        max_retries: u32,
+    ) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            pool,
+            url,
+            secret,
+            filter,
+            max_retries,
+        }
+    }
+
+    /// Posts `event` with retries, dead-letters it once they are exhausted
+    async fn deliver(&self, event: &EventRecord) -> Result<()> {
+        let payload = serde_json::to_value(event)?;
+        let body = serde_json::to_vec(&payload)?;
+        let signature = sign(self.secret.as_bytes(), &body);
+
+        let delivered = with_retries(self.max_retries, RETRY_BASE_DELAY, || {
+            self.post(&body, &signature)
+        })
+        .await;
+
+        if let Err(e) = delivered {
+            log::error!(
+                "Webhook {} failed for message {}: {:#?}",
+                self.url,
+                event.message_hash,
+                e
+            );
+            indexer_repo::webhook::save_dead_letter(
+                &self.pool,
+                &self.url,
+                &payload,
+                &e.to_string(),
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn post(&self, body: &[u8], signature: &str) -> Result<()> {
+        self.client
+            .post(&self.url)
+            .timeout(Duration::from_secs(NOTIFY_TIMEOUT_SECS))
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(SIGNATURE_HEADER, format!("sha256={signature}"))
+            .body(body.to_vec())
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EventSink for WebhookNotifier {
+    fn name(&self) -> &'static str {
+        "webhook notifier"
+    }
+
+    async fn send(&self, events: &[EventRecord]) -> Result<()> {
+        let matching = events.iter().filter(|e| self.filter.matches(e));
+        for_each_in_flight(matching, MAX_DELIVERIES_IN_FLIGHT, |event| {
+            self.deliver(event)
+        })
+        .await
+    }
+
+    async fn dead_letter(&self, events: &[EventRecord], error: &str) -> Result<()> {
+        for event in events.iter().filter(|e| self.filter.matches(e)) {
+            indexer_repo::webhook::save_dead_letter(
+                &self.pool,
+                &self.url,
+                &serde_json::to_value(event)?,
+                error,
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Hex encoded HMAC-SHA256 of `body`
+pub fn sign(secret: &[u8], body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Runs `deliver` for each item, at most `in_flight` at a time, and stops at the first
+/// error
+async fn for_each_in_flight<I, F, Fut>(items: I, in_flight: usize, deliver: F) -> Result<()>
+where
+    I: IntoIterator,
+    F: FnMut(I::Item) -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    let mut deliveries = stream::iter(items)
+        .map(deliver)
+        .buffer_unordered(in_flight.max(1));
+    while let Some(delivered) = deliveries.next().await {
+        delivered?;
+    }
+
+    Ok(())
+}
+
+/// Runs `f` up to `max_retries + 1` times, doubling the delay after each failure
+async fn with_retries<F, Fut>(max_retries: u32, base_delay: Duration, mut f: F) -> Result<()>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    let mut attempt = 0;
+    loop {
+        match f().await {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt >= max_retries => return Err(e),
+            Err(e) => {
+                let delay = base_delay * 2u32.saturating_pow(attempt);
+                log::warn!(
+                    "Attempt {} failed, retrying in {:?}: {}",
+                    attempt + 1,
+                    delay,
+                    e
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::Cell;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use anyhow::anyhow;
+    use indexer_repo::types::decoded::EventRecord;
+    use indexer_repo::types::{EventCategory, EventType};
+
+    use super::{for_each_in_flight, sign, with_retries, WebhookFilter};
+
+    fn state_changed(event_type: EventType, to: u8) -> EventRecord {
+        EventRecord {
+            event_category: EventCategory::DirectSell,
+            event_type,
+            address: "0:offer".to_string(),
+            created_lt: 1,
+            created_at: 0,
+            message_hash: "hash".to_string(),
+            nft: Some("0:nft".to_string()),
+            collection: Some("0:collection".to_string()),
+            raw_data: serde_json::json!({ "from": 2, "to": to }),
+        }
+    }
+
+    #[test]
+    fn test_filter_by_new_state() {
+        let filter = WebhookFilter {
+            event_types: ["DirectSellStateChanged:Filled", "AuctionComplete"]
+                .map(String::from)
+                .into(),
+            ..Default::default()
+        };
+
+        assert!(filter.matches(&state_changed(EventType::DirectSellStateChanged, 3)));
+        assert!(!filter.matches(&state_changed(EventType::DirectSellStateChanged, 2)));
+        assert!(!filter.matches(&state_changed(EventType::DirectBuyStateChanged, 3)));
+        assert!(filter.matches(&state_changed(EventType::AuctionComplete, 0)));
+    }
+
+    #[test]
+    fn test_filter_by_type_matches_every_state() {
+        let filter = WebhookFilter {
+            event_types: ["DirectBuyStateChanged".to_string()].into(),
+            ..Default::default()
+        };
+
+        for to in 2..=5 {
+            assert!(filter.matches(&state_changed(EventType::DirectBuyStateChanged, to)));
+        }
+    }
+
+    #[test]
+    fn test_hmac_signature() {
+        assert_eq!(
+            sign(b"key", b"The quick brown fox jumps over the lazy dog"),
+            "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retries_until_success() {
+        let calls = Cell::new(0);
+        let result = with_retries(3, Duration::ZERO, || {
+            calls.set(calls.get() + 1);
+            let call = calls.get();
+            async move {
+                if call < 3 {
+                    Err(anyhow!("endpoint down"))
+                } else {
+                    Ok(())
+                }
+            }
+        })
+        .await;
+
+        assert!(result.is_ok());
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_retries() {
+        let calls = Cell::new(0);
+        let result = with_retries(2, Duration::ZERO, || {
+            calls.set(calls.get() + 1);
+            async { Err(anyhow!("endpoint down")) }
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_deliveries_run_side_by_side_up_to_the_limit() {
+        let (in_flight, max_in_flight) = (&AtomicUsize::new(0), &AtomicUsize::new(0));
+        let delivered = &AtomicUsize::new(0);
+
+        let result = for_each_in_flight(0..5, 2, |_| async move {
+            let running = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            max_in_flight.fetch_max(running, Ordering::SeqCst);
+            tokio::task::yield_now().await;
+            in_flight.fetch_sub(1, Ordering::SeqCst);
+            delivered.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        })
+        .await;
+
+        assert!(result.is_ok());
+        assert_eq!(delivered.load(Ordering::SeqCst), 5);
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,43 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use indexer_repo::types::decoded::EventRecord;
+
+use super::EventSink;
+
+const WEBHOOK_TIMEOUT_SECS: u64 = 10;
+
+/// POSTs every committed batch as a JSON array
+pub struct WebhookSink {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl WebhookSink {
+    pub fn new(url: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+        }
+    }
+}
+
+#[async_trait]
+impl EventSink for WebhookSink {
+    fn name(&self) -> &'static str {
+        "webhook"
+    }
+
+    async fn send(&self, events: &[EventRecord]) -> Result<()> {
+        self.client
+            .post(&self.url)
+            .timeout(Duration::from_secs(WEBHOOK_TIMEOUT_SECS))
+            .json(events)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
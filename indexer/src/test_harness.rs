@@ -0,0 +1,1786 @@
+//! Drives a scripted stream of extracted events through decoding and `save_to_db`, the
+//! way the indexer loop does, against a database created by `#[sqlx::test]` from
+//! `DATABASE_URL` with the migrations applied. Tests using it are ignored by default,
+//! run them with `cargo test -- --ignored` next to a Postgres
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use bigdecimal::BigDecimal;
+use data_reader::RarityQueue;
+use indexer_api::ParserControl;
+use indexer_repo::checkpoint::get_checkpoint;
+use nekoton_abi::transaction_parser::{ExtractedOwned, ParsedType};
+use nekoton_abi::PackAbiPlain;
+use sqlx::PgPool;
+use tokio::sync::Mutex;
+use ton_block::{Message, MsgAddressInt, Transaction};
+use ton_types::UInt256;
+
+use crate::backfill::LtWindow;
+use crate::floor_refresh::FloorRefresh;
+use crate::missing_collections::CollectionResolver;
+use crate::parser::{
+    decode_entity, park, parser_of, save_to_db, unpack_entity, unpack_failure, unpark_events,
+    update_parked,
+};
+use crate::persistence::collection_cache::NftCollectionCache;
+use crate::persistence::collections_queue::CollectionsQueue;
+use crate::price::{UsdConverter, UsdRates};
+use crate::replay;
+use crate::resume::{checkpoint_of, ReplayGuard, StreamedTx};
+use crate::settings::runtime::RuntimeConfig;
+use crate::sinks::EventSinks;
+use crate::utils::DecodeContext;
+
+pub fn address(byte: u8) -> MsgAddressInt {
+    format!("0:{}", hex::encode([byte; 32])).parse().unwrap()
+}
+
+/// Transaction of the script, its events are emitted by `account`
+pub struct ScriptedTx {
+    account: MsgAddressInt,
+    lt: u64,
+    now: u32,
+    events: Vec<ExtractedOwned>,
+}
+
+impl ScriptedTx {
+    pub fn new(account: &MsgAddressInt, lt: u64, now: u32) -> Self {
+        Self {
+            account: account.clone(),
+            lt,
+            now,
+            events: Vec::new(),
+        }
+    }
+
+    /// Adds `event` packed into the tokens the ABI parser extracts, with a message hash
+    /// unique within the script
+    pub fn emit<T: PackAbiPlain>(mut self, name: &str, event: T) -> Self {
+        let mut hash = [0; 32];
+        hash[..8].copy_from_slice(&self.lt.to_be_bytes());
+        hash[8] = self.events.len() as u8;
+
+        self.events.push(ExtractedOwned {
+            function_id: 0,
+            name: name.to_string(),
+            bounced: false,
+            tokens: event.pack(),
+            message_hash: UInt256::from(hash),
+            message: Message::default(),
+            tx: Transaction::default(),
+            is_in_message: false,
+            parsed_type: ParsedType::Event,
+            decoded_headers: Vec::default(),
+        });
+        self
+    }
+
+    fn tx_data(&self) -> Transaction {
+        let mut tx_data = Transaction::default();
+        tx_data.set_logical_time(self.lt);
+        tx_data.account_addr = self.account.address();
+        tx_data.now = self.now;
+        tx_data
+    }
+}
+
+impl StreamedTx for ScriptedTx {
+    fn timestamp(&self) -> i64 {
+        self.now as i64
+    }
+
+    fn lt(&self) -> i64 {
+        self.lt as i64
+    }
+
+    fn hash(&self) -> String {
+        format!("{}:{}", self.account, self.lt)
+    }
+}
+
+/// Tokens have no USD rate
+struct NoRates;
+
+#[async_trait]
+impl UsdRates for NoRates {
+    async fn usd_rate(&self, _: &str, _: u64) -> Option<BigDecimal> {
+        None
+    }
+}
+
+/// Collections of the TIP-4.3 indexes, by nft address
+pub struct FixedCollections(pub HashMap<String, String>);
+
+#[async_trait]
+impl CollectionResolver for FixedCollections {
+    async fn resolve_collection(&self, nft: &MsgAddressInt) -> Result<Option<String>> {
+        Ok(self.0.get(&nft.to_string()).cloned())
+    }
+}
+
+/// Stands in for the transaction consumer, yields the scripted transactions batch by
+/// batch. Decoding is strict, a script event that fails to decode panics the test. One
+/// that doesn't unpack is dead-lettered, as in strict mode
+pub struct FakeConsumer {
+    batches: VecDeque<Vec<ScriptedTx>>,
+    lt_window: Option<LtWindow>,
+    resume: bool,
+    parser_control: ParserControl,
+    raw_event_chunk_size: Option<usize>,
+}
+
+impl FakeConsumer {
+    pub fn new(batches: Vec<Vec<ScriptedTx>>) -> Self {
+        Self {
+            batches: batches.into(),
+            lt_window: None,
+            resume: false,
+            parser_control: ParserControl::default(),
+            raw_event_chunk_size: None,
+        }
+    }
+
+    /// Drops the transactions outside `window` the way an audit run does
+    pub fn with_lt_window(mut self, window: LtWindow) -> Self {
+        self.lt_window = Some(window);
+        self
+    }
+
+    /// Parks the events of paused parsers and applies them once resumed, the way the
+    /// decoder and the batch writer do
+    pub fn with_parser_control(mut self, parser_control: ParserControl) -> Self {
+        self.parser_control = parser_control;
+        self
+    }
+
+    /// Inserts the raw events of a batch in statements of at most `size` rows
+    pub fn with_raw_event_chunk_size(mut self, size: usize) -> Self {
+        self.raw_event_chunk_size = Some(size);
+        self
+    }
+
+    /// Skips what was saved before the stored checkpoint and moves it with each batch,
+    /// like the indexer after a restart
+    pub fn resuming(mut self) -> Self {
+        self.resume = true;
+        self
+    }
+
+    /// Saves every batch in its own database transaction, without a checkpoint unless
+    /// resuming. Returns how many transactions were saved
+    pub async fn run(mut self, pool: &PgPool) -> Result<usize> {
+        let usd_converter = UsdConverter::new(Arc::new(NoRates));
+        let collection_queue = Mutex::new(CollectionsQueue::new(pool.clone()).await);
+        let collection_cache = NftCollectionCache::default();
+        let mut runtime_config = runtime_config();
+        if let Some(size) = self.raw_event_chunk_size {
+            runtime_config.raw_event_chunk_size = size;
+        }
+        let sinks = EventSinks::default();
+        let rarity_queue = RarityQueue::default();
+        let floor_refresh = FloorRefresh::default();
+        let mut replay_guard = match self.resume {
+            true => ReplayGuard::new(get_checkpoint(pool).await?),
+            false => ReplayGuard::default(),
+        };
+        let mut saved = 0;
+
+        while let Some(mut batch) = self.batches.pop_front() {
+            replay_guard.retain_new(&mut batch);
+            let checkpoint = checkpoint_of(&batch).filter(|_| self.resume);
+            if let Some(window) = &self.lt_window {
+                window.retain(&mut batch);
+            }
+
+            let mut data = Vec::new();
+            for tx in &batch {
+                for event in &tx.events {
+                    let ctx = DecodeContext {
+                        tx_data: tx.tx_data(),
+                        function_inputs: Vec::new(),
+                        message_hash: event.message_hash,
+                        max_listing_lifetime_secs: runtime_config.max_listing_lifetime_secs,
+                    };
+                    match unpack_entity(event) {
+                        Ok(Some(entity)) => {
+                            let decoded = decode_entity(entity.as_ref(), event, &ctx, true);
+                            let parser = parser_of(&event.name);
+                            if self.parser_control.is_active(parser) {
+                                data.extend(decoded);
+                            } else {
+                                data.extend(decoded.into_iter().filter_map(|d| park(parser, d)));
+                            }
+                        }
+                        Ok(None) => {}
+                        Err(e) => data.extend(unpack_failure(event, &ctx, e)),
+                    }
+                }
+            }
+
+            let (data, drained) =
+                unpark_events(pool, &self.parser_control, data, &runtime_config).await?;
+            save_to_db(
+                pool,
+                &usd_converter,
+                data.clone(),
+                &collection_queue,
+                &collection_cache,
+                &runtime_config,
+                &sinks,
+                &rarity_queue,
+                &floor_refresh,
+                checkpoint.as_ref(),
+            )
+            .await?;
+            update_parked(&self.parser_control, &data, &drained);
+            saved += batch.len();
+        }
+
+        Ok(saved)
+    }
+}
+
+/// `replay --from-lt` over the events the consumer stored
+pub async fn replay_from(pool: &PgPool, from_lt: i64) -> Result<()> {
+    replay::run(
+        pool.clone(),
+        UsdConverter::new(Arc::new(NoRates)),
+        runtime_config(),
+        from_lt,
+    )
+    .await
+}
+
+fn runtime_config() -> RuntimeConfig {
+    RuntimeConfig {
+        max_listing_lifetime_secs: u64::MAX,
+        raw_event_chunk_size: 1_000,
+        relist_window_secs: 7 * 24 * 60 * 60,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use bigdecimal::BigDecimal;
+    use chrono::NaiveDateTime;
+    use indexer_api::ParserControl;
+    use indexer_repo::auction::{get_auction, get_auction_bids};
+    use indexer_repo::batch::save_price_history;
+    use indexer_repo::checkpoint::get_checkpoint;
+    use indexer_repo::collection::{get_collection_stats, reconcile_collection_stats};
+    use indexer_repo::direct_buy::get_nft_offers;
+    use indexer_repo::direct_sell::{
+        expire_stale_direct_sells, get_active_direct_sells_by_nfts, get_direct_sells,
+    };
+    use indexer_repo::events::{
+        get_address_activity, get_collection_events, list_events, EventCursor, EventFilter,
+    };
+    use indexer_repo::nft::{get_nft, search_nfts_by_attributes};
+    use indexer_repo::price::NftPriceModel;
+    use indexer_repo::rollback::rollback_to_lt;
+    use indexer_repo::types::decoded::{EventRecord, NftPriceHistory};
+    use indexer_repo::types::{
+        AuctionStatus as RepoAuctionStatus, DirectBuyState, DirectSellState, EventType,
+        NftPriceSource,
+    };
+    use nekoton_abi::PackAbiPlain;
+    use sqlx::PgPool;
+    use ton_block::MsgAddressInt;
+
+    use crate::models::events::{
+        AuctionActive, AuctionCancelled, AuctionDeployed, BidPlaced, DirectBuyDeployed,
+        DirectBuyStateChanged, DirectSellDeployed, DirectSellStateChanged, NftBurned, NftCreated,
+        OwnerChanged,
+    };
+    use crate::models::types::{
+        AuctionDetails, AuctionStatus, DirectBuyInfo, DirectSellInfo, MarketOffer,
+    };
+    use crate::utils::timestamp_to_datetime;
+
+    use crate::backfill::LtWindow;
+    use crate::missing_collections::{backfill_missing_collections, CollectionsBackfill};
+
+    use super::{address, replay_from, FakeConsumer, FixedCollections, ScriptedTx};
+
+    fn state_changed(from: u8, to: u8, new_owner: MsgAddressInt) -> DirectSellStateChanged {
+        DirectSellStateChanged {
+            from,
+            to,
+            value2: DirectSellInfo {
+                factory: address(1),
+                creator: address(5),
+                token: address(4),
+                nft: address(3),
+                _time_tx: 1_700_000_000,
+                start: 1_700_000_000,
+                end: 1_700_086_400,
+                _price: 100,
+                wallet: address(8),
+                status: to,
+                collection: address(7),
+            },
+            old_owner: address(5),
+            new_owner,
+        }
+    }
+
+    #[ignore = "needs a Postgres at DATABASE_URL"]
+    #[sqlx::test(migrations = "../indexer-repo/migrations")]
+    async fn test_direct_sell_lifecycle(pool: PgPool) {
+        let (root, direct_sell) = (address(1), address(2));
+
+        FakeConsumer::new(vec![
+            vec![ScriptedTx::new(&root, 10, 1_700_000_000).emit(
+                "DirectSellDeployed",
+                DirectSellDeployed {
+                    direct_sell: direct_sell.clone(),
+                    sender: address(5),
+                    payment_token: address(4),
+                    nft: address(3),
+                    nonce: 0,
+                    price: 100,
+                },
+            )],
+            vec![ScriptedTx::new(&direct_sell, 20, 1_700_000_010)
+                .emit("DirectSellStateChanged", state_changed(1, 2, address(5)))],
+            vec![ScriptedTx::new(&direct_sell, 30, 1_700_000_500)
+                .emit("DirectSellStateChanged", state_changed(2, 3, address(6)))],
+        ])
+        .run(&pool)
+        .await
+        .unwrap();
+
+        let stored = get_direct_sells(&pool, &[&direct_sell.to_string()])
+            .await
+            .unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].state, DirectSellState::Filled);
+        assert_eq!(stored[0].price, 100.into());
+        assert_eq!(
+            stored[0].finished_at,
+            Some(timestamp_to_datetime(1_700_000_500))
+        );
+
+        let filter = EventFilter {
+            address: Some(direct_sell.to_string()),
+            event_type: None,
+            from_lt: None,
+        };
+        let (events, _) = list_events(&pool, &filter, None, 10).await.unwrap();
+        assert_eq!(events.len(), 2);
+
+        let sales: i64 =
+            sqlx::query_scalar("select count(*) from nft_price_history where source = $1")
+                .bind(direct_sell.to_string())
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(sales, 1);
+    }
+
+    /// Deploys `direct_sell` and activates it one transaction later
+    fn listed(direct_sell: &MsgAddressInt, lt: u64) -> Vec<ScriptedTx> {
+        vec![
+            ScriptedTx::new(&address(1), lt, 1_700_000_000).emit(
+                "DirectSellDeployed",
+                DirectSellDeployed {
+                    direct_sell: direct_sell.clone(),
+                    sender: address(5),
+                    payment_token: address(4),
+                    nft: address(3),
+                    nonce: 0,
+                    price: 100,
+                },
+            ),
+            ScriptedTx::new(direct_sell, lt + 1, 1_700_000_010)
+                .emit("DirectSellStateChanged", state_changed(1, 2, address(5))),
+        ]
+    }
+
+    #[ignore = "needs a Postgres at DATABASE_URL"]
+    #[sqlx::test(migrations = "../indexer-repo/migrations")]
+    async fn test_listing_counter_follows_list_and_delist(pool: PgPool) {
+        let collection = address(7).to_string();
+        let (first, second, third) = (address(20), address(21), address(22));
+        let active_listings = |pool: PgPool| {
+            let collection = collection.clone();
+            async move {
+                get_collection_stats(&pool, &collection)
+                    .await
+                    .unwrap()
+                    .map(|s| s.active_listings)
+            }
+        };
+
+        FakeConsumer::new(vec![listed(&first, 10), listed(&second, 20)])
+            .run(&pool)
+            .await
+            .unwrap();
+        assert_eq!(active_listings(pool.clone()).await, Some(2));
+
+        // delisted, bought, relisted
+        FakeConsumer::new(vec![
+            vec![ScriptedTx::new(&first, 30, 1_700_000_100)
+                .emit("DirectSellStateChanged", state_changed(2, 4, address(5)))],
+            vec![ScriptedTx::new(&second, 40, 1_700_000_200)
+                .emit("DirectSellStateChanged", state_changed(2, 3, address(6)))],
+            listed(&third, 50),
+        ])
+        .run(&pool)
+        .await
+        .unwrap();
+        assert_eq!(active_listings(pool.clone()).await, Some(1));
+
+        // a redelivered activation doesn't count the listing twice
+        FakeConsumer::new(vec![vec![ScriptedTx::new(&third, 51, 1_700_000_010)
+            .emit("DirectSellStateChanged", state_changed(1, 2, address(5)))]])
+        .run(&pool)
+        .await
+        .unwrap();
+        assert_eq!(active_listings(pool.clone()).await, Some(1));
+
+        sqlx::query(
+            "insert into nft_collection (address, owner, created, updated)
+             values ($1, $2, now(), now()) on conflict do nothing",
+        )
+        .bind(&collection)
+        .bind(address(5).to_string())
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query("update collection_stats set active_listings = 5 where collection = $1")
+            .bind(&collection)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert!(reconcile_collection_stats(&pool).await.unwrap() >= 1);
+        assert_eq!(active_listings(pool.clone()).await, Some(1));
+        assert_eq!(reconcile_collection_stats(&pool).await.unwrap(), 0);
+    }
+
+    #[ignore = "needs a Postgres at DATABASE_URL"]
+    #[sqlx::test(migrations = "../indexer-repo/migrations")]
+    async fn test_activity_of_an_address_that_sold_and_bid(pool: PgPool) {
+        let (wallet, direct_sell, auction) = (address(5), address(2), address(30));
+        let bid = BidPlaced {
+            buyer: wallet.clone(),
+            value: 50,
+            next_bid_value: 55,
+            value3: AuctionDetails {
+                auction_subject: address(31),
+                subject_owner: address(9),
+                payment_token: address(4),
+                wallet_for_bids: address(8),
+                start_time: 1_700_000_000,
+                duration: 3_600,
+                end_time: 1_700_003_600,
+                price: 10,
+                nonce: 0,
+                status: AuctionStatus::Active,
+                collection: address(7),
+            },
+        };
+
+        let mut batches = vec![listed(&direct_sell, 10)];
+        batches.push(vec![
+            ScriptedTx::new(&auction, 20, 1_700_000_100).emit("BidPlaced", bid),
+            ScriptedTx::new(&address(31), 21, 1_700_000_200).emit(
+                "OwnerChanged",
+                OwnerChanged {
+                    old_owner: address(9),
+                    new_owner: address(10),
+                },
+            ),
+        ]);
+        batches.push(vec![ScriptedTx::new(&direct_sell, 30, 1_700_000_300)
+            .emit("DirectSellStateChanged", state_changed(2, 3, address(6)))]);
+        FakeConsumer::new(batches).run(&pool).await.unwrap();
+
+        let activity = get_address_activity(&pool, &wallet.to_string(), 10)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|e| format!("{:?}", e.event_type))
+            .collect::<Vec<_>>();
+
+        // newest first; the transfer between other owners is left out
+        assert_eq!(
+            activity,
+            vec![
+                "DirectSellStateChanged",
+                "AuctionBidPlaced",
+                "DirectSellStateChanged",
+                "DirectSellDeployed",
+            ]
+        );
+
+        let buyer_activity = get_address_activity(&pool, &address(6).to_string(), 10)
+            .await
+            .unwrap();
+        assert_eq!(buyer_activity.len(), 1);
+    }
+
+    fn offer_changed(from: u8, to: u8, price: u128) -> DirectBuyStateChanged {
+        DirectBuyStateChanged {
+            from,
+            to,
+            value2: DirectBuyInfo {
+                factory: address(11),
+                creator: address(6),
+                spent_token: address(4),
+                nft: address(3),
+                _time_tx: 1_700_000_000,
+                _price: price,
+                spent_wallet: address(8),
+                status: to,
+                start_time_buy: 1_700_000_000,
+                duration_time_buy: 0,
+                end_time_buy: 0,
+                collection: address(7),
+            },
+            old_owner: address(5),
+            new_owner: address(6),
+        }
+    }
+
+    /// Deploys an offer of `price` for the nft and activates it one transaction later
+    fn offered(direct_buy: &MsgAddressInt, lt: u64, price: u128) -> Vec<ScriptedTx> {
+        vec![
+            ScriptedTx::new(&address(11), lt, 1_700_000_000).emit(
+                "DirectBuyDeployed",
+                DirectBuyDeployed {
+                    direct_buy: direct_buy.clone(),
+                    sender: address(6),
+                    token: address(4),
+                    nft: address(3),
+                    nonce: 0,
+                    amount: price,
+                },
+            ),
+            ScriptedTx::new(direct_buy, lt + 1, 1_700_000_010)
+                .emit("DirectBuyStateChanged", offer_changed(0, 2, price)),
+        ]
+    }
+
+    async fn offer_state(pool: &PgPool, direct_buy: &MsgAddressInt) -> DirectBuyState {
+        sqlx::query_scalar("select state from nft_direct_buy where address = $1")
+            .bind(direct_buy.to_string())
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[ignore = "needs a Postgres at DATABASE_URL"]
+    #[sqlx::test(migrations = "../indexer-repo/migrations")]
+    async fn test_filled_offer_leaves_the_nft_offers(pool: PgPool) {
+        let (low, high) = (address(40), address(41));
+        let nft = address(3).to_string();
+
+        FakeConsumer::new(vec![offered(&low, 10, 50), offered(&high, 20, 80)])
+            .run(&pool)
+            .await
+            .unwrap();
+        let offers = get_nft_offers(&pool, &nft).await.unwrap();
+        assert_eq!(
+            offers.iter().map(|o| o.address.clone()).collect::<Vec<_>>(),
+            vec![high.to_string(), low.to_string()]
+        );
+        assert_eq!(offers[0].price, 80.into());
+        assert_eq!(offers[0].buyer, Some(address(6).to_string()));
+
+        FakeConsumer::new(vec![vec![ScriptedTx::new(&high, 30, 1_700_000_500)
+            .emit("DirectBuyStateChanged", offer_changed(2, 3, 80))]])
+        .run(&pool)
+        .await
+        .unwrap();
+
+        assert_eq!(offer_state(&pool, &high).await, DirectBuyState::Filled);
+        let offers = get_nft_offers(&pool, &nft).await.unwrap();
+        assert_eq!(offers.len(), 1);
+        assert_eq!(offers[0].address, low.to_string());
+    }
+
+    #[ignore = "needs a Postgres at DATABASE_URL"]
+    #[sqlx::test(migrations = "../indexer-repo/migrations")]
+    async fn test_cancelled_offer_leaves_the_nft_offers(pool: PgPool) {
+        let direct_buy = address(40);
+
+        let mut batches = vec![offered(&direct_buy, 10, 50)];
+        batches.push(vec![ScriptedTx::new(&direct_buy, 20, 1_700_000_500)
+            .emit("DirectBuyStateChanged", offer_changed(2, 4, 50))]);
+        // an activation redelivered after the cancel doesn't revive the offer
+        batches.push(vec![ScriptedTx::new(&direct_buy, 11, 1_700_000_010)
+            .emit("DirectBuyStateChanged", offer_changed(0, 2, 50))]);
+        FakeConsumer::new(batches).run(&pool).await.unwrap();
+
+        assert_eq!(
+            offer_state(&pool, &direct_buy).await,
+            DirectBuyState::Cancelled
+        );
+        assert!(get_nft_offers(&pool, &address(3).to_string())
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[ignore = "needs a Postgres at DATABASE_URL"]
+    #[sqlx::test(migrations = "../indexer-repo/migrations")]
+    async fn test_only_transactions_in_the_lt_window_persist(pool: PgPool) {
+        let (before, inside, after) = (address(50), address(51), address(52));
+
+        FakeConsumer::new(vec![
+            listed(&before, 10),
+            listed(&inside, 20),
+            listed(&after, 30),
+        ])
+        .with_lt_window(LtWindow {
+            from_lt: Some(20),
+            to_lt: Some(21),
+        })
+        .run(&pool)
+        .await
+        .unwrap();
+
+        let stored = get_direct_sells(
+            &pool,
+            &[&before.to_string(), &inside.to_string(), &after.to_string()],
+        )
+        .await
+        .unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].address, inside.to_string());
+        assert_eq!(stored[0].state, DirectSellState::Active);
+
+        let (events, _) = list_events(&pool, &EventFilter::default(), None, 10)
+            .await
+            .unwrap();
+        assert!(events.iter().all(|e| (20..=21).contains(&e.created_lt)));
+        assert_eq!(events.len(), 2);
+    }
+
+    #[ignore = "needs a Postgres at DATABASE_URL"]
+    #[sqlx::test(migrations = "../indexer-repo/migrations")]
+    async fn test_transfer_keeps_the_minter(pool: PgPool) {
+        let (collection, nft) = (address(7), address(3));
+        let minted = NftCreated {
+            id: ton_types::UInt256::from([1; 32]),
+            nft: nft.clone(),
+            owner: address(5),
+            manager: address(5),
+            creator: address(9),
+        };
+
+        FakeConsumer::new(vec![
+            vec![ScriptedTx::new(&collection, 10, 1_700_000_000).emit("NftCreated", minted)],
+            vec![ScriptedTx::new(&nft, 20, 1_700_000_100).emit(
+                "OwnerChanged",
+                OwnerChanged {
+                    old_owner: address(5),
+                    new_owner: address(6),
+                },
+            )],
+        ])
+        .run(&pool)
+        .await
+        .unwrap();
+
+        let stored = get_nft(&pool, &nft.to_string()).await.unwrap().unwrap();
+        assert_eq!(stored.collection, collection.to_string());
+        assert_eq!(stored.owner, address(6).to_string());
+        assert_eq!(stored.manager, address(5).to_string());
+        assert_eq!(stored.owner_update_lt, 20);
+        assert_eq!(stored.minter, Some(address(9).to_string()));
+        assert_eq!(stored.created_lt, Some(10));
+        assert_eq!(
+            stored.created_at,
+            Some(timestamp_to_datetime(1_700_000_000))
+        );
+    }
+
+    fn transferred(lt: u64, now: u32, new_owner: u8) -> Vec<ScriptedTx> {
+        vec![ScriptedTx::new(&address(3), lt, now).emit(
+            "OwnerChanged",
+            OwnerChanged {
+                old_owner: address(5),
+                new_owner: address(new_owner),
+            },
+        )]
+    }
+
+    #[ignore = "needs a Postgres at DATABASE_URL"]
+    #[sqlx::test(migrations = "../indexer-repo/migrations")]
+    async fn test_restart_resumes_after_the_last_commit(pool: PgPool) {
+        let now = 1_700_000_000;
+
+        // stopped after two committed batches
+        let saved = FakeConsumer::new(vec![transferred(10, now, 6), transferred(20, now + 10, 7)])
+            .resuming()
+            .run(&pool)
+            .await
+            .unwrap();
+        assert_eq!(saved, 2);
+
+        // the new consumer redelivers the second batch from the committed offsets. A
+        // transaction of another account older than the previous batch isn't one
+        let late = vec![ScriptedTx::new(&address(1), 5, now + 15).emit(
+            "DirectSellDeployed",
+            DirectSellDeployed {
+                direct_sell: address(60),
+                sender: address(5),
+                payment_token: address(4),
+                nft: address(3),
+                nonce: 0,
+                price: 100,
+            },
+        )];
+        let saved = FakeConsumer::new(vec![
+            transferred(20, now + 10, 7),
+            transferred(30, now + 20, 8),
+            late,
+        ])
+        .resuming()
+        .run(&pool)
+        .await
+        .unwrap();
+        assert_eq!(saved, 2);
+
+        let stored = get_direct_sells(&pool, &[&address(60).to_string()])
+            .await
+            .unwrap();
+        assert_eq!(stored.len(), 1);
+        let checkpoint = get_checkpoint(&pool).await.unwrap().unwrap();
+        assert_eq!(checkpoint.tx_lt, 5);
+    }
+
+    /// Deploys a listing of `nft` without activating it, so it keeps the collection
+    /// of the nft row, none while the nft isn't indexed
+    fn deployed(direct_sell: &MsgAddressInt, nft: &MsgAddressInt, lt: u64) -> Vec<ScriptedTx> {
+        vec![ScriptedTx::new(&address(1), lt, 1_700_000_000).emit(
+            "DirectSellDeployed",
+            DirectSellDeployed {
+                direct_sell: direct_sell.clone(),
+                sender: address(5),
+                payment_token: address(4),
+                nft: nft.clone(),
+                nonce: 0,
+                price: 100,
+            },
+        )]
+    }
+
+    #[ignore = "needs a Postgres at DATABASE_URL"]
+    #[sqlx::test(migrations = "../indexer-repo/migrations")]
+    async fn test_backfill_fills_listings_missing_a_collection(pool: PgPool) {
+        let (minted, indexed, unknown) = (address(40), address(41), address(42));
+        let (collection, other_collection) = (address(7), address(17));
+        let listings = [address(60), address(61), address(62)];
+
+        FakeConsumer::new(vec![
+            deployed(&listings[0], &minted, 10),
+            deployed(&listings[1], &indexed, 11),
+            deployed(&listings[2], &unknown, 12),
+            vec![ScriptedTx::new(&collection, 20, 1_700_000_100).emit(
+                "NftCreated",
+                NftCreated {
+                    id: ton_types::UInt256::from([2; 32]),
+                    nft: minted.clone(),
+                    owner: address(5),
+                    manager: address(5),
+                    creator: address(5),
+                },
+            )],
+        ])
+        .run(&pool)
+        .await
+        .unwrap();
+
+        let resolver =
+            FixedCollections([(indexed.to_string(), other_collection.to_string())].into());
+        let backfill = backfill_missing_collections(&pool, &resolver)
+            .await
+            .unwrap();
+        assert_eq!(
+            backfill,
+            CollectionsBackfill {
+                nfts: 0,
+                listings: 2,
+                unresolved: 1
+            }
+        );
+
+        let listings = listings.iter().map(ToString::to_string).collect::<Vec<_>>();
+        let stored = get_direct_sells(
+            &pool,
+            &listings.iter().map(String::as_str).collect::<Vec<_>>(),
+        )
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|ds| (ds.nft, ds.collection))
+        .collect::<HashMap<_, _>>();
+        assert_eq!(stored[&minted.to_string()], Some(collection.to_string()));
+        assert_eq!(
+            stored[&indexed.to_string()],
+            Some(other_collection.to_string())
+        );
+        assert_eq!(stored[&unknown.to_string()], None);
+
+        // a second run only retries the unresolved nft
+        let backfill = backfill_missing_collections(&pool, &resolver)
+            .await
+            .unwrap();
+        assert_eq!(
+            backfill,
+            CollectionsBackfill {
+                nfts: 0,
+                listings: 0,
+                unresolved: 1
+            }
+        );
+    }
+
+    #[ignore = "needs a Postgres at DATABASE_URL"]
+    #[sqlx::test(migrations = "../indexer-repo/migrations")]
+    async fn test_paused_parser_applies_its_parked_events_on_resume(pool: PgPool) {
+        let direct_sell = address(2);
+        let parser_control = ParserControl::default();
+        assert!(parser_control.set_paused("direct_sell", true));
+
+        FakeConsumer::new(vec![listed(&direct_sell, 10)])
+            .with_parser_control(parser_control.clone())
+            .run(&pool)
+            .await
+            .unwrap();
+
+        let stored = get_direct_sells(&pool, &[&direct_sell.to_string()])
+            .await
+            .unwrap();
+        assert!(stored.is_empty());
+        assert_eq!(parked_events(&pool).await, 2);
+        assert_eq!(parser_control.parked(), ["direct_sell"]);
+
+        assert!(parser_control.set_paused("direct_sell", false));
+        FakeConsumer::new(vec![vec![ScriptedTx::new(&direct_sell, 30, 1_700_000_500)
+            .emit("DirectSellStateChanged", state_changed(2, 3, address(6)))]])
+        .with_parser_control(parser_control.clone())
+        .run(&pool)
+        .await
+        .unwrap();
+
+        // parked deploy and activation first, then the sale consumed after the resume
+        let stored = get_direct_sells(&pool, &[&direct_sell.to_string()])
+            .await
+            .unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].state, DirectSellState::Filled);
+        assert_eq!(parked_events(&pool).await, 0);
+        assert!(parser_control.parked().is_empty());
+    }
+
+    #[ignore = "needs a Postgres at DATABASE_URL"]
+    #[sqlx::test(migrations = "../indexer-repo/migrations")]
+    async fn test_rollback_takes_back_what_the_replay_applies_again(pool: PgPool) {
+        let (collection, nft, direct_sell) = (address(7), address(3), address(2));
+        sqlx::query("insert into roots (address, code) values ($1, 'sell')")
+            .bind(address(1).to_string())
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "insert into collection_royalty (collection, numerator, denominator, updated) \
+             values ($1, 5, 100, now())",
+        )
+        .bind(collection.to_string())
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let minted = vec![ScriptedTx::new(&collection, 5, 1_700_000_000).emit(
+            "NftCreated",
+            NftCreated {
+                id: ton_types::UInt256::from([1; 32]),
+                nft: nft.clone(),
+                owner: address(5),
+                manager: address(5),
+                creator: address(5),
+            },
+        )];
+        let sold = || {
+            vec![
+                ScriptedTx::new(&direct_sell, 30, 1_700_000_500)
+                    .emit("DirectSellStateChanged", state_changed(2, 3, address(6))),
+                ScriptedTx::new(&nft, 31, 1_700_000_500).emit(
+                    "OwnerChanged",
+                    OwnerChanged {
+                        old_owner: address(5),
+                        new_owner: address(6),
+                    },
+                ),
+            ]
+        };
+
+        FakeConsumer::new(vec![minted, listed(&direct_sell, 10), sold()])
+            .resuming()
+            .run(&pool)
+            .await
+            .unwrap();
+        assert_eq!(sale_totals(&pool).await, (1, 1, "5".parse().unwrap()));
+
+        rollback_to_lt(&pool, 20).await.unwrap();
+
+        assert_eq!(sale_totals(&pool).await, (0, 0, 0.into()));
+        let stored = get_nft(&pool, &nft.to_string()).await.unwrap().unwrap();
+        assert_eq!(stored.owner, address(5).to_string());
+        assert_eq!(get_checkpoint(&pool).await.unwrap(), None);
+
+        // the rewound stream brings the sale back, counted once
+        FakeConsumer::new(vec![sold()]).run(&pool).await.unwrap();
+
+        assert_eq!(sale_totals(&pool).await, (1, 1, "5".parse().unwrap()));
+        let stored = get_nft(&pool, &nft.to_string()).await.unwrap().unwrap();
+        assert_eq!(stored.owner, address(6).to_string());
+        let stored = get_direct_sells(&pool, &[&direct_sell.to_string()])
+            .await
+            .unwrap();
+        assert_eq!(stored[0].state, DirectSellState::Filled);
+    }
+
+    #[ignore = "needs a Postgres at DATABASE_URL"]
+    #[sqlx::test(migrations = "../indexer-repo/migrations")]
+    async fn test_replay_applies_events_over_their_own_updates(pool: PgPool) {
+        let (collection, nft) = (address(7), address(3));
+        let minted = vec![ScriptedTx::new(&collection, 10, 1_700_000_000).emit(
+            "NftCreated",
+            NftCreated {
+                id: ton_types::UInt256::from([1; 32]),
+                nft: nft.clone(),
+                owner: address(5),
+                manager: address(5),
+                creator: address(5),
+            },
+        )];
+
+        FakeConsumer::new(vec![minted, transferred(20, 1_700_000_100, 6)])
+            .run(&pool)
+            .await
+            .unwrap();
+        // what a decoding bug saved, the replay is there to fix it
+        sqlx::query("update nft set owner = $1 where address = $2")
+            .bind(address(9).to_string())
+            .bind(nft.to_string())
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        replay_from(&pool, 20).await.unwrap();
+
+        let stored = get_nft(&pool, &nft.to_string()).await.unwrap().unwrap();
+        assert_eq!(stored.owner, address(6).to_string());
+        assert_eq!(stored.owner_update_lt, 20);
+    }
+
+    #[ignore = "needs a Postgres at DATABASE_URL"]
+    #[sqlx::test(migrations = "../indexer-repo/migrations")]
+    async fn test_redelivered_mint_keeps_the_later_owner(pool: PgPool) {
+        let (collection, nft) = (address(7), address(3));
+        let minted = || {
+            vec![ScriptedTx::new(&collection, 10, 1_700_000_000).emit(
+                "NftCreated",
+                NftCreated {
+                    id: ton_types::UInt256::from([1; 32]),
+                    nft: nft.clone(),
+                    owner: address(5),
+                    manager: address(5),
+                    creator: address(5),
+                },
+            )]
+        };
+
+        FakeConsumer::new(vec![minted(), transferred(20, 1_700_000_100, 6), minted()])
+            .run(&pool)
+            .await
+            .unwrap();
+
+        let stored = get_nft(&pool, &nft.to_string()).await.unwrap().unwrap();
+        assert_eq!(stored.owner, address(6).to_string());
+        assert_eq!(stored.owner_update_lt, 20);
+        assert_eq!(stored.manager, address(5).to_string());
+        assert_eq!(stored.manager_update_lt, 10);
+        assert_eq!(stored.created_lt, Some(10));
+    }
+
+    #[ignore = "needs a Postgres at DATABASE_URL"]
+    #[sqlx::test(migrations = "../indexer-repo/migrations")]
+    async fn test_relisting_is_linked_to_the_cancelled_listing(pool: PgPool) {
+        let (first, second, third) = (address(20), address(21), address(22));
+        let relisted = |direct_sell: &MsgAddressInt, lt: u64, start: u32| {
+            let mut activated = state_changed(1, 2, address(5));
+            activated.value2.start = start as u64;
+            activated.value2.end = start as u64 + 86_400;
+            let mut listing = listed(direct_sell, lt);
+            listing[1] = ScriptedTx::new(direct_sell, lt + 1, start)
+                .emit("DirectSellStateChanged", activated);
+            listing
+        };
+        let cancelled = |direct_sell: &MsgAddressInt, lt: u64, now: u32| {
+            vec![ScriptedTx::new(direct_sell, lt, now)
+                .emit("DirectSellStateChanged", state_changed(2, 4, address(5)))]
+        };
+
+        FakeConsumer::new(vec![
+            listed(&first, 10),
+            cancelled(&first, 20, 1_700_000_100),
+            relisted(&second, 30, 1_700_000_200),
+            cancelled(&second, 40, 1_700_000_300),
+            // past the relist window
+            relisted(&third, 50, 1_700_000_300 + 8 * 24 * 60 * 60),
+        ])
+        .run(&pool)
+        .await
+        .unwrap();
+
+        let previous_listing = |direct_sell: MsgAddressInt| {
+            let pool = pool.clone();
+            async move {
+                sqlx::query_scalar::<_, Option<String>>(
+                    "select previous_listing_id from nft_direct_sell where address = $1",
+                )
+                .bind(direct_sell.to_string())
+                .fetch_one(&pool)
+                .await
+                .unwrap()
+            }
+        };
+        assert_eq!(previous_listing(first.clone()).await, None);
+        assert_eq!(previous_listing(second).await, Some(first.to_string()));
+        assert_eq!(previous_listing(third).await, None);
+    }
+
+    #[ignore = "needs a Postgres at DATABASE_URL"]
+    #[sqlx::test(migrations = "../indexer-repo/migrations")]
+    async fn test_event_feed_pages_in_time_order_across_accounts(pool: PgPool) {
+        let transfer = |nft: u8, lt: u64, now: u32| {
+            vec![ScriptedTx::new(&address(nft), lt, now).emit(
+                "OwnerChanged",
+                OwnerChanged {
+                    old_owner: address(5),
+                    new_owner: address(6),
+                },
+            )]
+        };
+        let accounts =
+            |events: &[EventRecord]| events.iter().map(|e| e.address.clone()).collect::<Vec<_>>();
+
+        // logical times of different accounts say nothing about their order
+        FakeConsumer::new(vec![
+            transfer(30, 100, 1_700_000_000),
+            transfer(31, 5, 1_700_000_100),
+            transfer(32, 50, 1_700_000_200),
+        ])
+        .run(&pool)
+        .await
+        .unwrap();
+
+        let filter = EventFilter::default();
+        let (first, next) = list_events(&pool, &filter, None, 2).await.unwrap();
+        assert_eq!(
+            accounts(&first),
+            [address(30).to_string(), address(31).to_string()]
+        );
+        let next = next.unwrap();
+        assert_eq!(next.to_string().parse::<EventCursor>().unwrap(), next);
+
+        // indexed between the page requests
+        FakeConsumer::new(vec![transfer(33, 1, 1_700_000_300)])
+            .run(&pool)
+            .await
+            .unwrap();
+
+        let (second, next) = list_events(&pool, &filter, Some(&next), 2).await.unwrap();
+        assert_eq!(
+            accounts(&second),
+            [address(32).to_string(), address(33).to_string()]
+        );
+        let (third, next) = list_events(&pool, &filter, next.as_ref(), 2).await.unwrap();
+        assert!(third.is_empty());
+        assert_eq!(next, None);
+    }
+
+    #[ignore = "needs a Postgres at DATABASE_URL"]
+    #[sqlx::test(migrations = "../indexer-repo/migrations")]
+    async fn test_redelivered_sale_is_counted_once(pool: PgPool) {
+        let (collection, direct_sell) = (address(7), address(2));
+        sqlx::query("insert into roots (address, code) values ($1, 'sell')")
+            .bind(address(1).to_string())
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "insert into collection_royalty (collection, numerator, denominator, updated) \
+             values ($1, 5, 100, now())",
+        )
+        .bind(collection.to_string())
+        .execute(&pool)
+        .await
+        .unwrap();
+        let sold = || {
+            ScriptedTx::new(&direct_sell, 30, 1_700_000_500)
+                .emit("DirectSellStateChanged", state_changed(2, 3, address(6)))
+        };
+
+        // twice within a batch, then once more in a later one
+        FakeConsumer::new(vec![
+            listed(&direct_sell, 10),
+            vec![sold(), sold()],
+            vec![sold()],
+        ])
+        .run(&pool)
+        .await
+        .unwrap();
+
+        assert_eq!(sale_totals(&pool).await, (1, 1, "5".parse().unwrap()));
+    }
+
+    #[ignore = "needs a Postgres at DATABASE_URL"]
+    #[sqlx::test(migrations = "../indexer-repo/migrations")]
+    async fn test_raw_events_are_saved_across_chunks_once(pool: PgPool) {
+        let mut batch = (0..5)
+            .map(|i| transferred(20 + i, 1_700_000_100, 6).remove(0))
+            .collect::<Vec<_>>();
+        // redelivered within the batch, it lands in another chunk than its first copy
+        batch.push(transferred(20, 1_700_000_100, 6).remove(0));
+
+        FakeConsumer::new(vec![batch])
+            .with_raw_event_chunk_size(2)
+            .run(&pool)
+            .await
+            .unwrap();
+
+        let (events, counted): (i64, i64) = sqlx::query_as(
+            "select (select count(*) from nft_events), \
+                    (select coalesce(sum(count), 0)::bigint from event_stats_daily)",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!((events, counted), (5, 5));
+    }
+
+    #[ignore = "needs a Postgres at DATABASE_URL"]
+    #[sqlx::test(migrations = "../indexer-repo/migrations")]
+    async fn test_listing_past_its_end_is_expired(pool: PgPool) {
+        let (ending, endless) = (address(20), address(21));
+        let mut without_end = listed(&endless, 20);
+        let mut activated = state_changed(1, 2, address(5));
+        activated.value2.end = 0;
+        without_end[1] =
+            ScriptedTx::new(&endless, 21, 1_700_000_010).emit("DirectSellStateChanged", activated);
+
+        FakeConsumer::new(vec![listed(&ending, 10), without_end])
+            .run(&pool)
+            .await
+            .unwrap();
+
+        // a day after the end of the first listing
+        let expired = expire_stale_direct_sells(&pool, timestamp_to_datetime(1_700_172_800))
+            .await
+            .unwrap();
+        assert_eq!(expired, 1);
+
+        let stored = get_direct_sells(&pool, &[&ending.to_string(), &endless.to_string()])
+            .await
+            .unwrap();
+        let state_of = |direct_sell: &MsgAddressInt| {
+            stored
+                .iter()
+                .find(|ds| ds.address == direct_sell.to_string())
+                .map(|ds| ds.state.clone())
+                .unwrap()
+        };
+        assert_eq!(state_of(&ending), DirectSellState::Expired);
+        assert_eq!(state_of(&endless), DirectSellState::Active);
+
+        // running again finds nothing new
+        let expired = expire_stale_direct_sells(&pool, timestamp_to_datetime(1_700_172_800))
+            .await
+            .unwrap();
+        assert_eq!(expired, 0);
+    }
+
+    #[ignore = "needs a Postgres at DATABASE_URL"]
+    #[sqlx::test(migrations = "../indexer-repo/migrations")]
+    async fn test_search_needs_every_trait(pool: PgPool) {
+        let collection = address(7).to_string();
+        let (first, second, third) = (address(30), address(31), address(32));
+        for (nft, trait_type, value) in [
+            (&first, "Background", serde_json::json!("Blue")),
+            (&first, "Eyes", serde_json::json!("Laser")),
+            (&first, "Level", serde_json::json!(1)),
+            (&second, "Background", serde_json::json!("Blue")),
+            (&second, "Eyes", serde_json::json!("Normal")),
+            (&second, "Level", serde_json::json!("1")),
+            (&third, "Background", serde_json::json!("Red")),
+            (&third, "Eyes", serde_json::json!("Laser")),
+        ] {
+            sqlx::query(
+                "insert into nft_attributes (nft, collection, raw, trait_type, value) \
+                 values ($1, $2, '{}', $3, $4)",
+            )
+            .bind(nft.to_string())
+            .bind(&collection)
+            .bind(trait_type)
+            .bind(value)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+        let search = |filters: &[(&str, &str)]| {
+            let (pool, collection) = (pool.clone(), collection.clone());
+            let filters = filters
+                .iter()
+                .map(|(t, v)| (t.to_string(), v.to_string()))
+                .collect::<Vec<_>>();
+            async move {
+                search_nfts_by_attributes(&pool, &collection, &filters, 10, 0)
+                    .await
+                    .unwrap()
+            }
+        };
+
+        assert_eq!(
+            search(&[("Background", "Blue"), ("Eyes", "Laser")]).await,
+            [first.to_string()]
+        );
+        // a repeated filter is still one trait
+        assert_eq!(
+            search(&[("Background", "Blue"), ("Background", "Blue")]).await,
+            [first.to_string(), second.to_string()]
+        );
+        // numbers and strings match as text
+        assert_eq!(
+            search(&[("Level", "1")]).await,
+            [first.to_string(), second.to_string()]
+        );
+        assert!(search(&[("Eyes", "Laser"), ("Eyes", "Normal")])
+            .await
+            .is_empty());
+        assert!(search(&[]).await.is_empty());
+    }
+
+    #[ignore = "needs a Postgres at DATABASE_URL"]
+    #[sqlx::test(migrations = "../indexer-repo/migrations")]
+    async fn test_collection_events_of_a_type_subset(pool: PgPool) {
+        let (collection, direct_sell) = (address(7), address(2));
+        let minted = |nft: u8, lt: u64, now: u32| {
+            vec![ScriptedTx::new(&collection, lt, now).emit(
+                "NftCreated",
+                NftCreated {
+                    id: ton_types::UInt256::from([nft; 32]),
+                    nft: address(nft),
+                    owner: address(5),
+                    manager: address(5),
+                    creator: address(5),
+                },
+            )]
+        };
+
+        FakeConsumer::new(vec![
+            minted(3, 5, 1_700_000_000),
+            listed(&direct_sell, 10),
+            vec![ScriptedTx::new(&direct_sell, 30, 1_700_000_500)
+                .emit("DirectSellStateChanged", state_changed(2, 3, address(6)))],
+            // after the range
+            minted(4, 40, 1_700_100_000),
+        ])
+        .run(&pool)
+        .await
+        .unwrap();
+
+        let events_of = |types: &'static [EventType]| {
+            let (pool, collection) = (pool.clone(), collection.to_string());
+            async move {
+                get_collection_events(
+                    &pool,
+                    &collection,
+                    1_700_000_000,
+                    1_700_100_000,
+                    Some(types),
+                )
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|e| e.created_lt)
+                .collect::<Vec<_>>()
+            }
+        };
+
+        // mint at lt 5, activation at 11 and sale at 30
+        assert_eq!(
+            events_of(&[EventType::DirectSellStateChanged]).await,
+            [11, 30]
+        );
+        assert_eq!(
+            events_of(&[EventType::NftCreated, EventType::DirectSellStateChanged]).await,
+            [5, 11, 30]
+        );
+        assert!(events_of(&[EventType::NftBurned]).await.is_empty());
+    }
+
+    #[ignore = "needs a Postgres at DATABASE_URL"]
+    #[sqlx::test(migrations = "../indexer-repo/migrations")]
+    async fn test_older_state_change_applied_second_is_ignored(pool: PgPool) {
+        let (direct_sell, direct_buy) = (address(2), address(40));
+        let mut deployed = listed(&direct_sell, 10);
+        let activated = deployed.pop().unwrap();
+        let mut offer = offered(&direct_buy, 20, 80);
+        let offer_activated = offer.pop().unwrap();
+
+        // the finishing changes are delivered before the activations they follow
+        FakeConsumer::new(vec![
+            deployed,
+            offer,
+            vec![
+                ScriptedTx::new(&direct_sell, 30, 1_700_000_500)
+                    .emit("DirectSellStateChanged", state_changed(2, 3, address(6))),
+                ScriptedTx::new(&direct_buy, 40, 1_700_000_600)
+                    .emit("DirectBuyStateChanged", offer_changed(2, 4, 80)),
+            ],
+            vec![activated, offer_activated],
+        ])
+        .run(&pool)
+        .await
+        .unwrap();
+
+        let stored = get_direct_sells(&pool, &[&direct_sell.to_string()])
+            .await
+            .unwrap();
+        assert_eq!(stored[0].state, DirectSellState::Filled);
+        assert_eq!(
+            stored[0].finished_at,
+            Some(timestamp_to_datetime(1_700_000_500))
+        );
+        assert_eq!(
+            offer_state(&pool, &direct_buy).await,
+            DirectBuyState::Cancelled
+        );
+    }
+
+    #[ignore = "needs a Postgres at DATABASE_URL"]
+    #[sqlx::test(migrations = "../indexer-repo/migrations")]
+    async fn test_burned_nft_listing_is_not_active(pool: PgPool) {
+        let (collection, nft, direct_sell) = (address(7), address(3), address(2));
+        let minted = vec![ScriptedTx::new(&collection, 5, 1_700_000_000).emit(
+            "NftCreated",
+            NftCreated {
+                id: ton_types::UInt256::from([1; 32]),
+                nft: nft.clone(),
+                owner: address(5),
+                manager: address(5),
+                creator: address(5),
+            },
+        )];
+        let burned = vec![ScriptedTx::new(&collection, 50, 1_700_000_600).emit(
+            "NftBurned",
+            NftBurned {
+                id: ton_types::UInt256::from([1; 32]),
+                nft: nft.clone(),
+                owner: address(5),
+                manager: address(5),
+            },
+        )];
+
+        FakeConsumer::new(vec![minted, listed(&direct_sell, 10), burned])
+            .run(&pool)
+            .await
+            .unwrap();
+
+        let stored = get_direct_sells(&pool, &[&direct_sell.to_string()])
+            .await
+            .unwrap();
+        assert_eq!(stored[0].state, DirectSellState::Cancelled);
+        assert_eq!(
+            stored[0].finished_at,
+            Some(timestamp_to_datetime(1_700_000_600))
+        );
+        let active = get_active_direct_sells_by_nfts(&pool, &[nft.to_string()])
+            .await
+            .unwrap();
+        assert!(active.is_empty());
+        let stored = get_nft(&pool, &nft.to_string()).await.unwrap().unwrap();
+        assert!(stored.burned);
+    }
+
+    #[ignore = "needs a Postgres at DATABASE_URL"]
+    #[sqlx::test(migrations = "../indexer-repo/migrations")]
+    async fn test_rollback_takes_back_the_royalty_accrued_at_the_sale(pool: PgPool) {
+        let (collection, nft, direct_sell) = (address(7), address(3), address(2));
+        sqlx::query("insert into roots (address, code) values ($1, 'sell')")
+            .bind(address(1).to_string())
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "insert into collection_royalty (collection, numerator, denominator, updated) \
+             values ($1, 5, 100, now())",
+        )
+        .bind(collection.to_string())
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        FakeConsumer::new(vec![
+            vec![ScriptedTx::new(&collection, 5, 1_700_000_000).emit(
+                "NftCreated",
+                NftCreated {
+                    id: ton_types::UInt256::from([1; 32]),
+                    nft: nft.clone(),
+                    owner: address(5),
+                    manager: address(5),
+                    creator: address(5),
+                },
+            )],
+            listed(&direct_sell, 10),
+            vec![ScriptedTx::new(&direct_sell, 30, 1_700_000_500)
+                .emit("DirectSellStateChanged", state_changed(2, 3, address(6)))],
+        ])
+        .run(&pool)
+        .await
+        .unwrap();
+        assert_eq!(sale_totals(&pool).await, (1, 1, "5".parse().unwrap()));
+
+        // a royalty read after the sale doesn't change what the sale accrued
+        sqlx::query("update collection_royalty set numerator = 10 where collection = $1")
+            .bind(collection.to_string())
+            .execute(&pool)
+            .await
+            .unwrap();
+        rollback_to_lt(&pool, 20).await.unwrap();
+
+        assert_eq!(sale_totals(&pool).await, (0, 0, 0.into()));
+    }
+
+    #[ignore = "needs a Postgres at DATABASE_URL"]
+    #[sqlx::test(migrations = "../indexer-repo/migrations")]
+    async fn test_daily_volumes_span_days_and_count_each_sale_once(pool: PgPool) {
+        let (collection, direct_sell, spoofed) = (address(7), address(2), address(4));
+        sqlx::query("insert into roots (address, code) values ($1, 'sell')")
+            .bind(address(1).to_string())
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "insert into deployed_offers (address, root, created) values ($1, $2, to_timestamp(0))",
+        )
+        .bind(direct_sell.to_string())
+        .bind(address(1).to_string())
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let day = 24 * 60 * 60;
+        let sale = |source: &MsgAddressInt, created_at, usd_price: Option<i32>| NftPriceHistory {
+            source: source.to_string(),
+            source_type: NftPriceSource::DirectSell,
+            created_at: NaiveDateTime::from_timestamp_opt(created_at, 0).unwrap(),
+            price: BigDecimal::from(1000),
+            price_token: address(8).to_string(),
+            price_token_symbol: None,
+            usd_price: usd_price.map(BigDecimal::from),
+            marketplace_fee: None,
+            royalty: None,
+            nft: address(3).to_string(),
+            collection: collection.to_string(),
+            buyer: None,
+            seller: None,
+        };
+        let sales = vec![
+            sale(&direct_sell, 10, Some(5)),
+            sale(&direct_sell, day - 10, Some(7)),
+            sale(&direct_sell, day + 10, None),
+            sale(&direct_sell, 2 * day + 10, Some(3)),
+            sale(&spoofed, 2 * day + 20, Some(100)),
+        ];
+
+        // delivered twice within the first batch and replayed with a new sale in the next
+        let mut tx = pool.begin().await.unwrap();
+        let doubled = [sales.clone(), sales.clone()].concat();
+        let inserted = save_price_history(&mut tx, &doubled).await.unwrap();
+        assert_eq!(inserted.len(), 5);
+        let mut replayed = sales;
+        replayed.push(sale(&direct_sell, 2 * day + 30, Some(1)));
+        let inserted = save_price_history(&mut tx, &replayed).await.unwrap();
+        assert_eq!(
+            inserted.into_iter().collect::<Vec<_>>(),
+            vec![(
+                direct_sell.to_string(),
+                NftPriceSource::DirectSell,
+                NaiveDateTime::from_timestamp_opt(2 * day + 30, 0).unwrap()
+            )]
+        );
+        tx.commit().await.unwrap();
+
+        let volumes: Vec<(String, BigDecimal, i64)> = sqlx::query_as(
+            "select day::text, volume_usd, sales from collection_volume_daily order by day",
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            volumes,
+            vec![
+                ("1970-01-01".to_string(), BigDecimal::from(12), 2),
+                ("1970-01-02".to_string(), BigDecimal::from(0), 1),
+                ("1970-01-03".to_string(), BigDecimal::from(4), 2),
+            ]
+        );
+    }
+
+    /// `NftCreated` of Nft.abi, emitted by the nft when it is deployed
+    #[derive(PackAbiPlain)]
+    struct NftDeployed {
+        #[abi]
+        id: ton_types::UInt256,
+        #[abi]
+        owner: MsgAddressInt,
+        #[abi]
+        manager: MsgAddressInt,
+        #[abi]
+        collection: MsgAddressInt,
+    }
+
+    #[ignore = "needs a Postgres at DATABASE_URL"]
+    #[sqlx::test(migrations = "../indexer-repo/migrations")]
+    async fn test_mint_with_the_nft_abi_nft_created_is_saved(pool: PgPool) {
+        let (collection, nft) = (address(7), address(3));
+        let mint = vec![
+            ScriptedTx::new(&collection, 10, 1_700_000_000).emit(
+                "NftCreated",
+                NftCreated {
+                    id: ton_types::UInt256::from([1; 32]),
+                    nft: nft.clone(),
+                    owner: address(5),
+                    manager: address(5),
+                    creator: address(5),
+                },
+            ),
+            ScriptedTx::new(&nft, 11, 1_700_000_000).emit(
+                "NftCreated",
+                NftDeployed {
+                    id: ton_types::UInt256::from([1; 32]),
+                    owner: address(5),
+                    manager: address(5),
+                    collection: collection.clone(),
+                },
+            ),
+        ];
+
+        // the harness decodes in strict mode
+        FakeConsumer::new(vec![mint]).run(&pool).await.unwrap();
+
+        let stored = get_nft(&pool, &nft.to_string()).await.unwrap().unwrap();
+        assert_eq!(stored.collection, collection.to_string());
+        let failed: i64 = sqlx::query_scalar("select count(*) from failed_events")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(failed, 0);
+    }
+
+    #[ignore = "needs a Postgres at DATABASE_URL"]
+    #[sqlx::test(migrations = "../indexer-repo/migrations")]
+    async fn test_redelivered_bid_is_saved_once(pool: PgPool) {
+        let (auction, now) = (address(30), 1_700_000_100);
+        let batch = || {
+            vec![
+                ScriptedTx::new(&auction, 20, now).emit(
+                    "BidPlaced",
+                    BidPlaced {
+                        buyer: address(5),
+                        value: 50,
+                        next_bid_value: 55,
+                        value3: AuctionDetails {
+                            auction_subject: address(31),
+                            subject_owner: address(9),
+                            payment_token: address(4),
+                            wallet_for_bids: address(8),
+                            start_time: 1_700_000_000,
+                            duration: 3_600,
+                            end_time: 1_700_003_600,
+                            price: 10,
+                            nonce: 0,
+                            status: AuctionStatus::Active,
+                            collection: address(7),
+                        },
+                    },
+                ),
+                // the checkpoint transaction, the bid shares its timestamp
+                ScriptedTx::new(&address(31), 21, now).emit(
+                    "OwnerChanged",
+                    OwnerChanged {
+                        old_owner: address(9),
+                        new_owner: address(10),
+                    },
+                ),
+            ]
+        };
+
+        FakeConsumer::new(vec![batch()])
+            .resuming()
+            .run(&pool)
+            .await
+            .unwrap();
+        // a new consumer redelivers the batch, only the checkpoint transaction is skipped
+        FakeConsumer::new(vec![batch()])
+            .resuming()
+            .run(&pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            get_auction_bids(&pool, &auction.to_string())
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        replay_from(&pool, 0).await.unwrap();
+        let bids = get_auction_bids(&pool, &auction.to_string()).await.unwrap();
+        assert_eq!(bids.len(), 1);
+        assert_eq!(bids[0].tx_lt, 20);
+    }
+
+    #[ignore = "needs a Postgres at DATABASE_URL"]
+    #[sqlx::test(migrations = "../indexer-repo/migrations")]
+    async fn test_counterparties_leave_out_self_trades(pool: PgPool) {
+        let (sold, self_traded) = (address(2), address(20));
+
+        let mut batches = vec![listed(&sold, 10), listed(&self_traded, 20)];
+        batches.push(vec![
+            ScriptedTx::new(&sold, 30, 1_700_000_300)
+                .emit("DirectSellStateChanged", state_changed(2, 3, address(6))),
+            // bought back by its own seller
+            ScriptedTx::new(&self_traded, 31, 1_700_000_400)
+                .emit("DirectSellStateChanged", state_changed(2, 3, address(5))),
+        ]);
+        FakeConsumer::new(batches).run(&pool).await.unwrap();
+
+        let counterparties = NftPriceModel::new(pool.clone())
+            .get_counterparty_stats(&address(5).to_string())
+            .await
+            .unwrap();
+        assert_eq!(counterparties.len(), 1);
+        assert_eq!(counterparties[0].address, address(6).to_string());
+        assert_eq!(counterparties[0].sold_to, 1);
+        assert_eq!(counterparties[0].bought_from, 0);
+    }
+
+    #[ignore = "needs a Postgres at DATABASE_URL"]
+    #[sqlx::test(migrations = "../indexer-repo/migrations")]
+    async fn test_cancel_after_bids_ends_the_auction(pool: PgPool) {
+        let (factory, auction) = (address(41), address(30));
+        let details = |status| AuctionDetails {
+            auction_subject: address(31),
+            subject_owner: address(9),
+            payment_token: address(4),
+            wallet_for_bids: address(8),
+            start_time: 1_700_000_000,
+            duration: 3_600,
+            end_time: 1_700_003_600,
+            price: 10,
+            nonce: 0,
+            status,
+            collection: address(7),
+        };
+        let bid = |value| BidPlaced {
+            buyer: address(5),
+            value,
+            next_bid_value: value + 5,
+            value3: details(AuctionStatus::Active),
+        };
+
+        // the bid at lt 25 is delivered after the cancel it precedes
+        FakeConsumer::new(vec![
+            vec![
+                ScriptedTx::new(&factory, 10, 1_700_000_000).emit(
+                    "AuctionDeployed",
+                    AuctionDeployed {
+                        offer: auction.clone(),
+                        offer_info: MarketOffer {
+                            collection: address(7),
+                            nft_owner: address(9),
+                            nft: address(31),
+                            offer: auction.clone(),
+                            price: 10,
+                            auction_duration: 3_600,
+                            deploy_nonce: 0,
+                        },
+                    },
+                ),
+                ScriptedTx::new(&auction, 15, 1_700_000_000).emit(
+                    "AuctionActive",
+                    AuctionActive {
+                        value0: details(AuctionStatus::Active),
+                    },
+                ),
+            ],
+            vec![ScriptedTx::new(&auction, 20, 1_700_000_100).emit("BidPlaced", bid(50))],
+            vec![ScriptedTx::new(&auction, 30, 1_700_000_200).emit(
+                "AuctionCancelled",
+                AuctionCancelled {
+                    value0: details(AuctionStatus::Cancelled),
+                },
+            )],
+            vec![ScriptedTx::new(&auction, 25, 1_700_000_150).emit("BidPlaced", bid(70))],
+        ])
+        .run(&pool)
+        .await
+        .unwrap();
+
+        let stored = get_auction(&pool, &auction.to_string())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(stored.status, RepoAuctionStatus::Cancelled));
+        assert_eq!(stored.tx_lt, 30);
+        assert_eq!(stored.max_bid, Some(BigDecimal::from(50)));
+        assert_eq!(
+            stored.finished_at,
+            Some(timestamp_to_datetime(1_700_000_200))
+        );
+        // the late bid is still a bid of the auction
+        assert_eq!(
+            get_auction_bids(&pool, &auction.to_string())
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    /// Stored sales, sales in the daily volume and royalties earned
+    async fn sale_totals(pool: &PgPool) -> (i64, i64, BigDecimal) {
+        sqlx::query_as(
+            "select (select count(*) from nft_price_history), \
+                    (select coalesce(sum(sales), 0)::bigint from collection_volume_daily), \
+                    (select coalesce(sum(amount), 0) from collection_royalty_earned)",
+        )
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    async fn parked_events(pool: &PgPool) -> i64 {
+        sqlx::query_scalar("select count(*) from parked_events")
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+}
@@ -3,10 +3,11 @@ use bigdecimal::num_bigint::Sign;
 use bigdecimal::BigDecimal;
 use chrono::NaiveDateTime;
 use num::BigInt;
-use serde::Serializer;
 use ton_block::{GetRepresentationHash, MsgAddressInt};
 use ton_types::UInt256;
 
+use crate::metrics;
+
 pub trait KeyInfo {
     fn get_account(&self) -> String;
     fn get_hash(&self) -> Result<Vec<u8>>;
@@ -38,20 +39,95 @@ pub struct DecodeContext {
     pub tx_data: ton_block::Transaction,
     pub function_inputs: Vec<ton_abi::Token>,
     pub message_hash: UInt256,
+    /// Listing/auction ends further than this from their start are clamped
+    pub max_listing_lifetime_secs: u64,
+}
+
+impl DecodeContext {
+    /// End of a listing or auction, clamped to `max_listing_lifetime_secs` after its start
+    pub fn listing_end(&self, start: u64, end: u64) -> NaiveDateTime {
+        match clamp_listing_end(start, end, self.max_listing_lifetime_secs) {
+            Some(clamped) => {
+                metrics::LISTING_ENDS_CLAMPED.inc();
+                log::warn!(
+                    "METRIC | Clamped listing end (address: {}, message hash: {}, end: {}, clamped to: {})",
+                    self.tx_data.get_account(),
+                    self.message_hash.to_string(),
+                    end,
+                    clamped
+                );
+                timestamp_to_datetime(i64::try_from(clamped).unwrap_or(i64::MAX))
+            }
+            None => timestamp_to_datetime(end.try_into().unwrap_or(i64::MAX)),
+        }
+    }
+}
+
+/// Returns the capped end if `end` exceeds `start + max_lifetime_secs`
+pub fn clamp_listing_end(start: u64, end: u64, max_lifetime_secs: u64) -> Option<u64> {
+    let horizon = start.saturating_add(max_lifetime_secs);
+    (end > horizon).then_some(horizon)
 }
 
-pub fn serialize_msg_address_int<S>(addr: &MsgAddressInt, s: S) -> Result<S::Ok, S::Error>
-where
-    S: Serializer,
-{
-    s.serialize_str(&addr.to_string())
+/// Addresses of serialized events in the `0:hex` form
+pub mod msg_address_int {
+    use std::str::FromStr;
+
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use ton_block::MsgAddressInt;
+
+    pub fn serialize<S>(addr: &MsgAddressInt, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        s.serialize_str(&addr.to_string())
+    }
+
+    pub fn deserialize<'de, D>(d: D) -> Result<MsgAddressInt, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let addr = String::deserialize(d)?;
+        MsgAddressInt::from_str(&addr)
+            .map_err(|e| D::Error::custom(format!("invalid address {addr}: {e}")))
+    }
 }
 
-pub fn serialize_uint256<S>(v: &UInt256, s: S) -> Result<S::Ok, S::Error>
-where
-    S: Serializer,
-{
-    s.serialize_str(u256_to_bigdecimal(v).to_string().as_str())
+/// 256-bit integers of serialized events as decimal strings
+pub mod uint256 {
+    use std::str::FromStr;
+
+    use num::BigUint;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use ton_types::UInt256;
+
+    use super::u256_to_bigdecimal;
+
+    pub fn serialize<S>(v: &UInt256, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        s.serialize_str(u256_to_bigdecimal(v).to_string().as_str())
+    }
+
+    pub fn deserialize<'de, D>(d: D) -> Result<UInt256, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let v = String::deserialize(d)?;
+        let bytes = BigUint::from_str(&v)
+            .map_err(|e| D::Error::custom(format!("invalid uint256 {v}: {e}")))?
+            .to_bytes_be();
+        if bytes.len() > 32 {
+            return Err(D::Error::custom(format!("uint256 overflow: {v}")));
+        }
+
+        let mut be = [0u8; 32];
+        be[32 - bytes.len()..].copy_from_slice(&bytes);
+        Ok(UInt256::from(be))
+    }
 }
 
 pub fn u128_to_bigdecimal(i: u128) -> BigDecimal {
@@ -62,6 +138,22 @@ pub fn u256_to_bigdecimal(i: &UInt256) -> BigDecimal {
     BigDecimal::new(BigInt::from_bytes_be(Sign::Plus, i.as_slice()), 0)
 }
 
+pub fn is_zero_address(addr: &MsgAddressInt) -> bool {
+    addr.to_string()
+        .split_once(':')
+        .map(|(_, hex)| hex.chars().all(|c| c == '0'))
+        .unwrap_or(true)
+}
+
 pub fn timestamp_to_datetime(ts: i64) -> NaiveDateTime {
     NaiveDateTime::from_timestamp_opt(ts, 0).unwrap_or_default()
 }
+
+/// Like `timestamp_to_datetime` for times set by the contract rather than the
+/// transaction: zero or unrepresentable values are missing instead of the epoch
+pub fn to_datetime(ts: impl TryInto<i64>) -> Option<NaiveDateTime> {
+    ts.try_into()
+        .ok()
+        .filter(|ts| *ts > 0)
+        .and_then(|ts| NaiveDateTime::from_timestamp_opt(ts, 0))
+}
@@ -0,0 +1,348 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use anyhow::{anyhow, bail, Result};
+use chrono::NaiveDateTime;
+use indexer_repo::direct_sell::{get_direct_sells, DirectSellRecord};
+use indexer_repo::events::{get_collection_events, list_events, EventFilter};
+use indexer_repo::types::decoded::{DirectSell, EventRecord};
+use indexer_repo::types::{DirectSellState, EventType};
+use sqlx::PgPool;
+
+use crate::persistence::entities::Decoded;
+use crate::replay::redecode;
+use crate::settings::runtime::RuntimeConfig;
+
+const EVENTS_PER_PAGE: i64 = 1000;
+
+/// Listings whose stored events are checked
+#[derive(Debug, PartialEq, Eq)]
+pub enum VerifyTarget {
+    Collection(String),
+    Address(String),
+}
+
+/// `verify --collection <address>` or `verify --address <direct sell>`: replays the
+/// stored events in memory and compares the result to the derived `nft_direct_sell` rows
+#[derive(Debug, PartialEq, Eq)]
+pub struct VerifyArgs {
+    pub target: VerifyTarget,
+}
+
+impl VerifyArgs {
+    /// `None` unless the process was started with the `verify` command
+    pub fn parse(args: &[String]) -> Result<Option<Self>> {
+        let Some((command, options)) = args.split_first() else {
+            return Ok(None);
+        };
+        if command != "verify" {
+            return Ok(None);
+        }
+
+        let target = match options {
+            [option, address] if option == "--collection" => {
+                VerifyTarget::Collection(address.clone())
+            }
+            [option, address] if option == "--address" => VerifyTarget::Address(address.clone()),
+            _ => bail!("verify expects --collection <address> or --address <address>"),
+        };
+
+        Ok(Some(Self { target }))
+    }
+}
+
+/// Field of a stored listing that differs from the state its events lead to
+#[derive(Debug, PartialEq, Eq)]
+pub struct Mismatch {
+    pub address: String,
+    pub field: &'static str,
+    pub expected: String,
+    pub actual: String,
+}
+
+/// Reports every mismatch and fails if there is any
+pub async fn run(
+    pool: &PgPool,
+    runtime_config: &RuntimeConfig,
+    target: &VerifyTarget,
+) -> Result<()> {
+    let events = match target {
+        VerifyTarget::Collection(collection) => {
+            get_collection_events(
+                pool,
+                collection,
+                0,
+                i64::MAX,
+                Some(&[EventType::DirectSellStateChanged]),
+            )
+            .await?
+        }
+        VerifyTarget::Address(address) => address_events(pool, address).await?,
+    };
+
+    let expected = expected_direct_sells(&events, runtime_config)?;
+    let addresses = expected.keys().map(String::as_str).collect::<Vec<_>>();
+    let stored = get_direct_sells(pool, &addresses)
+        .await?
+        .into_iter()
+        .map(|ds| (ds.address.clone(), ds))
+        .collect::<BTreeMap<_, _>>();
+
+    let now = chrono::Utc::now().naive_utc();
+    let mismatches = expected
+        .values()
+        .flat_map(|ds| compare(ds, stored.get(&ds.address), now))
+        .collect::<Vec<_>>();
+
+    for m in &mismatches {
+        println!(
+            "{}\t{}\texpected: {}\tactual: {}",
+            m.address, m.field, m.expected, m.actual
+        );
+    }
+    println!(
+        "Verified {} direct sells from {} events, {} mismatches",
+        expected.len(),
+        events.len(),
+        mismatches.len()
+    );
+
+    if !mismatches.is_empty() {
+        bail!("{} mismatches in nft_direct_sell", mismatches.len());
+    }
+
+    Ok(())
+}
+
+async fn address_events(pool: &PgPool, address: &str) -> Result<Vec<EventRecord>> {
+    let filter = EventFilter {
+        address: Some(address.to_string()),
+        event_type: Some(EventType::DirectSellStateChanged),
+        from_lt: None,
+    };
+
+    let mut events = Vec::new();
+    let mut cursor = None;
+    loop {
+        let (page, next) = list_events(pool, &filter, cursor.as_ref(), EVENTS_PER_PAGE).await?;
+        events.extend(page);
+        match next {
+            Some(next) => cursor = Some(next),
+            None => return Ok(events),
+        }
+    }
+}
+
+/// Listing each address ends up with once its state changes are applied in logical
+/// time order, as `update_direct_sell_state` does
+fn expected_direct_sells(
+    events: &[EventRecord],
+    runtime_config: &RuntimeConfig,
+) -> Result<BTreeMap<String, DirectSell>> {
+    let mut expected = BTreeMap::new();
+
+    for event in events {
+        match redecode(event, runtime_config).map_err(|e| {
+            anyhow!(
+                "failed to decode {} (lt: {}): {e:#}",
+                event.message_hash,
+                event.created_lt
+            )
+        })? {
+            Decoded::DirectSellStateChanged((ds, _)) => {
+                expected.insert(ds.address.clone(), ds);
+            }
+            Decoded::ShouldSkip => {}
+            _ => bail!("unexpected entity decoded from {}", event.message_hash),
+        }
+    }
+
+    Ok(expected)
+}
+
+fn compare(
+    expected: &DirectSell,
+    stored: Option<&DirectSellRecord>,
+    now: NaiveDateTime,
+) -> Vec<Mismatch> {
+    let mut mismatches = Vec::new();
+    let mut check = |field: &'static str, expected_value: &dyn Display, actual: &dyn Display| {
+        let (expected_value, actual) = (expected_value.to_string(), actual.to_string());
+        if expected_value != actual {
+            mismatches.push(Mismatch {
+                address: expected.address.clone(),
+                field,
+                expected: expected_value,
+                actual,
+            });
+        }
+    };
+
+    let Some(stored) = stored else {
+        check("row", &"present", &"missing");
+        return mismatches;
+    };
+
+    // No event marks a listing that ran out, the reaper expires it in place
+    let ran_out = expected.expired_at.timestamp() != 0 && expected.expired_at < now;
+    let expected_state = if expected.state == DirectSellState::Active && ran_out {
+        DirectSellState::Expired
+    } else {
+        expected.state.clone()
+    };
+
+    check("nft", &expected.nft, &stored.nft);
+    check(
+        "collection",
+        &optional(&expected.collection),
+        &optional(&stored.collection),
+    );
+    check("price_token", &expected.price_token, &stored.price_token);
+    check("price", &expected.price, &stored.price);
+    check("seller", &expected.seller, &optional(&stored.seller));
+    check(
+        "state",
+        &format!("{expected_state:?}"),
+        &format!("{:?}", stored.state),
+    );
+    check(
+        "finished_at",
+        &optional(&expected.finished_at),
+        &optional(&stored.finished_at),
+    );
+    check(
+        "expired_at",
+        &expected.expired_at,
+        &optional(&stored.expired_at),
+    );
+    check(
+        "created",
+        &optional(&expected.created),
+        &optional(&stored.created),
+    );
+
+    mismatches
+}
+
+fn optional<T: Display>(value: &Option<T>) -> String {
+    value
+        .as_ref()
+        .map_or_else(|| "null".to_string(), T::to_string)
+}
+
+#[cfg(test)]
+mod test {
+    use bigdecimal::BigDecimal;
+    use indexer_repo::direct_sell::DirectSellRecord;
+    use indexer_repo::types::decoded::DirectSell;
+    use indexer_repo::types::DirectSellState;
+
+    use crate::utils::timestamp_to_datetime;
+
+    use super::{compare, Mismatch, VerifyArgs, VerifyTarget};
+
+    fn expected(state: DirectSellState, expired_at: i64) -> DirectSell {
+        DirectSell {
+            address: "0:direct_sell".to_string(),
+            root: "0:root".to_string(),
+            nft: "0:nft".to_string(),
+            collection: Some("0:collection".to_string()),
+            price_token: "0:token".to_string(),
+            price_token_symbol: None,
+            price: BigDecimal::from(100),
+            price_normalized: None,
+            seller: "0:seller".to_string(),
+            finished_at: None,
+            expired_at: timestamp_to_datetime(expired_at),
+            state,
+            created: Some(timestamp_to_datetime(1_000)),
+            updated: timestamp_to_datetime(2_000),
+            tx_lt: 1,
+        }
+    }
+
+    fn stored(ds: &DirectSell) -> DirectSellRecord {
+        DirectSellRecord {
+            address: ds.address.clone(),
+            nft: ds.nft.clone(),
+            collection: ds.collection.clone(),
+            price_token: ds.price_token.clone(),
+            price: ds.price.clone(),
+            seller: Some(ds.seller.clone()),
+            finished_at: ds.finished_at,
+            expired_at: Some(ds.expired_at),
+            state: ds.state.clone(),
+            created: ds.created,
+        }
+    }
+
+    #[test]
+    fn test_verify_command_is_parsed() {
+        let args = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(VerifyArgs::parse(&args(&["replay"])).unwrap(), None);
+        assert_eq!(
+            VerifyArgs::parse(&args(&["verify", "--collection", "0:c"])).unwrap(),
+            Some(VerifyArgs {
+                target: VerifyTarget::Collection("0:c".to_string())
+            })
+        );
+        assert_eq!(
+            VerifyArgs::parse(&args(&["verify", "--address", "0:a"])).unwrap(),
+            Some(VerifyArgs {
+                target: VerifyTarget::Address("0:a".to_string())
+            })
+        );
+        assert!(VerifyArgs::parse(&args(&["verify"])).is_err());
+    }
+
+    #[test]
+    fn test_matching_listing_has_no_mismatches() {
+        let ds = expected(DirectSellState::Filled, 0);
+
+        assert!(compare(&ds, Some(&stored(&ds)), timestamp_to_datetime(5_000)).is_empty());
+    }
+
+    #[test]
+    fn test_drifted_fields_are_reported() {
+        let ds = expected(DirectSellState::Active, 0);
+        let mut drifted = stored(&ds);
+        drifted.price = BigDecimal::from(90);
+        drifted.seller = None;
+
+        assert_eq!(
+            compare(&ds, Some(&drifted), timestamp_to_datetime(5_000)),
+            vec![
+                Mismatch {
+                    address: ds.address.clone(),
+                    field: "price",
+                    expected: "100".to_string(),
+                    actual: "90".to_string(),
+                },
+                Mismatch {
+                    address: ds.address.clone(),
+                    field: "seller",
+                    expected: "0:seller".to_string(),
+                    actual: "null".to_string(),
+                },
+            ]
+        );
+        assert_eq!(
+            compare(&ds, None, timestamp_to_datetime(5_000))[0].field,
+            "row"
+        );
+    }
+
+    #[test]
+    fn test_listing_expired_by_the_reaper_is_not_a_mismatch() {
+        let ds = expected(DirectSellState::Active, 3_000);
+        let mut reaped = stored(&ds);
+        reaped.state = DirectSellState::Expired;
+
+        assert!(compare(&ds, Some(&reaped), timestamp_to_datetime(5_000)).is_empty());
+        assert_eq!(
+            compare(&ds, Some(&reaped), timestamp_to_datetime(2_500))[0].field,
+            "state"
+        );
+    }
+}
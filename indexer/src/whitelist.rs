@@ -0,0 +1,202 @@
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use indexer_repo::whitelist::{filter_whitelisted_offers, get_active_roots};
+use sqlx::PgPool;
+
+use crate::persistence::entities::Decoded;
+use crate::settings::config::WhitelistMode;
+
+/// Roots are added by hand, re-reading them every few minutes picks up new ones
+const ROOTS_REFRESH_PERIOD: Duration = Duration::from_secs(5 * 60);
+
+/// Contract an event is emitted by, as far as the whitelist is concerned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Emitter {
+    /// Marketplace factory, whitelisted through `roots`
+    Root,
+    /// Auction or direct buy/sell, whitelisted once deployed by a whitelisted root
+    Offer,
+}
+
+fn emitter_of(event_name: &str) -> Option<Emitter> {
+    match event_name {
+        "AuctionDeployed" | "AuctionDeclined" | "DirectBuyDeployed" | "DirectBuyDeclined"
+        | "DirectSellDeployed" | "DirectSellDeclined" => Some(Emitter::Root),
+        "AuctionCreated"
+        | "AuctionActive"
+        | "BidPlaced"
+        | "BidDeclined"
+        | "AuctionComplete"
+        | "AuctionCancelled"
+        | "DirectBuyStateChanged"
+        | "DirectSellStateChanged" => Some(Emitter::Offer),
+        _ => None,
+    }
+}
+
+/// Marketplace contracts whose events are indexed. Anyone can deploy contracts with
+/// the marketplace ABIs, only offers of known roots are trusted. Nft and collection
+/// events are not gated
+pub struct Whitelist {
+    mode: WhitelistMode,
+    roots: HashSet<String>,
+    roots_loaded: Option<Instant>,
+    offers: HashSet<String>,
+}
+
+impl Whitelist {
+    pub fn new(mode: WhitelistMode) -> Self {
+        Self {
+            mode,
+            roots: HashSet::new(),
+            roots_loaded: None,
+            offers: HashSet::new(),
+        }
+    }
+
+    pub fn mode(&self) -> WhitelistMode {
+        self.mode
+    }
+
+    /// Loads the roots if they are stale and the offers among `accounts` not known yet,
+    /// called once per batch before [`Whitelist::allows`]
+    pub async fn prepare(&mut self, pool: &PgPool, accounts: &[(&str, &str)]) -> Result<()> {
+        if self.mode == WhitelistMode::Off {
+            return Ok(());
+        }
+
+        if self
+            .roots_loaded
+            .map_or(true, |loaded| loaded.elapsed() >= ROOTS_REFRESH_PERIOD)
+        {
+            self.roots = get_active_roots(pool).await?;
+            self.roots_loaded = Some(Instant::now());
+        }
+
+        let mut unknown = accounts
+            .iter()
+            .filter(|(event_name, _)| emitter_of(event_name) == Some(Emitter::Offer))
+            .map(|(_, account)| *account)
+            .filter(|account| !self.offers.contains(*account))
+            .collect::<Vec<_>>();
+        unknown.sort_unstable();
+        unknown.dedup();
+        if !unknown.is_empty() {
+            self.offers
+                .extend(filter_whitelisted_offers(pool, &unknown).await?);
+        }
+
+        Ok(())
+    }
+
+    /// Whether an event emitted by `account` comes from a whitelisted contract
+    pub fn allows(&self, event_name: &str, account: &str) -> bool {
+        match emitter_of(event_name) {
+            _ if self.mode == WhitelistMode::Off => true,
+            Some(Emitter::Root) => self.roots.contains(account),
+            Some(Emitter::Offer) => self.offers.contains(account),
+            None => true,
+        }
+    }
+
+    /// Offers deployed by a whitelisted root are trusted right away, their first state
+    /// changes may come in the same batch as the deployment
+    pub fn record_deployed(&mut self, decoded: &[Decoded]) {
+        for d in decoded {
+            let offer = match d {
+                Decoded::AuctionDeployed((_, offer))
+                | Decoded::DirectBuyDeployed((_, offer))
+                | Decoded::DirectSellDeployed((_, offer)) => offer,
+                _ => continue,
+            };
+            if self.roots.contains(&offer.root) {
+                self.offers.insert(offer.address.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDateTime;
+    use indexer_repo::types::decoded::{AuctionDeployed, OfferDeployed};
+
+    use super::*;
+
+    fn whitelist(mode: WhitelistMode) -> Whitelist {
+        let mut whitelist = Whitelist::new(mode);
+        whitelist.roots.insert("0:root".to_string());
+        whitelist.offers.insert("0:offer".to_string());
+        whitelist
+    }
+
+    fn deployed(address: &str, root: &str) -> Decoded {
+        Decoded::AuctionDeployed((
+            AuctionDeployed {
+                address: address.to_string(),
+                root: root.to_string(),
+                nft: "0:nft".to_string(),
+                collection: "0:collection".to_string(),
+                nft_owner: "0:owner".to_string(),
+                tx_lt: 1,
+            },
+            OfferDeployed {
+                address: address.to_string(),
+                root: root.to_string(),
+                created: NaiveDateTime::default(),
+            },
+        ))
+    }
+
+    #[test]
+    fn test_only_whitelisted_marketplace_contracts_are_allowed() {
+        let whitelist = whitelist(WhitelistMode::Enforce);
+
+        assert!(whitelist.allows("DirectSellDeployed", "0:root"));
+        assert!(!whitelist.allows("DirectSellDeployed", "0:fake_root"));
+        assert!(whitelist.allows("DirectSellStateChanged", "0:offer"));
+        assert!(!whitelist.allows("DirectSellStateChanged", "0:fake_offer"));
+        // The root address doesn't whitelist offer events emitted by it
+        assert!(!whitelist.allows("BidPlaced", "0:root"));
+        assert!(whitelist.allows("NftCreated", "0:any_collection"));
+        assert!(whitelist.allows("OwnerChanged", "0:any_nft"));
+    }
+
+    #[test]
+    fn test_disabled_whitelist_allows_everything() {
+        let whitelist = whitelist(WhitelistMode::Off);
+
+        assert!(whitelist.allows("DirectSellDeployed", "0:fake_root"));
+        assert!(whitelist.allows("AuctionComplete", "0:fake_offer"));
+    }
+
+    #[test]
+    fn test_offers_of_whitelisted_roots_are_whitelisted_on_deploy() {
+        let mut whitelist = whitelist(WhitelistMode::Enforce);
+
+        whitelist.record_deployed(&[
+            deployed("0:new_offer", "0:root"),
+            deployed("0:spoofed_offer", "0:fake_root"),
+        ]);
+
+        assert!(whitelist.allows("AuctionActive", "0:new_offer"));
+        assert!(!whitelist.allows("AuctionActive", "0:spoofed_offer"));
+    }
+
+    #[test]
+    fn test_offers_of_every_root_are_whitelisted() {
+        let mut whitelist = whitelist(WhitelistMode::Enforce);
+        whitelist.roots.insert("0:collection_root".to_string());
+
+        whitelist.record_deployed(&[
+            deployed("0:root_offer", "0:root"),
+            deployed("0:collection_offer", "0:collection_root"),
+        ]);
+
+        assert!(whitelist.allows("AuctionDeployed", "0:collection_root"));
+        assert!(whitelist.allows("BidPlaced", "0:root_offer"));
+        assert!(whitelist.allows("BidPlaced", "0:collection_offer"));
+    }
+}